@@ -0,0 +1,210 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use indexmap::IndexMap;
+
+use crate::convert::ConvertError;
+use crate::lossy::Tracker;
+use crate::value::Value;
+
+/// Convert a map of scalars into a C header of `#define` macros.
+///
+/// Nested maps are flattened, joining the keys with `_`. Names are
+/// upper-cased, and characters which cannot appear in a C identifier are
+/// replaced with `_`. Strings are quoted, booleans become `1` or `0` and nulls
+/// define the macro without a value. Arrays cannot be converted.
+pub fn to_string(value: &Value) -> Result<String, ConvertError> {
+    to_string_tracked(value, &mut Tracker::new())
+}
+
+/// Convert a map of scalars into a C header like `to_string`, reporting the
+/// booleans which become integers to `tracker`.
+pub(crate) fn to_string_tracked(
+    value: &Value,
+    tracker: &mut Tracker,
+) -> Result<String, ConvertError> {
+    let map = match value {
+        Value::Map(map) => map,
+        _ => return Err(ConvertError::NonMapRoot),
+    };
+
+    let mut output = String::new();
+    write_map(&mut output, &mut HashSet::new(), None, map, tracker)?;
+
+    Ok(output)
+}
+
+fn write_map(
+    output: &mut String,
+    names: &mut HashSet<String>,
+    prefix: Option<&str>,
+    map: &IndexMap<String, Value>,
+    tracker: &mut Tracker,
+) -> Result<(), ConvertError> {
+    for (key, value) in map {
+        let name = match prefix {
+            Some(prefix) => format!("{}_{}", prefix, sanitize(key)),
+            None => identifier(key),
+        };
+        tracker.nest(key, |tracker| {
+            write_macro(output, names, name, value, tracker)
+        })?;
+    }
+
+    Ok(())
+}
+
+fn write_macro(
+    output: &mut String,
+    names: &mut HashSet<String>,
+    name: String,
+    value: &Value,
+    tracker: &mut Tracker,
+) -> Result<(), ConvertError> {
+    let body = match value {
+        Value::Map(map) => return write_map(output, names, Some(&name), map, tracker),
+        Value::Null => None,
+        Value::Bool(bool) => {
+            tracker.compare(value, &Value::Integer(u64::from(*bool).into()));
+            Some(u8::from(*bool).to_string())
+        }
+        Value::Integer(int) => Some(match (int.as_i64(), int.as_u64()) {
+            (Some(sint), _) if sint < 0 => format!("({})", sint),
+            (Some(sint), _) => sint.to_string(),
+            (_, Some(uint)) => format!("{}ULL", uint),
+            _ => return Err(ConvertError::IntegerOutOfRange(int.clone())),
+        }),
+        Value::Float(float) if !float.is_finite() => {
+            return Err(ConvertError::NonFiniteFloat(*float))
+        }
+        Value::Float(float) if float.is_sign_negative() => Some(format!("({:?})", float)),
+        Value::Float(float) => Some(format!("{:?}", float)),
+        Value::String(str) => Some(quote(str)),
+        Value::Array(_) => return Err(ConvertError::UnsupportedValue(name)),
+    };
+
+    if !names.insert(name.clone()) {
+        return Err(ConvertError::DuplicateKey(name));
+    }
+    match body {
+        Some(body) => writeln!(output, "#define {} {}", name, body),
+        None => writeln!(output, "#define {}", name),
+    }
+    .unwrap();
+
+    Ok(())
+}
+
+/// Upper-case the key, replacing characters which cannot appear in a C
+/// identifier with `_`.
+pub(crate) fn sanitize(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Convert the key into a C identifier, which does not start with a digit.
+pub(crate) fn identifier(key: &str) -> String {
+    let name = sanitize(key);
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+fn quote(str: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in str.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_ascii_control() => write!(quoted, "\\{:03o}", c as u8).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn c_header() {
+        let value = map(vec![
+            ("name", Value::String("a \"b\"\n\u{7f}".to_string())),
+            ("debug", Value::Bool(true)),
+            ("offset", Value::Integer((-1_i64).into())),
+            ("max", Value::Integer(u64::MAX.into())),
+            ("ratio", Value::Float(0.5)),
+            ("feature", Value::Null),
+            (
+                "net",
+                map(vec![("http-port", Value::Integer(80_u64.into()))]),
+            ),
+            ("2nd", Value::Float(-1.0)),
+        ]);
+
+        assert_eq!(
+            to_string(&value).unwrap(),
+            "#define NAME \"a \\\"b\\\"\\n\\177\"\n\
+             #define DEBUG 1\n\
+             #define OFFSET (-1)\n\
+             #define MAX 18446744073709551615ULL\n\
+             #define RATIO 0.5\n\
+             #define FEATURE\n\
+             #define NET_HTTP_PORT 80\n\
+             #define _2ND (-1.0)\n"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(matches!(
+            to_string(&Value::Array(vec![])),
+            Err(ConvertError::NonMapRoot)
+        ));
+        assert!(matches!(
+            to_string(&map(vec![("a", Value::Array(vec![]))])),
+            Err(ConvertError::UnsupportedValue(name)) if name == "A"
+        ));
+        assert!(matches!(
+            to_string(&map(vec![("a", Value::Float(f64::NAN))])),
+            Err(ConvertError::NonFiniteFloat(_))
+        ));
+        assert!(matches!(
+            to_string(&map(vec![
+                ("a_b", Value::Null),
+                ("a", map(vec![("b", Value::Null)])),
+            ])),
+            Err(ConvertError::DuplicateKey(name)) if name == "A_B"
+        ));
+    }
+}
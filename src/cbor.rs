@@ -0,0 +1,584 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::collections::{HashMap, HashSet};
+
+use half::f16;
+use indexmap::IndexMap;
+use num_bigint::{BigInt, Sign};
+use num_traits::ToPrimitive;
+
+use crate::lossy::Tracker;
+use crate::value::{FloatWidth, Value};
+
+/// The self-describe tag (55799) of CBOR.
+pub const SELF_DESCRIBE_TAG: [u8; 3] = [0xd9, 0xd9, 0xf7];
+
+/// Serialize the value to CBOR bytes, encoding floats in `width`.
+///
+/// Keys of maps are sorted in the same order as `serde_cbor` does, that is,
+/// shorter keys first and then lexically.
+pub fn to_vec(value: &Value, width: FloatWidth) -> Vec<u8> {
+    to_vec_tracked(value, width, &mut Tracker::new())
+}
+
+/// Serialize the value to CBOR bytes like `to_vec`, reporting the floats
+/// which are rounded to `tracker`.
+pub(crate) fn to_vec_tracked(value: &Value, width: FloatWidth, tracker: &mut Tracker) -> Vec<u8> {
+    let mut buf = Vec::new();
+    encode(&mut buf, value, width, tracker);
+
+    buf
+}
+
+fn write_head(buf: &mut Vec<u8>, major: u8, arg: u64) {
+    let major = major << 5;
+    if arg < 24 {
+        buf.push(major | arg as u8);
+    } else if arg <= u8::MAX.into() {
+        buf.push(major | 24);
+        buf.push(arg as u8);
+    } else if arg <= u16::MAX.into() {
+        buf.push(major | 25);
+        buf.extend_from_slice(&(arg as u16).to_be_bytes());
+    } else if arg <= u32::MAX.into() {
+        buf.push(major | 26);
+        buf.extend_from_slice(&(arg as u32).to_be_bytes());
+    } else {
+        buf.push(major | 27);
+        buf.extend_from_slice(&arg.to_be_bytes());
+    }
+}
+
+fn write_text(buf: &mut Vec<u8>, str: &str) {
+    write_head(buf, 3, str.len() as u64);
+    buf.extend_from_slice(str.as_bytes());
+}
+
+/// Write the float in `width`, and return the value which is written.
+fn write_float(buf: &mut Vec<u8>, float: f64, width: FloatWidth) -> f64 {
+    let width = match width {
+        FloatWidth::Shortest if float.is_nan() => FloatWidth::Half,
+        FloatWidth::Shortest if f64::from(float as f32) != float => FloatWidth::Double,
+        FloatWidth::Shortest if f32::from(f16::from_f64(float)) != float as f32 => {
+            FloatWidth::Single
+        }
+        FloatWidth::Shortest => FloatWidth::Half,
+        width => width,
+    };
+
+    match width {
+        FloatWidth::Half => {
+            let half = if float.is_nan() {
+                f16::NAN
+            } else {
+                f16::from_f64(float)
+            };
+            buf.push(0xf9);
+            buf.extend_from_slice(&half.to_bits().to_be_bytes());
+
+            half.into()
+        }
+        FloatWidth::Single => {
+            buf.push(0xfa);
+            buf.extend_from_slice(&(float as f32).to_bits().to_be_bytes());
+
+            (float as f32).into()
+        }
+        FloatWidth::Double | FloatWidth::Shortest => {
+            buf.push(0xfb);
+            buf.extend_from_slice(&float.to_bits().to_be_bytes());
+
+            float
+        }
+    }
+}
+
+fn encode(buf: &mut Vec<u8>, value: &Value, width: FloatWidth, tracker: &mut Tracker) {
+    match value {
+        Value::Null => buf.push(0xf6),
+        Value::Bool(false) => buf.push(0xf4),
+        Value::Bool(true) => buf.push(0xf5),
+        Value::Integer(int) => match (int.as_u64(), int.as_i64()) {
+            (Some(uint), _) => write_head(buf, 0, uint),
+            (_, Some(sint)) => write_head(buf, 1, !sint as u64),
+            _ => write_big_integer(buf, &int.to_bigint()),
+        },
+        Value::Float(float) => {
+            let written = write_float(buf, *float, width);
+            tracker.compare(value, &Value::Float(written));
+        }
+        Value::String(str) => write_text(buf, str),
+        Value::Array(arr) => {
+            write_head(buf, 4, arr.len() as u64);
+            for (i, v) in arr.iter().enumerate() {
+                tracker.nest(&i.to_string(), |tracker| encode(buf, v, width, tracker));
+            }
+        }
+        Value::Map(map) => {
+            write_head(buf, 5, map.len() as u64);
+            for (k, v) in sorted_entries(map) {
+                write_text(buf, k);
+                tracker.nest(k, |tracker| encode(buf, v, width, tracker));
+            }
+        }
+    }
+}
+
+/// Write the integer out of the range of both `i64` and `u64`.
+///
+/// Negative integers down to -2^64 are integers of CBOR, and others are
+/// bignums (tag 2 or 3).
+fn write_big_integer(buf: &mut Vec<u8>, int: &BigInt) {
+    let negative = int.sign() == Sign::Minus;
+    // The argument of a negative integer is -1 minus the value.
+    let arg = if negative {
+        BigInt::from(-1) - int
+    } else {
+        int.clone()
+    };
+    let major = u8::from(negative);
+    if let Some(arg) = arg.to_u64() {
+        return write_head(buf, major, arg);
+    }
+
+    let bytes = arg.magnitude().to_bytes_be();
+    write_head(buf, 6, u64::from(major) + 2);
+    write_head(buf, 2, bytes.len() as u64);
+    buf.extend_from_slice(&bytes);
+}
+
+/// Sort the entries of the map in the same order as `serde_cbor` does.
+fn sorted_entries(map: &IndexMap<String, Value>) -> Vec<(&String, &Value)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+
+    entries
+}
+
+/// Get the length of the head whose argument is `arg`.
+const fn head_len(arg: u64) -> usize {
+    match arg {
+        0..=23 => 1,
+        24..=0xff => 2,
+        0x100..=0xffff => 3,
+        0x1_0000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+/// The statistics of the keys of maps in CBOR.
+#[derive(Debug, Default, PartialEq)]
+pub struct KeyStats {
+    /// The number of keys.
+    pub count: usize,
+    /// The number of distinct keys.
+    pub distinct: usize,
+    /// The size of the keys in bytes.
+    pub bytes: usize,
+    /// The size of the keys in bytes if they were shared by stringref (tags
+    /// 25 and 256), including the tag which enables it.
+    pub shared_bytes: usize,
+}
+
+/// Count the keys of the value as `to_vec` writes them, and estimate their
+/// size if repeated keys were shared by stringref.
+///
+/// As stringref does, the first occurrence of a key is written as is and is
+/// added to the table only if it is longer than a reference to it, and later
+/// occurrences are written as references. Strings other than keys are not
+/// shared.
+pub fn key_stats(value: &Value) -> KeyStats {
+    let mut stats = KeyStats {
+        // Tag 256 marks the scope of the table.
+        shared_bytes: 3,
+        ..KeyStats::default()
+    };
+    let mut table = HashMap::new();
+    let mut distinct = HashSet::new();
+    count_keys(value, &mut table, &mut distinct, &mut stats);
+    stats.distinct = distinct.len();
+
+    stats
+}
+
+fn count_keys<'a>(
+    value: &'a Value,
+    table: &mut HashMap<&'a str, usize>,
+    distinct: &mut HashSet<&'a str>,
+    stats: &mut KeyStats,
+) {
+    match value {
+        Value::Array(arr) => {
+            for v in arr {
+                count_keys(v, table, distinct, stats);
+            }
+        }
+        Value::Map(map) => {
+            for (k, v) in sorted_entries(map) {
+                let len = head_len(k.len() as u64) + k.len();
+                stats.count += 1;
+                stats.bytes += len;
+                distinct.insert(k.as_str());
+                match table.get(k.as_str()) {
+                    // Tag 25 followed by the index.
+                    Some(&index) => stats.shared_bytes += 2 + head_len(index as u64),
+                    None => {
+                        stats.shared_bytes += len;
+                        let min_len = match table.len() {
+                            0..=23 => 3,
+                            24..=0xff => 4,
+                            0x100..=0xffff => 5,
+                            0x1_0000..=0xffff_ffff => 7,
+                            _ => 11,
+                        };
+                        if k.len() >= min_len {
+                            table.insert(k.as_str(), table.len());
+                        }
+                    }
+                }
+                count_keys(v, table, distinct, stats);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The maximum depth of nested data items which `find_undefined` walks.
+const MAX_DEPTH: usize = 256;
+
+/// Find the first `undefined` (simple value 23) in CBOR bytes.
+///
+/// Returns the byte offset of it, or `None` if the input does not contain it
+/// or is not well-formed.
+pub fn find_undefined(input: &[u8]) -> Option<usize> {
+    match skip_item(input, 0, 0) {
+        Err(Stop::Undefined(offset)) => Some(offset),
+        _ => None,
+    }
+}
+
+/// Replace invalid UTF-8 sequences in text strings of CBOR bytes with U+FFFD.
+///
+/// Both keys and values of maps are replaced. Returns `None` if the input is
+/// not well-formed.
+pub fn replace_invalid_utf8(input: &[u8]) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len());
+    rewrite_item(input, 0, 0, &mut output).ok()?;
+
+    Some(output)
+}
+
+enum Stop {
+    Undefined(usize),
+    Break(usize),
+    Malformed,
+}
+
+/// Read the argument of the head at `pos`.
+///
+/// Returns the major type, the argument (`None` if indefinite) and the
+/// position after the head.
+fn read_head(input: &[u8], pos: usize) -> Result<(u8, u8, Option<u64>, usize), Stop> {
+    let initial = *input.get(pos).ok_or(Stop::Malformed)?;
+    let (major, info) = (initial >> 5, initial & 0x1f);
+    let len = match info {
+        0..=23 => return Ok((major, info, Some(info.into()), pos + 1)),
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        31 => return Ok((major, info, None, pos + 1)),
+        _ => return Err(Stop::Malformed),
+    };
+    let bytes = input.get(pos + 1..pos + 1 + len).ok_or(Stop::Malformed)?;
+    let arg = bytes.iter().fold(0, |arg, &b| (arg << 8) | u64::from(b));
+
+    Ok((major, info, Some(arg), pos + 1 + len))
+}
+
+/// Skip the data item at `pos`, and return the position after it.
+fn skip_item(input: &[u8], pos: usize, depth: usize) -> Result<usize, Stop> {
+    if depth > MAX_DEPTH {
+        return Err(Stop::Malformed);
+    }
+
+    let (major, info, arg, next) = read_head(input, pos)?;
+    match (major, arg) {
+        (0 | 1, Some(_)) => Ok(next),
+        (2 | 3, Some(len)) => {
+            let len = usize::try_from(len).map_err(|_| Stop::Malformed)?;
+            let end = next.checked_add(len).ok_or(Stop::Malformed)?;
+            if end > input.len() {
+                return Err(Stop::Malformed);
+            }
+
+            Ok(end)
+        }
+        (2..=5, None) => {
+            let mut next = next;
+            loop {
+                match skip_item(input, next, depth + 1) {
+                    Ok(pos) => next = pos,
+                    Err(Stop::Break(pos)) => return Ok(pos),
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        (4, Some(len)) => (0..len).try_fold(next, |pos, _| skip_item(input, pos, depth + 1)),
+        (5, Some(len)) => (0..len).try_fold(next, |pos, _| {
+            let pos = skip_item(input, pos, depth + 1)?;
+            skip_item(input, pos, depth + 1)
+        }),
+        (6, Some(_)) => skip_item(input, next, depth + 1),
+        (7, None) => Err(Stop::Break(next)),
+        (7, Some(_)) if info == 23 => Err(Stop::Undefined(pos)),
+        (7, Some(_)) => Ok(next),
+        _ => Err(Stop::Malformed),
+    }
+}
+
+/// Copy the data item at `pos` to `output` replacing invalid UTF-8 sequences in
+/// text strings, and return the position after it.
+fn rewrite_item(
+    input: &[u8],
+    pos: usize,
+    depth: usize,
+    output: &mut Vec<u8>,
+) -> Result<usize, Stop> {
+    if depth > MAX_DEPTH {
+        return Err(Stop::Malformed);
+    }
+
+    let (major, _, arg, next) = read_head(input, pos)?;
+    match (major, arg) {
+        (3, _) => {
+            let (text, end) = read_string(input, pos, 3, depth)?.ok_or(Stop::Malformed)?;
+            match String::from_utf8(text) {
+                Ok(_) => output.extend_from_slice(&input[pos..end]),
+                Err(err) => write_text(output, &String::from_utf8_lossy(err.as_bytes())),
+            }
+
+            Ok(end)
+        }
+        (2..=5, None) => {
+            output.extend_from_slice(&input[pos..next]);
+            let mut next = next;
+            loop {
+                match rewrite_item(input, next, depth + 1, output) {
+                    Ok(pos) => next = pos,
+                    Err(Stop::Break(pos)) => {
+                        output.push(0xff);
+                        return Ok(pos);
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        (4, Some(len)) => {
+            output.extend_from_slice(&input[pos..next]);
+            (0..len).try_fold(next, |pos, _| rewrite_item(input, pos, depth + 1, output))
+        }
+        (5, Some(len)) => {
+            output.extend_from_slice(&input[pos..next]);
+            (0..len).try_fold(next, |pos, _| {
+                let pos = rewrite_item(input, pos, depth + 1, output)?;
+                rewrite_item(input, pos, depth + 1, output)
+            })
+        }
+        (6, Some(_)) => {
+            output.extend_from_slice(&input[pos..next]);
+            rewrite_item(input, next, depth + 1, output)
+        }
+        (7, None) => Err(Stop::Break(next)),
+        _ => {
+            let end = match skip_item(input, pos, depth) {
+                Err(Stop::Undefined(_)) => next,
+                end => end?,
+            };
+            output.extend_from_slice(&input[pos..end]);
+            Ok(end)
+        }
+    }
+}
+
+/// Read the byte string (major type 2) or the text string (major type 3) at
+/// `pos`, and return its content and the position after it.
+///
+/// Returns `None` if the data item is not of `major`.
+fn read_string(
+    input: &[u8],
+    pos: usize,
+    major: u8,
+    depth: usize,
+) -> Result<Option<(Vec<u8>, usize)>, Stop> {
+    let mut bytes = Vec::new();
+    let end = match read_head(input, pos)? {
+        (m, _, Some(_), start) if m == major => {
+            let end = skip_item(input, pos, depth)?;
+            bytes.extend_from_slice(&input[start..end]);
+
+            end
+        }
+        (m, _, None, mut next) if m == major => loop {
+            match read_head(input, next)? {
+                (7, _, None, end) => break end,
+                (m, _, Some(_), start) if m == major => {
+                    let end = skip_item(input, next, depth + 1)?;
+                    bytes.extend_from_slice(&input[start..end]);
+                    next = end;
+                }
+                _ => return Err(Stop::Malformed),
+            }
+        },
+        _ => return Ok(None),
+    };
+
+    Ok(Some((bytes, end)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_cbor::Value as Cbor;
+
+    use super::*;
+
+    #[test]
+    fn undefined() {
+        assert_eq!(find_undefined(&[0xf7]), Some(0));
+        assert_eq!(find_undefined(&[0x82, 0xf6, 0xf7]), Some(2));
+        assert_eq!(find_undefined(&[0xa1, 0x61, 0x61, 0xf7]), Some(3));
+        assert_eq!(find_undefined(&[0x9f, 0x41, 0xf7, 0xf7, 0xff]), Some(3));
+        assert_eq!(find_undefined(&[0xd8, 0x20, 0xf7]), Some(2));
+        assert_eq!(find_undefined(&[0xf9, 0x00, 0xf7]), None);
+        assert_eq!(find_undefined(&[0x82, 0xf6, 0xf6]), None);
+        assert_eq!(find_undefined(&[0x82, 0xf6]), None);
+        assert_eq!(
+            find_undefined(&[0x5b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]),
+            None
+        );
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        assert_eq!(
+            replace_invalid_utf8(&[0xa1, 0x61, 0xff, 0x62, 0xc3, 0x28]).unwrap(),
+            [0xa1, 0x63, 0xef, 0xbf, 0xbd, 0x64, 0xef, 0xbf, 0xbd, 0x28]
+        );
+        assert_eq!(
+            replace_invalid_utf8(&[0x7f, 0x61, 0xc3, 0x61, 0x28, 0xff]).unwrap(),
+            [0x64, 0xef, 0xbf, 0xbd, 0x28]
+        );
+        assert_eq!(
+            replace_invalid_utf8(&[0x7f, 0x61, 0xc3, 0x61, 0xa9, 0xff]).unwrap(),
+            [0x7f, 0x61, 0xc3, 0x61, 0xa9, 0xff]
+        );
+        assert_eq!(
+            replace_invalid_utf8(&[0x82, 0x7f, 0x61, 0x61, 0xff, 0x42, 0xc3, 0x28]).unwrap(),
+            [0x82, 0x7f, 0x61, 0x61, 0xff, 0x42, 0xc3, 0x28]
+        );
+        assert_eq!(replace_invalid_utf8(&[0x7f, 0x41, 0x61, 0xff]), None);
+    }
+
+    #[test]
+    fn keys() {
+        let record = |id: u64| {
+            Value::Map(
+                vec![
+                    ("id".to_string(), Value::Integer(id.into())),
+                    ("message".to_string(), Value::Null),
+                ]
+                .into_iter()
+                .collect(),
+            )
+        };
+        let value = Value::Array((0..3).map(record).collect());
+
+        assert_eq!(
+            key_stats(&value),
+            KeyStats {
+                count: 6,
+                distinct: 2,
+                bytes: 3 * (3 + 8),
+                // "id" is too short to be shared, and "message" is shared as
+                // the first entry of the table.
+                shared_bytes: 3 + 3 * 3 + 8 + 2 * 3,
+            }
+        );
+        assert_eq!(key_stats(&Value::Null).count, 0);
+    }
+
+    #[test]
+    fn same_as_serde_cbor() {
+        let value = Value::Map(
+            vec![
+                ("key".to_string(), Value::String("value".to_string())),
+                ("a".to_string(), Value::Integer(i64::MIN.into())),
+                ("b".to_string(), Value::Integer(u64::MAX.into())),
+                (
+                    "c".to_string(),
+                    Value::Array(vec![
+                        Value::Null,
+                        Value::Bool(true),
+                        Value::Float(1.5),
+                        Value::Float(0.1),
+                        Value::Float(f64::INFINITY),
+                        Value::Float(f64::NAN),
+                    ]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        assert_eq!(
+            to_vec(&value, FloatWidth::Shortest),
+            serde_cbor::to_vec(&Cbor::from(value)).unwrap()
+        );
+    }
+
+    #[test]
+    fn wide_integers() {
+        assert_eq!(
+            to_vec(
+                &Value::Integer((-(1_i128 << 64)).into()),
+                FloatWidth::Shortest
+            ),
+            [0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(
+            to_vec(&Value::Integer((1_u128 << 64).into()), FloatWidth::Shortest),
+            [0xc2, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            to_vec(&Value::Integer(i128::MIN.into()), FloatWidth::Shortest),
+            [
+                0xc3, 0x50, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                0xff, 0xff, 0xff, 0xff
+            ]
+        );
+    }
+
+    #[test]
+    fn float_width() {
+        let value = Value::Float(1.5);
+
+        assert_eq!(to_vec(&value, FloatWidth::Half), [0xf9, 0x3e, 0x00]);
+        assert_eq!(
+            to_vec(&value, FloatWidth::Single),
+            [0xfa, 0x3f, 0xc0, 0x00, 0x00]
+        );
+        assert_eq!(
+            to_vec(&value, FloatWidth::Double),
+            [0xfb, 0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+        assert_eq!(to_vec(&value, FloatWidth::Shortest), [0xf9, 0x3e, 0x00]);
+        assert_eq!(
+            to_vec(&Value::Float(0.1), FloatWidth::Shortest),
+            [0xfb, 0x3f, 0xb9, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a]
+        );
+    }
+}
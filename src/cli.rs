@@ -4,16 +4,22 @@
 // Copyright (C) 2021 Shun Sakai
 //
 
+use std::ffi::OsStr;
 use std::io;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use clap::{crate_name, AppSettings, ArgEnum, IntoApp, Parser};
 use clap_complete::Shell;
 
-use crate::config::Config;
+use dsconv::value::{
+    ByteStringMode, Color, Depth, Encoding, FloatFormat, FloatWidth, Format, InputFormat, KeyCase,
+    KeyConflict, OutputFormat, ParseErrorPolicy, SortScope, TextEncoding, TimeZone, Transform,
+};
+
+use crate::config::{Config, Profile, SortKeys};
 use crate::long_version;
-use crate::value::{Color, InputFormat, OutputFormat};
 
 #[derive(Parser)]
 #[clap(
@@ -27,17 +33,110 @@ pub struct Opt {
     /// Specify input format.
     ///
     /// This option can be omitted if the input file is specified and <FORMAT>
-    /// can be determined from the filename extension.
+    /// can be determined from the filename extension, or if the input is CBOR,
+    /// MessagePack or Parquet. Parquet requires dsconv built with the
+    /// `parquet` feature.
     #[clap(short, long, value_name = "FORMAT", arg_enum, ignore_case = true)]
     pub from: Option<InputFormat>,
 
     /// Specify output format.
     ///
     /// This option can be omitted if the output file is specified and <FORMAT>
-    /// can be determined from the filename extension.
+    /// can be determined from the filename extension. Parquet requires dsconv
+    /// built with the `parquet` feature.
     #[clap(short, long, value_name = "FORMAT", arg_enum, ignore_case = true)]
     pub to: Option<OutputFormat>,
 
+    /// Apply the options of the profile <NAME> in the config file.
+    ///
+    /// Options specified on the command line take precedence over the
+    /// profile.
+    #[clap(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Describe the known lossy conversions before converting.
+    ///
+    /// The description is based on the input and output formats, and is
+    /// printed to stderr.
+    #[clap(long)]
+    pub explain: bool,
+
+    /// Fail instead of converting if information would be lost.
+    ///
+    /// Every node which would be dropped or transformed by the conversion,
+    /// such as a float rounded to a narrower width, is reported with its JSON
+    /// Pointer.
+    #[clap(long)]
+    pub fail_on_lossy: bool,
+
+    /// Warn about each number whose value or type would change.
+    ///
+    /// Integers and floats which would be changed by the conversion, such as
+    /// a float rounded to a narrower width, are reported to stderr with their
+    /// JSON Pointers. The conversion continues.
+    #[clap(long)]
+    pub warn_number_loss: bool,
+
+    /// Specify how to handle conflicts of map keys.
+    ///
+    /// This policy applies wherever two entries with the same key meet in a
+    /// map, such as duplicate keys in the input or duplicate headers of CSV
+    /// and TSV. Repeated keys of URL-encoded forms and repeated directives of
+    /// nginx configurations are not conflicts, they become arrays.
+    #[clap(
+        long,
+        value_name = "POLICY",
+        arg_enum,
+        ignore_case = true,
+        default_value_t
+    )]
+    pub on_key_conflict: KeyConflict,
+
+    /// Specify how to handle malformed records of NDJSON, JSON text sequences,
+    /// CSV and TSV.
+    ///
+    /// A malformed record is a line of NDJSON or a text of a JSON text
+    /// sequence which is not valid JSON, or a record of CSV or TSV with the
+    /// wrong number of fields or invalid UTF-8.
+    /// The number of skipped or replaced records is reported to stderr unless
+    /// `--quiet` is specified.
+    #[clap(
+        long,
+        value_name = "POLICY",
+        arg_enum,
+        ignore_case = true,
+        default_value_t
+    )]
+    pub on_parse_error: ParseErrorPolicy,
+
+    /// Replace invalid UTF-8 sequences in the input with U+FFFD.
+    ///
+    /// This applies to both keys and values of CBOR and MessagePack, and to
+    /// the whole input of text formats. By default, invalid UTF-8 sequences
+    /// are an error.
+    #[clap(long)]
+    pub lossy_utf8: bool,
+
+    /// Specify how byte strings in the input are converted.
+    ///
+    /// <MODE> is `error` (fail the conversion, default), `base64` (Base64
+    /// strings) or `hexdump` (space-separated hex digits for inspection).
+    /// `hexdump` shows only the first 32 bytes of longer byte strings followed
+    /// by their length, so it is lossy. This applies to both keys and values
+    /// of CBOR and MessagePack, and to binary values of Parquet.
+    #[clap(long, value_name = "MODE", arg_enum, default_value_t)]
+    pub bytes: ByteStringMode,
+
+    /// Print the detected input format and exit.
+    ///
+    /// The format is determined from the filename extension, or from the
+    /// content if the input is CBOR, MessagePack or Parquet. Only the leading
+    /// bytes of the content are read, unless the input starts with an array,
+    /// which both CBOR and MessagePack can start with. If multiple files are
+    /// specified, each line is prefixed with the filename.
+    #[clap(long, conflicts_with_all = &["from", "stream"])]
+    pub detect_only: bool,
+
     /// List supported input formats.
     #[clap(long, conflicts_with = "list-output-formats")]
     pub list_input_formats: bool,
@@ -50,14 +149,248 @@ pub struct Opt {
     #[clap(short, long, value_name = "FILE", conflicts_with = "color")]
     pub output: Option<PathBuf>,
 
+    /// Write each entry of the root map to its own file in `--output-dir`.
+    ///
+    /// Each file is named after the key with the extension of the output
+    /// format, such as `web.json`. The root must be a map.
+    #[clap(long, requires = "output-dir")]
+    pub explode: bool,
+
+    /// Output the files of `--explode` to <DIR>.
+    ///
+    /// <DIR> is created if it does not exist.
+    #[clap(
+        long,
+        value_name = "DIR",
+        requires = "explode",
+        conflicts_with_all = &["output", "assert-equal-to"]
+    )]
+    pub output_dir: Option<PathBuf>,
+
+    /// Wrap a scalar at the root in the collection which the output format
+    /// requires.
+    ///
+    /// If the output format requires a map at the root, such as TOML, the
+    /// scalar is wrapped in a map with <KEY>. If it requires an array, such as
+    /// lines, the scalar is wrapped in an array. Otherwise, this option has no
+    /// effect. Without this option, a scalar at the root of such a format is an
+    /// error.
+    #[clap(long, value_name = "KEY")]
+    pub wrap: Option<String>,
+
+    /// Merge the output into the existing file of `--output`.
+    ///
+    /// If the file exists, it is read in the output format and the converted
+    /// value is merged into it recursively. Maps are merged key by key, and
+    /// other values, including arrays, are replaced by the converted value.
+    /// If the file does not exist, this is the same as not specifying this
+    /// option. This requires `--output`.
+    #[clap(long)]
+    pub merge_into: bool,
+
+    /// Compare the output with <FILE> instead of writing it.
+    ///
+    /// If they differ, every differing line is reported, or the number of
+    /// differing bytes if either is not valid UTF-8, and dsconv exits with a
+    /// non-zero status.
+    #[clap(long, value_name = "FILE", conflicts_with = "output")]
+    pub assert_equal_to: Option<PathBuf>,
+
+    /// Write the types of the input values to <FILE> as JSON.
+    ///
+    /// The written value has the same shape as the input, and each scalar is
+    /// replaced with the name of its type when it was parsed: "null",
+    /// "boolean", "integer", "float" or "string". This is taken after
+    /// `--skip` and `--limit`, and before any other transformations.
+    #[clap(long, value_name = "FILE")]
+    pub types_out: Option<PathBuf>,
+
+    /// Decode the input from <ENCODING> before parsing.
+    ///
+    /// Whitespace and newlines in the input are ignored.
+    #[clap(long, value_name = "ENCODING", arg_enum, ignore_case = true)]
+    pub decode_input: Option<Encoding>,
+
+    /// Encode the output in <ENCODING>.
+    ///
+    /// The encoded output is followed by a newline.
+    #[clap(long, value_name = "ENCODING", arg_enum, ignore_case = true)]
+    pub encode_output: Option<Encoding>,
+
+    /// Write text output in <ENCODING>.
+    ///
+    /// <ENCODING> is a label of the WHATWG Encoding Standard, such as
+    /// `utf-16le`, `shift_jis` or `windows-1252`. Labels are case-insensitive.
+    /// It is an error if the output contains characters which <ENCODING>
+    /// cannot represent. This is applied before `--encode-output`. The output
+    /// of CBOR and MessagePack is not affected.
+    #[clap(long, value_name = "ENCODING", default_value_t)]
+    pub output_encoding: TextEncoding,
+
+    /// Prefix the byte order mark to text output.
+    ///
+    /// This is available when the output encoding is UTF-8, UTF-16LE or
+    /// UTF-16BE. The output of CBOR and MessagePack is not affected.
+    #[clap(long)]
+    pub bom: bool,
+
+    /// Write <TEXT> as a comment at the beginning of the output.
+    ///
+    /// Each line of <TEXT> becomes a line comment. This option is available
+    /// when the output is C header, shell exports, JSONC, RON, TOML or YAML.
+    #[clap(long, value_name = "TEXT")]
+    pub header_comment: Option<String>,
+
+    /// Pipe the output through <COMMAND> before writing it.
+    ///
+    /// <COMMAND> is run by the shell, reads the output from stdin and writes
+    /// the result to stdout. If it exits with a non-zero status, dsconv
+    /// aborts.
+    #[clap(long, value_name = "COMMAND")]
+    pub pipe_through: Option<String>,
+
     /// Output as a pretty-printed string.
     ///
     /// If the value is omitted, it is the same as selecting `true`.
     /// The value of this option is case-sensitive.
-    /// This option is available when the output is JSON or TOML.
+    /// This option is available when the output is JSON, RON or TOML.
     #[clap(short, long, value_name = "BOOLEAN", possible_values = &["true", "false"])]
     pub pretty: Option<Option<bool>>,
 
+    /// Convert integers which JavaScript cannot represent exactly to strings.
+    ///
+    /// Integers whose absolute value is greater than 2^53 - 1 are written as
+    /// strings. Other integers are left as numbers.
+    /// This option is available when the output is JSON, NDJSON or a JSON
+    /// text sequence.
+    #[clap(long)]
+    pub js_safe_integers: bool,
+
+    /// Write arrays which consist only of scalars on a single line.
+    ///
+    /// Maps and arrays which contain collections are still written one entry
+    /// per line.
+    /// This option is available when the output is pretty-printed JSON or
+    /// YAML.
+    #[clap(long)]
+    pub compact_scalar_arrays: bool,
+
+    /// Output canonical JSON as defined by RFC 8785.
+    ///
+    /// Keys are sorted, no whitespace is written and numbers are written as
+    /// ECMAScript does. The output is not followed by a newline, so it can be
+    /// signed as is. This option is available when the output is JSON.
+    #[clap(long, conflicts_with = "pretty")]
+    pub jcs: bool,
+
+    /// Specify how floats are written.
+    ///
+    /// Floats are written as Go, Python or JavaScript writes them, so the
+    /// output can be compared with the output of these languages. Integral
+    /// floats may lose the fractional part. By default, the shortest
+    /// representation is written. This option is available when the output
+    /// is JSON, NDJSON, JSON text sequence or YAML.
+    #[clap(long, value_name = "FORMAT", arg_enum, conflicts_with = "jcs")]
+    pub float_format: Option<FloatFormat>,
+
+    /// Write collections nested deeper than <N> levels on a single line.
+    ///
+    /// This option is available when the output is pretty-printed RON.
+    #[clap(long, value_name = "N")]
+    pub ron_depth_limit: Option<usize>,
+
+    /// Convert the elements of arrays with mixed types to strings.
+    ///
+    /// TOML requires the elements of an array to be the same type. By default,
+    /// such an array is an error.
+    /// This option is available when the output is TOML.
+    #[clap(long)]
+    pub toml_stringify_mixed_arrays: bool,
+
+    /// Use literal strings of TOML for strings which need escapes.
+    ///
+    /// Strings containing backslashes or quotes are emitted in single quotes
+    /// if possible. Pretty-printed TOML always does this.
+    /// This option is available when the output is TOML.
+    #[clap(long)]
+    pub toml_literal_strings: bool,
+
+    /// Write arrays with more than <N> elements one element per line in TOML.
+    ///
+    /// Other arrays are written on a single line, even if `--pretty` is
+    /// specified. Arrays of tables are not affected.
+    /// This option is available when the output is TOML.
+    #[clap(long, value_name = "N")]
+    pub toml_array_wrap: Option<usize>,
+
+    /// Reject undefined in CBOR.
+    ///
+    /// By default, undefined in the input is converted to null.
+    #[clap(long)]
+    pub cbor_strict_undefined: bool,
+
+    /// Convert bignums (tags 2 and 3) in CBOR to integers.
+    ///
+    /// By default, bignums are read as byte strings, which is an error.
+    #[clap(long)]
+    pub preserve_cbor_bignum: bool,
+
+    /// Keep numbers in JSON which cannot be represented exactly as floats as
+    /// strings.
+    ///
+    /// By default, such numbers are rounded to the nearest float, and numbers
+    /// which are out of range of floats are an error. Keeping them is opt-in
+    /// because it changes numbers into strings in the output, which is not
+    /// what most data expects.
+    #[clap(long)]
+    pub preserve_json_decimal: bool,
+
+    /// Specify the width of floats in CBOR.
+    ///
+    /// If `shortest` is selected, each float is encoded in the smallest width
+    /// that represents it without loss. Other widths round floats as needed.
+    /// This option is available when the output is CBOR.
+    #[clap(long, value_name = "WIDTH", arg_enum)]
+    pub cbor_float_width: Option<FloatWidth>,
+
+    /// Prefix the self-describe tag of CBOR to the output.
+    ///
+    /// The output starts with the bytes `D9 D9 F7` (tag 55799), which mark it
+    /// as CBOR. This option is available when the output is CBOR.
+    #[clap(long)]
+    pub cbor_self_describe: bool,
+
+    /// Specify the number of spaces for each indentation level of YAML.
+    ///
+    /// This option is available when the output is YAML.
+    #[clap(long, value_name = "N", default_value = "2")]
+    pub yaml_indent: NonZeroUsize,
+
+    /// Write maps which consist of `__tag` and `__value` as tagged nodes of
+    /// YAML.
+    ///
+    /// For example, `{"__tag": "Ref", "__value": "a"}` is written as
+    /// `!Ref a`. This option is available when the output is YAML.
+    #[clap(long)]
+    pub yaml_tags: bool,
+
+    /// Write multi-line strings as literal block scalars of YAML.
+    ///
+    /// For example, "a\nb\n" is written as `|` followed by the indented lines.
+    /// Strings which start with whitespace or contain control characters are
+    /// quoted as usual. This option is available when the output is YAML.
+    #[clap(long)]
+    pub yaml_literal_block: bool,
+
+    /// Warn about bare scalars in YAML which YAML 1.1 reads as booleans.
+    ///
+    /// Plain scalars such as `no`, `yes`, `on` and `off` are read as strings,
+    /// but other parsers may read them as booleans. Quote them in the input to
+    /// silence the warning, or use `--coerce-bools` to convert them to
+    /// booleans. This option is available when the input is YAML.
+    #[clap(long)]
+    pub tolerant_yaml: bool,
+
     /// Specify when to use colored output.
     #[clap(
         long,
@@ -68,9 +401,500 @@ pub struct Opt {
     )]
     pub color: Color,
 
+    /// Escape C0 control characters in the output as `\uXXXX` when stdout is
+    /// a terminal.
+    ///
+    /// This keeps control characters in untrusted data from garbling the
+    /// terminal. Tabs and line feeds are kept. Output to files and pipes is
+    /// not changed. This option is available when the output is a text format
+    /// encoded in UTF-8.
+    #[clap(long)]
+    pub escape_control: bool,
+
+    /// Fail if any of the keys do not exist in the root map.
+    ///
+    /// This is checked before any transforms. Multiple keys are separated by
+    /// commas.
+    #[clap(
+        long,
+        value_name = "KEY",
+        use_delimiter = true,
+        multiple_occurrences = true
+    )]
+    pub required_keys: Vec<String>,
+
+    /// Check `--required-keys` in the map at <POINTER> instead of the root.
+    ///
+    /// <POINTER> is a JSON Pointer to the map.
+    #[clap(long, value_name = "POINTER", requires = "required-keys")]
+    pub required_keys_at: Option<String>,
+
+    /// Apply the transforms in the specified order.
+    ///
+    /// Transforms which are not specified follow in the default order, which
+    /// is group-by, ungroup, fold-keys, strip-prefix, key-order, dedupe-array,
+    /// sort-arrays, trim-strings, replace, replace-regex, max-string-length,
+    /// stringify-under, coerce-bools, parse-radix, datetime-tz, null-as,
+    /// flatten-arrays, arrays-as-maps, maps-as-arrays, delete and set.
+    /// fold-keys includes `--lowercase-keys` and `--uppercase-keys`.
+    /// Specifying a transform here does not enable it. Multiple transforms are
+    /// separated by commas.
+    #[clap(
+        long,
+        value_name = "TRANSFORM",
+        arg_enum,
+        ignore_case = true,
+        use_delimiter = true,
+        multiple_occurrences = true
+    )]
+    pub transform_order: Vec<Transform>,
+
+    /// Reshape an array of maps into a map keyed by the value of <KEY>.
+    ///
+    /// Each element is stored under the value of its <KEY> converted to a
+    /// string. Duplicate keys are handled by `--on-key-conflict`.
+    #[clap(long, value_name = "KEY")]
+    pub group_by: Option<String>,
+
+    /// Remove <KEY> from each element when using `--group-by`.
+    #[clap(long, requires = "group-by")]
+    pub drop_group_key: bool,
+
+    /// Expand a map of maps into an array of maps.
+    ///
+    /// The key of each entry is stored as <KEY> of the element. It is an error
+    /// if the element already has <KEY>.
+    #[clap(long, value_name = "KEY", conflicts_with = "group-by")]
+    pub ungroup: Option<String>,
+
+    /// Convert keys of maps to <CASE>, and merge the keys which become the
+    /// same.
+    ///
+    /// If the value is omitted, it is the same as selecting `lower`. The merged
+    /// keys are handled by `--on-key-conflict`.
+    #[clap(long, value_name = "CASE", arg_enum, ignore_case = true)]
+    pub fold_keys: Option<Option<KeyCase>>,
+
+    /// Convert keys of maps to lowercase, and merge the keys which become the
+    /// same.
+    ///
+    /// This is the same as `--fold-keys lower`.
+    #[clap(long, conflicts_with_all = &["fold-keys", "uppercase-keys"])]
+    pub lowercase_keys: bool,
+
+    /// Convert keys of maps to uppercase, and merge the keys which become the
+    /// same.
+    ///
+    /// This is the same as `--fold-keys upper`.
+    #[clap(long, conflicts_with = "fold-keys")]
+    pub uppercase_keys: bool,
+
+    /// Remove <PREFIX> from keys of the root map.
+    ///
+    /// Keys which do not start with <PREFIX> are left as they are. The keys
+    /// which become the same are handled by `--on-key-conflict`.
+    #[clap(long, value_name = "PREFIX")]
+    pub strip_prefix: Option<String>,
+
+    /// Remove the prefix of `--strip-prefix` from keys of all maps.
+    #[clap(long, requires = "strip-prefix")]
+    pub strip_prefix_recursive: bool,
+
+    /// Move the listed keys to the front of each map in the given order.
+    ///
+    /// Other keys follow in the original order. Multiple keys are separated by
+    /// commas.
+    #[clap(
+        long,
+        value_name = "KEY",
+        use_delimiter = true,
+        multiple_occurrences = true
+    )]
+    pub key_order: Vec<String>,
+
+    /// Sort keys of maps lexically when the output is in <SCOPE>.
+    ///
+    /// <SCOPE> is `all`, `binary` (CBOR and MessagePack) or `text` (the other
+    /// formats). If the value is omitted, it is the same as selecting `all`.
+    /// Keys of CBOR maps are always sorted shorter keys first.
+    #[clap(long, value_name = "SCOPE", arg_enum, ignore_case = true)]
+    pub sort_keys: Option<Option<SortScope>>,
+
+    /// Make the output reproducible for hashing or committing.
+    ///
+    /// Keys of maps are sorted in all formats unless `--sort-keys` is
+    /// specified. JSON is written as canonical JSON as `--jcs` does unless
+    /// `--sort-keys`, `--pretty` or `--float-format` is specified. Other
+    /// output is the same as without this option.
+    #[clap(long)]
+    pub deterministic: bool,
+
+    /// Remove duplicate elements from all arrays.
+    ///
+    /// Elements are compared structurally and the first occurrences are kept
+    /// in the original order.
+    #[clap(long)]
+    pub dedupe_array: bool,
+
+    /// Remove duplicate elements from the array at <POINTER>.
+    ///
+    /// <POINTER> is a JSON Pointer to the array. This option can be specified
+    /// multiple times.
+    #[clap(long, value_name = "POINTER", multiple_occurrences = true)]
+    pub dedupe_array_at: Vec<String>,
+
+    /// Sort the elements of all arrays in the canonical order.
+    ///
+    /// Null, booleans, numbers, strings, arrays and maps come in this order.
+    /// Values of the same type are compared by their values, arrays element by
+    /// element, and maps by their entries sorted by key. Nested arrays are
+    /// sorted first, so the output does not depend on the order of the input
+    /// arrays.
+    #[clap(long)]
+    pub sort_arrays: bool,
+
+    /// Sort the elements of the array at <POINTER> and all arrays in it in the
+    /// canonical order.
+    ///
+    /// <POINTER> is a JSON Pointer to the array. This option can be specified
+    /// multiple times.
+    #[clap(long, value_name = "POINTER", multiple_occurrences = true)]
+    pub sort_arrays_at: Vec<String>,
+
+    /// Remove leading and trailing whitespace from strings.
+    #[clap(long)]
+    pub trim_strings: bool,
+
+    /// Also replace each run of whitespace inside strings with a single space.
+    #[clap(long, requires = "trim-strings")]
+    pub collapse_whitespace: bool,
+
+    /// Also apply `--trim-strings` to keys of maps.
+    ///
+    /// The keys which become the same are handled by `--on-key-conflict`.
+    #[clap(long, requires = "trim-strings")]
+    pub trim_keys: bool,
+
+    /// Replace <FROM> with <TO> in strings.
+    ///
+    /// <FROM> is a literal string. This option can be specified multiple times,
+    /// and the replacements are applied in order.
+    #[clap(
+        long,
+        value_names = &["FROM", "TO"],
+        number_of_values = 2,
+        multiple_occurrences = true
+    )]
+    pub replace: Vec<String>,
+
+    /// Replace matches of <REGEX> with <TO> in strings.
+    ///
+    /// <TO> can refer to capture groups such as `$1`. This option can be
+    /// specified multiple times, and the replacements are applied after
+    /// `--replace`.
+    #[clap(
+        long,
+        value_names = &["REGEX", "TO"],
+        number_of_values = 2,
+        multiple_occurrences = true
+    )]
+    pub replace_regex: Vec<String>,
+
+    /// Also apply `--replace` and `--replace-regex` to keys of maps.
+    ///
+    /// The keys which become the same are handled by `--on-key-conflict`.
+    #[clap(long)]
+    pub replace_keys: bool,
+
+    /// Truncate strings longer than <N> characters.
+    ///
+    /// The length is counted by Unicode scalar values and an ellipsis is
+    /// appended to the truncated strings.
+    #[clap(long, value_name = "N")]
+    pub max_string_length: Option<usize>,
+
+    /// Convert booleans, integers and floats under <POINTER> to strings.
+    ///
+    /// <POINTER> is a JSON Pointer to the subtree. This option can be
+    /// specified multiple times.
+    #[clap(long, value_name = "POINTER", multiple_occurrences = true)]
+    pub stringify_under: Vec<String>,
+
+    /// Convert strings such as `yes` and `off` to booleans.
+    ///
+    /// The strings are compared case-insensitively. By default, `true`, `yes`,
+    /// `on` and `1` are converted to true, and `false`, `no`, `off` and `0`
+    /// are converted to false. Keys of maps are left as they are.
+    #[clap(long)]
+    pub coerce_bools: bool,
+
+    /// Specify the strings which `--coerce-bools` converts to true.
+    ///
+    /// Multiple tokens are separated by commas.
+    #[clap(
+        long,
+        value_name = "TOKEN",
+        use_delimiter = true,
+        multiple_occurrences = true,
+        requires = "coerce-bools"
+    )]
+    pub true_tokens: Vec<String>,
+
+    /// Specify the strings which `--coerce-bools` converts to false.
+    ///
+    /// Multiple tokens are separated by commas.
+    #[clap(
+        long,
+        value_name = "TOKEN",
+        use_delimiter = true,
+        multiple_occurrences = true,
+        requires = "coerce-bools"
+    )]
+    pub false_tokens: Vec<String>,
+
+    /// Convert strings such as `0xFF`, `0o17` and `0b1010` to integers.
+    ///
+    /// Only strings with these prefixes are converted. The integers can be out
+    /// of the range of 64-bit integers.
+    #[clap(long)]
+    pub parse_radix: bool,
+
+    /// Convert datetimes with an offset into <TZ>.
+    ///
+    /// <TZ> is `utc`, `local` or an offset such as `+09:00`. Strings of RFC
+    /// 3339 datetimes with an offset are converted, including TOML offset
+    /// datetimes. Local datetimes, dates and times are left as they are.
+    #[clap(long, value_name = "TZ")]
+    pub datetime_tz: Option<TimeZone>,
+
+    /// Replace nulls with <STRING>.
+    ///
+    /// This applies to any output format. <STRING> can be empty.
+    #[clap(long, value_name = "STRING")]
+    pub null_as: Option<String>,
+
+    /// Flatten nested arrays up to <DEPTH> levels.
+    ///
+    /// <DEPTH> is a non-negative integer or `full`. If the value is omitted,
+    /// it is the same as selecting `1`. Maps and scalars are left as they are.
+    #[clap(long, value_name = "DEPTH")]
+    pub flatten_arrays: Option<Option<Depth>>,
+
+    /// Convert arrays into maps keyed by the indices of the elements.
+    ///
+    /// The keys are `0`, `1`, and so on. Nested arrays are also converted.
+    #[clap(long, conflicts_with = "maps-as-arrays")]
+    pub arrays_as_maps: bool,
+
+    /// Convert maps whose keys are the indices from `0` into arrays.
+    ///
+    /// The keys must be exactly `0` to `<N> - 1`, in any order. Other maps,
+    /// including empty maps, are left as they are. This reverses
+    /// `--arrays-as-maps` except for empty arrays.
+    #[clap(long)]
+    pub maps_as_arrays: bool,
+
+    /// Delete the value at <POINTER>.
+    ///
+    /// <POINTER> is a JSON Pointer to a value of a map or an element of an
+    /// array. Subsequent elements of the array are shifted. It is an error if
+    /// the value does not exist. This option can be specified multiple times.
+    #[clap(long, value_name = "POINTER", multiple_occurrences = true)]
+    pub delete: Vec<String>,
+
+    /// Ignore the pointers of `--delete` which do not exist.
+    #[clap(long, requires = "delete")]
+    pub delete_missing_ok: bool,
+
+    /// Set <VALUE> at <POINTER>.
+    ///
+    /// <POINTER> is a JSON Pointer, and <VALUE> is a JSON value, such as
+    /// `/metadata/version="1.2.3"`. Intermediate maps are created as needed,
+    /// but elements of arrays cannot be created. Since <POINTER> can contain
+    /// `=`, the first `=` which is followed by a valid JSON value separates
+    /// them. This option can be specified multiple times.
+    #[clap(long, value_name = "POINTER=VALUE", multiple_occurrences = true)]
+    pub set: Vec<String>,
+
+    /// Output a JSON Schema inferred from the input instead of converting it.
+    ///
+    /// The schema follows draft-07 and is output as JSON.
+    #[clap(long, conflicts_with = "to")]
+    pub infer_schema: bool,
+
+    /// Output structural statistics of the input as JSON instead of
+    /// converting it.
+    ///
+    /// The statistics are the number of values, the maximum depth, the
+    /// number of values of each type, the lengths of arrays, the number of
+    /// distinct keys and the total size of strings.
+    #[clap(long, conflicts_with_all = &["to", "infer-schema"])]
+    pub stats: bool,
+
+    /// Print the size of the input in each binary output format instead of
+    /// converting it.
+    ///
+    /// The input is converted to each format in memory, and the formats and
+    /// the sizes in bytes are printed as a table. Nothing is written.
+    #[clap(long, conflicts_with_all = &["to", "output", "infer-schema", "stats"])]
+    pub size_report: bool,
+
+    /// Print how many bytes sharing repeated keys would save in CBOR instead
+    /// of converting the input (experimental, report-only).
+    ///
+    /// Repeated keys are counted as if they were replaced with references to
+    /// a key table by stringref (CBOR tags 25 and 256). This only reports the
+    /// potential savings: the keys are not shared yet, and nothing is written.
+    #[clap(
+        long,
+        conflicts_with_all = &["to", "output", "infer-schema", "stats", "size-report"]
+    )]
+    pub minify_keys: bool,
+
+    /// Browse the input in the terminal, and write the selected value in the
+    /// selected format.
+    ///
+    /// The tree is browsed with the arrow keys, and `w` writes the value at the
+    /// current position to a file or stdout. The terminal UI is drawn on
+    /// stderr, and the output to stdout is written after quitting with `q`.
+    /// Transforms are applied before browsing. This requires dsconv built
+    /// with the `interactive` feature.
+    #[clap(
+        long,
+        conflicts_with_all = &[
+            "to",
+            "output",
+            "output-dir",
+            "infer-schema",
+            "stats",
+            "size-report",
+            "minify-keys"
+        ]
+    )]
+    pub interactive: bool,
+
+    /// Print the converted value as pretty-printed JSON to stderr.
+    ///
+    /// This is printed before the output, regardless of the output format.
+    /// The JSON is colored according to `--color`.
+    #[clap(long)]
+    pub inspect: bool,
+
+    /// Print a summary of the applied transforms to stderr.
+    ///
+    /// Each line shows a transform and how many values it affected. This is
+    /// not printed if `--quiet` is specified.
+    #[clap(long)]
+    pub audit: bool,
+
     /// Input from <FILE>.
+    ///
+    /// Multiple files can be specified when using `--collect` or `--concat`.
     #[clap(value_name = "FILE")]
-    pub input: Option<PathBuf>,
+    pub input: Vec<PathBuf>,
+
+    /// Fail if an input is larger than <BYTES>.
+    ///
+    /// This applies to each input file and stdin, and is checked before
+    /// parsing.
+    #[clap(long, value_name = "BYTES")]
+    pub max_file_size: Option<u64>,
+
+    /// Drop the first <N> elements of the root array before conversion.
+    ///
+    /// This is applied before `--limit`. If the root is not an array, this
+    /// option has no effect. With `--stream`, the first <N> records are
+    /// discarded without being converted.
+    #[clap(long, value_name = "N")]
+    pub skip: Option<usize>,
+
+    /// Convert only the first <N> elements of the root array.
+    ///
+    /// If the root is not an array, this option has no effect. If the input
+    /// is NDJSON, a JSON text sequence, CSV or TSV, reading stops after the
+    /// records skipped by `--skip` and <N> records.
+    #[clap(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Convert CSV or TSV to NDJSON one record at a time.
+    ///
+    /// The input is not read into memory at once. Transforms are not applied,
+    /// but `--decode-input` and `--max-file-size` apply to the input as usual.
+    /// This option is available only when the input is CSV or TSV and the
+    /// output is NDJSON.
+    #[clap(
+        long,
+        conflicts_with_all = &["collect", "concat", "assert-equal-to", "types-out"]
+    )]
+    pub stream: bool,
+
+    /// Collect every document of the input into an array.
+    ///
+    /// Each YAML document in a multi-document file is an element. Other
+    /// formats contribute a single element per file.
+    #[clap(long, conflicts_with = "concat")]
+    pub collect: bool,
+
+    /// Concatenate the root arrays of the inputs into an array.
+    ///
+    /// The root of every input must be an array. Unlike `--collect`, the
+    /// arrays are not nested, and maps are not merged.
+    #[clap(long)]
+    pub concat: bool,
+
+    /// Build a map from the environment variables instead of reading the
+    /// input.
+    ///
+    /// Only the variables whose names start with <PREFIX> followed by the
+    /// separator are used if <PREFIX> is specified. The rest of each name is
+    /// split by `--env-separator` into the keys of nested maps, such as
+    /// `APP__DB__HOST` into `{"DB":{"HOST":...}}` with the prefix `APP`. The
+    /// values are strings.
+    #[clap(
+        long,
+        value_name = "PREFIX",
+        conflicts_with_all = &["input", "from", "collect", "concat"]
+    )]
+    pub from_env: Option<Option<String>>,
+
+    /// Output `export` statements of shell which set environment variables.
+    ///
+    /// This is the same as `--to env`, and the names start with <PREFIX>
+    /// followed by the separator if <PREFIX> is specified. Nested maps and
+    /// arrays are flattened, joining the keys and the indices with
+    /// `--env-separator`, such as `{"db":{"host":...}}` into `DB__HOST`. Names
+    /// are upper-cased, and values are single-quoted.
+    #[clap(
+        long,
+        value_name = "PREFIX",
+        conflicts_with_all = &[
+            "to",
+            "infer-schema",
+            "stats",
+            "size-report",
+            "minify-keys",
+            "interactive"
+        ]
+    )]
+    pub to_env: Option<Option<String>>,
+
+    /// Separate the keys of nested maps by <SEPARATOR> in the names of
+    /// environment variables.
+    ///
+    /// This applies to `--from-env` and the output of shell exports.
+    /// <SEPARATOR> must not be empty, and it must consist of ASCII letters,
+    /// digits and `_` for the output of shell exports.
+    #[clap(
+        long,
+        value_name = "SEPARATOR",
+        default_value = "__",
+        forbid_empty_values = true
+    )]
+    pub env_separator: String,
+
+    /// Do not show the progress, the summary of `--audit` and warnings.
+    #[clap(short, long)]
+    pub quiet: bool,
 
     /// Generate shell completion.
     ///
@@ -84,19 +908,107 @@ pub struct Opt {
 impl Opt {
     /// Apply the config from the config file.
     pub fn apply_config(mut self) -> Result<Self> {
-        if let Some(path) = Config::path() {
-            let config = Config::read(path)?;
+        let config = Config::path().map(Config::read).transpose()?;
 
-            if let Some(pretty) = config.pretty {
-                if self.pretty.is_none() {
-                    self.pretty = Some(Some(pretty));
-                }
+        if let Some(name) = self.profile.clone() {
+            let profile = config
+                .as_ref()
+                .and_then(|c| c.profile.get(&name))
+                .with_context(|| format!("The profile is not defined: {}", name))?;
+            self.apply_profile(profile)
+                .with_context(|| format!("Failed to apply the profile {}", name))?;
+        }
+
+        if let Some(pretty) = config.and_then(|c| c.pretty) {
+            if self.pretty.is_none() {
+                self.pretty = pretty.get(self.output_format()).map(Some);
             }
         }
 
         Ok(self)
     }
 
+    /// Apply the options of the profile which are not specified on the command
+    /// line.
+    fn apply_profile(&mut self, profile: &Profile) -> Result<()> {
+        if let (None, Some(to)) = (self.to, &profile.to) {
+            self.to = Some(OutputFormat::from_str(to, true).map_err(|e| anyhow!(e))?);
+        }
+        if let (None, Some(pretty)) = (self.pretty, profile.pretty) {
+            self.pretty = Some(Some(pretty));
+        }
+        if let (None, Some(sort_keys)) = (self.sort_keys, &profile.sort_keys) {
+            self.sort_keys = match sort_keys {
+                SortKeys::Enabled(true) => Some(None),
+                SortKeys::Enabled(false) => None,
+                SortKeys::Scope(scope) => {
+                    Some(Some(scope.parse().with_context(|| {
+                        format!("Invalid scope of sort_keys: {}", scope)
+                    })?))
+                }
+            };
+        }
+        if self.key_order.is_empty() {
+            self.key_order = profile.key_order.clone();
+        }
+
+        Ok(())
+    }
+
+    /// Get the output format.
+    ///
+    /// If neither `--to` nor `--to-env` is specified, it is determined from
+    /// the filename extension of the output file.
+    pub fn output_format(&self) -> Option<Format> {
+        if self.to_env.is_some() {
+            return Some(Format::Env);
+        }
+
+        self.to.map(Format::from).or_else(|| {
+            self.output.as_ref().and_then(|o| {
+                o.extension()
+                    .and_then(OsStr::to_str)
+                    .and_then(|e| e.parse().ok())
+            })
+        })
+    }
+
+    /// Get the case which keys of maps are converted to.
+    ///
+    /// This is specified by `--fold-keys`, `--lowercase-keys` or
+    /// `--uppercase-keys`.
+    pub fn key_case(&self) -> Option<KeyCase> {
+        if self.lowercase_keys {
+            Some(KeyCase::Lower)
+        } else if self.uppercase_keys {
+            Some(KeyCase::Upper)
+        } else {
+            self.fold_keys.map(Option::unwrap_or_default)
+        }
+    }
+
+    /// Get the scope of sorting keys.
+    ///
+    /// This is specified by `--sort-keys` or `--deterministic`.
+    pub fn sort_scope(&self) -> Option<SortScope> {
+        match self.sort_keys {
+            Some(scope) => Some(scope.unwrap_or_default()),
+            None => self.deterministic.then(|| SortScope::All),
+        }
+    }
+
+    /// Return `true` if JSON is written as canonical JSON.
+    ///
+    /// This is specified by `--jcs`, or by `--deterministic` if no option
+    /// which canonical JSON ignores is specified.
+    pub fn is_jcs(&self) -> bool {
+        self.jcs
+            || (self.deterministic
+                && self.sort_keys.is_none()
+                && self.float_format.is_none()
+                && !matches!(self.pretty, Some(None | Some(true))))
+    }
+
     /// Generate shell completion to stdout.
     pub fn generate_completion(shell: Shell) {
         clap_complete::generate(
@@ -4,6 +4,7 @@
 // Copyright (C) 2021 Shun Sakai
 //
 
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -12,9 +13,60 @@ use clap::crate_name;
 use directories::ProjectDirs;
 use serde::Deserialize;
 
+use dsconv::value::Format;
+
 #[derive(Deserialize)]
 pub struct Config {
+    pub pretty: Option<Pretty>,
+    #[serde(default)]
+    pub profile: HashMap<String, Profile>,
+}
+
+/// A named set of options selected by `--profile`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub to: Option<String>,
     pub pretty: Option<bool>,
+    pub sort_keys: Option<SortKeys>,
+    #[serde(default)]
+    pub key_order: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum SortKeys {
+    Enabled(bool),
+    Scope(String),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Pretty {
+    All(bool),
+    PerFormat(PerFormat),
+}
+
+/// The settings of `pretty` for each output format.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PerFormat {
+    pub json: Option<bool>,
+    pub ron: Option<bool>,
+    pub toml: Option<bool>,
+}
+
+impl Pretty {
+    /// Get the setting for the output format.
+    pub fn get(&self, format: Option<Format>) -> Option<bool> {
+        match (self, format) {
+            (Self::All(pretty), _) => Some(*pretty),
+            (Self::PerFormat(formats), Some(Format::Json | Format::Jsonc)) => formats.json,
+            (Self::PerFormat(formats), Some(Format::Ron)) => formats.ron,
+            (Self::PerFormat(formats), Some(Format::Toml)) => formats.toml,
+            _ => None,
+        }
+    }
 }
 
 impl Config {
@@ -39,3 +91,46 @@ impl Config {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pretty() {
+        let config: Config = toml::from_str("pretty = true").unwrap();
+        let pretty = config.pretty.unwrap();
+        assert_eq!(pretty.get(Some(Format::Json)), Some(true));
+        assert_eq!(pretty.get(None), Some(true));
+
+        let config: Config = toml::from_str("[pretty]\njson = true\ntoml = false").unwrap();
+        let pretty = config.pretty.unwrap();
+        assert_eq!(pretty.get(Some(Format::Json)), Some(true));
+        assert_eq!(pretty.get(Some(Format::Toml)), Some(false));
+        assert_eq!(pretty.get(Some(Format::Yaml)), None);
+
+        assert!(toml::from_str::<Config>("[pretty]\njsno = true").is_err());
+        assert!(toml::from_str::<Config>("[pretty]\nyaml = true").is_err());
+    }
+
+    #[test]
+    fn profile() {
+        let config: Config = toml::from_str(
+            "[profile.k8s]\nto = \"yaml\"\nsort_keys = true\nkey_order = [\"apiVersion\", \"kind\"]\n\n\
+             [profile.binary]\nsort_keys = \"binary\"",
+        )
+        .unwrap();
+        assert!(config.pretty.is_none());
+
+        let k8s = &config.profile["k8s"];
+        assert_eq!(k8s.to.as_deref(), Some("yaml"));
+        assert!(matches!(k8s.sort_keys, Some(SortKeys::Enabled(true))));
+        assert_eq!(k8s.key_order, ["apiVersion", "kind"]);
+        assert!(matches!(
+            config.profile["binary"].sort_keys,
+            Some(SortKeys::Scope(ref scope)) if scope == "binary"
+        ));
+
+        assert!(toml::from_str::<Config>("[profile.a]\nunknown = 1").is_err());
+    }
+}
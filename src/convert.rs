@@ -4,87 +4,281 @@
 // Copyright (C) 2021 Shun Sakai
 //
 
-use anyhow::{anyhow, Context, Result};
-use rmpv::Value as MessagePack;
+use std::error::Error;
+use std::fmt;
+
+use indexmap::IndexMap;
+use num_bigint::{BigInt, Sign};
+use rmpv::{Utf8String, Value as MessagePack};
 use ron::Value as Ron;
 use serde_cbor::Value as Cbor;
 use serde_json::Value as Json;
 use serde_yaml::Value as Yaml;
 use toml::Value as Toml;
 
-use crate::value::Value;
+use crate::lossy::Tracker;
+use crate::value::{Integer, Value};
+use crate::Options;
 
-impl TryFrom<Cbor> for Value {
-    type Error = anyhow::Error;
+/// The error type for conversions between the intermediate representation and
+/// each format.
+#[derive(Debug)]
+pub enum ConvertError {
+    /// The key of the map is not a string.
+    NonStringKey,
+    /// A byte string cannot be converted.
+    ByteStringUnsupported,
+    /// A semantic tag cannot be converted.
+    SemanticTag(u64),
+    /// An extension type cannot be converted.
+    Extension(i8),
+    /// The string contains invalid UTF-8 sequence.
+    InvalidUtf8(String),
+    /// Null does not exist in the format.
+    NullUnsupported,
+    /// Infinite or NaN values are not allowed in the format.
+    NonFiniteFloat(f64),
+    /// The integer is out of range of the format.
+    IntegerOutOfRange(Integer),
+    /// The number is out of range of `f64`.
+    FloatOutOfRange(String),
+    /// The key appears more than once in the map.
+    DuplicateKey(String),
+    /// The elements of the array at the JSON Pointer are not the same type.
+    MixedArray(String),
+    /// The root is not an array.
+    NonArrayRoot,
+    /// The element at the index is not a string.
+    NonStringElement(usize),
+    /// The string at the index contains a line break.
+    MultilineElement(usize),
+    /// The root is not a map.
+    NonMapRoot,
+    /// The separator cannot be used in names of environment variables.
+    InvalidSeparator(String),
+    /// The value at the path cannot be represented in the format.
+    UnsupportedValue(String),
+}
 
-    fn try_from(value: Cbor) -> Result<Self> {
+impl fmt::Display for ConvertError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonStringKey => write!(fmt, "The key is not a string"),
+            Self::ByteStringUnsupported => write!(fmt, "A byte string cannot be converted"),
+            Self::SemanticTag(tag) => write!(fmt, "A semantic tag cannot be converted: {}", tag),
+            Self::Extension(ty) => write!(fmt, "An extension cannot be converted: {}", ty),
+            Self::InvalidUtf8(str) => {
+                write!(fmt, "The string contains invalid UTF-8 sequence: {}", str)
+            }
+            Self::NullUnsupported => write!(fmt, "Null does not exist"),
+            Self::NonFiniteFloat(float) => {
+                write!(fmt, "Infinite or NaN values are not allowed: {}", float)
+            }
+            Self::IntegerOutOfRange(int) => write!(fmt, "Out of range of integer: {}", int),
+            Self::FloatOutOfRange(num) => write!(fmt, "Out of range of float: {}", num),
+            Self::DuplicateKey(key) => write!(fmt, "The key is duplicated: {}", key),
+            Self::MixedArray(path) if path.is_empty() => {
+                write!(fmt, "The elements of the root array are not the same type")
+            }
+            Self::MixedArray(path) => write!(
+                fmt,
+                "The elements of the array at {} are not the same type",
+                path
+            ),
+            Self::NonArrayRoot => write!(fmt, "The root is not an array"),
+            Self::NonStringElement(index) => {
+                write!(fmt, "The element at index {} is not a string", index)
+            }
+            Self::MultilineElement(index) => {
+                write!(fmt, "The element at index {} contains a line break", index)
+            }
+            Self::NonMapRoot => write!(fmt, "The root is not a map"),
+            Self::InvalidSeparator(separator) => write!(
+                fmt,
+                "The separator cannot be used in names of environment variables: {:?}",
+                separator
+            ),
+            Self::UnsupportedValue(path) => {
+                write!(fmt, "The value at {} cannot be represented", path)
+            }
+        }
+    }
+}
+
+impl Error for ConvertError {}
+
+impl Value {
+    /// Convert from a CBOR value, converting byte strings and bignums (tags 2
+    /// and 3) according to `options`.
+    pub fn from_cbor(value: Cbor, options: &Options) -> Result<Self, ConvertError> {
         match value {
             Cbor::Null => Ok(Self::Null),
             Cbor::Bool(bool) => Ok(Self::Bool(bool)),
-            Cbor::Integer(int) => match (i64::try_from(int), u64::try_from(int)) {
-                (Ok(sint), _) => Ok(Self::Integer(sint.into())),
-                (_, Ok(uint)) => Ok(Self::Integer(uint.into())),
-                _ => unreachable!(),
-            },
+            Cbor::Integer(int) => Ok(Self::Integer(int.into())),
             Cbor::Float(float) => Ok(Self::Float(float)),
-            Cbor::Bytes(_) => Err(anyhow!("A byte string cannot be converted")),
+            Cbor::Bytes(bytes) => Ok(Self::String(from_byte_string(&bytes, options)?)),
             Cbor::Text(str) => Ok(Self::String(str)),
             Cbor::Array(arr) => {
-                let arr: Result<Vec<_>> = arr.into_iter().map(TryFrom::try_from).collect();
+                let arr: Result<Vec<_>, ConvertError> = arr
+                    .into_iter()
+                    .map(|v| Self::from_cbor(v, options))
+                    .collect();
 
                 Ok(Self::Array(arr?))
             }
             Cbor::Map(map) => {
-                let (keys, values): (Result<Vec<_>>, Result<Vec<_>>) = (
-                    map.keys()
-                        .cloned()
-                        .map(|k| {
-                            serde_cbor::value::from_value(k).context("The key is not a string")
-                        })
-                        .collect(),
-                    map.values().cloned().map(TryFrom::try_from).collect(),
-                );
+                let mut obj = IndexMap::with_capacity(map.len());
+                for (k, v) in map {
+                    let k = match k {
+                        Cbor::Text(str) => str,
+                        // Byte strings of valid UTF-8 are also accepted as
+                        // keys.
+                        Cbor::Bytes(bytes) => match options.byte_strings.encode(&bytes) {
+                            Some(str) => str,
+                            None => {
+                                String::from_utf8(bytes).map_err(|_| ConvertError::NonStringKey)?
+                            }
+                        },
+                        _ => return Err(ConvertError::NonStringKey),
+                    };
+                    obj.insert(k, Self::from_cbor(v, options)?);
+                }
 
-                Ok(Self::Map(
-                    keys?.into_iter().zip(values?.into_iter()).collect(),
-                ))
+                Ok(Self::Map(obj))
+            }
+            Cbor::Tag(tag @ (2 | 3), content) if options.preserve_cbor_bignum => match *content {
+                Cbor::Bytes(bytes) => {
+                    let magnitude = BigInt::from_bytes_be(Sign::Plus, &bytes);
+                    // The value of tag 3 is -1 minus the content.
+                    let int = if tag == 3 { -1 - magnitude } else { magnitude };
+
+                    Ok(Self::Integer(int.into()))
+                }
+                _ => Err(ConvertError::SemanticTag(tag)),
+            },
+            // Bignums are read as byte strings unless they are preserved.
+            Cbor::Tag(2 | 3, content) | Cbor::Tag(SELF_DESCRIBE, content) => {
+                Self::from_cbor(*content, options)
             }
-            Cbor::Tag(..) => Err(anyhow!("A semantic tag cannot be converted")),
+            Cbor::Tag(tag, _) => Err(ConvertError::SemanticTag(tag)),
+            // The remaining variant is hidden and never constructed. Undefined
+            // is decoded as null, and other simple values are rejected by
+            // serde_cbor.
             _ => unreachable!(),
         }
     }
 }
 
-impl From<Json> for Value {
-    fn from(value: Json) -> Self {
-        match value {
+/// The tag which marks the data item as CBOR.
+const SELF_DESCRIBE: u64 = 55799;
+
+impl TryFrom<Cbor> for Value {
+    type Error = ConvertError;
+
+    fn try_from(value: Cbor) -> Result<Self, Self::Error> {
+        Self::from_cbor(value, &Options::default())
+    }
+}
+
+impl Value {
+    /// Convert from a JSON value.
+    ///
+    /// Numbers which `f64` cannot hold exactly are rounded, or kept as written
+    /// in strings if `options` preserves them. Numbers which are out of range
+    /// of `f64` are an error unless they are kept.
+    pub fn from_json(value: Json, options: &Options) -> Result<Self, ConvertError> {
+        let value = match value {
             Json::Null => Self::Null,
             Json::Bool(bool) => Self::Bool(bool),
-            Json::Number(num) => match (num.as_i64(), num.as_u64(), num.as_f64()) {
-                (Some(sint), ..) => Self::Integer(sint.into()),
-                (_, Some(uint), _) => Self::Integer(uint.into()),
-                (.., Some(float)) => Self::Float(float),
-                _ => unreachable!(),
+            Json::Number(num) => match (num.as_i64(), num.as_u64()) {
+                (Some(sint), _) => Self::Integer(sint.into()),
+                (_, Some(uint)) => Self::Integer(uint.into()),
+                _ => {
+                    let str = normalize_exponent(&num.to_string());
+                    match (str.parse(), str.parse::<f64>()) {
+                        (Ok(int), _) => Self::Integer(int),
+                        (_, Ok(float))
+                            if float.is_finite()
+                                && (!options.preserve_json_decimal
+                                    || !exceeds_f64_precision(&str, float)) =>
+                        {
+                            Self::Float(float)
+                        }
+                        // Keep the number as written rather than lose
+                        // precision.
+                        _ if options.preserve_json_decimal => Self::String(str),
+                        _ => return Err(ConvertError::FloatOutOfRange(str)),
+                    }
+                }
             },
             Json::String(str) => Self::String(str),
             Json::Array(arr) => {
-                let arr = arr.into_iter().map(From::from).collect();
+                let arr: Result<Vec<_>, _> = arr
+                    .into_iter()
+                    .map(|v| Self::from_json(v, options))
+                    .collect();
 
-                Self::Array(arr)
+                Self::Array(arr?)
             }
             Json::Object(obj) => {
-                let map = obj.into_iter().map(|(k, v)| (k, v.into())).collect();
+                let mut map = IndexMap::with_capacity(obj.len());
+                for (k, v) in obj {
+                    map.insert(k, Self::from_json(v, options)?);
+                }
 
                 Self::Map(map)
             }
-        }
+        };
+
+        Ok(value)
     }
 }
 
-impl TryFrom<MessagePack> for Value {
-    type Error = anyhow::Error;
+impl TryFrom<Json> for Value {
+    type Error = ConvertError;
+
+    fn try_from(value: Json) -> Result<Self, Self::Error> {
+        Self::from_json(value, &Options::default())
+    }
+}
+
+/// Write the exponent of the number in lowercase and without `+` (e.g. `1E+400`
+/// to `1e400`), since `serde_json` keeps the number as written.
+fn normalize_exponent(num: &str) -> String {
+    num.replacen('E', "e", 1).replacen("e+", "e", 1)
+}
+
+/// Returns `true` if the number is not written as the shortest representation
+/// of the nearest `f64`, that is, `f64` cannot hold it exactly.
+fn exceeds_f64_precision(num: &str, float: f64) -> bool {
+    decimal_digits(num) != decimal_digits(&format!("{:e}", float))
+}
+
+/// Split the decimal number into its significant digits and the exponent of
+/// the last digit (e.g. `("25", -3)` for `0.0250`). Zero has no digits.
+fn decimal_digits(num: &str) -> (String, i64) {
+    let num = num.trim_start_matches('-');
+    let (mantissa, exp) = match num.find(|c| c == 'e' || c == 'E') {
+        Some(pos) => (&num[..pos], num[pos + 1..].parse().unwrap_or_default()),
+        None => (num, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", int, frac);
+    let trimmed = digits.trim_end_matches('0');
+    let exp = exp - frac.len() as i64 + (digits.len() - trimmed.len()) as i64;
+    let trimmed = trimmed.trim_start_matches('0');
+
+    if trimmed.is_empty() {
+        (String::new(), 0)
+    } else {
+        (trimmed.to_string(), exp)
+    }
+}
 
-    fn try_from(value: MessagePack) -> Result<Self> {
+impl Value {
+    /// Convert from a MessagePack value, resolving duplicate map keys, invalid
+    /// UTF-8 sequences and binaries according to `options`.
+    pub fn from_messagepack(value: MessagePack, options: &Options) -> Result<Self, ConvertError> {
         match value {
             MessagePack::Nil => Ok(Self::Null),
             MessagePack::Boolean(bool) => Ok(Self::Bool(bool)),
@@ -95,56 +289,80 @@ impl TryFrom<MessagePack> for Value {
             },
             MessagePack::F32(float) => Ok(Self::Float(float.into())),
             MessagePack::F64(float) => Ok(Self::Float(float)),
-            MessagePack::String(str) => {
-                let str = str
-                    .as_str()
-                    .with_context(|| {
-                        format!("The string contains invalid UTF-8 sequence: {}", str)
-                    })?
-                    .to_string();
-
-                Ok(Self::String(str))
-            }
-            MessagePack::Binary(_) => Err(anyhow!("A byte array cannot be converted")),
+            MessagePack::String(str) => Ok(Self::String(from_utf8_string(&str, options)?)),
+            MessagePack::Binary(bytes) => Ok(Self::String(from_byte_string(&bytes, options)?)),
             MessagePack::Array(arr) => {
-                let arr: Result<Vec<_>> = arr.into_iter().map(TryFrom::try_from).collect();
+                let arr: Result<Vec<_>, ConvertError> = arr
+                    .into_iter()
+                    .map(|v| Self::from_messagepack(v, options))
+                    .collect();
 
                 Ok(Self::Array(arr?))
             }
             MessagePack::Map(map) => {
-                let (keys, values): (Result<Vec<_>>, Result<Vec<_>>) = (
-                    map.iter()
-                        .map(|(k, _)| k)
-                        .map(|k| k.as_str().context("The key is not a string"))
-                        .map(|k| k.map(str::to_string))
-                        .collect(),
-                    map.into_iter()
-                        .map(|(_, v)| v)
-                        .map(TryFrom::try_from)
-                        .collect(),
-                );
+                let mut obj = IndexMap::with_capacity(map.len());
+                for (k, v) in map {
+                    let k = match k {
+                        MessagePack::String(str) => from_utf8_string(&str, options)?,
+                        MessagePack::Binary(bytes) => options
+                            .byte_strings
+                            .encode(&bytes)
+                            .ok_or(ConvertError::NonStringKey)?,
+                        _ => return Err(ConvertError::NonStringKey),
+                    };
+                    let v = Self::from_messagepack(v, options)?;
 
-                Ok(Self::Map(
-                    keys?.into_iter().zip(values?.into_iter()).collect(),
-                ))
+                    options
+                        .on_key_conflict
+                        .insert(&mut obj, k, v)
+                        .map_err(ConvertError::DuplicateKey)?;
+                }
+
+                Ok(Self::Map(obj))
             }
-            MessagePack::Ext(..) => Err(anyhow!("An extension cannot be converted")),
+            MessagePack::Ext(ty, _) => Err(ConvertError::Extension(ty)),
         }
     }
 }
 
+/// Convert a MessagePack string, replacing invalid UTF-8 sequences in it with
+/// U+FFFD if `options` allows it.
+fn from_utf8_string(str: &Utf8String, options: &Options) -> Result<String, ConvertError> {
+    match str.as_str() {
+        Some(str) => Ok(str.to_string()),
+        None if options.lossy_utf8 => Ok(String::from_utf8_lossy(str.as_bytes()).into_owned()),
+        None => Err(ConvertError::InvalidUtf8(str.to_string())),
+    }
+}
+
+/// Convert a byte string into a string as `options` selects.
+fn from_byte_string(bytes: &[u8], options: &Options) -> Result<String, ConvertError> {
+    options
+        .byte_strings
+        .encode(bytes)
+        .ok_or(ConvertError::ByteStringUnsupported)
+}
+
+impl TryFrom<MessagePack> for Value {
+    type Error = ConvertError;
+
+    fn try_from(value: MessagePack) -> Result<Self, Self::Error> {
+        Self::from_messagepack(value, &Options::default())
+    }
+}
+
 impl TryFrom<Ron> for Value {
-    type Error = anyhow::Error;
+    type Error = ConvertError;
 
-    fn try_from(value: Ron) -> Result<Self> {
+    fn try_from(value: Ron) -> Result<Self, Self::Error> {
         match value {
             Ron::Bool(bool) => Ok(Self::Bool(bool)),
             Ron::Char(char) => Ok(Self::String(char.into())),
             Ron::Map(map) => {
-                let (keys, values): (Result<Vec<_>>, Result<Vec<_>>) = (
+                let (keys, values): (Result<Vec<_>, _>, Result<Vec<_>, ConvertError>) = (
                     map.keys()
                         .cloned()
-                        .map(|k| k.into_rust().context("The key is not a string"))
+                        .map(|k| k.into_rust().map_err(|_| ConvertError::NonStringKey))
                         .collect(),
                     map.values().cloned().map(TryFrom::try_from).collect(),
                 );
@@ -158,14 +376,16 @@ impl TryFrom<Ron> for Value {
                 (_, Some(float)) => Ok(Self::Float(float)),
                 _ => unreachable!(),
             },
-            Ron::Option(_) => Err(anyhow!("The Option type cannot be converted")),
+            Ron::Option(Some(value)) => Self::try_from(*value),
+            Ron::Option(None) => Ok(Self::Null),
             Ron::String(str) => Ok(Self::String(str)),
             Ron::Seq(seq) => {
-                let arr: Result<Vec<_>> = seq.into_iter().map(TryFrom::try_from).collect();
+                let arr: Result<Vec<_>, ConvertError> =
+                    seq.into_iter().map(TryFrom::try_from).collect();
 
                 Ok(Self::Array(arr?))
             }
-            Ron::Unit => Err(anyhow!("The unit type cannot be converted")),
+            Ron::Unit => Ok(Self::Null),
         }
     }
 }
@@ -193,9 +413,9 @@ impl From<Toml> for Value {
 }
 
 impl TryFrom<Yaml> for Value {
-    type Error = anyhow::Error;
+    type Error = ConvertError;
 
-    fn try_from(value: Yaml) -> Result<Self> {
+    fn try_from(value: Yaml) -> Result<Self, Self::Error> {
         match value {
             Yaml::Null => Ok(Self::Null),
             Yaml::Bool(bool) => Ok(Self::Bool(bool)),
@@ -207,15 +427,16 @@ impl TryFrom<Yaml> for Value {
             },
             Yaml::String(str) => Ok(Self::String(str)),
             Yaml::Sequence(seq) => {
-                let arr: Result<Vec<_>> = seq.into_iter().map(TryFrom::try_from).collect();
+                let arr: Result<Vec<_>, ConvertError> =
+                    seq.into_iter().map(TryFrom::try_from).collect();
 
                 Ok(Self::Array(arr?))
             }
             Yaml::Mapping(map) => {
-                let (keys, values): (Result<Vec<_>>, Result<Vec<_>>) = (
+                let (keys, values): (Result<Vec<_>, _>, Result<Vec<_>, ConvertError>) = (
                     map.iter()
                         .map(|(k, _)| k)
-                        .map(|k| k.as_str().context("The key is not a string"))
+                        .map(|k| k.as_str().ok_or(ConvertError::NonStringKey))
                         .map(|k| k.map(str::to_string))
                         .collect(),
                     map.into_iter()
@@ -240,7 +461,17 @@ impl From<Value> for Cbor {
             Value::Integer(int) => match (int.as_i64(), int.as_u64()) {
                 (Some(sint), _) => Self::Integer(sint.into()),
                 (_, Some(uint)) => Self::Integer(uint.into()),
-                _ => unreachable!(),
+                // Out of the range of 64 bits, so it is a bignum.
+                _ => {
+                    let int = int.to_bigint();
+                    let (tag, magnitude) = if int.sign() == Sign::Minus {
+                        (3, BigInt::from(-1) - int)
+                    } else {
+                        (2, int)
+                    };
+
+                    Self::Tag(tag, Box::new(Self::Bytes(magnitude.to_bytes_be().1)))
+                }
             },
             Value::Float(float) => Self::Float(float),
             Value::String(str) => Self::Text(str),
@@ -258,132 +489,269 @@ impl From<Value> for Cbor {
     }
 }
 
-impl TryFrom<Value> for Json {
-    type Error = anyhow::Error;
+impl TryFrom<&Value> for Json {
+    type Error = ConvertError;
 
-    fn try_from(value: Value) -> Result<Self> {
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
         match value {
             Value::Null => Ok(Self::Null),
-            Value::Bool(bool) => Ok(Self::Bool(bool)),
+            Value::Bool(bool) => Ok(Self::Bool(*bool)),
             Value::Integer(int) => match (int.as_i64(), int.as_u64()) {
                 (Some(sint), _) => Ok(Self::Number(sint.into())),
                 (_, Some(uint)) => Ok(Self::Number(uint.into())),
-                _ => unreachable!(),
+                // The number keeps its digits with `arbitrary_precision`.
+                _ => Ok(Self::Number(
+                    serde_json::from_str(&int.to_string()).expect("Invalid number"),
+                )),
             },
             Value::Float(float) => {
-                let float = serde_json::Number::from_f64(float).with_context(|| {
-                    format!("Infinite or NaN values are not allowed: {}", float)
-                })?;
+                let float = serde_json::Number::from_f64(*float)
+                    .ok_or(ConvertError::NonFiniteFloat(*float))?;
 
                 Ok(Self::Number(float))
             }
-            Value::String(str) => Ok(Self::String(str)),
+            Value::String(str) => Ok(Self::String(str.clone())),
             Value::Array(arr) => {
-                let arr: Result<Vec<_>> = arr.into_iter().map(TryFrom::try_from).collect();
+                let arr: Result<Vec<_>, ConvertError> = arr.iter().map(TryFrom::try_from).collect();
 
                 Ok(Self::Array(arr?))
             }
             Value::Map(map) => {
-                let values: Result<Vec<_>> = map.values().cloned().map(TryFrom::try_from).collect();
+                let obj: Result<serde_json::Map<_, _>, ConvertError> = map
+                    .iter()
+                    .map(|(k, v)| Ok((k.clone(), Self::try_from(v)?)))
+                    .collect();
 
-                Ok(Self::Object(
-                    map.keys().cloned().zip(values?.into_iter()).collect(),
-                ))
+                Ok(Self::Object(obj?))
             }
         }
     }
 }
 
-impl From<Value> for MessagePack {
-    fn from(value: Value) -> Self {
-        match value {
-            Value::Null => Self::Nil,
-            Value::Bool(bool) => Self::Boolean(bool),
-            Value::Integer(int) => match (int.as_i64(), int.as_u64()) {
-                (Some(sint), _) => Self::Integer(sint.into()),
-                (_, Some(uint)) => Self::Integer(uint.into()),
-                _ => unreachable!(),
+impl TryFrom<Value> for Json {
+    type Error = ConvertError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Self::try_from(&value)
+    }
+}
+
+impl Value {
+    /// Convert to a MessagePack value, reporting the integers which become
+    /// strings to `tracker`.
+    pub(crate) fn to_messagepack(&self, tracker: &mut Tracker) -> MessagePack {
+        match self {
+            Self::Null => MessagePack::Nil,
+            Self::Bool(bool) => MessagePack::Boolean(*bool),
+            Self::Integer(int) => match (int.as_i64(), int.as_u64()) {
+                (Some(sint), _) => MessagePack::Integer(sint.into()),
+                (_, Some(uint)) => MessagePack::Integer(uint.into()),
+                _ => MessagePack::String(wide_integer_to_string(self, tracker).into()),
             },
-            Value::Float(float) => Self::F64(float),
-            Value::String(str) => Self::String(str.into()),
-            Value::Array(arr) => {
-                let arr = arr.into_iter().map(From::from).collect();
+            Self::Float(float) => MessagePack::F64(*float),
+            Self::String(str) => MessagePack::String(str.as_str().into()),
+            Self::Array(arr) => {
+                let arr = arr
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| tracker.nest(&i.to_string(), |tracker| v.to_messagepack(tracker)))
+                    .collect();
 
-                Self::Array(arr)
+                MessagePack::Array(arr)
             }
-            Value::Map(map) => {
-                let map = map.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+            Self::Map(map) => {
+                let map = map
+                    .iter()
+                    .map(|(k, v)| {
+                        let v = tracker.nest(k, |tracker| v.to_messagepack(tracker));
+                        (k.as_str().into(), v)
+                    })
+                    .collect();
 
-                Self::Map(map)
+                MessagePack::Map(map)
             }
         }
     }
 }
 
-impl TryFrom<Value> for Toml {
-    type Error = anyhow::Error;
+/// Convert the integer which is out of the range of 64 bits to a string, and
+/// report it to `tracker`.
+fn wide_integer_to_string(value: &Value, tracker: &mut Tracker) -> String {
+    let str = match value {
+        Value::Integer(int) => int.to_string(),
+        _ => unreachable!(),
+    };
+    tracker.compare(value, &Value::String(str.clone()));
 
-    fn try_from(value: Value) -> Result<Self> {
-        match value {
-            Value::Null => Err(anyhow!("Null does not exist")),
-            Value::Bool(bool) => Ok(Self::Boolean(bool)),
-            Value::Integer(int) => {
+    str
+}
+
+impl From<&Value> for MessagePack {
+    fn from(value: &Value) -> Self {
+        value.to_messagepack(&mut Tracker::new())
+    }
+}
+
+impl From<Value> for MessagePack {
+    fn from(value: Value) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl Value {
+    /// Convert to a TOML value.
+    ///
+    /// TOML requires the elements of an array to be the same type. If
+    /// `stringify_mixed_arrays` is `true`, the elements of such an array are
+    /// converted to strings, otherwise it is an error.
+    pub fn to_toml(&self, stringify_mixed_arrays: bool) -> Result<Toml, ConvertError> {
+        self.to_toml_tracked(stringify_mixed_arrays, &mut Tracker::new())
+    }
+
+    /// Convert to a TOML value like `to_toml`, reporting the strings which
+    /// become datetimes and the elements which become strings to `tracker`.
+    pub(crate) fn to_toml_tracked(
+        &self,
+        stringify_mixed_arrays: bool,
+        tracker: &mut Tracker,
+    ) -> Result<Toml, ConvertError> {
+        match self {
+            Self::Null => Err(ConvertError::NullUnsupported),
+            Self::Bool(bool) => Ok(Toml::Boolean(*bool)),
+            Self::Integer(int) => {
                 let int = int
                     .as_i64()
-                    .with_context(|| format!("Out of range of integer: {}", int))?;
+                    .ok_or_else(|| ConvertError::IntegerOutOfRange(int.clone()))?;
 
-                Ok(Self::Integer(int))
+                Ok(Toml::Integer(int))
             }
-            Value::Float(float) => Ok(Self::Float(float)),
-            Value::String(str) => match str.parse() {
-                Ok(dt) => Ok(Self::Datetime(dt)),
-                _ => Ok(Self::String(str)),
+            Self::Float(float) => Ok(Toml::Float(*float)),
+            Self::String(str) => match str.parse() {
+                Ok(dt) => {
+                    tracker.report("the string is converted to a datetime");
+                    Ok(Toml::Datetime(dt))
+                }
+                _ => Ok(Toml::String(str.clone())),
             },
-            Value::Array(arr) => {
-                let arr: Result<Vec<_>> = arr.into_iter().map(TryFrom::try_from).collect();
+            Self::Array(arr) => {
+                let mut elems = Vec::with_capacity(arr.len());
+                for (i, v) in arr.iter().enumerate() {
+                    elems.push(tracker.nest(&i.to_string(), |tracker| {
+                        v.to_toml_tracked(stringify_mixed_arrays, tracker)
+                    })?);
+                }
 
-                Ok(Self::Array(arr?))
+                if elems.windows(2).all(|e| e[0].same_type(&e[1])) {
+                    return Ok(Toml::Array(elems));
+                }
+                if !stringify_mixed_arrays {
+                    return Err(ConvertError::MixedArray(tracker.pointer().to_string()));
+                }
+
+                let mut strs = Vec::with_capacity(elems.len());
+                for (i, (v, e)) in arr.iter().zip(elems).enumerate() {
+                    let str = match e {
+                        Toml::String(str) => str,
+                        Toml::Integer(int) => int.to_string(),
+                        Toml::Float(float) => float.to_string(),
+                        Toml::Boolean(bool) => bool.to_string(),
+                        Toml::Datetime(dt) => dt.to_string(),
+                        Toml::Array(_) | Toml::Table(_) => {
+                            return Err(ConvertError::MixedArray(tracker.pointer().to_string()))
+                        }
+                    };
+                    tracker.nest(&i.to_string(), |tracker| {
+                        tracker.compare(v, &Value::String(str.clone()));
+                    });
+                    strs.push(Toml::String(str));
+                }
+
+                Ok(Toml::Array(strs))
             }
-            Value::Map(map) => {
-                let values: Result<Vec<_>> = map.values().cloned().map(TryFrom::try_from).collect();
+            Self::Map(map) => {
+                let mut table = toml::map::Map::with_capacity(map.len());
+                for (k, v) in map {
+                    let v = tracker.nest(k, |tracker| {
+                        v.to_toml_tracked(stringify_mixed_arrays, tracker)
+                    })?;
 
-                Ok(Self::Table(
-                    map.keys().cloned().zip(values?.into_iter()).collect(),
-                ))
+                    table.insert(k.clone(), v);
+                }
+
+                Ok(Toml::Table(table))
             }
         }
     }
 }
 
-impl From<Value> for Yaml {
-    fn from(value: Value) -> Self {
-        match value {
-            Value::Null => Self::Null,
-            Value::Bool(bool) => Self::Bool(bool),
-            Value::Integer(int) => match (int.as_i64(), int.as_u64()) {
-                (Some(sint), _) => Self::Number(sint.into()),
-                (_, Some(uint)) => Self::Number(uint.into()),
-                _ => unreachable!(),
+/// Append a reference token of JSON Pointer to `path`.
+pub(crate) fn push_pointer_token(path: &mut String, token: &str) {
+    path.push('/');
+    path.push_str(&token.replace('~', "~0").replace('/', "~1"));
+}
+
+impl TryFrom<Value> for Toml {
+    type Error = ConvertError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        value.to_toml(false)
+    }
+}
+
+impl Value {
+    /// Convert to a YAML value, reporting the integers which become strings to
+    /// `tracker`.
+    pub(crate) fn to_yaml(&self, tracker: &mut Tracker) -> Yaml {
+        match self {
+            Self::Null => Yaml::Null,
+            Self::Bool(bool) => Yaml::Bool(*bool),
+            Self::Integer(int) => match (int.as_i64(), int.as_u64()) {
+                (Some(sint), _) => Yaml::Number(sint.into()),
+                (_, Some(uint)) => Yaml::Number(uint.into()),
+                _ => Yaml::String(wide_integer_to_string(self, tracker)),
             },
-            Value::Float(float) => Self::Number(float.into()),
-            Value::String(str) => Self::String(str),
-            Value::Array(arr) => {
-                let seq = arr.into_iter().map(From::from).collect();
+            Self::Float(float) => Yaml::Number((*float).into()),
+            Self::String(str) => Yaml::String(str.clone()),
+            Self::Array(arr) => {
+                let seq = arr
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| tracker.nest(&i.to_string(), |tracker| v.to_yaml(tracker)))
+                    .collect();
 
-                Self::Sequence(seq)
+                Yaml::Sequence(seq)
             }
-            Value::Map(map) => {
-                let map = map.into_iter().map(|(k, v)| (k.into(), v.into())).collect();
+            Self::Map(map) => {
+                let map = map
+                    .iter()
+                    .map(|(k, v)| {
+                        let v = tracker.nest(k, |tracker| v.to_yaml(tracker));
+                        (Yaml::String(k.clone()), v)
+                    })
+                    .collect();
 
-                Self::Mapping(map)
+                Yaml::Mapping(map)
             }
         }
     }
 }
 
+impl From<&Value> for Yaml {
+    fn from(value: &Value) -> Self {
+        value.to_yaml(&mut Tracker::new())
+    }
+}
+
+impl From<Value> for Yaml {
+    fn from(value: Value) -> Self {
+        Self::from(&value)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::value::{ByteStringMode, KeyConflict};
 
     #[test]
     fn cbor2ir() {
@@ -400,11 +768,26 @@ mod tests {
             TryInto::<Value>::try_into(Cbor::Integer(u64::MAX.into())).unwrap(),
             Value::Integer(u64::MAX.into())
         );
+        assert_eq!(
+            TryInto::<Value>::try_into(Cbor::Integer(-(1 << 64))).unwrap(),
+            Value::Integer((-(1_i128 << 64)).into())
+        );
         assert_eq!(
             TryInto::<Value>::try_into(Cbor::Float(f64::default())).unwrap(),
             Value::Float(f64::default())
         );
-        assert!(TryInto::<Value>::try_into(Cbor::Bytes(vec![u8::MIN])).is_err());
+        assert!(matches!(
+            TryInto::<Value>::try_into(Cbor::Bytes(vec![u8::MIN])),
+            Err(ConvertError::ByteStringUnsupported)
+        ));
+        assert_eq!(
+            Value::from_cbor(
+                Cbor::Bytes(vec![u8::MIN]),
+                &Options::new().byte_strings(ByteStringMode::Hexdump)
+            )
+            .unwrap(),
+            Value::String("00".to_string())
+        );
         assert_eq!(
             TryInto::<Value>::try_into(Cbor::Text(String::default())).unwrap(),
             Value::String(String::default())
@@ -422,47 +805,171 @@ mod tests {
             .unwrap(),
             Value::Map(vec![(String::default(), Value::Null)].into_iter().collect())
         );
-        assert!(TryInto::<Value>::try_into(Cbor::Tag(u64::MIN, Box::new(Cbor::Null))).is_err());
+        assert!(matches!(
+            TryInto::<Value>::try_into(Cbor::Tag(u64::MIN, Box::new(Cbor::Null))),
+            Err(ConvertError::SemanticTag(_))
+        ));
+
+        assert!(matches!(
+            TryInto::<Value>::try_into(Cbor::Map(
+                vec![(Cbor::Null, Cbor::Null)].into_iter().collect()
+            )),
+            Err(ConvertError::NonStringKey)
+        ));
+    }
+
+    #[test]
+    fn cbor_bignums() {
+        let decode = |input: &[u8]| {
+            Value::from_cbor(
+                serde_cbor::from_slice(input).unwrap(),
+                &Options::new().preserve_cbor_bignum(true),
+            )
+        };
+
+        assert_eq!(
+            decode(&[0xc2, 0x42, 0x01, 0x00]).unwrap(),
+            Value::Integer(256_u64.into())
+        );
+        assert_eq!(
+            decode(&[0xc3, 0x48, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).unwrap(),
+            Value::Integer(i64::MIN.into())
+        );
+        assert_eq!(
+            decode(&[0xc2, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap(),
+            Value::Integer("18446744073709551616".parse().unwrap())
+        );
+        assert_eq!(
+            decode(&[0xc3, 0x48, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).unwrap(),
+            Value::Integer("-18446744073709551616".parse().unwrap())
+        );
+        assert_eq!(
+            decode(&[0x82, 0xc2, 0x5f, 0x41, 0x01, 0x40, 0xff, 0xc2, 0x40]).unwrap(),
+            Value::Array(vec![
+                Value::Integer(1_u64.into()),
+                Value::Integer(0_u64.into())
+            ])
+        );
+        assert!(matches!(
+            decode(&[0xc2, 0x61, 0x78]),
+            Err(ConvertError::SemanticTag(2))
+        ));
 
-        assert!(TryInto::<Value>::try_into(Cbor::Map(
-            vec![(Cbor::Null, Cbor::Null)].into_iter().collect()
-        ))
-        .is_err());
+        assert!(matches!(
+            TryInto::<Value>::try_into(
+                serde_cbor::from_slice::<Cbor>(&[0xc2, 0x41, 0x01]).unwrap()
+            ),
+            Err(ConvertError::ByteStringUnsupported)
+        ));
+        assert_eq!(
+            TryInto::<Value>::try_into(
+                serde_cbor::from_slice::<Cbor>(&[0xd9, 0xd9, 0xf7, 0xf6]).unwrap()
+            )
+            .unwrap(),
+            Value::Null
+        );
     }
 
     #[test]
     fn json2ir() {
-        assert_eq!(Into::<Value>::into(Json::Null), Value::Null);
+        let json = |input| serde_json::from_str::<Json>(input).unwrap();
+
+        assert_eq!(TryInto::<Value>::try_into(Json::Null).unwrap(), Value::Null);
         assert_eq!(
-            Into::<Value>::into(Json::Bool(bool::default())),
+            TryInto::<Value>::try_into(Json::Bool(bool::default())).unwrap(),
             Value::Bool(bool::default())
         );
         assert_eq!(
-            Into::<Value>::into(Json::Number(i64::MIN.into())),
+            TryInto::<Value>::try_into(Json::Number(i64::MIN.into())).unwrap(),
             Value::Integer(i64::MIN.into())
         );
         assert_eq!(
-            Into::<Value>::into(Json::Number(u64::MAX.into())),
+            TryInto::<Value>::try_into(Json::Number(u64::MAX.into())).unwrap(),
             Value::Integer(u64::MAX.into())
         );
         assert_eq!(
-            Into::<Value>::into(Json::Number(
+            TryInto::<Value>::try_into(Json::Number(
                 serde_json::Number::from_f64(f64::default()).unwrap()
-            )),
+            ))
+            .unwrap(),
             Value::Float(f64::default())
         );
         assert_eq!(
-            Into::<Value>::into(Json::String(String::default())),
+            TryInto::<Value>::try_into(json("0.1")).unwrap(),
+            Value::Float(0.1)
+        );
+        assert_eq!(
+            TryInto::<Value>::try_into(json("123456789012345678901234567890")).unwrap(),
+            Value::Integer(123_456_789_012_345_678_901_234_567_890_u128.into())
+        );
+        assert_eq!(
+            TryInto::<Value>::try_into(json("-1234567890123456789012345678901234567890")).unwrap(),
+            Value::Integer("-1234567890123456789012345678901234567890".parse().unwrap())
+        );
+        assert_eq!(
+            TryInto::<Value>::try_into(json("3.14159265358979323846264338327")).unwrap(),
+            Value::Float(std::f64::consts::PI)
+        );
+        assert!(matches!(
+            TryInto::<Value>::try_into(json("1e400")),
+            Err(ConvertError::FloatOutOfRange(num)) if num == "1e400"
+        ));
+        assert!(matches!(
+            TryInto::<Value>::try_into(json("[-1E+400]")),
+            Err(ConvertError::FloatOutOfRange(num)) if num == "-1e400"
+        ));
+
+        let options = Options::new().preserve_json_decimal(true);
+        assert_eq!(
+            Value::from_json(json("3.14159265358979323846264338327"), &options).unwrap(),
+            Value::String("3.14159265358979323846264338327".to_string())
+        );
+        assert_eq!(
+            Value::from_json(json("0.30000000000000001"), &options).unwrap(),
+            Value::String("0.30000000000000001".to_string())
+        );
+        assert_eq!(
+            Value::from_json(json("1e400"), &options).unwrap(),
+            Value::String("1e400".to_string())
+        );
+        assert_eq!(
+            Value::from_json(json("1E+400"), &options).unwrap(),
+            Value::String("1e400".to_string())
+        );
+        assert_eq!(
+            Value::from_json(json("1e-400"), &options).unwrap(),
+            Value::String("1e-400".to_string())
+        );
+        assert_eq!(
+            Value::from_json(json("0.5"), &options).unwrap(),
+            Value::Float(0.5)
+        );
+        assert_eq!(
+            Value::from_json(json("0.30000000000000004"), &options).unwrap(),
+            Value::Float(0.30000000000000004)
+        );
+        assert_eq!(
+            Value::from_json(json("1.50e2"), &options).unwrap(),
+            Value::Float(150.0)
+        );
+        assert_eq!(
+            Value::from_json(json("0.0"), &options).unwrap(),
+            Value::Float(0.0)
+        );
+
+        assert_eq!(
+            TryInto::<Value>::try_into(Json::String(String::default())).unwrap(),
             Value::String(String::default())
         );
         assert_eq!(
-            Into::<Value>::into(Json::Array(vec![Json::Null])),
+            TryInto::<Value>::try_into(Json::Array(vec![Json::Null])).unwrap(),
             Value::Array(vec![Value::Null])
         );
         assert_eq!(
-            Into::<Value>::into(Json::Object(
+            TryInto::<Value>::try_into(Json::Object(
                 vec![(String::default(), Json::Null)].into_iter().collect()
-            )),
+            ))
+            .unwrap(),
             Value::Map(vec![(String::default(), Value::Null)].into_iter().collect())
         );
     }
@@ -497,7 +1004,18 @@ mod tests {
             TryInto::<Value>::try_into(MessagePack::String(String::default().into())).unwrap(),
             Value::String(String::default())
         );
-        assert!(TryInto::<Value>::try_into(MessagePack::Binary(vec![u8::MIN])).is_err());
+        assert!(matches!(
+            TryInto::<Value>::try_into(MessagePack::Binary(vec![u8::MIN])),
+            Err(ConvertError::ByteStringUnsupported)
+        ));
+        assert_eq!(
+            Value::from_messagepack(
+                MessagePack::Binary(vec![u8::MIN]),
+                &Options::new().byte_strings(ByteStringMode::Base64)
+            )
+            .unwrap(),
+            Value::String("AA==".to_string())
+        );
         assert_eq!(
             TryInto::<Value>::try_into(MessagePack::Array(vec![MessagePack::Nil])).unwrap(),
             Value::Array(vec![Value::Null])
@@ -514,16 +1032,85 @@ mod tests {
             .unwrap(),
             Value::Map(vec![(String::default(), Value::Null)].into_iter().collect())
         );
-        assert!(
-            TryInto::<Value>::try_into(MessagePack::Ext(i8::default(), vec![u8::MIN])).is_err()
-        );
+        assert!(matches!(
+            TryInto::<Value>::try_into(MessagePack::Ext(i8::default(), vec![u8::MIN])),
+            Err(ConvertError::Extension(_))
+        ));
 
-        assert!(TryInto::<Value>::try_into(MessagePack::Map(
-            vec![(MessagePack::Nil, MessagePack::Nil)]
+        let invalid = rmpv::decode::read_value(&mut &[0xa2, b'a', 0xff][..]).unwrap();
+        assert!(matches!(
+            TryInto::<Value>::try_into(invalid.clone()),
+            Err(ConvertError::InvalidUtf8(_))
+        ));
+        assert!(matches!(
+            TryInto::<Value>::try_into(MessagePack::Map(vec![(invalid.clone(), MessagePack::Nil)])),
+            Err(ConvertError::InvalidUtf8(_))
+        ));
+        assert_eq!(
+            Value::from_messagepack(
+                MessagePack::Map(vec![(invalid.clone(), invalid)]),
+                &Options::new().lossy_utf8(true)
+            )
+            .unwrap(),
+            Value::Map(
+                vec![(
+                    "a\u{fffd}".to_string(),
+                    Value::String("a\u{fffd}".to_string())
+                )]
                 .into_iter()
                 .collect()
-        ))
-        .is_err());
+            )
+        );
+        assert!(matches!(
+            TryInto::<Value>::try_into(MessagePack::Map(
+                vec![(MessagePack::Nil, MessagePack::Nil)]
+                    .into_iter()
+                    .collect()
+            )),
+            Err(ConvertError::NonStringKey)
+        ));
+
+        let duplicated = MessagePack::Map(vec![
+            (
+                MessagePack::String("a".into()),
+                MessagePack::Integer(0.into()),
+            ),
+            (
+                MessagePack::String("a".into()),
+                MessagePack::Integer(1.into()),
+            ),
+        ]);
+        assert!(matches!(
+            Value::from_messagepack(
+                duplicated.clone(),
+                &Options::new().on_key_conflict(KeyConflict::Error)
+            ),
+            Err(ConvertError::DuplicateKey(_))
+        ));
+        assert_eq!(
+            Value::from_messagepack(
+                duplicated.clone(),
+                &Options::new().on_key_conflict(KeyConflict::First)
+            )
+            .unwrap(),
+            Value::Map(
+                vec![("a".to_string(), Value::Integer(0_u64.into()))]
+                    .into_iter()
+                    .collect()
+            )
+        );
+        assert_eq!(
+            Value::from_messagepack(
+                duplicated,
+                &Options::new().on_key_conflict(KeyConflict::Last)
+            )
+            .unwrap(),
+            Value::Map(
+                vec![("a".to_string(), Value::Integer(1_u64.into()))]
+                    .into_iter()
+                    .collect()
+            )
+        );
     }
 
     #[test]
@@ -557,7 +1144,15 @@ mod tests {
             TryInto::<Value>::try_into(Ron::Number(f64::default().into())).unwrap(),
             Value::Float(f64::default())
         );
-        assert!(TryInto::<Value>::try_into(Ron::Option(Option::default())).is_err());
+        assert_eq!(
+            TryInto::<Value>::try_into(Ron::Option(Option::default())).unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            TryInto::<Value>::try_into(Ron::Option(Some(Box::new(Ron::Bool(bool::default())))))
+                .unwrap(),
+            Value::Bool(bool::default())
+        );
         assert_eq!(
             TryInto::<Value>::try_into(Ron::String(String::default())).unwrap(),
             Value::String(String::default())
@@ -566,14 +1161,45 @@ mod tests {
             TryInto::<Value>::try_into(Ron::Seq(vec![Ron::Bool(bool::default())])).unwrap(),
             Value::Array(vec![Value::Bool(bool::default())])
         );
-        assert!(TryInto::<Value>::try_into(Ron::Unit).is_err());
+        assert_eq!(TryInto::<Value>::try_into(Ron::Unit).unwrap(), Value::Null);
+
+        assert!(matches!(
+            TryInto::<Value>::try_into(Ron::Map(
+                vec![(Ron::Bool(bool::default()), Ron::Bool(bool::default()))]
+                    .into_iter()
+                    .collect()
+            )),
+            Err(ConvertError::NonStringKey)
+        ));
+    }
 
-        assert!(TryInto::<Value>::try_into(Ron::Map(
-            vec![(Ron::Bool(bool::default()), Ron::Bool(bool::default()))]
+    #[test]
+    fn ron_extensions() {
+        // `implicit_some` with an explicit `Some` overflows the stack in RON
+        // 0.7.
+        let ron: Ron = ron::from_str("#![enable(implicit_some)]\n(a: 1)").unwrap();
+        assert_eq!(
+            TryInto::<Value>::try_into(ron).unwrap(),
+            Value::Map(
+                vec![("a".to_string(), Value::Integer(1_i64.into()))]
+                    .into_iter()
+                    .collect()
+            )
+        );
+        let ron: Ron =
+            ron::from_str("#![enable(unwrap_newtypes)]\n(a: Some(1), b: None, c: ())").unwrap();
+        assert_eq!(
+            TryInto::<Value>::try_into(ron).unwrap(),
+            Value::Map(
+                vec![
+                    ("a".to_string(), Value::Integer(1_i64.into())),
+                    ("b".to_string(), Value::Null),
+                    ("c".to_string(), Value::Null),
+                ]
                 .into_iter()
                 .collect()
-        ))
-        .is_err());
+            )
+        );
     }
 
     #[test]
@@ -653,10 +1279,12 @@ mod tests {
             Value::Map(vec![(String::default(), Value::Null)].into_iter().collect())
         );
 
-        assert!(TryInto::<Value>::try_into(Yaml::Mapping(
-            vec![(Yaml::Null, Yaml::Null)].into_iter().collect()
-        ))
-        .is_err());
+        assert!(matches!(
+            TryInto::<Value>::try_into(Yaml::Mapping(
+                vec![(Yaml::Null, Yaml::Null)].into_iter().collect()
+            )),
+            Err(ConvertError::NonStringKey)
+        ));
     }
 
     #[test]
@@ -674,6 +1302,17 @@ mod tests {
             Into::<Cbor>::into(Value::Integer(u64::MAX.into())),
             Cbor::Integer(u64::MAX.into())
         );
+        assert_eq!(
+            Into::<Cbor>::into(Value::Integer(i128::MIN.into())),
+            Cbor::Tag(
+                3,
+                Box::new(Cbor::Bytes([&[0x7f_u8] as &[u8], &[0xff; 15]].concat()))
+            )
+        );
+        assert_eq!(
+            Into::<Cbor>::into(Value::Integer(u128::MAX.into())),
+            Cbor::Tag(2, Box::new(Cbor::Bytes(vec![0xff; 16])))
+        );
         assert_eq!(
             Into::<Cbor>::into(Value::Float(f64::default())),
             Cbor::Float(f64::default())
@@ -713,6 +1352,12 @@ mod tests {
             TryInto::<Json>::try_into(Value::Integer(u64::MAX.into())).unwrap(),
             Json::Number(u64::MAX.into())
         );
+        assert_eq!(
+            TryInto::<Json>::try_into(Value::Integer(u128::MAX.into()))
+                .unwrap()
+                .to_string(),
+            u128::MAX.to_string()
+        );
         assert_eq!(
             TryInto::<Json>::try_into(Value::Float(f64::default())).unwrap(),
             Json::Number(serde_json::Number::from_f64(f64::default()).unwrap())
@@ -733,7 +1378,10 @@ mod tests {
             Json::Object(vec![(String::default(), Json::Null)].into_iter().collect())
         );
 
-        assert!(TryInto::<Json>::try_into(Value::Float(f64::NAN)).is_err());
+        assert!(matches!(
+            TryInto::<Json>::try_into(Value::Float(f64::NAN)),
+            Err(ConvertError::NonFiniteFloat(_))
+        ));
         assert!(TryInto::<Json>::try_into(Value::Float(f64::INFINITY)).is_err());
         assert!(TryInto::<Json>::try_into(Value::Float(f64::NEG_INFINITY)).is_err());
     }
@@ -753,6 +1401,10 @@ mod tests {
             Into::<MessagePack>::into(Value::Integer(u64::MAX.into())),
             MessagePack::Integer(u64::MAX.into())
         );
+        assert_eq!(
+            Into::<MessagePack>::into(Value::Integer(i128::MIN.into())),
+            MessagePack::String(i128::MIN.to_string().into())
+        );
         assert_eq!(
             Into::<MessagePack>::into(Value::Float(f64::default())),
             MessagePack::F64(f64::default())
@@ -782,7 +1434,10 @@ mod tests {
 
     #[test]
     fn ir2toml() {
-        assert!(TryInto::<Toml>::try_into(Value::Null).is_err());
+        assert!(matches!(
+            TryInto::<Toml>::try_into(Value::Null),
+            Err(ConvertError::NullUnsupported)
+        ));
         assert_eq!(
             TryInto::<Toml>::try_into(Value::Bool(bool::default())).unwrap(),
             Toml::Boolean(bool::default())
@@ -821,7 +1476,44 @@ mod tests {
             )
         );
 
-        assert!(TryInto::<Toml>::try_into(Value::Integer(u64::MAX.into())).is_err());
+        assert!(matches!(
+            TryInto::<Toml>::try_into(Value::Integer(u64::MAX.into())),
+            Err(ConvertError::IntegerOutOfRange(_))
+        ));
+
+        let mixed = Value::Map(
+            vec![(
+                "a/b".to_string(),
+                Value::Array(vec![
+                    Value::Integer(i64::default().into()),
+                    Value::String(String::default()),
+                ]),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        assert!(matches!(
+            TryInto::<Toml>::try_into(mixed.clone()),
+            Err(ConvertError::MixedArray(path)) if path == "/a~1b"
+        ));
+        assert_eq!(
+            mixed.to_toml(true).unwrap(),
+            Toml::Table(
+                vec![(
+                    "a/b".to_string(),
+                    Toml::Array(vec![
+                        Toml::String(i64::default().to_string()),
+                        Toml::String(String::default())
+                    ])
+                )]
+                .into_iter()
+                .collect()
+            )
+        );
+        assert!(matches!(
+            Value::Array(vec![Value::Bool(bool::default()), Value::Array(vec![])]).to_toml(true),
+            Err(ConvertError::MixedArray(_))
+        ));
     }
 
     #[test]
@@ -839,6 +1531,10 @@ mod tests {
             Into::<Yaml>::into(Value::Integer(u64::MAX.into())),
             Yaml::Number(u64::MAX.into())
         );
+        assert_eq!(
+            Into::<Yaml>::into(Value::Integer(u128::MAX.into())),
+            Yaml::String(u128::MAX.to_string())
+        );
         assert_eq!(
             Into::<Yaml>::into(Value::Float(f64::default())),
             Yaml::Number(f64::default().into())
@@ -0,0 +1,641 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+//! Deserialization into the value of each format, which resolves keys that
+//! appear more than once in a map.
+//!
+//! The values of the formats keep only one of the duplicate keys, so the
+//! policy has to be applied while deserializing.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Read;
+use std::marker::PhantomData;
+
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_cbor::Value as Cbor;
+use serde_json::Value as Json;
+use serde_yaml::Value as Yaml;
+
+use crate::value::KeyConflict;
+use crate::ConvertError;
+
+/// The key of the map which `serde_json` uses to pass a number with
+/// `arbitrary_precision`.
+const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// Return `true` if the value of the key which is already in the map is
+/// replaced with the new value.
+fn replaces<E: de::Error>(
+    on_conflict: KeyConflict,
+    key: impl FnOnce() -> String,
+) -> Result<bool, E> {
+    match on_conflict {
+        KeyConflict::Error => Err(E::custom(ConvertError::DuplicateKey(key()))),
+        KeyConflict::First => Ok(false),
+        KeyConflict::Last => Ok(true),
+    }
+}
+
+/// A seed which deserializes a JSON value.
+#[derive(Clone, Copy, Debug)]
+pub struct JsonSeed(pub KeyConflict);
+
+impl<'de> DeserializeSeed<'de> for JsonSeed {
+    type Value = Json;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Json, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for JsonSeed {
+    type Value = Json;
+
+    fn expecting(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("any valid JSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Json, E> {
+        Ok(Json::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Json, E> {
+        Ok(Json::Number(v.into()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Json, E> {
+        Ok(Json::Number(v.into()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Json, E> {
+        Ok(serde_json::Number::from_f64(v).map_or(Json::Null, Json::Number))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Json, E> {
+        Ok(Json::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Json, E> {
+        Ok(Json::String(v))
+    }
+
+    fn visit_none<E>(self) -> Result<Json, E> {
+        Ok(Json::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Json, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_unit<E>(self) -> Result<Json, E> {
+        Ok(Json::Null)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Json, A::Error> {
+        let mut arr = Vec::new();
+        while let Some(elem) = seq.next_element_seed(self)? {
+            arr.push(elem);
+        }
+
+        Ok(Json::Array(arr))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Json, A::Error> {
+        let mut obj = serde_json::Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if obj.is_empty() && key == NUMBER_TOKEN {
+                let number = map.next_value::<String>()?;
+
+                return serde_json::from_str(&number)
+                    .map(Json::Number)
+                    .map_err(de::Error::custom);
+            }
+
+            let value = map.next_value_seed(self)?;
+            if !obj.contains_key(&key) || replaces(self.0, || key.clone())? {
+                obj.insert(key, value);
+            }
+        }
+
+        Ok(Json::Object(obj))
+    }
+}
+
+/// The policy for duplicate keys as a type, for deserializers which only
+/// accept types instead of seeds.
+pub trait Policy {
+    const ON_CONFLICT: KeyConflict;
+}
+
+/// Return an error for duplicate keys.
+#[derive(Debug)]
+pub enum OnError {}
+
+/// Keep the first value of duplicate keys.
+#[derive(Debug)]
+pub enum OnFirst {}
+
+/// Keep the last value of duplicate keys.
+#[derive(Debug)]
+pub enum OnLast {}
+
+impl Policy for OnError {
+    const ON_CONFLICT: KeyConflict = KeyConflict::Error;
+}
+
+impl Policy for OnFirst {
+    const ON_CONFLICT: KeyConflict = KeyConflict::First;
+}
+
+impl Policy for OnLast {
+    const ON_CONFLICT: KeyConflict = KeyConflict::Last;
+}
+
+/// A JSON value which is deserialized with the policy `P`.
+#[derive(Debug)]
+pub struct JsonWith<P>(pub Json, PhantomData<P>);
+
+impl<'de, P: Policy> Deserialize<'de> for JsonWith<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        JsonSeed(P::ON_CONFLICT)
+            .deserialize(deserializer)
+            .map(|value| Self(value, PhantomData))
+    }
+}
+
+/// A seed which deserializes a YAML value.
+#[derive(Clone, Copy, Debug)]
+pub struct YamlSeed(pub KeyConflict);
+
+impl<'de> DeserializeSeed<'de> for YamlSeed {
+    type Value = Yaml;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Yaml, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for YamlSeed {
+    type Value = Yaml;
+
+    fn expecting(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("any YAML value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Yaml, E> {
+        Ok(Yaml::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Yaml, E> {
+        Ok(Yaml::Number(v.into()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Yaml, E> {
+        Ok(Yaml::Number(v.into()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Yaml, E> {
+        Ok(Yaml::Number(v.into()))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Yaml, E> {
+        Ok(Yaml::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Yaml, E> {
+        Ok(Yaml::String(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<Yaml, E> {
+        Ok(Yaml::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Yaml, E> {
+        Ok(Yaml::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Yaml, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Yaml, A::Error> {
+        let mut arr = Vec::new();
+        while let Some(elem) = seq.next_element_seed(self)? {
+            arr.push(elem);
+        }
+
+        Ok(Yaml::Sequence(arr))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Yaml, A::Error> {
+        let mut mapping = serde_yaml::Mapping::new();
+        while let Some(key) = map.next_key_seed(self)? {
+            let value = map.next_value_seed(self)?;
+            if !mapping.contains_key(&key) || replaces(self.0, || yaml_key(&key))? {
+                mapping.insert(key, value);
+            }
+        }
+
+        Ok(Yaml::Mapping(mapping))
+    }
+}
+
+fn yaml_key(key: &Yaml) -> String {
+    match key {
+        Yaml::String(str) => str.clone(),
+        key => format!("{:?}", key),
+    }
+}
+
+/// A seed which deserializes a RON value.
+#[derive(Clone, Copy, Debug)]
+pub struct RonSeed(pub KeyConflict);
+
+impl<'de> DeserializeSeed<'de> for RonSeed {
+    type Value = ron::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<ron::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for RonSeed {
+    type Value = ron::Value;
+
+    fn expecting(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("a RON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<ron::Value, E> {
+        Ok(ron::Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<ron::Value, E> {
+        Ok(ron::Value::Number(ron::Number::new(v)))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<ron::Value, E> {
+        Ok(ron::Value::Number(ron::Number::new(v)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<ron::Value, E> {
+        Ok(ron::Value::Number(ron::Number::new(v)))
+    }
+
+    // `ron::Value` cannot hold 128-bit integers, so they become floats as
+    // `ron` does.
+    #[allow(clippy::cast_precision_loss)]
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<ron::Value, E> {
+        self.visit_f64(v as f64)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<ron::Value, E> {
+        self.visit_f64(v as f64)
+    }
+
+    fn visit_char<E>(self, v: char) -> Result<ron::Value, E> {
+        Ok(ron::Value::Char(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<ron::Value, E> {
+        Ok(ron::Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<ron::Value, E> {
+        Ok(ron::Value::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<ron::Value, E> {
+        self.visit_byte_buf(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<ron::Value, E> {
+        String::from_utf8(v)
+            .map(ron::Value::String)
+            .map_err(E::custom)
+    }
+
+    fn visit_none<E>(self) -> Result<ron::Value, E> {
+        Ok(ron::Value::Option(None))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<ron::Value, D::Error> {
+        let value = deserializer.deserialize_any(self)?;
+
+        Ok(ron::Value::Option(Some(Box::new(value))))
+    }
+
+    fn visit_unit<E>(self) -> Result<ron::Value, E> {
+        Ok(ron::Value::Unit)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<ron::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<ron::Value, A::Error> {
+        let mut arr = Vec::new();
+        while let Some(elem) = seq.next_element_seed(self)? {
+            arr.push(elem);
+        }
+
+        Ok(ron::Value::Seq(arr))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<ron::Value, A::Error> {
+        let mut obj = ron::Map::new();
+        while let Some(key) = map.next_key_seed(self)? {
+            let value = map.next_value_seed(self)?;
+            // `ron::Map` cannot look up keys, so the old value is put back if
+            // it is kept.
+            if let Some(old) = obj.insert(key.clone(), value) {
+                if !replaces(self.0, || ron_key(&key))? {
+                    obj.insert(key, old);
+                }
+            }
+        }
+
+        Ok(ron::Value::Map(obj))
+    }
+}
+
+fn ron_key(key: &ron::Value) -> String {
+    match key {
+        ron::Value::String(str) => str.clone(),
+        key => format!("{:?}", key),
+    }
+}
+
+/// A seed which deserializes a CBOR value.
+#[derive(Clone, Copy, Debug)]
+pub struct CborSeed(pub KeyConflict);
+
+impl<'de> DeserializeSeed<'de> for CborSeed {
+    type Value = Cbor;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Cbor, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for CborSeed {
+    type Value = Cbor;
+
+    fn expecting(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str("any valid CBOR value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Cbor, E> {
+        Ok(Cbor::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Cbor, E> {
+        Ok(Cbor::Integer(v.into()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Cbor, E> {
+        Ok(Cbor::Integer(v.into()))
+    }
+
+    fn visit_i128<E>(self, v: i128) -> Result<Cbor, E> {
+        Ok(Cbor::Integer(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Cbor, E> {
+        Ok(Cbor::Float(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Cbor, E> {
+        Ok(Cbor::Text(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Cbor, E> {
+        Ok(Cbor::Text(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Cbor, E> {
+        Ok(Cbor::Bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Cbor, E> {
+        Ok(Cbor::Bytes(v))
+    }
+
+    fn visit_none<E>(self) -> Result<Cbor, E> {
+        Ok(Cbor::Null)
+    }
+
+    fn visit_unit<E>(self) -> Result<Cbor, E> {
+        Ok(Cbor::Null)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<Cbor, D::Error> {
+        let tag = serde_cbor::tags::current_cbor_tag();
+        let value = deserializer.deserialize_any(self)?;
+
+        Ok(match tag {
+            Some(tag) => Cbor::Tag(tag, Box::new(value)),
+            None => value,
+        })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Cbor, A::Error> {
+        let mut arr = Vec::new();
+        while let Some(elem) = seq.next_element_seed(self)? {
+            arr.push(elem);
+        }
+
+        Ok(Cbor::Array(arr))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Cbor, A::Error> {
+        let mut obj = BTreeMap::new();
+        while let Some(key) = map.next_key_seed(self)? {
+            let value = map.next_value_seed(self)?;
+            if !obj.contains_key(&key) || replaces(self.0, || cbor_key(&key))? {
+                obj.insert(key, value);
+            }
+        }
+
+        Ok(Cbor::Map(obj))
+    }
+}
+
+fn cbor_key(key: &Cbor) -> String {
+    match key {
+        Cbor::Text(str) => str.clone(),
+        key => format!("{:?}", key),
+    }
+}
+
+/// Deserialize a JSON value from a string.
+pub fn json_from_str(input: &str, on_conflict: KeyConflict) -> serde_json::Result<Json> {
+    let mut deserializer = serde_json::Deserializer::from_str(input);
+    let value = JsonSeed(on_conflict).deserialize(&mut deserializer)?;
+    deserializer.end()?;
+
+    Ok(value)
+}
+
+/// Deserialize a JSON value from a reader.
+pub fn json_from_reader<R: Read>(reader: R, on_conflict: KeyConflict) -> serde_json::Result<Json> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let value = JsonSeed(on_conflict).deserialize(&mut deserializer)?;
+    deserializer.end()?;
+
+    Ok(value)
+}
+
+/// Deserialize a JSON value from a string of JSON5.
+pub fn json5_from_str(input: &str, on_conflict: KeyConflict) -> json5::Result<Json> {
+    JsonSeed(on_conflict).deserialize(&mut json5::Deserializer::from_str(input)?)
+}
+
+/// Deserialize a JSON value from a string of Hjson.
+pub fn hjson_from_str(input: &str, on_conflict: KeyConflict) -> deser_hjson::Result<Json> {
+    // The deserializer of `deser_hjson` is private, so the policy is passed as
+    // a type.
+    match on_conflict {
+        KeyConflict::Error => deser_hjson::from_str::<JsonWith<OnError>>(input).map(|v| v.0),
+        KeyConflict::First => deser_hjson::from_str::<JsonWith<OnFirst>>(input).map(|v| v.0),
+        KeyConflict::Last => deser_hjson::from_str::<JsonWith<OnLast>>(input).map(|v| v.0),
+    }
+}
+
+/// Deserialize a RON value from a string.
+pub fn ron_from_str(input: &str, on_conflict: KeyConflict) -> ron::Result<ron::Value> {
+    let mut deserializer = ron::Deserializer::from_str(input)?;
+    let value = RonSeed(on_conflict).deserialize(&mut deserializer)?;
+    deserializer.end()?;
+
+    Ok(value)
+}
+
+/// Deserialize a CBOR value from bytes.
+pub fn cbor_from_slice(input: &[u8], on_conflict: KeyConflict) -> serde_cbor::Result<Cbor> {
+    let mut deserializer = serde_cbor::Deserializer::from_slice(input);
+    let value = CborSeed(on_conflict).deserialize(&mut deserializer)?;
+    deserializer.end()?;
+
+    Ok(value)
+}
+
+/// Deserialize a CBOR value from a reader.
+pub fn cbor_from_reader<R: Read>(reader: R, on_conflict: KeyConflict) -> serde_cbor::Result<Cbor> {
+    let mut deserializer = serde_cbor::Deserializer::from_reader(reader);
+    let value = CborSeed(on_conflict).deserialize(&mut deserializer)?;
+    deserializer.end()?;
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json() {
+        let input = r#"{"a":1,"b":{"c":2,"c":3},"a":4}"#;
+        let read = |on_conflict| json_from_str(input, on_conflict);
+
+        assert!(read(KeyConflict::Error)
+            .unwrap_err()
+            .to_string()
+            .starts_with("The key is duplicated: c"));
+        assert_eq!(
+            read(KeyConflict::First).unwrap(),
+            serde_json::json!({"a": 1, "b": {"c": 2}})
+        );
+        assert_eq!(
+            read(KeyConflict::Last).unwrap(),
+            serde_json::json!({"a": 4, "b": {"c": 3}})
+        );
+
+        let number = "123456789012345678901234567890";
+        assert_eq!(
+            json_from_str(number, KeyConflict::Error)
+                .unwrap()
+                .to_string(),
+            number
+        );
+        assert!(json5_from_str("{a: 1, a: 2}", KeyConflict::Error).is_err());
+        assert_eq!(
+            hjson_from_str("{a: 1\na: 2}", KeyConflict::First).unwrap(),
+            serde_json::json!({"a": 1})
+        );
+    }
+
+    #[test]
+    fn yaml() {
+        let input = "a: 1\nb: 2\na: 3\n";
+
+        assert!(YamlSeed(KeyConflict::Error)
+            .deserialize(serde_yaml::Deserializer::from_str(input))
+            .is_err());
+        assert_eq!(
+            YamlSeed(KeyConflict::First)
+                .deserialize(serde_yaml::Deserializer::from_str(input))
+                .unwrap(),
+            serde_yaml::from_str::<Yaml>("a: 1\nb: 2\n").unwrap()
+        );
+        assert_eq!(
+            YamlSeed(KeyConflict::Last)
+                .deserialize(serde_yaml::Deserializer::from_str(input))
+                .unwrap(),
+            serde_yaml::from_str::<Yaml>("a: 3\nb: 2\n").unwrap()
+        );
+    }
+
+    #[test]
+    fn ron() {
+        let input = r#"{"a": 1, "b": Some(2), "a": 3}"#;
+        let read = |on_conflict| ron_from_str(input, on_conflict);
+
+        assert!(read(KeyConflict::Error).is_err());
+        assert_eq!(
+            read(KeyConflict::First).unwrap(),
+            ron::from_str::<ron::Value>(r#"{"a": 1, "b": Some(2)}"#).unwrap()
+        );
+        assert_eq!(
+            read(KeyConflict::Last).unwrap(),
+            ron::from_str::<ron::Value>(r#"{"a": 3, "b": Some(2)}"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn cbor() {
+        // {"a": 1, "a": 55799(2)}
+        let input = [0xa2, 0x61, 0x61, 0x01, 0x61, 0x61, 0xd9, 0xd9, 0xf7, 0x02];
+        let read = |on_conflict| cbor_from_slice(&input, on_conflict);
+
+        assert!(read(KeyConflict::Error).is_err());
+        assert_eq!(
+            read(KeyConflict::First).unwrap(),
+            Cbor::Map(
+                vec![(Cbor::Text("a".to_string()), Cbor::Integer(1))]
+                    .into_iter()
+                    .collect()
+            )
+        );
+        assert_eq!(
+            read(KeyConflict::Last).unwrap(),
+            Cbor::Map(
+                vec![(
+                    Cbor::Text("a".to_string()),
+                    Cbor::Tag(55799, Box::new(Cbor::Integer(2)))
+                )]
+                .into_iter()
+                .collect()
+            )
+        );
+    }
+}
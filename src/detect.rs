@@ -0,0 +1,130 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::io::{self, Read};
+
+use serde_cbor::Value as Cbor;
+
+use crate::value::Format;
+
+/// Detect the format of binary input from its leading bytes.
+///
+/// This recognizes the self-describe tag of CBOR, the magic number of Parquet,
+/// the first bytes that only appear in either CBOR or MessagePack, and falls
+/// back to decoding the whole input for the leading bytes that both formats
+/// share. Returns `None` if the input does not look like any supported binary
+/// format.
+pub fn sniff_binary(bytes: &[u8]) -> Option<Format> {
+    match bytes {
+        // The self-describe tag (55799).
+        [0xd9, 0xd9, 0xf7, ..] => Some(Format::Cbor),
+        // The magic number of Parquet. "P" is a byte string of 16 bytes in
+        // CBOR and the integer 80 in MessagePack, neither of which is a
+        // document on its own here.
+        [b'P', b'A', b'R', b'1', ..] => Some(Format::Parquet),
+        // array 16 and array 32 of MessagePack, which are reserved in CBOR.
+        [0xdc | 0xdd, ..] => Some(Format::MessagePack),
+        // Maps of CBOR.
+        [0xa0..=0xbb | 0xbf, ..] => Some(Format::Cbor),
+        // Arrays of both formats.
+        [0x80..=0x9b | 0x9f, ..] => {
+            if is_messagepack(bytes) {
+                Some(Format::MessagePack)
+            } else if serde_cbor::from_slice::<Cbor>(bytes).is_ok() {
+                Some(Format::Cbor)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Detect the format of binary input from `reader`, reading only as many
+/// bytes as needed.
+///
+/// The leading bytes are usually enough. The rest is read only when the input
+/// starts with an array, which both CBOR and MessagePack can start with.
+pub fn sniff_reader<R: Read>(mut reader: R) -> io::Result<Option<Format>> {
+    let mut bytes = Vec::new();
+    reader.by_ref().take(4).read_to_end(&mut bytes)?;
+    if let [0x80..=0x9b | 0x9f, ..] = bytes[..] {
+        reader.read_to_end(&mut bytes)?;
+    }
+
+    Ok(sniff_binary(&bytes))
+}
+
+fn is_messagepack(bytes: &[u8]) -> bool {
+    rmp_serde::from_read_ref::<_, Vec<u8>>(bytes).map_or(false, |buf| {
+        let mut buf = buf.as_slice();
+
+        rmpv::decode::read_value(&mut buf).is_ok() && buf.is_empty()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniff_cbor() {
+        assert!(matches!(
+            sniff_binary(include_bytes!("../tests/resource/sample.cbor")),
+            Some(Format::Cbor)
+        ));
+        assert!(matches!(
+            sniff_binary(&[0xd9, 0xd9, 0xf7, 0xf6]),
+            Some(Format::Cbor)
+        ));
+        assert!(matches!(sniff_binary(&[0x81, 0xf6]), Some(Format::Cbor)));
+    }
+
+    #[test]
+    fn sniff_messagepack() {
+        assert!(matches!(
+            sniff_binary(include_bytes!("../tests/resource/sample.msgpack")),
+            Some(Format::MessagePack)
+        ));
+    }
+
+    #[test]
+    fn sniff_parquet() {
+        assert!(matches!(
+            sniff_binary(b"PAR1\x15\x04"),
+            Some(Format::Parquet)
+        ));
+        assert!(matches!(
+            sniff_reader((&b"PAR1"[..]).chain(io::repeat(0))).unwrap(),
+            Some(Format::Parquet)
+        ));
+        assert!(sniff_binary(b"PAR").is_none());
+    }
+
+    #[test]
+    fn sniff_text() {
+        assert!(sniff_binary(include_bytes!("../tests/resource/sample.json")).is_none());
+        assert!(sniff_binary(include_bytes!("../tests/resource/sample.toml")).is_none());
+        assert!(sniff_binary(include_bytes!("../tests/resource/sample.yaml")).is_none());
+        assert!(sniff_binary(&[]).is_none());
+    }
+
+    #[test]
+    fn sniff_leading_bytes() {
+        // The bytes after the leading ones are not read unless the input starts
+        // with an array.
+        let input = [0xd9, 0xd9, 0xf7];
+        assert!(matches!(
+            sniff_reader((&input[..]).chain(io::repeat(0))).unwrap(),
+            Some(Format::Cbor)
+        ));
+        assert!(sniff_reader(&b"{}"[..]).unwrap().is_none());
+        assert!(matches!(
+            sniff_reader(&include_bytes!("../tests/resource/sample.msgpack")[..]).unwrap(),
+            Some(Format::MessagePack)
+        ));
+    }
+}
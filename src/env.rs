@@ -0,0 +1,286 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+use indexmap::IndexMap;
+
+use crate::c_header;
+use crate::convert::ConvertError;
+use crate::lossy::Tracker;
+use crate::value::Value;
+
+/// Build a map from environment variables.
+///
+/// Only the variables whose names start with `prefix` followed by
+/// `separator` are used, and they are removed from the names. The rest of
+/// each name is split by `separator` into the keys of nested maps, and the
+/// value is a string. The variables are sorted by name, since the order of
+/// the environment is unspecified.
+///
+/// It is an error if `separator` is empty, or a variable is both a value and a
+/// map, such as `DB` and `DB__HOST`.
+pub fn from_vars<I>(vars: I, prefix: &str, separator: &str) -> Result<Value>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    if separator.is_empty() {
+        bail!("The separator is empty");
+    }
+
+    let mut vars: Vec<_> = vars
+        .into_iter()
+        .filter(|(name, _)| strip_prefix(name, prefix, separator).is_some())
+        .collect();
+    vars.sort();
+
+    let mut root = IndexMap::new();
+    for (name, value) in vars {
+        let rest = strip_prefix(&name, prefix, separator).expect("The prefix does not match");
+        let mut keys = rest.split(separator).peekable();
+        let mut map = &mut root;
+        while let Some(key) = keys.next() {
+            if keys.peek().is_none() {
+                if map.contains_key(key) {
+                    bail!("{} is both a value and a map", name);
+                }
+                map.insert(key.to_string(), Value::String(value));
+                break;
+            }
+
+            match map
+                .entry(key.to_string())
+                .or_insert_with(|| Value::Map(IndexMap::new()))
+            {
+                Value::Map(child) => map = child,
+                _ => bail!("{} is both a value and a map", name),
+            }
+        }
+    }
+
+    Ok(Value::Map(root))
+}
+
+/// Convert a map into `export` statements of POSIX shell, which set
+/// environment variables.
+///
+/// Nested maps and arrays are flattened, joining `prefix`, the keys and the
+/// indices with `separator`. Names are upper-cased, and characters which
+/// cannot appear in a name are replaced with `_`. Values are single-quoted,
+/// and nulls become empty strings. Empty arrays and maps are dropped.
+///
+/// `separator` must consist of one or more ASCII letters, digits and `_`.
+pub fn to_string(value: &Value, prefix: &str, separator: &str) -> Result<String, ConvertError> {
+    to_string_tracked(value, prefix, separator, &mut Tracker::new())
+}
+
+/// Convert a map into `export` statements like `to_string`, reporting the
+/// values which become strings or are dropped to `tracker`.
+pub(crate) fn to_string_tracked(
+    value: &Value,
+    prefix: &str,
+    separator: &str,
+    tracker: &mut Tracker,
+) -> Result<String, ConvertError> {
+    let map = match value {
+        Value::Map(map) => map,
+        _ => return Err(ConvertError::NonMapRoot),
+    };
+    if separator.is_empty()
+        || !separator
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(ConvertError::InvalidSeparator(separator.to_string()));
+    }
+
+    let mut output = String::new();
+    let prefix = (!prefix.is_empty()).then(|| c_header::sanitize(prefix));
+    let mut names = HashSet::new();
+    for (key, value) in map {
+        let name = match prefix {
+            Some(ref prefix) => format!("{}{}{}", prefix, separator, c_header::sanitize(key)),
+            None => c_header::identifier(key),
+        };
+        tracker.nest(key, |tracker| {
+            write_exports(&mut output, &mut names, name, separator, value, tracker)
+        })?;
+    }
+
+    Ok(output)
+}
+
+fn write_exports(
+    output: &mut String,
+    names: &mut HashSet<String>,
+    name: String,
+    separator: &str,
+    value: &Value,
+    tracker: &mut Tracker,
+) -> Result<(), ConvertError> {
+    let str = match value {
+        Value::Array(arr) if arr.is_empty() => {
+            tracker.report("the empty array is dropped");
+            return Ok(());
+        }
+        Value::Map(map) if map.is_empty() => {
+            tracker.report("the empty map is dropped");
+            return Ok(());
+        }
+        Value::Array(arr) => {
+            for (i, v) in arr.iter().enumerate() {
+                let name = format!("{}{}{}", name, separator, i);
+                tracker.nest(&i.to_string(), |tracker| {
+                    write_exports(output, names, name, separator, v, tracker)
+                })?;
+            }
+            return Ok(());
+        }
+        Value::Map(map) => {
+            for (k, v) in map {
+                let name = format!("{}{}{}", name, separator, c_header::sanitize(k));
+                tracker.nest(k, |tracker| {
+                    write_exports(output, names, name, separator, v, tracker)
+                })?;
+            }
+            return Ok(());
+        }
+        Value::Null => String::new(),
+        Value::Bool(bool) => bool.to_string(),
+        Value::Integer(int) => int.to_string(),
+        Value::Float(float) if !float.is_finite() => {
+            return Err(ConvertError::NonFiniteFloat(*float))
+        }
+        Value::Float(float) => float.to_string(),
+        Value::String(str) => str.clone(),
+    };
+    tracker.compare(value, &Value::String(str.clone()));
+
+    if !names.insert(name.clone()) {
+        return Err(ConvertError::DuplicateKey(name));
+    }
+    writeln!(output, "export {}='{}'", name, str.replace('\'', "'\\''")).unwrap();
+
+    Ok(())
+}
+
+/// Remove `prefix` and `separator` following it from the name.
+///
+/// Returns `None` if the name does not start with them, or nothing follows
+/// them.
+fn strip_prefix<'a>(name: &'a str, prefix: &str, separator: &str) -> Option<&'a str> {
+    let rest = name.strip_prefix(prefix)?;
+    let rest = if prefix.is_empty() || prefix.ends_with(separator) {
+        rest
+    } else {
+        rest.strip_prefix(separator)?
+    };
+
+    (!rest.is_empty()).then(|| rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn nested() {
+        let vars = vars(&[
+            ("APP__DB__PORT", "5432"),
+            ("APP__DB__HOST", "localhost"),
+            ("APP__NAME", "dsconv"),
+            ("APPLE", "a"),
+            ("HOME", "/root"),
+        ]);
+        let value: Value =
+            serde_json::json!({"DB": {"HOST": "localhost", "PORT": "5432"}, "NAME": "dsconv"})
+                .try_into()
+                .unwrap();
+
+        assert_eq!(from_vars(vars, "APP", "__").unwrap(), value);
+    }
+
+    #[test]
+    fn without_prefix() {
+        let value: Value = serde_json::json!({"A": {"B": "1"}, "C": "2"})
+            .try_into()
+            .unwrap();
+
+        assert_eq!(
+            from_vars(vars(&[("C", "2"), ("A_B", "1")]), "", "_").unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn exports() {
+        let value: Value = serde_json::json!({
+            "db": {"host": "localhost", "port": 5432},
+            "list": ["a", "it's"],
+            "debug": true,
+            "empty": null,
+            "none": [],
+        })
+        .try_into()
+        .unwrap();
+
+        assert_eq!(
+            to_string(&value, "app", "__").unwrap(),
+            "export APP__DB__HOST='localhost'\n\
+             export APP__DB__PORT='5432'\n\
+             export APP__LIST__0='a'\n\
+             export APP__LIST__1='it'\\''s'\n\
+             export APP__DEBUG='true'\n\
+             export APP__EMPTY=''\n"
+        );
+        assert_eq!(
+            to_string(
+                &serde_json::json!({"1st": {"a-b": 1}}).try_into().unwrap(),
+                "",
+                "_"
+            )
+            .unwrap(),
+            "export _1ST_A_B='1'\n"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(matches!(
+            to_string(&Value::Array(vec![]), "", "_"),
+            Err(ConvertError::NonMapRoot)
+        ));
+        assert!(matches!(
+            to_string(&serde_json::json!({"a_b": 1, "a": {"b": 2}}).try_into().unwrap(), "", "_"),
+            Err(ConvertError::DuplicateKey(name)) if name == "A_B"
+        ));
+        for separator in ["", ".", "-", "a b"] {
+            assert!(matches!(
+                to_string(&serde_json::json!({"a": {"b": 1}}).try_into().unwrap(), "", separator),
+                Err(ConvertError::InvalidSeparator(s)) if s == separator
+            ));
+        }
+    }
+
+    #[test]
+    fn conflict() {
+        assert!(from_vars(
+            vars(&[("APP__DB", "a"), ("APP__DB__HOST", "b")]),
+            "APP",
+            "__"
+        )
+        .is_err());
+        assert!(from_vars(vars(&[("APP__DB", "a")]), "APP", "").is_err());
+    }
+}
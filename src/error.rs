@@ -0,0 +1,98 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::error::Error;
+use std::fmt;
+
+use clap::ErrorKind;
+use dsconv::lossy::Loss;
+
+/// An error which means that the format is unknown or cannot be determined.
+#[derive(Debug)]
+pub enum FormatError {
+    Unknown {
+        direction: &'static str,
+        value: String,
+    },
+    Undeterminable(&'static str),
+}
+
+impl FormatError {
+    /// The exit status for this error.
+    pub const EXIT_CODE: i32 = 3;
+
+    /// Convert from an error of invalid `--from` or `--to`.
+    ///
+    /// Returns `None` if the error is not about these options.
+    pub fn from_clap(err: &clap::Error) -> Option<Self> {
+        if err.kind != ErrorKind::InvalidValue {
+            return None;
+        }
+
+        let direction = match err.info.first() {
+            Some(arg) if arg.starts_with("--from") => "input",
+            Some(arg) if arg.starts_with("--to") => "output",
+            _ => return None,
+        };
+        let value = err.info.get(1)?.clone();
+
+        Some(Self::Unknown { direction, value })
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown { direction, value } => {
+                write!(f, "Unknown {} format: {}", direction, value)
+            }
+            Self::Undeterminable(direction) => {
+                write!(f, "Unable to determine {} format", direction)
+            }
+        }
+    }
+}
+
+impl Error for FormatError {}
+
+/// An error which means that required keys are missing.
+#[derive(Debug)]
+pub struct MissingKeysError(pub Vec<String>);
+
+impl MissingKeysError {
+    /// The exit status for this error.
+    pub const EXIT_CODE: i32 = 4;
+}
+
+impl fmt::Display for MissingKeysError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Missing required keys: {}", self.0.join(", "))
+    }
+}
+
+impl Error for MissingKeysError {}
+
+/// An error which means that the conversion would lose information.
+#[derive(Debug)]
+pub struct LossyError(pub Vec<Loss>);
+
+impl LossyError {
+    /// The exit status for this error.
+    pub const EXIT_CODE: i32 = 5;
+}
+
+impl fmt::Display for LossyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The conversion would lose information:")?;
+        for loss in &self.0 {
+            write!(f, "\n  {}", loss)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for LossyError {}
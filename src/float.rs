@@ -0,0 +1,234 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use crate::value::FloatFormat;
+
+/// Format the finite `float` as the language selected by `format` does.
+///
+/// Returns `None` for `FloatFormat::Shortest`, since each serializer writes
+/// the shortest representation by itself.
+pub fn to_string(float: f64, format: FloatFormat) -> Option<String> {
+    match format {
+        FloatFormat::Shortest => None,
+        FloatFormat::Go => Some(to_go_string(float)),
+        FloatFormat::Python => Some(to_python_string(float)),
+        FloatFormat::Javascript => Some(to_ecmascript_string(float)),
+    }
+}
+
+/// Get the decimal digits and the exponent of `float` in scientific
+/// notation, with `precision` digits after the point if specified, otherwise
+/// with the shortest digits which represent it.
+fn decompose(float: f64, precision: Option<usize>) -> (String, i32) {
+    let sci = match precision {
+        Some(precision) => format!("{:.*e}", precision, float),
+        None => format!("{:e}", float),
+    };
+    let (mantissa, exponent) = sci.split_once('e').expect("Invalid scientific notation");
+
+    (
+        mantissa.replace('.', ""),
+        exponent.parse().expect("Invalid exponent"),
+    )
+}
+
+/// Get the shortest digits which represent the positive `float`, and the
+/// exponent of the first one.
+///
+/// If two candidates are equally close to the exact value, the even one is
+/// chosen as ECMAScript does.
+fn shortest_digits(float: f64) -> (String, i32) {
+    let (digits, exponent) = decompose(float, None);
+    // Every finite double has at most 767 significant decimal digits.
+    let (exact, exact_exponent) = decompose(float, Some(767));
+    if exponent != exact_exponent
+        || !exact[digits.len()..].starts_with('5')
+        || exact[digits.len() + 1..].bytes().any(|b| b != b'0')
+    {
+        return (digits, exponent);
+    }
+
+    let lower = exact[..digits.len()].to_string();
+    let upper = (lower.parse::<u64>().expect("Too many digits") + 1).to_string();
+    let candidate = if digits == lower { upper } else { lower };
+    let is_even = candidate.ends_with(|c| matches!(c, '0' | '2' | '4' | '6' | '8'));
+    let candidate_float = format!("{}.{}e{}", &candidate[..1], &candidate[1..], exponent);
+    let is_same = candidate_float.parse::<f64>() == Ok(float);
+    if candidate.len() == digits.len() && is_even && is_same {
+        (candidate, exponent)
+    } else {
+        (digits, exponent)
+    }
+}
+
+/// Write the digits in scientific notation with the exponent of at least two
+/// digits and its sign (e.g. `1.5e+07`), as C's `printf` does.
+fn scientific(digits: &str, exponent: i32) -> String {
+    let (first, rest) = digits.split_at(1);
+    let dot = if rest.is_empty() { "" } else { "." };
+    let sign = if exponent < 0 { '-' } else { '+' };
+
+    format!("{}{}{}e{}{:02}", first, dot, rest, sign, exponent.abs())
+}
+
+/// Write the digits in positional notation, where `point` is the position of
+/// the decimal point relative to the first digit.
+fn positional(digits: &str, point: i32) -> String {
+    let len = digits.len() as i32;
+    if len <= point {
+        format!("{}{}", digits, "0".repeat((point - len) as usize))
+    } else if 0 < point {
+        let (int, frac) = digits.split_at(point as usize);
+        format!("{}.{}", int, frac)
+    } else {
+        format!("0.{}{}", "0".repeat(-point as usize), digits)
+    }
+}
+
+fn with_sign(float: f64, abs: String) -> String {
+    if float.is_sign_negative() {
+        format!("-{}", abs)
+    } else {
+        abs
+    }
+}
+
+/// Format the finite `float` as `Number.prototype.toString` of ECMAScript.
+pub fn to_ecmascript_string(float: f64) -> String {
+    if float == 0.0 {
+        return "0".to_string();
+    }
+
+    let (digits, exponent) = shortest_digits(float.abs());
+    // The position of the decimal point relative to the first digit.
+    let point = exponent + 1;
+
+    let abs = if -6 < point && point <= 21 {
+        positional(&digits, point)
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let dot = if rest.is_empty() { "" } else { "." };
+        format!("{}{}{}e{:+}", first, dot, rest, exponent)
+    };
+
+    with_sign(float, abs)
+}
+
+/// Format the finite `float` as `repr` of Python.
+pub fn to_python_string(float: f64) -> String {
+    if float == 0.0 {
+        return with_sign(float, "0.0".to_string());
+    }
+
+    let (digits, exponent) = shortest_digits(float.abs());
+    let point = exponent + 1;
+
+    let abs = if -4 < point && point <= 16 {
+        let abs = positional(&digits, point);
+        if abs.contains('.') {
+            abs
+        } else {
+            format!("{}.0", abs)
+        }
+    } else {
+        scientific(&digits, exponent)
+    };
+
+    with_sign(float, abs)
+}
+
+/// Format the finite `float` as `strconv.FormatFloat(f, 'g', -1, 64)` of Go,
+/// which is also used by the `%v` verb of `fmt`.
+pub fn to_go_string(float: f64) -> String {
+    if float == 0.0 {
+        return with_sign(float, "0".to_string());
+    }
+
+    let (digits, exponent) = shortest_digits(float.abs());
+
+    let abs = if (-4..6).contains(&exponent) {
+        positional(&digits, exponent + 1)
+    } else {
+        scientific(&digits, exponent)
+    };
+
+    with_sign(float, abs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn number() {
+        for (bits, expected) in [
+            (0x0000_0000_0000_0000, "0"),
+            (0x8000_0000_0000_0000, "0"),
+            (0x0000_0000_0000_0001, "5e-324"),
+            (0x8000_0000_0000_0001, "-5e-324"),
+            (0x7fef_ffff_ffff_ffff, "1.7976931348623157e+308"),
+            (0x4340_0000_0000_0000, "9007199254740992"),
+            (0x4430_0000_0000_0000, "295147905179352830000"),
+            (0x44b5_2d02_c7e1_4af5, "9.999999999999997e+22"),
+            (0x44b5_2d02_c7e1_4af6, "1e+23"),
+            (0x444b_1ae4_d6e2_ef50, "1e+21"),
+            (0x3eb0_c6f7_a0b5_ed8c, "9.999999999999997e-7"),
+            (0x3eb0_c6f7_a0b5_ed8d, "0.000001"),
+            (0x41b3_de43_5555_5554, "333333333.33333325"),
+            (0xbecb_f647_612f_3696, "-0.0000033333333333333333"),
+            (0x4314_3ff3_c1cb_0959, "1424953923781206.2"),
+        ] {
+            assert_eq!(to_ecmascript_string(f64::from_bits(bits)), expected);
+        }
+    }
+
+    #[test]
+    fn python() {
+        for (bits, expected) in [
+            (0x0000_0000_0000_0000, "0.0"),
+            (0x8000_0000_0000_0000, "-0.0"),
+            (0x0000_0000_0000_0001, "5e-324"),
+            (0x7fef_ffff_ffff_ffff, "1.7976931348623157e+308"),
+            (0x4341_c379_37e0_8000, "1e+16"),
+            (0x430c_6bf5_2634_0000, "1000000000000000.0"),
+            (0x405e_c000_0000_0000, "123.0"),
+            (0x3fb9_9999_9999_999a, "0.1"),
+            (0x3f1a_36e2_eb1c_432d, "0.0001"),
+            (0x3ee4_f8b5_88e3_68f1, "1e-05"),
+            (0xbe84_21f5_f40d_8376, "-1.5e-07"),
+            (0x41b3_de43_5555_5554, "333333333.33333325"),
+        ] {
+            assert_eq!(to_python_string(f64::from_bits(bits)), expected);
+        }
+    }
+
+    #[test]
+    fn go() {
+        for (bits, expected) in [
+            (0x0000_0000_0000_0000, "0"),
+            (0x8000_0000_0000_0000, "-0"),
+            (0x0000_0000_0000_0001, "5e-324"),
+            (0x7fef_ffff_ffff_ffff, "1.7976931348623157e+308"),
+            (0x4480_f0cf_064d_d592, "1e+22"),
+            (0x4132_d687_0000_0000, "1.234567e+06"),
+            (0x40f8_6a00_0000_0000, "100000"),
+            (0x405e_c000_0000_0000, "123"),
+            (0x3fb9_9999_9999_999a, "0.1"),
+            (0x3f1a_36e2_eb1c_432d, "0.0001"),
+            (0x3ee4_f8b5_88e3_68f1, "1e-05"),
+            (0xbe84_21f5_f40d_8376, "-1.5e-07"),
+            (0x41b3_de43_5555_5554, "3.3333333333333325e+08"),
+        ] {
+            assert_eq!(to_go_string(f64::from_bits(bits)), expected);
+        }
+    }
+
+    #[test]
+    fn shortest() {
+        assert!(to_string(1.5, FloatFormat::Shortest).is_none());
+        assert_eq!(to_string(1e21, FloatFormat::Javascript).unwrap(), "1e+21");
+    }
+}
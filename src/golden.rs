@@ -0,0 +1,94 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::str;
+
+use anyhow::{bail, Result};
+
+/// Compare the output with the expected bytes.
+///
+/// If they differ, the error lists every differing line with its number, or
+/// tells the number of differing bytes and the first of them if either is not
+/// valid UTF-8.
+pub fn compare(expected: &[u8], actual: &[u8]) -> Result<()> {
+    if expected == actual {
+        return Ok(());
+    }
+
+    if let (Ok(expected), Ok(actual)) = (str::from_utf8(expected), str::from_utf8(actual)) {
+        let mut expected_lines = expected.split_inclusive('\n');
+        let mut actual_lines = actual.split_inclusive('\n');
+        let mut diffs = Vec::new();
+        for line in 1.. {
+            match (expected_lines.next(), actual_lines.next()) {
+                (None, None) => break,
+                (Some(e), Some(a)) if e == a => continue,
+                (e, a) => diffs.push(format!(
+                    "line {}\n-{}\n+{}",
+                    line,
+                    e.map_or("(end of file)", |e| e.trim_end_matches('\n')),
+                    a.map_or("(end of file)", |a| a.trim_end_matches('\n'))
+                )),
+            }
+        }
+        bail!("{}\n{}", differ(diffs.len(), "line"), diffs.join("\n"));
+    }
+
+    let offset = expected
+        .iter()
+        .zip(actual)
+        .position(|(e, a)| e != a)
+        .unwrap_or_else(|| expected.len().min(actual.len()));
+    let count = expected.iter().zip(actual).filter(|(e, a)| e != a).count()
+        + expected.len().max(actual.len())
+        - expected.len().min(actual.len());
+    bail!(
+        "{}, first at byte {} (expected {} bytes, got {} bytes)",
+        differ(count, "byte"),
+        offset,
+        expected.len(),
+        actual.len()
+    )
+}
+
+/// Describe that `count` of `noun` differ.
+fn differ(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {} differs", noun)
+    } else {
+        format!("{} {}s differ", count, noun)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same() {
+        assert!(compare(b"a\nb\n", b"a\nb\n").is_ok());
+    }
+
+    #[test]
+    fn differ() {
+        assert_eq!(
+            compare(b"a\nb\n", b"a\nc\n").unwrap_err().to_string(),
+            "1 line differs\nline 2\n-b\n+c"
+        );
+        assert_eq!(
+            compare(b"a\nb\nc\nd\n", b"x\nb\ny\n")
+                .unwrap_err()
+                .to_string(),
+            "3 lines differ\nline 1\n-a\n+x\nline 3\n-c\n+y\nline 4\n-d\n+(end of file)"
+        );
+        assert_eq!(
+            compare(&[0xff, 0x00, 0x02], &[0xff, 0x01])
+                .unwrap_err()
+                .to_string(),
+            "2 bytes differ, first at byte 1 (expected 3 bytes, got 2 bytes)"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use anyhow::{ensure, Context, Result};
+use dsconv::value::Encoding;
+
+/// A reader which fails once more than `max` bytes are read.
+pub struct MaxSize<R> {
+    inner: R,
+    max: u64,
+    read: u64,
+}
+
+impl<R: Read> MaxSize<R> {
+    pub fn new(inner: R, max: u64) -> Self {
+        Self {
+            inner,
+            max,
+            read: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for MaxSize<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        if self.read > self.max {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("The input exceeds the maximum size of {} bytes", self.max),
+            ));
+        }
+
+        Ok(n)
+    }
+}
+
+/// Open `file`, or stdin if `file` is `None`, without reading it.
+///
+/// A file larger than `max_file_size` is rejected here, and stdin fails when
+/// more than `max_file_size` bytes are read from it. Returns the reader and
+/// the size of the file.
+pub fn open(file: Option<&Path>, max_file_size: u64) -> Result<(Box<dyn Read>, Option<u64>)> {
+    let (reader, len): (Box<dyn Read>, _) = match file {
+        Some(file) => {
+            let context = || format!("Failed to read bytes from {}", file.display());
+
+            let f = File::open(file).with_context(context)?;
+            let len = f.metadata().with_context(context)?.len();
+            ensure!(
+                len <= max_file_size,
+                "{} exceeds the maximum size of {} bytes",
+                file.display(),
+                max_file_size
+            );
+
+            (Box::new(f), Some(len))
+        }
+        None => (Box::new(io::stdin()), None),
+    };
+
+    Ok((Box::new(MaxSize::new(reader, max_file_size)), len))
+}
+
+/// The number of bytes which are read from the inner reader of `Decode` at a
+/// time.
+const DECODE_CHUNK_LEN: u64 = 8192;
+
+/// A reader which decodes the bytes read from `inner` in `encoding`.
+///
+/// The input is decoded a block at a time, so it is never read as a whole.
+pub struct Decode<R> {
+    inner: R,
+    encoding: Encoding,
+    encoded: Vec<u8>,
+    decoded: Vec<u8>,
+    pos: usize,
+    eof: bool,
+}
+
+impl<R: Read> Decode<R> {
+    pub fn new(inner: R, encoding: Encoding) -> Self {
+        Self {
+            inner,
+            encoding,
+            encoded: Vec::new(),
+            decoded: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Decode the complete blocks of the next chunk of the input.
+    fn fill(&mut self) -> io::Result<()> {
+        let mut chunk = Vec::new();
+        let n = (&mut self.inner)
+            .take(DECODE_CHUNK_LEN)
+            .read_to_end(&mut chunk)?;
+        self.eof = n == 0;
+        self.encoded
+            .extend(chunk.into_iter().filter(|b| !b.is_ascii_whitespace()));
+
+        let block = match self.encoding {
+            Encoding::Base64 => 4,
+            Encoding::Base32 => 8,
+            Encoding::Hex => 2,
+        };
+        let len = if self.eof {
+            self.encoded.len()
+        } else {
+            self.encoded.len() / block * block
+        };
+        self.decoded = self.encoding.decode(&self.encoded[..len]).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Failed to decode the input from {}: {}", self.encoding, err),
+            )
+        })?;
+        self.encoded.drain(..len);
+        self.pos = 0;
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Decode<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.decoded.len() && !self.eof {
+            self.fill()?;
+        }
+        let n = (&self.decoded[self.pos..]).read(buf)?;
+        self.pos += n;
+
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_size() {
+        let mut buf = Vec::new();
+        assert!(MaxSize::new(&b"abc"[..], 3).read_to_end(&mut buf).is_ok());
+        assert_eq!(buf, b"abc");
+        assert!(MaxSize::new(&b"abcd"[..], 3).read_to_end(&mut buf).is_err());
+    }
+
+    #[test]
+    fn decode() {
+        let input = "YWJj\nZGVm\n".repeat(1000) + "Zw==\n";
+        let mut buf = Vec::new();
+        Decode::new(input.as_bytes(), Encoding::Base64)
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, ("abcdef".repeat(1000) + "g").as_bytes());
+
+        let mut buf = Vec::new();
+        Decode::new(&b"61 62 6"[..], Encoding::Hex)
+            .read_to_end(&mut buf)
+            .unwrap_err();
+    }
+}
@@ -0,0 +1,115 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+const KEY: &str = "\x1b[34m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[36m";
+const LITERAL: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Highlight a JSON string with ANSI escape sequences.
+///
+/// The input must be valid JSON.
+pub fn highlight(json: &str) -> String {
+    let mut output = String::with_capacity(json.len() * 2);
+    let mut chars = json.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '"' => {
+                let mut end = json.len();
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => {
+                            end = i + 1;
+                            break;
+                        }
+                        _ => (),
+                    }
+                }
+                let is_key = json[end..].trim_start().starts_with(':');
+                output.push_str(if is_key { KEY } else { STRING });
+                output.push_str(&json[start..end]);
+                output.push_str(RESET);
+            }
+            '-' | '0'..='9' | 't' | 'f' | 'n' => {
+                let mut end = json.len();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.') {
+                        chars.next();
+                    } else {
+                        end = i;
+                        break;
+                    }
+                }
+                output.push_str(if matches!(c, 't' | 'f' | 'n') {
+                    LITERAL
+                } else {
+                    NUMBER
+                });
+                output.push_str(&json[start..end]);
+                output.push_str(RESET);
+            }
+            c => output.push(c),
+        }
+    }
+
+    output
+}
+
+/// Escape C0 control characters in the output as `\uXXXX`, so that they
+/// cannot control the terminal.
+///
+/// Tabs and line feeds are kept, and carriage returns are kept only if they
+/// are followed by a line feed. The output must be ASCII-compatible such as
+/// UTF-8.
+pub fn escape_control(output: &[u8]) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(output.len());
+    for (i, &b) in output.iter().enumerate() {
+        match b {
+            b'\t' | b'\n' => escaped.push(b),
+            b'\r' if output.get(i + 1) == Some(&b'\n') => escaped.push(b),
+            0x00..=0x1f => escaped.extend(format!("\\u{:04x}", b).bytes()),
+            _ => escaped.push(b),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_json() {
+        assert_eq!(
+            highlight(r#"{"a\"": ["b", -1.5e3, true, null]}"#),
+            concat!(
+                "{\x1b[34m\"a\\\"\"\x1b[0m: [",
+                "\x1b[32m\"b\"\x1b[0m, ",
+                "\x1b[36m-1.5e3\x1b[0m, ",
+                "\x1b[33mtrue\x1b[0m, ",
+                "\x1b[33mnull\x1b[0m]}"
+            )
+        );
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(
+            escape_control(b"a\x1b[2J\tb\r\nc\rd\x00\n"),
+            b"a\\u001b[2J\tb\r\nc\\u000dd\\u0000\n"
+        );
+        assert_eq!(
+            escape_control("\u{3042}\x7f".as_bytes()),
+            "\u{3042}\x7f".as_bytes()
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::fs;
+use std::io::{self, Write};
+
+use anyhow::{Context, Result};
+use clap::ArgEnum;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::{Backend, CrosstermBackend};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+
+use dsconv::value::{Format, OutputFormat, Value};
+use dsconv::Options;
+
+/// Browse the value in the terminal, and write the selected value in the
+/// selected format.
+///
+/// The tree is drawn on stderr, so stdout can be redirected. The values
+/// written to stdout are buffered and written after quitting.
+pub fn explore(value: &Value, options: &Options) -> Result<()> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stderr()))
+        .context("Failed to initialize the terminal")?;
+    enable_raw_mode().context("Failed to enable the raw mode of the terminal")?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen)
+        .context("Failed to enter the alternate screen")?;
+
+    let mut browser = Browser::new(value, options);
+    let result = run(&mut terminal, &mut browser);
+
+    disable_raw_mode().context("Failed to disable the raw mode of the terminal")?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)
+        .context("Failed to leave the alternate screen")?;
+    terminal
+        .show_cursor()
+        .context("Failed to show the cursor")?;
+    result?;
+
+    io::stdout()
+        .write_all(&browser.stdout)
+        .context("Failed to write to stdout")
+}
+
+/// Draw the browser and handle key presses until quitting.
+fn run<B: Backend>(terminal: &mut Terminal<B>, browser: &mut Browser) -> Result<()> {
+    loop {
+        terminal
+            .draw(|f| browser.draw(f))
+            .context("Failed to draw the terminal")?;
+        if let Event::Key(key) = event::read().context("Failed to read an event")? {
+            if key.kind == KeyEventKind::Press && browser.handle_key(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// What the keys operate on.
+enum Mode {
+    Browse,
+    /// Selecting the output format.
+    SelectFormat(usize),
+    /// Entering the output file for the format.
+    EnterFile(Format, String),
+}
+
+/// The state of the tree browser.
+struct Browser<'a> {
+    options: &'a Options,
+    /// The JSON Pointers, the values and the selected entries from the root to
+    /// the current position.
+    stack: Vec<(String, &'a Value, usize)>,
+    mode: Mode,
+    /// The message shown at the bottom instead of the help of the keys.
+    status: String,
+    /// The output which is written to stdout after quitting.
+    stdout: Vec<u8>,
+}
+
+impl<'a> Browser<'a> {
+    fn new(value: &'a Value, options: &'a Options) -> Self {
+        Self {
+            options,
+            stack: vec![(String::new(), value, 0)],
+            mode: Mode::Browse,
+            status: String::new(),
+            stdout: Vec::new(),
+        }
+    }
+
+    /// Get the JSON Pointer, the value and the selected entry at the current
+    /// position.
+    fn position(&self) -> (&str, &'a Value, usize) {
+        let (pointer, value, selected) = self.stack.last().expect("The stack is empty");
+        (pointer, value, *selected)
+    }
+
+    /// Handle the key, and return `true` if quitting.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        self.status.clear();
+        match &mut self.mode {
+            Mode::Browse => return self.browse(code),
+            Mode::SelectFormat(selected) => match code {
+                KeyCode::Up | KeyCode::Char('k') => *selected = selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => {
+                    *selected = (*selected + 1).min(OutputFormat::value_variants().len() - 1);
+                }
+                KeyCode::Enter => {
+                    let format = OutputFormat::value_variants()[*selected].into();
+                    self.mode = Mode::EnterFile(format, String::new());
+                }
+                KeyCode::Esc => self.mode = Mode::Browse,
+                _ => (),
+            },
+            Mode::EnterFile(format, file) => match code {
+                KeyCode::Char(c) => file.push(c),
+                KeyCode::Backspace => {
+                    file.pop();
+                }
+                KeyCode::Enter => {
+                    let (format, file) = (*format, file.clone());
+                    self.mode = Mode::Browse;
+                    if let Err(err) = self.write(format, &file) {
+                        self.status = format!("Error: {:#}", err);
+                    }
+                }
+                KeyCode::Esc => self.mode = Mode::Browse,
+                _ => (),
+            },
+        }
+        false
+    }
+
+    /// Handle the key in the tree, and return `true` if quitting.
+    fn browse(&mut self, code: KeyCode) -> bool {
+        let (pointer, current, selected) = self.position();
+        let len = entries(current).count();
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => self.select(selected.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.select((selected + 1).min(len.saturating_sub(1)));
+            }
+            KeyCode::Right | KeyCode::Enter | KeyCode::Char('l') => {
+                if let Some((token, child)) = entries(current).nth(selected) {
+                    let pointer = format!(
+                        "{}/{}",
+                        pointer,
+                        token.replace('~', "~0").replace('/', "~1")
+                    );
+                    self.stack.push((pointer, child, 0));
+                }
+            }
+            KeyCode::Left | KeyCode::Backspace | KeyCode::Char('h') if self.stack.len() > 1 => {
+                self.stack.pop();
+            }
+            KeyCode::Char('w') => self.mode = Mode::SelectFormat(0),
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            _ => (),
+        }
+        false
+    }
+
+    /// Select the entry at the current position.
+    fn select(&mut self, index: usize) {
+        if let Some((_, _, selected)) = self.stack.last_mut() {
+            *selected = index;
+        }
+    }
+
+    /// Write the value at the current position in the format to the file, or
+    /// to stdout after quitting if the file is empty.
+    fn write(&mut self, format: Format, file: &str) -> Result<()> {
+        let mut buf = Vec::new();
+        dsconv::io::write_value(format, self.position().1, &mut buf, self.options)?;
+        if file.is_empty() {
+            self.status = format!("{} bytes will be written to stdout", buf.len());
+            self.stdout.extend(buf);
+        } else {
+            fs::write(file, &buf).with_context(|| format!("Failed to write to {}", file))?;
+            self.status = format!("Wrote {} bytes to {}", buf.len(), file);
+        }
+
+        Ok(())
+    }
+
+    fn draw<B: Backend>(&self, frame: &mut Frame<B>) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(frame.size());
+        let highlight = Style::default().add_modifier(Modifier::REVERSED);
+
+        let (pointer, current, selected) = self.position();
+        match &self.mode {
+            Mode::Browse => {
+                let title = if pointer.is_empty() {
+                    "(root)"
+                } else {
+                    pointer
+                };
+                let mut items: Vec<_> = entries(current)
+                    .map(|(token, v)| ListItem::new(format!("{}: {}", token, preview(v))))
+                    .collect();
+                if items.is_empty() {
+                    items.push(ListItem::new(preview(current)));
+                }
+                let mut state = ListState::default();
+                state.select(Some(selected));
+                let list = List::new(items)
+                    .block(Block::default().borders(Borders::ALL).title(title))
+                    .highlight_style(highlight);
+                frame.render_stateful_widget(list, chunks[0], &mut state);
+            }
+            Mode::SelectFormat(selected) => {
+                let items: Vec<_> = OutputFormat::value_variants()
+                    .iter()
+                    .map(|f| ListItem::new(Format::from(*f).to_string()))
+                    .collect();
+                let mut state = ListState::default();
+                state.select(Some(*selected));
+                let list = List::new(items)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title("Output format"),
+                    )
+                    .highlight_style(highlight);
+                frame.render_stateful_widget(list, chunks[0], &mut state);
+            }
+            Mode::EnterFile(format, file) => {
+                let title = format!("Output file of {} (empty for stdout)", format);
+                let input = Paragraph::new(file.as_str())
+                    .block(Block::default().borders(Borders::ALL).title(title));
+                frame.render_widget(input, chunks[0]);
+            }
+        }
+
+        let help = match self.mode {
+            _ if !self.status.is_empty() => self.status.as_str(),
+            Mode::Browse => "↑/↓: select  →/Enter: open  ←: up  w: write  q: quit",
+            Mode::SelectFormat(_) => "↑/↓: select  Enter: choose  Esc: cancel",
+            Mode::EnterFile(..) => "Enter: write  Esc: cancel",
+        };
+        frame.render_widget(Paragraph::new(help), chunks[1]);
+    }
+}
+
+/// Iterate over the reference tokens and the values of the entries of the
+/// array or the map.
+fn entries(value: &Value) -> Box<dyn Iterator<Item = (String, &Value)> + '_> {
+    match value {
+        Value::Array(arr) => Box::new(arr.iter().enumerate().map(|(i, v)| (i.to_string(), v))),
+        Value::Map(map) => Box::new(map.iter().map(|(k, v)| (k.clone(), v))),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
+/// The maximum number of characters of a preview of a string.
+const PREVIEW_LEN: usize = 40;
+
+/// Get a one-line summary of the value.
+fn preview(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(bool) => bool.to_string(),
+        Value::Integer(int) => int.to_string(),
+        Value::Float(float) => float.to_string(),
+        Value::String(str) if str.chars().count() > PREVIEW_LEN => {
+            let str: String = str.chars().take(PREVIEW_LEN).collect();
+            format!("{:?}...", str)
+        }
+        Value::String(str) => format!("{:?}", str),
+        Value::Array(arr) => format!("array of {} elements", arr.len()),
+        Value::Map(map) => format!("map of {} entries", map.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn browse() {
+        let value = Value::Map(
+            vec![
+                ("a".to_string(), Value::Null),
+                (
+                    "b/c".to_string(),
+                    Value::Array(vec![Value::Bool(true), Value::Bool(false)]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let options = Options::default();
+        let mut browser = Browser::new(&value, &options);
+
+        assert!(!browser.handle_key(KeyCode::Down));
+        assert!(!browser.handle_key(KeyCode::Down));
+        assert_eq!(browser.position().2, 1);
+        browser.handle_key(KeyCode::Enter);
+        browser.handle_key(KeyCode::Char('j'));
+        assert_eq!(browser.position().0, "/b~1c");
+        assert_eq!(browser.position().2, 1);
+        browser.handle_key(KeyCode::Enter);
+        assert_eq!(browser.position().0, "/b~1c/1");
+        assert_eq!(browser.position().1, &Value::Bool(false));
+        browser.handle_key(KeyCode::Enter);
+        assert_eq!(browser.position().0, "/b~1c/1");
+
+        browser.handle_key(KeyCode::Left);
+        browser.handle_key(KeyCode::Left);
+        browser.handle_key(KeyCode::Left);
+        assert_eq!(browser.position().0, "");
+        assert_eq!(browser.position().2, 1);
+        assert!(browser.handle_key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn write() {
+        let value = Value::Array(vec![Value::Integer(1_i64.into())]);
+        let options = Options::default();
+        let mut browser = Browser::new(&value, &options);
+        let json = OutputFormat::value_variants()
+            .iter()
+            .position(|f| matches!(Format::from(*f), Format::Json))
+            .unwrap();
+
+        browser.handle_key(KeyCode::Char('w'));
+        for _ in 0..json {
+            browser.handle_key(KeyCode::Down);
+        }
+        browser.handle_key(KeyCode::Enter);
+        assert!(matches!(browser.mode, Mode::EnterFile(Format::Json, _)));
+        browser.handle_key(KeyCode::Enter);
+        assert!(matches!(browser.mode, Mode::Browse));
+        assert_eq!(browser.stdout, b"[1]\n");
+        assert_eq!(browser.status, "4 bytes will be written to stdout");
+
+        browser.handle_key(KeyCode::Char('w'));
+        browser.handle_key(KeyCode::Esc);
+        assert!(matches!(browser.mode, Mode::Browse));
+        assert!(!browser.handle_key(KeyCode::Char('x')));
+        assert!(browser.status.is_empty());
+    }
+}
@@ -0,0 +1,882 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::io::{BufRead, Read, Write};
+use std::{mem, str};
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::de::DeserializeSeed;
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+use serde_json::Value as Json;
+use toml::Value as Toml;
+
+use crate::de::YamlSeed;
+use crate::lossy::Tracker;
+use crate::value::{FloatFormat, Format, KeyConflict, ParseErrorPolicy, Tracked, Value};
+use crate::{
+    c_header, cbor, de, env, json, jsonc, lines, nginx, openstep, reg, tabular, toml_array,
+    urlencoded, yaml, Options,
+};
+
+/// Read a value in `format` from `reader`.
+///
+/// Duplicate keys of maps and invalid UTF-8 sequences are handled as `options`
+/// selects. The reader should be buffered, since some formats are read from it
+/// in small pieces.
+pub fn read_value<R: Read>(format: Format, mut reader: R, options: &Options) -> Result<Value> {
+    // Other formats and the input which has to be scanned or repaired as a
+    // whole are read to the end first.
+    let ir = match format {
+        Format::Cbor if !options.lossy_utf8 && !options.cbor_strict_undefined => Value::from_cbor(
+            de::cbor_from_reader(reader, options.on_key_conflict)
+                .context("Failed to deserialize from a CBOR bytes")?,
+            options,
+        )
+        .context("Failed to convert from a CBOR value")?,
+        Format::Json if !options.lossy_utf8 => Value::from_json(
+            de::json_from_reader(reader, options.on_key_conflict)
+                .context("Failed to deserialize from a JSON string")?,
+            options,
+        )
+        .context("Failed to convert from a JSON value")?,
+        Format::MessagePack => read_messagepack(reader, options)?,
+        Format::Yaml if !options.lossy_utf8 => YamlSeed(options.on_key_conflict)
+            .deserialize(serde_yaml::Deserializer::from_reader(reader))
+            .context("Failed to deserialize from a YAML string")?
+            .try_into()
+            .context("Failed to convert from a YAML value")?,
+        #[cfg(feature = "parquet")]
+        Format::Parquet => {
+            let mut input = Vec::new();
+            reader
+                .read_to_end(&mut input)
+                .context("Failed to read bytes")?;
+
+            crate::parquet::from_vec(input, options).context("Failed to read a Parquet file")?
+        }
+        format => {
+            let mut input = Vec::new();
+            reader
+                .read_to_end(&mut input)
+                .context("Failed to read bytes")?;
+
+            from_slice(&input, format, options)?
+        }
+    };
+
+    Ok(ir)
+}
+
+/// Read each document in `format` from `reader`.
+///
+/// Only YAML can contain multiple documents. The input in other formats is a
+/// single document.
+pub fn read_documents<R: Read>(
+    format: Format,
+    mut reader: R,
+    options: &Options,
+) -> Result<Vec<Value>> {
+    match format {
+        Format::Yaml if options.lossy_utf8 => {
+            let mut input = Vec::new();
+            reader
+                .read_to_end(&mut input)
+                .context("Failed to read bytes")?;
+
+            yaml_documents(
+                serde_yaml::Deserializer::from_str(&to_str(&input, options)?),
+                options.on_key_conflict,
+            )
+        }
+        Format::Yaml => yaml_documents(
+            serde_yaml::Deserializer::from_reader(reader),
+            options.on_key_conflict,
+        ),
+        format => read_value(format, reader, options).map(|ir| vec![ir]),
+    }
+}
+
+/// Convert each document of YAML.
+fn yaml_documents(
+    deserializer: serde_yaml::Deserializer<'_>,
+    on_key_conflict: KeyConflict,
+) -> Result<Vec<Value>> {
+    deserializer
+        .map(|document| {
+            YamlSeed(on_key_conflict)
+                .deserialize(document)
+                .context("Failed to deserialize from a YAML string")?
+                .try_into()
+                .context("Failed to convert from a YAML value")
+        })
+        .collect()
+}
+
+/// Read the records of NDJSON, JSON text sequences, CSV or TSV in `format`
+/// from `reader` into an array.
+///
+/// Records are read one at a time. If `limit` is specified, reading stops
+/// once the array has that many elements. Malformed records are handled by
+/// `on_parse_error`. Returns the array and the number of malformed records
+/// which are skipped or replaced with null.
+pub fn read_records<R: BufRead>(
+    format: Format,
+    mut reader: R,
+    on_parse_error: ParseErrorPolicy,
+    limit: Option<usize>,
+    options: &Options,
+) -> Result<(Value, usize)> {
+    let delimiter = match format {
+        Format::Csv => b',',
+        Format::JsonSeq | Format::Ndjson => {
+            let separator = if let Format::JsonSeq = format {
+                0x1e
+            } else {
+                b'\n'
+            };
+            let texts = reader.split(separator).map(|text| {
+                text.context("Failed to read bytes")
+                    .and_then(|text| to_str(&text, options).map(Cow::into_owned))
+            });
+
+            return json_records(texts, format, on_parse_error, limit, options);
+        }
+        Format::Tsv => b'\t',
+        format => bail!("{} is not a sequence of records", format),
+    };
+
+    let mut arr = Vec::new();
+    let mut push = |record| {
+        arr.push(record);
+
+        Ok::<_, csv::Error>(())
+    };
+    let malformed = if options.lossy_utf8 {
+        // Invalid UTF-8 sequences cannot be replaced by the reader of CSV.
+        let mut input = Vec::new();
+        reader
+            .read_to_end(&mut input)
+            .context("Failed to read bytes")?;
+
+        tabular::for_each_record(
+            to_str(&input, options)?.as_bytes(),
+            delimiter,
+            0,
+            limit,
+            on_parse_error,
+            options.on_key_conflict,
+            &mut push,
+        )
+    } else {
+        tabular::for_each_record(
+            reader,
+            delimiter,
+            0,
+            limit,
+            on_parse_error,
+            options.on_key_conflict,
+            &mut push,
+        )
+    }
+    .with_context(|| format!("Failed to read {} records", format))?;
+
+    Ok((Value::Array(arr), malformed))
+}
+
+/// Parse each of `texts` as JSON, ignoring blank ones, until the array has
+/// `limit` elements.
+fn json_records<T: AsRef<str>>(
+    texts: impl Iterator<Item = Result<T>>,
+    format: Format,
+    on_parse_error: ParseErrorPolicy,
+    limit: Option<usize>,
+    options: &Options,
+) -> Result<(Value, usize)> {
+    let mut malformed = 0;
+    let mut arr = Vec::new();
+    for text in texts {
+        if matches!(limit, Some(limit) if arr.len() >= limit) {
+            break;
+        }
+        let text = text?;
+        let text = text.as_ref();
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        match (
+            de::json_from_str(text, options.on_key_conflict),
+            on_parse_error,
+        ) {
+            (Ok(obj), _) => arr.push(
+                Value::from_json(obj, options).context("Failed to convert from a JSON value")?,
+            ),
+            (Err(err), ParseErrorPolicy::Abort) => {
+                return Err(err)
+                    .with_context(|| format!("Failed to deserialize from a {} string", format))
+            }
+            (Err(_), ParseErrorPolicy::Skip) => malformed += 1,
+            (Err(_), ParseErrorPolicy::Null) => {
+                malformed += 1;
+                arr.push(Value::Null);
+            }
+        }
+    }
+
+    Ok((Value::Array(arr), malformed))
+}
+
+/// Read a MessagePack value, which is wrapped in a byte array.
+fn read_messagepack<R: Read>(reader: R, options: &Options) -> Result<Value> {
+    let bytes = rmp_serde::from_read::<_, Vec<u8>>(reader)
+        .context("Failed to deserialize from a MessagePack bytes")?;
+
+    Value::from_messagepack(rmpv::decode::read_value(&mut bytes.as_slice())?, options)
+        .context("Failed to convert from a MessagePack value")
+}
+
+/// Convert `input` to a string, replacing invalid UTF-8 sequences with U+FFFD
+/// if `options` allows it.
+fn to_str<'a>(input: &'a [u8], options: &Options) -> Result<Cow<'a, str>> {
+    if options.lossy_utf8 {
+        Ok(String::from_utf8_lossy(input))
+    } else {
+        str::from_utf8(input)
+            .map(Cow::Borrowed)
+            .context("Failed to convert from bytes to a string")
+    }
+}
+
+fn from_slice(input: &[u8], format: Format, options: &Options) -> Result<Value> {
+    let ir = match format {
+        Format::Cbor => {
+            // `serde_cbor` decodes undefined as null.
+            if options.cbor_strict_undefined {
+                if let Some(offset) = cbor::find_undefined(input) {
+                    bail!(
+                        "CBOR undefined at byte offset {} cannot be converted",
+                        offset
+                    );
+                }
+            }
+
+            // `serde_cbor` rejects invalid UTF-8 sequences while decoding, so
+            // they are replaced beforehand. Malformed input is decoded as is
+            // to report the error.
+            let replaced = options
+                .lossy_utf8
+                .then(|| cbor::replace_invalid_utf8(input))
+                .flatten();
+            Value::from_cbor(
+                de::cbor_from_slice(
+                    replaced.as_deref().unwrap_or(input),
+                    options.on_key_conflict,
+                )
+                .context("Failed to deserialize from a CBOR bytes")?,
+                options,
+            )
+            .context("Failed to convert from a CBOR value")?
+        }
+        Format::Csv | Format::JsonSeq | Format::Ndjson | Format::Tsv => {
+            read_records(format, input, ParseErrorPolicy::Abort, None, options)?.0
+        }
+        Format::Hjson => Value::from_json(
+            de::hjson_from_str(&to_str(input, options)?, options.on_key_conflict)
+                .context("Failed to deserialize from a Hjson string")?,
+            options,
+        )
+        .context("Failed to convert from a JSON value")?,
+        Format::Json => Value::from_json(
+            de::json_from_str(&to_str(input, options)?, options.on_key_conflict)
+                .context("Failed to deserialize from a JSON string")?,
+            options,
+        )
+        .context("Failed to convert from a JSON value")?,
+        Format::Json5 => Value::from_json(
+            de::json5_from_str(&to_str(input, options)?, options.on_key_conflict)
+                .context("Failed to deserialize from a JSON5 string")?,
+            options,
+        )
+        .context("Failed to convert from a JSON value")?,
+        Format::Jsonc => Value::from_json(
+            de::json_from_str(
+                &jsonc::strip(&to_str(input, options)?),
+                options.on_key_conflict,
+            )
+            .context("Failed to deserialize from a JSONC string")?,
+            options,
+        )
+        .context("Failed to convert from a JSON value")?,
+        Format::Lines => lines::from_str(&to_str(input, options)?),
+        Format::MessagePack => read_messagepack(input, options)?,
+        Format::Nginx => nginx::from_str(&to_str(input, options)?)
+            .context("Failed to deserialize from an nginx configuration")?,
+        Format::OpenStep => openstep::from_str(&to_str(input, options)?, options.on_key_conflict)
+            .context("Failed to deserialize from an OpenStep property list")?,
+        // The Parquet reader takes the ownership of the input, so it is read in
+        // `read_value`.
+        #[cfg(feature = "parquet")]
+        Format::Parquet => unreachable!(),
+        #[cfg(not(feature = "parquet"))]
+        Format::Parquet => bail!("Parquet requires dsconv built with the `parquet` feature"),
+        Format::CHeader | Format::Env | Format::Reg => {
+            bail!("{} is only available as an output format", format)
+        }
+        Format::Ron => de::ron_from_str(&to_str(input, options)?, options.on_key_conflict)
+            .context("Failed to deserialize from a RON string")?
+            .try_into()
+            .context("Failed to convert from a RON value")?,
+        Format::Toml => toml::from_str::<Toml>(&to_str(input, options)?)
+            .context("Failed to deserialize from a TOML string")?
+            .into(),
+        Format::UrlEncoded => urlencoded::from_str(&to_str(input, options)?)
+            .context("Failed to deserialize from a URL-encoded form")?,
+        Format::Yaml => YamlSeed(options.on_key_conflict)
+            .deserialize(serde_yaml::Deserializer::from_str(&to_str(input, options)?))
+            .context("Failed to deserialize from a YAML string")?
+            .try_into()
+            .context("Failed to convert from a YAML value")?,
+    };
+
+    Ok(ir)
+}
+
+/// Write a value in `format` to `writer`.
+///
+/// Text formats except Windows Registry files end with a newline.
+pub fn write_value<W: Write>(
+    format: Format,
+    value: &Value,
+    writer: W,
+    options: &Options,
+) -> Result<()> {
+    write_value_tracked(format, value, writer, options, &mut Tracker::new())
+}
+
+/// Write a value in `format` to `writer` like `write_value`, reporting the
+/// nodes which are dropped or transformed to `tracker`.
+pub fn write_value_tracked<W: Write>(
+    format: Format,
+    value: &Value,
+    mut writer: W,
+    options: &Options,
+    tracker: &mut Tracker,
+) -> Result<()> {
+    if let (Some(root), true) = (format.required_root(), value.is_scalar()) {
+        bail!(
+            "{} requires {} at the root, but the root is a scalar ({})",
+            format,
+            root,
+            value.type_name()
+        );
+    }
+
+    let output = match format {
+        Format::Cbor => {
+            // `serde_cbor` cannot write bignums.
+            let mut buf = cbor::to_vec_tracked(value, options.cbor_float_width, tracker);
+
+            if options.cbor_self_describe {
+                buf.splice(..0, cbor::SELF_DESCRIBE_TAG);
+            }
+
+            buf
+        }
+        Format::CHeader => c_header::to_string_tracked(value, tracker)
+            .context("Failed to convert to a C header")?
+            .into_bytes(),
+        Format::Env => {
+            env::to_string_tracked(value, &options.env_prefix, &options.env_separator, tracker)
+                .context("Failed to convert to shell exports")?
+                .into_bytes()
+        }
+        Format::Json if options.jcs => json::to_string_canonical_tracked(value, tracker)
+            .context("Failed to convert to canonical JSON")?
+            .into_bytes(),
+        Format::Json | Format::Jsonc => {
+            let mut obj = Json::try_from(value).context("Failed to convert to a JSON value")?;
+            json::format_floats(&mut obj, options.float_format);
+
+            let mut buf = if options.pretty && options.compact_scalar_arrays {
+                json::to_string_pretty_compact(&obj).map(String::into_bytes)
+            } else if options.pretty {
+                serde_json::to_vec_pretty(&obj)
+            } else {
+                serde_json::to_vec(&obj)
+            }
+            .context("Failed to serialize to a JSON string")?;
+            buf.push(b'\n');
+
+            buf
+        }
+        Format::Lines => lines::to_string(value)
+            .context("Failed to convert to lines")?
+            .into_bytes(),
+        Format::MessagePack => {
+            let mut buf = Vec::new();
+            rmpv::encode::write_value(&mut buf, &value.to_messagepack(tracker))
+                .context("Failed to write a MessagePack value to buffer")?;
+
+            rmp_serde::to_vec(&buf).context("Failed to serialize to a MessagePack bytes")?
+        }
+        Format::JsonSeq | Format::Ndjson => {
+            let elems = match value {
+                Value::Array(arr) => arr.iter().collect(),
+                value => vec![value],
+            };
+
+            let mut buf = Vec::new();
+            for elem in elems {
+                let mut obj = Json::try_from(elem).context("Failed to convert to a JSON value")?;
+                json::format_floats(&mut obj, options.float_format);
+                if let Format::JsonSeq = format {
+                    buf.push(0x1e);
+                }
+                serde_json::to_writer(&mut buf, &obj)
+                    .with_context(|| format!("Failed to serialize to a {} string", format))?;
+                buf.push(b'\n');
+            }
+
+            buf
+        }
+        #[cfg(feature = "parquet")]
+        Format::Parquet => {
+            crate::parquet::to_vec(value).context("Failed to write a Parquet file")?
+        }
+        #[cfg(not(feature = "parquet"))]
+        Format::Parquet => bail!("Parquet requires dsconv built with the `parquet` feature"),
+        Format::Reg => reg::to_string_tracked(value, tracker)
+            .context("Failed to convert to a Windows Registry file")?
+            .into_bytes(),
+        Format::Ron => {
+            let cell = RefCell::new(mem::take(tracker));
+            let tracked = Tracked {
+                value,
+                tracker: &cell,
+            };
+            let str = if options.pretty {
+                let mut config = ron::ser::PrettyConfig::new().decimal_floats(true);
+                if let Some(limit) = options.ron_depth_limit {
+                    config = config.depth_limit(limit);
+                }
+                ron::ser::to_string_pretty(&tracked, config)
+            } else {
+                ron::to_string(&tracked)
+            };
+            *tracker = cell.into_inner();
+            let str = str.context("Failed to serialize to a RON string")?;
+
+            format!("{}\n", str).into_bytes()
+        }
+        Format::Toml => {
+            let obj = value
+                .to_toml_tracked(options.toml_stringify_mixed_arrays, tracker)
+                .context("Failed to convert to a TOML value")?;
+            ensure!(obj.is_table(), "TOML requires a map at the root");
+
+            let serialize = |value: &Toml| -> Result<String, toml::ser::Error> {
+                let mut buf = String::new();
+                let mut serializer = if options.pretty {
+                    toml::Serializer::pretty(&mut buf)
+                } else {
+                    toml::Serializer::new(&mut buf)
+                };
+                if options.toml_literal_strings {
+                    serializer.pretty_string_literal(true);
+                }
+                if options.toml_array_wrap.is_some() {
+                    serializer.pretty_array(false);
+                }
+                SourceOrder(value).serialize(&mut serializer)?;
+
+                Ok(buf)
+            };
+
+            match options.toml_array_wrap {
+                Some(max) => toml_array::to_string(&obj, max, serialize),
+                None => serialize(&obj),
+            }
+            .context("Failed to serialize to a TOML string")?
+            .into_bytes()
+        }
+        Format::UrlEncoded => {
+            let str = urlencoded::to_string_tracked(value, tracker)
+                .context("Failed to convert to a URL-encoded form")?;
+
+            format!("{}\n", str).into_bytes()
+        }
+        Format::Yaml => {
+            let is_default_style = options.yaml_indent == 2
+                && !options.yaml_tags
+                && !options.yaml_literal_block
+                && !options.compact_scalar_arrays
+                && matches!(options.float_format, FloatFormat::Shortest);
+
+            if is_default_style {
+                serde_yaml::to_string(&value.to_yaml(tracker))
+            } else {
+                yaml::to_string_tracked(value, options, tracker)
+            }
+            .context("Failed to serialize to a YAML string")?
+            .into_bytes()
+        }
+        Format::Csv
+        | Format::Hjson
+        | Format::Json5
+        | Format::Nginx
+        | Format::OpenStep
+        | Format::Tsv => bail!("{} is only available as an input format", format),
+    };
+
+    writer
+        .write_all(&output)
+        .context("Failed to write the output")
+}
+
+/// A TOML value which is serialized keeping the order of the keys.
+///
+/// TOML requires the values of a table to precede its subtables, so the
+/// values are written first and then the subtables and the arrays of tables.
+/// Each of them keeps the order in the source, unlike `toml::Value` which
+/// writes all the arrays of tables before the subtables.
+struct SourceOrder<'a>(&'a Toml);
+
+impl SourceOrder<'_> {
+    fn is_table(value: &Toml) -> bool {
+        match value {
+            Toml::Table(_) => true,
+            Toml::Array(arr) => arr.iter().any(Toml::is_table),
+            _ => false,
+        }
+    }
+}
+
+impl Serialize for SourceOrder<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Toml::Table(table) => {
+                let mut map = serializer.serialize_map(Some(table.len()))?;
+                for (k, v) in table.iter().filter(|(_, v)| !Self::is_table(v)) {
+                    map.serialize_entry(k, &SourceOrder(v))?;
+                }
+                for (k, v) in table.iter().filter(|(_, v)| Self::is_table(v)) {
+                    map.serialize_entry(k, &SourceOrder(v))?;
+                }
+                map.end()
+            }
+            Toml::Array(arr) => {
+                let mut seq = serializer.serialize_seq(Some(arr.len()))?;
+                for v in arr {
+                    seq.serialize_element(&SourceOrder(v))?;
+                }
+                seq.end()
+            }
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::ByteStringMode;
+
+    #[test]
+    fn round_trip() {
+        let value = Value::Map(
+            vec![
+                ("a".to_string(), Value::Integer((-1_i64).into())),
+                (
+                    "b".to_string(),
+                    Value::Array(vec![
+                        Value::String("c".to_string()),
+                        Value::String("d".to_string()),
+                    ]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        for format in [
+            Format::Cbor,
+            Format::Json,
+            Format::MessagePack,
+            Format::Ron,
+            Format::Toml,
+            Format::Yaml,
+        ] {
+            let mut buf = Vec::new();
+            write_value(format, &value, &mut buf, &Options::default()).unwrap();
+            assert_eq!(
+                read_value(format, buf.as_slice(), &Options::default()).unwrap(),
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn cbor_self_describe() {
+        let mut buf = Vec::new();
+        let options = Options::new().cbor_self_describe(true);
+        write_value(Format::Cbor, &Value::Bool(true), &mut buf, &options).unwrap();
+        assert_eq!(buf, [0xd9, 0xd9, 0xf7, 0xf5]);
+        assert_eq!(
+            read_value(Format::Cbor, buf.as_slice(), &Options::default()).unwrap(),
+            Value::Bool(true)
+        );
+    }
+
+    #[test]
+    fn records() {
+        let input = &b"1\n{\n\n2\n"[..];
+
+        assert!(read_records(
+            Format::Ndjson,
+            input,
+            ParseErrorPolicy::Abort,
+            None,
+            &Options::default()
+        )
+        .is_err());
+        assert_eq!(
+            read_records(
+                Format::Ndjson,
+                input,
+                ParseErrorPolicy::Skip,
+                None,
+                &Options::default()
+            )
+            .unwrap(),
+            (
+                Value::Array(vec![
+                    Value::Integer(1_u64.into()),
+                    Value::Integer(2_u64.into())
+                ]),
+                1
+            )
+        );
+        assert_eq!(
+            read_records(
+                Format::Ndjson,
+                input,
+                ParseErrorPolicy::Null,
+                None,
+                &Options::default()
+            )
+            .unwrap()
+            .1,
+            1
+        );
+        assert!(read_records(
+            Format::Json,
+            input,
+            ParseErrorPolicy::Skip,
+            None,
+            &Options::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn toml_source_order() {
+        let table = |k: &str| {
+            Value::Map(
+                vec![(k.to_string(), Value::Integer(1_u64.into()))]
+                    .into_iter()
+                    .collect(),
+            )
+        };
+        let value = Value::Map(
+            vec![
+                ("a".to_string(), Value::Integer(1_u64.into())),
+                ("t".to_string(), table("x")),
+                ("aot".to_string(), Value::Array(vec![table("y")])),
+                ("b".to_string(), Value::Array(vec![])),
+                ("s".to_string(), table("z")),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let mut buf = Vec::new();
+        write_value(Format::Toml, &value, &mut buf, &Options::default()).unwrap();
+
+        assert_eq!(
+            str::from_utf8(&buf).unwrap(),
+            "a = 1\nb = []\n\n[t]\nx = 1\n\n[[aot]]\ny = 1\n\n[s]\nz = 1\n"
+        );
+    }
+
+    #[test]
+    fn jcs() {
+        let value = Value::Map(
+            vec![
+                ("b".to_string(), Value::Float(1.0)),
+                ("a".to_string(), Value::Array(vec![])),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let mut buf = Vec::new();
+        let options = Options::new().pretty(true).jcs(true);
+        write_value(Format::Json, &value, &mut buf, &options).unwrap();
+
+        assert_eq!(buf, br#"{"a":[],"b":1}"#);
+    }
+
+    #[test]
+    fn scalar_root() {
+        let value = Value::Integer(1_u64.into());
+        for format in [Format::Toml, Format::Lines, Format::UrlEncoded] {
+            assert!(write_value(format, &value, Vec::new(), &Options::default()).is_err());
+        }
+        assert_eq!(
+            write_value(Format::Toml, &value, Vec::new(), &Options::default())
+                .unwrap_err()
+                .to_string(),
+            "TOML requires a map at the root, but the root is a scalar (integer)"
+        );
+
+        let mut buf = Vec::new();
+        write_value(Format::Json, &value, &mut buf, &Options::default()).unwrap();
+        assert_eq!(buf, b"1\n");
+    }
+
+    #[test]
+    fn cbor_undefined() {
+        let input = [0x82, 0xf6, 0xf7];
+        assert_eq!(
+            read_value(Format::Cbor, &input[..], &Options::default()).unwrap(),
+            Value::Array(vec![Value::Null, Value::Null])
+        );
+        assert!(read_value(
+            Format::Cbor,
+            &input[..],
+            &Options::new().cbor_strict_undefined(true)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let lossy = Options::new().lossy_utf8(true);
+
+        let input = rmp_serde::to_vec(&[0x81, 0xa2, b'a', 0xff, 0xa1, 0xc3]).unwrap();
+        assert!(read_value(Format::MessagePack, input.as_slice(), &Options::default()).is_err());
+        assert_eq!(
+            read_value(Format::MessagePack, input.as_slice(), &lossy).unwrap(),
+            Value::Map(
+                vec![(
+                    "a\u{fffd}".to_string(),
+                    Value::String("\u{fffd}".to_string())
+                )]
+                .into_iter()
+                .collect()
+            )
+        );
+
+        // {"a\xff": "\xc3"}
+        let input = [0xa1, 0x62, b'a', 0xff, 0x61, 0xc3];
+        assert!(read_value(Format::Cbor, &input[..], &Options::default()).is_err());
+        assert_eq!(
+            read_value(Format::Cbor, &input[..], &lossy).unwrap(),
+            Value::Map(
+                vec![(
+                    "a\u{fffd}".to_string(),
+                    Value::String("\u{fffd}".to_string())
+                )]
+                .into_iter()
+                .collect()
+            )
+        );
+
+        assert!(read_value(Format::Yaml, &b"a: \xff\n"[..], &Options::default()).is_err());
+        assert_eq!(
+            read_value(Format::Yaml, &b"a: \xff\n"[..], &lossy).unwrap(),
+            Value::Map(
+                vec![("a".to_string(), Value::String("\u{fffd}".to_string()))]
+                    .into_iter()
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn byte_strings() {
+        // {"a": [bin8 0xa1 0x62]}
+        let input = rmp_serde::to_vec(&[0x81, 0xa1, b'a', 0x91, 0xc4, 0x02, 0xa1, 0x62]).unwrap();
+        assert!(read_value(Format::MessagePack, input.as_slice(), &Options::default()).is_err());
+        assert_eq!(
+            read_value(
+                Format::MessagePack,
+                input.as_slice(),
+                &Options::new().byte_strings(ByteStringMode::Hexdump)
+            )
+            .unwrap(),
+            Value::Map(
+                vec![(
+                    "a".to_string(),
+                    Value::Array(vec![Value::String("a1 62".to_string())])
+                )]
+                .into_iter()
+                .collect()
+            )
+        );
+
+        // {h'61': [h'a16162', null]}
+        let input = [0xa1, 0x41, 0x61, 0x82, 0x43, 0xa1, 0x61, 0x62, 0xf6];
+        assert!(read_value(Format::Cbor, &input[..], &Options::default()).is_err());
+        assert_eq!(
+            read_value(
+                Format::Cbor,
+                &input[..],
+                &Options::new().byte_strings(ByteStringMode::Base64)
+            )
+            .unwrap(),
+            Value::Map(
+                vec![(
+                    "YQ==".to_string(),
+                    Value::Array(vec![Value::String("oWFi".to_string()), Value::Null])
+                )]
+                .into_iter()
+                .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn json_seq() {
+        let input = &b"\x1e{\"a\":1}\n\x1e2\n\x1e[tru\n\x1e\n"[..];
+        assert!(read_value(Format::JsonSeq, input, &Options::default()).is_err());
+
+        let (value, malformed) = read_records(
+            Format::JsonSeq,
+            input,
+            ParseErrorPolicy::Skip,
+            None,
+            &Options::default(),
+        )
+        .unwrap();
+        assert_eq!(malformed, 1);
+
+        let mut buf = Vec::new();
+        write_value(Format::JsonSeq, &value, &mut buf, &Options::default()).unwrap();
+        assert_eq!(buf, b"\x1e{\"a\":1}\n\x1e2\n");
+        assert_eq!(
+            read_value(Format::JsonSeq, buf.as_slice(), &Options::default()).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(read_value(Format::Reg, &b""[..], &Options::default()).is_err());
+        assert!(write_value(Format::Csv, &Value::Null, Vec::new(), &Options::default()).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
+use serde_json::Value as Json;
+
+use crate::convert::ConvertError;
+use crate::float;
+use crate::lossy::Tracker;
+use crate::value::{FloatFormat, Value};
+
+/// Serialize the value to a pretty-printed JSON string like
+/// `serde_json::to_string_pretty`, but write non-empty arrays which consist
+/// only of scalars on a single line (e.g. `[1, 2, 3]`).
+pub fn to_string_pretty_compact(value: &Json) -> serde_json::Result<String> {
+    let mut buf = String::new();
+    write_value(&mut buf, value, 0)?;
+
+    Ok(buf)
+}
+
+fn write_indent(buf: &mut String, depth: usize) {
+    buf.push('\n');
+    buf.extend((0..depth * 2).map(|_| ' '));
+}
+
+fn write_value(buf: &mut String, value: &Json, depth: usize) -> serde_json::Result<()> {
+    match value {
+        Json::Array(arr) if arr.is_empty() => buf.push_str("[]"),
+        Json::Array(arr) if arr.iter().all(|v| !v.is_array() && !v.is_object()) => {
+            let elems = arr
+                .iter()
+                .map(serde_json::to_string)
+                .collect::<Result<Vec<_>, _>>()?;
+            buf.push('[');
+            buf.push_str(&elems.join(", "));
+            buf.push(']');
+        }
+        Json::Array(arr) => {
+            buf.push('[');
+            for (i, v) in arr.iter().enumerate() {
+                if i > 0 {
+                    buf.push(',');
+                }
+                write_indent(buf, depth + 1);
+                write_value(buf, v, depth + 1)?;
+            }
+            write_indent(buf, depth);
+            buf.push(']');
+        }
+        Json::Object(obj) if obj.is_empty() => buf.push_str("{}"),
+        Json::Object(obj) => {
+            buf.push('{');
+            for (i, (k, v)) in obj.iter().enumerate() {
+                if i > 0 {
+                    buf.push(',');
+                }
+                write_indent(buf, depth + 1);
+                buf.push_str(&serde_json::to_string(k)?);
+                buf.push_str(": ");
+                write_value(buf, v, depth + 1)?;
+            }
+            write_indent(buf, depth);
+            buf.push('}');
+        }
+        _ => buf.push_str(&serde_json::to_string(value)?),
+    }
+
+    Ok(())
+}
+
+/// Rewrite the floats in the value as the language selected by `format`
+/// writes them.
+///
+/// This relies on the `arbitrary_precision` feature of `serde_json`, which
+/// keeps the representation of numbers as is.
+pub fn format_floats(value: &mut Json, format: FloatFormat) {
+    match value {
+        Json::Number(num) if num.is_f64() => {
+            let str = num.as_f64().and_then(|f| float::to_string(f, format));
+            if let Some(str) = str {
+                *num = serde_json::from_str(&str).expect("Invalid number");
+            }
+        }
+        Json::Array(arr) => arr.iter_mut().for_each(|v| format_floats(v, format)),
+        Json::Object(map) => map.values_mut().for_each(|v| format_floats(v, format)),
+        _ => {}
+    }
+}
+
+/// Serialize the value to a canonical JSON string as defined by RFC 8785
+/// (JSON Canonicalization Scheme).
+///
+/// Keys are sorted by their UTF-16 code units, no whitespace is written, and
+/// numbers are written as ECMAScript does. Since numbers are IEEE 754 doubles
+/// in JCS, integers which cannot be represented exactly are rounded.
+pub fn to_string_canonical(value: &Value) -> Result<String, ConvertError> {
+    to_string_canonical_tracked(value, &mut Tracker::new())
+}
+
+/// Serialize the value to a canonical JSON string like `to_string_canonical`,
+/// reporting the integers which are rounded to `tracker`.
+pub(crate) fn to_string_canonical_tracked(
+    value: &Value,
+    tracker: &mut Tracker,
+) -> Result<String, ConvertError> {
+    let mut buf = String::new();
+    write_canonical(&mut buf, value, tracker)?;
+
+    Ok(buf)
+}
+
+fn write_canonical(
+    buf: &mut String,
+    value: &Value,
+    tracker: &mut Tracker,
+) -> Result<(), ConvertError> {
+    match value {
+        Value::Null => buf.push_str("null"),
+        Value::Bool(bool) => buf.push_str(&bool.to_string()),
+        Value::Integer(int) => {
+            let float = int.as_f64();
+            if BigInt::from_f64(float).as_ref() != Some(&int.to_bigint()) {
+                tracker.compare(value, &Value::Float(float));
+            }
+            buf.push_str(&float::to_ecmascript_string(float));
+        }
+        Value::Float(float) if !float.is_finite() => {
+            return Err(ConvertError::NonFiniteFloat(*float))
+        }
+        Value::Float(float) => buf.push_str(&float::to_ecmascript_string(*float)),
+        Value::String(str) => buf.push_str(&quote(str)),
+        Value::Array(arr) => {
+            buf.push('[');
+            for (i, v) in arr.iter().enumerate() {
+                if i > 0 {
+                    buf.push(',');
+                }
+                tracker.nest(&i.to_string(), |tracker| write_canonical(buf, v, tracker))?;
+            }
+            buf.push(']');
+        }
+        Value::Map(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_cached_key(|(k, _)| k.encode_utf16().collect::<Vec<_>>());
+
+            buf.push('{');
+            for (i, (k, v)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    buf.push(',');
+                }
+                buf.push_str(&quote(k));
+                buf.push(':');
+                tracker.nest(k, |tracker| write_canonical(buf, v, tracker))?;
+            }
+            buf.push('}');
+        }
+    }
+
+    Ok(())
+}
+
+fn quote(str: &str) -> String {
+    serde_json::to_string(str).expect("Failed to serialize a string")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact() {
+        let value: Json =
+            serde_json::from_str(r#"{"a":[1,"b",null,true,1.5],"c":[[1],{"d":[]}],"e":{},"f":[]}"#)
+                .unwrap();
+
+        assert_eq!(
+            to_string_pretty_compact(&value).unwrap(),
+            concat!(
+                "{\n",
+                "  \"a\": [1, \"b\", null, true, 1.5],\n",
+                "  \"c\": [\n",
+                "    [1],\n",
+                "    {\n",
+                "      \"d\": []\n",
+                "    }\n",
+                "  ],\n",
+                "  \"e\": {},\n",
+                "  \"f\": []\n",
+                "}"
+            )
+        );
+    }
+
+    #[test]
+    fn same_as_serde_json() {
+        let value: Json =
+            serde_json::from_str(r#"{"a":[{"b":"\n"},[]],"c":{"d":{"e":null}}}"#).unwrap();
+
+        assert_eq!(
+            to_string_pretty_compact(&value).unwrap(),
+            serde_json::to_string_pretty(&value).unwrap()
+        );
+    }
+
+    #[test]
+    fn float_format() {
+        let mut value: Json = serde_json::from_str(r#"{"a":[1e16,1e-5,10],"b":100000.0}"#).unwrap();
+        super::format_floats(&mut value, FloatFormat::Python);
+
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"{"a":[1e+16,1e-05,10],"b":100000.0}"#
+        );
+
+        super::format_floats(&mut value, FloatFormat::Go);
+
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"{"a":[1e+16,1e-05,10],"b":100000}"#
+        );
+    }
+
+    #[test]
+    fn canonical() {
+        let value = Value::Map(
+            vec![
+                (
+                    "\u{20ac}".to_string(),
+                    Value::String("\u{1f600}\n\u{1f}".to_string()),
+                ),
+                ("\u{1f600}".to_string(), Value::Null),
+                ("b".to_string(), Value::Array(vec![Value::Bool(true)])),
+                ("a".to_string(), Value::Integer(u64::MAX.into())),
+                ("c".to_string(), Value::Float(1e-7)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        assert_eq!(
+            to_string_canonical(&value).unwrap(),
+            "{\"a\":18446744073709552000,\"b\":[true],\"c\":1e-7,\"\u{20ac}\":\"\u{1f600}\\n\\u001f\",\"\u{1f600}\":null}"
+        );
+        assert!(matches!(
+            to_string_canonical(&Value::Float(f64::INFINITY)),
+            Err(ConvertError::NonFiniteFloat(_))
+        ));
+    }
+}
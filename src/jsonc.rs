@@ -0,0 +1,97 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+/// Strip comments and trailing commas from a JSONC string.
+///
+/// Comments and trailing commas are replaced with spaces so that positions in
+/// error messages still point into the original input.
+pub fn strip(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    let mut comma = None;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                comma = None;
+                output.push(c);
+                while let Some(c) = chars.next() {
+                    output.push(c);
+                    match c {
+                        '\\' => output.extend(chars.next()),
+                        '"' => break,
+                        _ => (),
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                output.push(' ');
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        output.push(c);
+                        break;
+                    }
+                    output.push(' ');
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                output.push_str("  ");
+                while let Some(c) = chars.next() {
+                    if c == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        output.push_str("  ");
+                        break;
+                    }
+                    output.push(if c == '\n' { c } else { ' ' });
+                }
+            }
+            ',' => {
+                comma = Some(output.len());
+                output.push(c);
+            }
+            '}' | ']' => {
+                if let Some(pos) = comma.take() {
+                    output.replace_range(pos..=pos, " ");
+                }
+                output.push(c);
+            }
+            c if c.is_whitespace() => output.push(c),
+            c => {
+                comma = None;
+                output.push(c);
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_comments() {
+        assert_eq!(
+            strip("{\n  // comment\n  \"a\": 1 /* b */\n}"),
+            "{\n            \n  \"a\": 1        \n}"
+        );
+        assert_eq!(
+            strip(r#"{"a": "// not a comment /* */"}"#),
+            r#"{"a": "// not a comment /* */"}"#
+        );
+        assert_eq!(strip(r#"["\"//"]"#), r#"["\"//"]"#);
+    }
+
+    #[test]
+    fn strip_trailing_commas() {
+        assert_eq!(strip("[1, 2,]"), "[1, 2 ]");
+        assert_eq!(strip("{\"a\": [1,],\n}"), "{\"a\": [1 ] \n}");
+        assert_eq!(strip("[1, // c\n]"), "[1      \n]");
+        assert_eq!(strip(r#"[",]"]"#), r#"[",]"]"#);
+    }
+}
@@ -0,0 +1,609 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+//! A data-serialization format converter.
+
+pub mod c_header;
+pub mod cbor;
+pub mod convert;
+pub mod de;
+pub mod detect;
+pub mod env;
+pub mod float;
+pub mod io;
+pub mod json;
+pub mod jsonc;
+pub mod lines;
+pub mod lossy;
+pub mod nginx;
+pub mod openstep;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod reg;
+pub mod schema;
+pub mod stats;
+pub mod tabular;
+pub mod toml_array;
+pub mod transform;
+pub mod urlencoded;
+pub mod value;
+pub mod yaml;
+
+pub use crate::convert::ConvertError;
+pub use crate::value::Value;
+
+use crate::value::{
+    ByteStringMode, Depth, FloatFormat, FloatWidth, KeyCase, KeyConflict, SortScope, TimeZone,
+    Transform,
+};
+
+/// The options which affect how a value is read, transformed and written.
+///
+/// # Examples
+///
+/// ```
+/// use dsconv::value::FloatWidth;
+/// use dsconv::Options;
+///
+/// let options = Options::new()
+///     .pretty(true)
+///     .cbor_float_width(FloatWidth::Single)
+///     .yaml_indent(4);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub(crate) pretty: bool,
+    pub(crate) compact_scalar_arrays: bool,
+    pub(crate) jcs: bool,
+    pub(crate) float_format: FloatFormat,
+    pub(crate) cbor_float_width: FloatWidth,
+    pub(crate) cbor_self_describe: bool,
+    pub(crate) ron_depth_limit: Option<usize>,
+    pub(crate) toml_stringify_mixed_arrays: bool,
+    pub(crate) toml_literal_strings: bool,
+    pub(crate) toml_array_wrap: Option<usize>,
+    pub(crate) yaml_indent: usize,
+    pub(crate) yaml_tags: bool,
+    pub(crate) yaml_literal_block: bool,
+    pub(crate) env_prefix: String,
+    pub(crate) env_separator: String,
+    pub(crate) on_key_conflict: KeyConflict,
+    pub(crate) lossy_utf8: bool,
+    pub(crate) byte_strings: ByteStringMode,
+    pub(crate) preserve_cbor_bignum: bool,
+    pub(crate) preserve_json_decimal: bool,
+    pub(crate) cbor_strict_undefined: bool,
+    pub(crate) transform_order: Vec<Transform>,
+    pub(crate) group_by: Option<String>,
+    pub(crate) drop_group_key: bool,
+    pub(crate) ungroup: Option<String>,
+    pub(crate) key_case: Option<KeyCase>,
+    pub(crate) strip_prefix: Option<String>,
+    pub(crate) strip_prefix_recursive: bool,
+    pub(crate) key_order: Vec<String>,
+    pub(crate) dedupe_array: bool,
+    pub(crate) dedupe_array_at: Vec<String>,
+    pub(crate) sort_arrays: bool,
+    pub(crate) sort_arrays_at: Vec<String>,
+    pub(crate) trim_strings: bool,
+    pub(crate) collapse_whitespace: bool,
+    pub(crate) trim_keys: bool,
+    pub(crate) replace: Vec<(String, String)>,
+    pub(crate) replace_regex: Vec<(String, String)>,
+    pub(crate) replace_keys: bool,
+    pub(crate) max_string_length: Option<usize>,
+    pub(crate) stringify_under: Vec<String>,
+    pub(crate) coerce_bools: bool,
+    pub(crate) true_tokens: Vec<String>,
+    pub(crate) false_tokens: Vec<String>,
+    pub(crate) parse_radix: bool,
+    pub(crate) datetime_tz: Option<TimeZone>,
+    pub(crate) null_as: Option<String>,
+    pub(crate) flatten_arrays: Option<Depth>,
+    pub(crate) arrays_as_maps: bool,
+    pub(crate) maps_as_arrays: bool,
+    pub(crate) delete: Vec<String>,
+    pub(crate) delete_missing_ok: bool,
+    pub(crate) set: Vec<(String, Value)>,
+    pub(crate) merge_into: Option<Value>,
+    pub(crate) sort_keys: Option<SortScope>,
+    pub(crate) wrap: Option<String>,
+    pub(crate) js_safe_integers: bool,
+}
+
+impl Options {
+    /// Create the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Output as a pretty-printed string. This applies to JSON, RON and TOML.
+    #[must_use]
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// Write arrays which consist only of scalars on a single line in
+    /// pretty-printed JSON and YAML.
+    #[must_use]
+    pub fn compact_scalar_arrays(mut self, compact: bool) -> Self {
+        self.compact_scalar_arrays = compact;
+        self
+    }
+
+    /// Output JSON canonicalized by RFC 8785 (JSON Canonicalization Scheme).
+    ///
+    /// This takes precedence over `pretty`, and the output is not followed by
+    /// a newline.
+    #[must_use]
+    pub fn jcs(mut self, jcs: bool) -> Self {
+        self.jcs = jcs;
+        self
+    }
+
+    /// Set how floats are written in JSON, NDJSON, JSON text sequences and
+    /// YAML.
+    ///
+    /// This is ignored if `jcs` is set, since JCS defines how numbers are
+    /// written.
+    #[must_use]
+    pub fn float_format(mut self, format: FloatFormat) -> Self {
+        self.float_format = format;
+        self
+    }
+
+    /// Set the width of floats in CBOR.
+    #[must_use]
+    pub fn cbor_float_width(mut self, width: FloatWidth) -> Self {
+        self.cbor_float_width = width;
+        self
+    }
+
+    /// Prefix the self-describe tag (55799) to CBOR.
+    #[must_use]
+    pub fn cbor_self_describe(mut self, self_describe: bool) -> Self {
+        self.cbor_self_describe = self_describe;
+        self
+    }
+
+    /// Write collections nested deeper than `limit` levels on a single line
+    /// in pretty-printed RON.
+    #[must_use]
+    pub fn ron_depth_limit(mut self, limit: Option<usize>) -> Self {
+        self.ron_depth_limit = limit;
+        self
+    }
+
+    /// Convert the elements of arrays with mixed types to strings in TOML.
+    #[must_use]
+    pub fn toml_stringify_mixed_arrays(mut self, stringify: bool) -> Self {
+        self.toml_stringify_mixed_arrays = stringify;
+        self
+    }
+
+    /// Use literal strings of TOML for strings which need escapes.
+    #[must_use]
+    pub fn toml_literal_strings(mut self, literal: bool) -> Self {
+        self.toml_literal_strings = literal;
+        self
+    }
+
+    /// Write arrays longer than `max` elements one element per line in TOML.
+    #[must_use]
+    pub fn toml_array_wrap(mut self, max: Option<usize>) -> Self {
+        self.toml_array_wrap = max;
+        self
+    }
+
+    /// Set the number of spaces for each indentation level of YAML.
+    #[must_use]
+    pub fn yaml_indent(mut self, indent: usize) -> Self {
+        self.yaml_indent = indent;
+        self
+    }
+
+    /// Write maps which consist of `__tag` and `__value` as tagged nodes of
+    /// YAML.
+    #[must_use]
+    pub fn yaml_tags(mut self, tags: bool) -> Self {
+        self.yaml_tags = tags;
+        self
+    }
+
+    /// Write multi-line strings as literal block scalars (`|`) of YAML.
+    #[must_use]
+    pub fn yaml_literal_block(mut self, literal: bool) -> Self {
+        self.yaml_literal_block = literal;
+        self
+    }
+
+    /// Set the prefix of the names of shell exports.
+    #[must_use]
+    pub fn env_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.env_prefix = prefix.into();
+        self
+    }
+
+    /// Set the separator which joins the keys of nested maps in the names of
+    /// shell exports.
+    #[must_use]
+    pub fn env_separator(mut self, separator: impl Into<String>) -> Self {
+        self.env_separator = separator.into();
+        self
+    }
+
+    /// Set how keys which appear more than once in a map are handled when
+    /// reading.
+    #[must_use]
+    pub fn on_key_conflict(mut self, on_conflict: KeyConflict) -> Self {
+        self.on_key_conflict = on_conflict;
+        self
+    }
+
+    /// Replace invalid UTF-8 sequences with U+FFFD when reading instead of
+    /// returning an error.
+    #[must_use]
+    pub fn lossy_utf8(mut self, lossy: bool) -> Self {
+        self.lossy_utf8 = lossy;
+        self
+    }
+
+    /// Set how byte strings of CBOR and binaries of MessagePack are converted
+    /// when reading.
+    #[must_use]
+    pub fn byte_strings(mut self, mode: ByteStringMode) -> Self {
+        self.byte_strings = mode;
+        self
+    }
+
+    /// Convert bignums (tags 2 and 3) of CBOR to integers when reading.
+    #[must_use]
+    pub fn preserve_cbor_bignum(mut self, preserve: bool) -> Self {
+        self.preserve_cbor_bignum = preserve;
+        self
+    }
+
+    /// Keep numbers of JSON which `f64` cannot hold exactly as strings when
+    /// reading instead of rounding them.
+    #[must_use]
+    pub fn preserve_json_decimal(mut self, preserve: bool) -> Self {
+        self.preserve_json_decimal = preserve;
+        self
+    }
+
+    /// Reject undefined of CBOR when reading instead of converting it to null.
+    #[must_use]
+    pub fn cbor_strict_undefined(mut self, strict: bool) -> Self {
+        self.cbor_strict_undefined = strict;
+        self
+    }
+
+    /// Set the transforms which are applied first by `transform::apply`, in
+    /// this order. The others follow in the default order.
+    #[must_use]
+    pub fn transform_order(mut self, order: Vec<Transform>) -> Self {
+        self.transform_order = order;
+        self
+    }
+
+    /// Reshape an array of maps into a map keyed by the value of `key`.
+    #[must_use]
+    pub fn group_by(mut self, key: Option<String>) -> Self {
+        self.group_by = key;
+        self
+    }
+
+    /// Remove the key of `group_by` from each element.
+    #[must_use]
+    pub fn drop_group_key(mut self, drop: bool) -> Self {
+        self.drop_group_key = drop;
+        self
+    }
+
+    /// Reshape a map of maps into an array, storing each key under `key`.
+    #[must_use]
+    pub fn ungroup(mut self, key: Option<String>) -> Self {
+        self.ungroup = key;
+        self
+    }
+
+    /// Convert keys of maps to `case`.
+    #[must_use]
+    pub fn key_case(mut self, case: Option<KeyCase>) -> Self {
+        self.key_case = case;
+        self
+    }
+
+    /// Remove `prefix` from keys of the root map.
+    #[must_use]
+    pub fn strip_prefix(mut self, prefix: Option<String>) -> Self {
+        self.strip_prefix = prefix;
+        self
+    }
+
+    /// Remove the prefix of `strip_prefix` from keys of nested maps too.
+    #[must_use]
+    pub fn strip_prefix_recursive(mut self, recursive: bool) -> Self {
+        self.strip_prefix_recursive = recursive;
+        self
+    }
+
+    /// Move `keys` to the front of each map, in this order.
+    #[must_use]
+    pub fn key_order(mut self, keys: Vec<String>) -> Self {
+        self.key_order = keys;
+        self
+    }
+
+    /// Remove duplicate elements from every array.
+    #[must_use]
+    pub fn dedupe_array(mut self, dedupe: bool) -> Self {
+        self.dedupe_array = dedupe;
+        self
+    }
+
+    /// Remove duplicate elements from the arrays at `pointers`.
+    #[must_use]
+    pub fn dedupe_array_at(mut self, pointers: Vec<String>) -> Self {
+        self.dedupe_array_at = pointers;
+        self
+    }
+
+    /// Sort every array in the canonical order of values.
+    #[must_use]
+    pub fn sort_arrays(mut self, sort: bool) -> Self {
+        self.sort_arrays = sort;
+        self
+    }
+
+    /// Sort the arrays at `pointers` in the canonical order of values.
+    #[must_use]
+    pub fn sort_arrays_at(mut self, pointers: Vec<String>) -> Self {
+        self.sort_arrays_at = pointers;
+        self
+    }
+
+    /// Remove leading and trailing whitespace from strings.
+    #[must_use]
+    pub fn trim_strings(mut self, trim: bool) -> Self {
+        self.trim_strings = trim;
+        self
+    }
+
+    /// Also replace each run of whitespace inside strings with a single space
+    /// when trimming them.
+    #[must_use]
+    pub fn collapse_whitespace(mut self, collapse: bool) -> Self {
+        self.collapse_whitespace = collapse;
+        self
+    }
+
+    /// Trim keys of maps as well as strings.
+    #[must_use]
+    pub fn trim_keys(mut self, trim: bool) -> Self {
+        self.trim_keys = trim;
+        self
+    }
+
+    /// Replace each occurrence of the first string of each pair in strings
+    /// with the second one.
+    #[must_use]
+    pub fn replace(mut self, pairs: Vec<(String, String)>) -> Self {
+        self.replace = pairs;
+        self
+    }
+
+    /// Replace each match of the regular expression of each pair in strings
+    /// with the second string, which may refer to capture groups.
+    #[must_use]
+    pub fn replace_regex(mut self, pairs: Vec<(String, String)>) -> Self {
+        self.replace_regex = pairs;
+        self
+    }
+
+    /// Apply `replace` and `replace_regex` to keys of maps as well.
+    #[must_use]
+    pub fn replace_keys(mut self, replace: bool) -> Self {
+        self.replace_keys = replace;
+        self
+    }
+
+    /// Truncate strings longer than `max` characters, appending an ellipsis.
+    #[must_use]
+    pub fn max_string_length(mut self, max: Option<usize>) -> Self {
+        self.max_string_length = max;
+        self
+    }
+
+    /// Convert the scalars under `pointers` to strings.
+    #[must_use]
+    pub fn stringify_under(mut self, pointers: Vec<String>) -> Self {
+        self.stringify_under = pointers;
+        self
+    }
+
+    /// Convert strings which are boolean tokens to booleans.
+    #[must_use]
+    pub fn coerce_bools(mut self, coerce: bool) -> Self {
+        self.coerce_bools = coerce;
+        self
+    }
+
+    /// Set the tokens which `coerce_bools` converts to `true`. If empty,
+    /// `transform::TRUE_TOKENS` is used.
+    #[must_use]
+    pub fn true_tokens(mut self, tokens: Vec<String>) -> Self {
+        self.true_tokens = tokens;
+        self
+    }
+
+    /// Set the tokens which `coerce_bools` converts to `false`. If empty,
+    /// `transform::FALSE_TOKENS` is used.
+    #[must_use]
+    pub fn false_tokens(mut self, tokens: Vec<String>) -> Self {
+        self.false_tokens = tokens;
+        self
+    }
+
+    /// Convert strings such as `0xFF`, `0o17` and `0b1010` to integers.
+    #[must_use]
+    pub fn parse_radix(mut self, parse: bool) -> Self {
+        self.parse_radix = parse;
+        self
+    }
+
+    /// Convert strings of RFC 3339 datetimes to `tz`.
+    #[must_use]
+    pub fn datetime_tz(mut self, tz: Option<TimeZone>) -> Self {
+        self.datetime_tz = tz;
+        self
+    }
+
+    /// Replace nulls with `str`.
+    #[must_use]
+    pub fn null_as(mut self, str: Option<String>) -> Self {
+        self.null_as = str;
+        self
+    }
+
+    /// Flatten nested arrays up to `depth` levels.
+    #[must_use]
+    pub fn flatten_arrays(mut self, depth: Option<Depth>) -> Self {
+        self.flatten_arrays = depth;
+        self
+    }
+
+    /// Convert arrays into maps keyed by the indices of the elements.
+    #[must_use]
+    pub fn arrays_as_maps(mut self, convert: bool) -> Self {
+        self.arrays_as_maps = convert;
+        self
+    }
+
+    /// Convert maps whose keys are the indices from `0` into arrays.
+    #[must_use]
+    pub fn maps_as_arrays(mut self, convert: bool) -> Self {
+        self.maps_as_arrays = convert;
+        self
+    }
+
+    /// Delete the values at `pointers`.
+    #[must_use]
+    pub fn delete(mut self, pointers: Vec<String>) -> Self {
+        self.delete = pointers;
+        self
+    }
+
+    /// Ignore the pointers of `delete` which do not exist instead of
+    /// returning an error.
+    #[must_use]
+    pub fn delete_missing_ok(mut self, ok: bool) -> Self {
+        self.delete_missing_ok = ok;
+        self
+    }
+
+    /// Set the value at the pointer of each pair.
+    #[must_use]
+    pub fn set(mut self, assignments: Vec<(String, Value)>) -> Self {
+        self.set = assignments;
+        self
+    }
+
+    /// Merge the value into `base` before writing it.
+    #[must_use]
+    pub fn merge_into(mut self, base: Option<Value>) -> Self {
+        self.merge_into = base;
+        self
+    }
+
+    /// Sort the keys of maps lexically if the output format is in `scope`.
+    #[must_use]
+    pub fn sort_keys(mut self, scope: Option<SortScope>) -> Self {
+        self.sort_keys = scope;
+        self
+    }
+
+    /// Wrap a scalar at the root in the collection which the output format
+    /// requires, using `key` as the key of a map.
+    #[must_use]
+    pub fn wrap(mut self, key: Option<String>) -> Self {
+        self.wrap = key;
+        self
+    }
+
+    /// Convert integers which JavaScript cannot represent exactly to strings
+    /// if the output format is JSON, JSONC, JSON text sequences or NDJSON.
+    #[must_use]
+    pub fn js_safe_integers(mut self, stringify: bool) -> Self {
+        self.js_safe_integers = stringify;
+        self
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            pretty: false,
+            compact_scalar_arrays: false,
+            jcs: false,
+            float_format: FloatFormat::default(),
+            cbor_float_width: FloatWidth::default(),
+            cbor_self_describe: false,
+            ron_depth_limit: None,
+            toml_stringify_mixed_arrays: false,
+            toml_literal_strings: false,
+            toml_array_wrap: None,
+            yaml_indent: 2,
+            yaml_tags: false,
+            yaml_literal_block: false,
+            env_prefix: String::new(),
+            env_separator: "__".to_string(),
+            on_key_conflict: KeyConflict::default(),
+            lossy_utf8: false,
+            byte_strings: ByteStringMode::default(),
+            preserve_cbor_bignum: false,
+            preserve_json_decimal: false,
+            cbor_strict_undefined: false,
+            transform_order: Vec::new(),
+            group_by: None,
+            drop_group_key: false,
+            ungroup: None,
+            key_case: None,
+            strip_prefix: None,
+            strip_prefix_recursive: false,
+            key_order: Vec::new(),
+            dedupe_array: false,
+            dedupe_array_at: Vec::new(),
+            sort_arrays: false,
+            sort_arrays_at: Vec::new(),
+            trim_strings: false,
+            collapse_whitespace: false,
+            trim_keys: false,
+            replace: Vec::new(),
+            replace_regex: Vec::new(),
+            replace_keys: false,
+            max_string_length: None,
+            stringify_under: Vec::new(),
+            coerce_bools: false,
+            true_tokens: Vec::new(),
+            false_tokens: Vec::new(),
+            parse_radix: false,
+            datetime_tz: None,
+            null_as: None,
+            flatten_arrays: None,
+            arrays_as_maps: false,
+            maps_as_arrays: false,
+            delete: Vec::new(),
+            delete_missing_ok: false,
+            set: Vec::new(),
+            merge_into: None,
+            sort_keys: None,
+            wrap: None,
+            js_safe_integers: false,
+        }
+    }
+}
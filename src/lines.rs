@@ -0,0 +1,90 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use crate::convert::ConvertError;
+use crate::value::Value;
+
+/// Convert lines of text into an array of strings.
+///
+/// Trailing empty lines are ignored.
+pub fn from_str(input: &str) -> Value {
+    let mut lines: Vec<_> = input.lines().collect();
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+
+    Value::Array(
+        lines
+            .into_iter()
+            .map(|l| Value::String(l.to_string()))
+            .collect(),
+    )
+}
+
+/// Convert an array of strings into lines of text.
+///
+/// Each element is followed by a newline. Strings which contain a line break
+/// cannot be converted.
+pub fn to_string(value: &Value) -> Result<String, ConvertError> {
+    let arr = match value {
+        Value::Array(arr) => arr,
+        _ => return Err(ConvertError::NonArrayRoot),
+    };
+
+    arr.iter()
+        .enumerate()
+        .map(|(i, v)| match v {
+            Value::String(str) if str.contains(|c| matches!(c, '\n' | '\r')) => {
+                Err(ConvertError::MultilineElement(i))
+            }
+            Value::String(str) => Ok(format!("{}\n", str)),
+            _ => Err(ConvertError::NonStringElement(i)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_to_array() {
+        assert_eq!(
+            from_str("a\r\nb\n\nc\n\n\n"),
+            Value::Array(vec![
+                Value::String("a".to_string()),
+                Value::String("b".to_string()),
+                Value::String(String::new()),
+                Value::String("c".to_string()),
+            ])
+        );
+        assert_eq!(from_str(""), Value::Array(vec![]));
+    }
+
+    #[test]
+    fn array_to_lines() {
+        assert_eq!(to_string(&from_str("a\n\nb")).unwrap(), "a\n\nb\n");
+        assert!(matches!(
+            to_string(&Value::Null),
+            Err(ConvertError::NonArrayRoot)
+        ));
+        assert!(matches!(
+            to_string(&Value::Array(vec![
+                Value::String("a".to_string()),
+                Value::Array(vec![])
+            ])),
+            Err(ConvertError::NonStringElement(1))
+        ));
+        assert!(matches!(
+            to_string(&Value::Array(vec![Value::String("a\nb".to_string())])),
+            Err(ConvertError::MultilineElement(0))
+        ));
+        assert!(matches!(
+            to_string(&Value::Array(vec![Value::String("a\r".to_string())])),
+            Err(ConvertError::MultilineElement(0))
+        ));
+    }
+}
@@ -0,0 +1,319 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::fmt;
+
+use crate::convert::push_pointer_token;
+use crate::io;
+use crate::value::{Format, Value};
+use crate::Options;
+
+/// A node whose information is dropped or transformed by a conversion.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Loss {
+    /// The JSON Pointer to the node.
+    pub pointer: String,
+    /// What happens to the node.
+    pub description: String,
+}
+
+impl fmt::Display for Loss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.pointer.is_empty() {
+            write!(f, "(root): {}", self.description)
+        } else {
+            write!(f, "{}: {}", self.pointer, self.description)
+        }
+    }
+}
+
+/// A record of the nodes which a conversion drops or transforms.
+///
+/// Each converter reports to it as it converts the nodes, so the losses are
+/// exactly those of the conversion.
+#[derive(Debug, Default)]
+pub struct Tracker {
+    path: String,
+    losses: Vec<Loss>,
+    numbers: Vec<Loss>,
+}
+
+impl Tracker {
+    /// Create an empty tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The JSON Pointer to the node being converted.
+    pub(crate) fn pointer(&self) -> &str {
+        &self.path
+    }
+
+    /// Point to the child node at `token`, and return the length of the
+    /// pointer to the parent to pass to `leave`.
+    pub(crate) fn enter(&mut self, token: &str) -> usize {
+        let len = self.path.len();
+        push_pointer_token(&mut self.path, token);
+
+        len
+    }
+
+    /// Point back to the parent node.
+    pub(crate) fn leave(&mut self, len: usize) {
+        self.path.truncate(len);
+    }
+
+    /// Call `f` with the tracker pointing to the child node at `token`.
+    pub(crate) fn nest<T>(&mut self, token: &str, f: impl FnOnce(&mut Self) -> T) -> T {
+        let len = self.enter(token);
+        let result = f(self);
+        self.leave(len);
+
+        result
+    }
+
+    /// Report that the node being converted is dropped or transformed.
+    pub(crate) fn report(&mut self, description: impl Into<String>) {
+        self.losses.push(Loss {
+            pointer: self.path.clone(),
+            description: description.into(),
+        });
+    }
+
+    /// Compare the node being converted with what it becomes in the output,
+    /// and report it if they differ.
+    pub(crate) fn compare(&mut self, before: &Value, after: &Value) {
+        if before == after {
+            return;
+        }
+
+        let description = match (before, after) {
+            (Value::Integer(_) | Value::Float(_), Value::Integer(_) | Value::Float(_)) => format!(
+                "the {} {} is rounded to {}",
+                before.type_name(),
+                number(before),
+                number(after)
+            ),
+            _ => format!(
+                "the {} is converted to {}",
+                before.type_name(),
+                with_article(after.type_name())
+            ),
+        };
+        if let Value::Integer(_) | Value::Float(_) = before {
+            self.numbers.push(Loss {
+                pointer: self.path.clone(),
+                description: description.clone(),
+            });
+        }
+        self.report(description);
+    }
+
+    /// The nodes which are dropped or transformed.
+    pub fn losses(&self) -> &[Loss] {
+        &self.losses
+    }
+
+    /// The integers and floats whose value or type changes.
+    pub fn numbers(&self) -> &[Loss] {
+        &self.numbers
+    }
+}
+
+fn number(value: &Value) -> String {
+    match value {
+        Value::Integer(int) => int.to_string(),
+        Value::Float(float) => float.to_string(),
+        _ => unreachable!(),
+    }
+}
+
+fn with_article(type_name: &str) -> String {
+    if type_name.starts_with(|c| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')) {
+        format!("an {}", type_name)
+    } else {
+        format!("a {}", type_name)
+    }
+}
+
+/// Convert the value to `to` in the tracking mode, discarding the output.
+///
+/// Nodes which cause an error in the conversion are not reported, since the
+/// conversion stops there.
+pub fn track(value: &Value, to: Format, options: &Options) -> Tracker {
+    let mut tracker = Tracker::new();
+    // The error is reported by the conversion which writes the output.
+    let _ = io::write_value_tracked(to, value, std::io::sink(), options, &mut tracker);
+
+    tracker
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::FloatWidth;
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    fn losses(value: &Value, to: Format, options: &Options) -> Vec<String> {
+        track(value, to, options)
+            .losses()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn cbor_float_width() {
+        let value = map(vec![(
+            "a",
+            Value::Array(vec![Value::Float(0.5), Value::Float(0.1)]),
+        )]);
+        let options = Options::new().cbor_float_width(FloatWidth::Half);
+
+        assert_eq!(
+            track(&value, Format::Cbor, &options).losses(),
+            [Loss {
+                pointer: "/a/1".to_string(),
+                description: "the float 0.1 is rounded to 0.0999755859375".to_string(),
+            }]
+        );
+        assert!(losses(&value, Format::Cbor, &Options::default()).is_empty());
+        assert!(losses(&value, Format::Json, &options).is_empty());
+    }
+
+    #[test]
+    fn toml() {
+        let value = map(vec![
+            ("date", Value::String("1979-05-27".to_string())),
+            (
+                "mixed",
+                Value::Array(vec![Value::Bool(true), Value::String("a".to_string())]),
+            ),
+        ]);
+        let options = Options::new().toml_stringify_mixed_arrays(true);
+
+        assert_eq!(
+            losses(&value, Format::Toml, &options),
+            [
+                "/date: the string is converted to a datetime",
+                "/mixed/0: the boolean is converted to a string",
+            ]
+        );
+        // The conversion stops at the mixed array.
+        assert_eq!(losses(&value, Format::Toml, &Options::default()).len(), 1);
+        assert!(losses(&value, Format::Yaml, &options).is_empty());
+    }
+
+    #[test]
+    fn reg() {
+        let value = map(vec![(
+            r"HKEY_CURRENT_USER\Software\dsconv",
+            map(vec![
+                ("Enabled", Value::Bool(true)),
+                ("Count", Value::Integer(u64::from(u32::MAX).into())),
+                ("Large", Value::Integer(u64::MAX.into())),
+            ]),
+        )]);
+
+        assert_eq!(
+            losses(&value, Format::Reg, &Options::default()),
+            [
+                r"/HKEY_CURRENT_USER\Software\dsconv/Enabled: the boolean is converted to a DWORD",
+                r"/HKEY_CURRENT_USER\Software\dsconv/Large: the integer is converted to a QWORD",
+            ]
+        );
+    }
+
+    #[test]
+    fn wide_integers() {
+        let value = map(vec![
+            ("a", Value::Integer(u64::MAX.into())),
+            ("b", Value::Array(vec![Value::Integer(i128::MIN.into())])),
+        ]);
+
+        for format in [Format::MessagePack, Format::Ron, Format::Yaml] {
+            assert_eq!(
+                losses(&value, format, &Options::default()),
+                ["/b/0: the integer is converted to a string"]
+            );
+        }
+        assert_eq!(
+            losses(&value, Format::Yaml, &Options::new().yaml_indent(4)),
+            ["/b/0: the integer is converted to a string"]
+        );
+        assert!(losses(&value, Format::Cbor, &Options::default()).is_empty());
+        assert!(losses(&value, Format::Json, &Options::default()).is_empty());
+        assert_eq!(
+            losses(&value, Format::Json, &Options::new().jcs(true)),
+            ["/a: the integer 18446744073709551615 is rounded to 18446744073709552000"]
+        );
+    }
+
+    #[test]
+    fn env() {
+        let value = map(vec![
+            ("a", Value::Null),
+            ("b", Value::Array(vec![])),
+            ("c", map(vec![("d", Value::String("x".to_string()))])),
+        ]);
+
+        assert_eq!(
+            losses(&value, Format::Env, &Options::default()),
+            [
+                "/a: the null is converted to a string",
+                "/b: the empty array is dropped",
+            ]
+        );
+    }
+
+    #[test]
+    fn urlencoded() {
+        let value = map(vec![
+            ("a", Value::Integer(1_u64.into())),
+            ("b", Value::Array(vec![map(vec![("c", Value::Null)])])),
+            ("d", map(vec![])),
+            ("e", Value::String("x".to_string())),
+        ]);
+
+        assert_eq!(
+            losses(&value, Format::UrlEncoded, &Options::default()),
+            [
+                "/a: the integer is converted to a string",
+                "/b: the array is converted to a map keyed by indices",
+                "/b/0/c: the null is converted to a string",
+                "/d: the empty map is dropped",
+            ]
+        );
+        assert!(losses(&map(vec![]), Format::UrlEncoded, &Options::default()).is_empty());
+    }
+
+    #[test]
+    fn numbers() {
+        let value = map(vec![("a", Value::Bool(true)), ("b", Value::Float(0.5))]);
+        let tracker = track(&value, Format::CHeader, &Options::default());
+
+        assert_eq!(tracker.losses().len(), 1);
+        assert!(tracker.numbers().is_empty());
+
+        let tracker = track(&value, Format::Env, &Options::default());
+        assert_eq!(tracker.losses().len(), 2);
+        assert_eq!(
+            tracker.numbers(),
+            [Loss {
+                pointer: "/b".to_string(),
+                description: "the float is converted to a string".to_string(),
+            }]
+        );
+    }
+}
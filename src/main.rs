@@ -6,31 +6,87 @@
 
 mod cli;
 mod config;
-mod convert;
+mod error;
+mod golden;
+mod input;
+mod inspect;
+#[cfg(feature = "interactive")]
+mod interactive;
 mod macros;
-mod value;
+mod progress;
 
+use std::env;
 use std::ffi::OsStr;
-use std::fs;
-use std::io::{self, Read, Write};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 use std::str;
 
 use anyhow::{bail, ensure, Context, Result};
 use bat::PrettyPrinter;
-use clap::{ArgEnum, Parser};
+use clap::{ArgEnum, ErrorKind, IntoApp, Parser};
 use dialoguer::theme::ColorfulTheme;
-use rmpv::Value as MessagePack;
-use ron::Value as Ron;
-use serde_cbor::Value as Cbor;
 use serde_json::Value as Json;
-use serde_yaml::Value as Yaml;
-use toml::Value as Toml;
+
+use dsconv::value::{Color, Format, InputFormat, OutputFormat, ParseErrorPolicy, Value};
+use dsconv::{cbor, lossy, schema, stats, transform, yaml, Options};
 
 use crate::cli::Opt;
-use crate::value::{Color, Format, InputFormat, OutputFormat, Value};
+use crate::error::{FormatError, LossyError, MissingKeysError};
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {:?}", err);
+
+        let code = if err.chain().any(|e| e.is::<FormatError>()) {
+            FormatError::EXIT_CODE
+        } else if err.chain().any(|e| e.is::<MissingKeysError>()) {
+            MissingKeysError::EXIT_CODE
+        } else if err.chain().any(|e| e.is::<LossyError>()) {
+            LossyError::EXIT_CODE
+        } else {
+            1
+        };
+        process::exit(code);
+    }
+}
 
-fn main() -> Result<()> {
-    let opt = Opt::parse().apply_config()?;
+fn run() -> Result<()> {
+    let opt = match Opt::try_parse() {
+        Ok(opt) => opt,
+        Err(err) => match FormatError::from_clap(&err) {
+            Some(err) => return Err(err.into()),
+            None => err.exit(),
+        },
+    }
+    .apply_config()?;
+
+    // `--merge-into` cannot require `--output` with `requires`, which clap does
+    // not check because `--output` conflicts with `--color`, which has a
+    // default value.
+    if opt.merge_into && opt.output.is_none() {
+        Opt::into_app()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--merge-into requires --output <FILE>",
+            )
+            .exit();
+    }
+
+    // The byte order mark is not defined except for UTF-8 and UTF-16.
+    if opt.bom && !opt.output_encoding.has_bom() {
+        Opt::into_app()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!(
+                    "--bom requires UTF-8 or UTF-16 as --output-encoding, but got {}",
+                    opt.output_encoding
+                ),
+            )
+            .exit();
+    }
 
     if let Some(shell) = opt.generate_completion {
         if let Some(out_dir) = opt.output {
@@ -57,150 +113,199 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    let input = match opt.input {
-        Some(ref file) => fs::read(file)
-            .with_context(|| format!("Failed to read bytes from {}", file.display()))?,
-        _ if atty::is(atty::Stream::Stdin) => {
-            dialoguer::Input::<String>::with_theme(&ColorfulTheme::default())
-                .with_prompt("Input")
-                .interact()
-                .context("Failed to read a string from stdin")?
-                .into_bytes()
-        }
-        _ => {
-            let mut buf = Vec::new();
-            io::stdin()
-                .read_to_end(&mut buf)
-                .context("Failed to read bytes from stdin")?;
-            buf
+    if opt.detect_only {
+        return detect_only(&opt);
+    }
+    if opt.stream {
+        return stream(&opt);
+    }
+
+    let mut ir = match opt.from_env {
+        Some(ref prefix) => {
+            let vars = env::vars_os()
+                .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?)));
+
+            dsconv::env::from_vars(
+                vars,
+                prefix.as_deref().unwrap_or_default(),
+                &opt.env_separator,
+            )
+            .context("Failed to build a map from the environment variables")?
         }
+        None => read_inputs(&opt)?,
     };
+    if let Value::Array(arr) = &mut ir {
+        if let Some(skip) = opt.skip {
+            arr.drain(..skip.min(arr.len()));
+        }
+        if let Some(limit) = opt.limit {
+            arr.truncate(limit);
+        }
+    }
+    let types = opt.types_out.as_ref().map(|_| ir.types());
 
-    let ir: Value = match opt.from.map(Format::from).or_else(|| {
-        opt.input.clone().and_then(|i| {
-            i.extension()
-                .and_then(OsStr::to_str)
-                .and_then(|e| e.parse().ok())
-        })
-    }) {
-        Some(Format::Cbor) => serde_cbor::from_slice::<Cbor>(&input)
-            .context("Failed to deserialize from a CBOR bytes")?
-            .try_into()
-            .context("Failed to convert from a CBOR value")?,
-        Some(Format::Hjson) => deser_hjson::from_str::<Json>(
-            str::from_utf8(&input).context("Failed to convert from bytes to a string")?,
-        )
-        .context("Failed to deserialize from a Hjson string")?
-        .into(),
-        Some(Format::Json) => serde_json::from_str::<Json>(
-            str::from_utf8(&input).context("Failed to convert from bytes to a string")?,
-        )
-        .context("Failed to deserialize from a JSON string")?
-        .into(),
-        Some(Format::Json5) => json5::from_str::<Json>(
-            str::from_utf8(&input).context("Failed to convert from bytes to a string")?,
-        )
-        .context("Failed to deserialize from a JSON5 string")?
-        .into(),
-        Some(Format::MessagePack) => rmpv::decode::read_value(
-            &mut rmp_serde::from_read_ref::<_, Vec<u8>>(&input)
-                .context("Failed to deserialize from a MessagePack bytes")?
-                .as_slice(),
-        )?
-        .try_into()
-        .context("Failed to convert from a MessagePack value")?,
-        Some(Format::Ron) => ron::from_str::<Ron>(
-            str::from_utf8(&input).context("Failed to convert from bytes to a string")?,
-        )
-        .context("Failed to deserialize from a RON string")?
-        .try_into()
-        .context("Failed to convert from a RON value")?,
-        Some(Format::Toml) => toml::from_str::<Toml>(
-            str::from_utf8(&input).context("Failed to convert from bytes to a string")?,
-        )
-        .context("Failed to deserialize from a TOML string")?
-        .into(),
-        Some(Format::Yaml) => serde_yaml::from_str::<Yaml>(
-            str::from_utf8(&input).context("Failed to convert from bytes to a string")?,
-        )
-        .context("Failed to deserialize from a YAML string")?
-        .try_into()
-        .context("Failed to convert from a YAML value")?,
-        None => bail!("Unable to determine input format"),
-    };
-
-    let output = match opt.to.map(Format::from).or_else(|| {
-        opt.output.clone().and_then(|o| {
-            o.extension()
-                .and_then(OsStr::to_str)
-                .and_then(|e| e.parse().ok())
-        })
-    }) {
-        Some(Format::Cbor) => {
-            serde_cbor::to_vec(&Cbor::from(ir)).context("Failed to serialize to a CBOR bytes")?
+    if !opt.required_keys.is_empty() {
+        let pointer = opt.required_keys_at.as_deref().unwrap_or_default();
+        let missing = transform::missing_keys(&ir, pointer, &opt.required_keys)
+            .context("Failed to check the required keys")?;
+        if !missing.is_empty() {
+            return Err(MissingKeysError(missing).into());
         }
-        Some(Format::Json) => {
-            let obj: Json = ir.try_into().context("Failed to convert to a JSON value")?;
+    }
 
-            if opt.pretty.map_or(false, |p| p.unwrap_or(true)) {
-                format!(
-                    "{}\n",
-                    serde_json::to_string_pretty(&obj)
-                        .context("Failed to serialize to a JSON string")?
-                )
-                .into_bytes()
-            } else {
-                format!(
-                    "{}\n",
-                    serde_json::to_string(&obj).context("Failed to serialize to a JSON string")?
-                )
-                .into_bytes()
-            }
+    let audit = |message: String| {
+        if opt.audit && !opt.quiet {
+            eprintln!("{}", message);
         }
-        Some(Format::MessagePack) => {
-            let mut buf = Vec::new();
-            rmpv::encode::write_value(&mut buf, &MessagePack::from(ir))
-                .context("Failed to write a MessagePack value to buffer")?;
+    };
 
-            rmp_serde::to_vec(&buf).context("Failed to serialize to a MessagePack bytes")?
+    ir = transform::apply(ir, &transform_options(&opt)?, &audit)?;
+
+    if opt.inspect {
+        let obj =
+            Json::try_from(&ir).context("Failed to convert to a JSON value for inspection")?;
+        let json =
+            serde_json::to_string_pretty(&obj).context("Failed to serialize to a JSON string")?;
+
+        let is_colored_output = match opt.color {
+            Color::Auto => atty::is(atty::Stream::Stderr),
+            Color::Always => true,
+            Color::Never => false,
+        };
+        if is_colored_output {
+            eprintln!("{}", inspect::highlight(&json));
+        } else {
+            eprintln!("{}", json);
         }
-        Some(Format::Toml) => {
-            let obj: Toml = ir.try_into().context("Failed to convert to a TOML value")?;
+    }
 
-            if opt.pretty.map_or(false, |p| p.unwrap_or(true)) {
-                toml::to_string_pretty(&obj)
-                    .context("Failed to serialize to a TOML string")?
-                    .into_bytes()
-            } else {
-                toml::to_string(&obj)
-                    .context("Failed to serialize to a TOML string")?
-                    .into_bytes()
-            }
+    if opt.size_report {
+        let options = Options::new()
+            .cbor_float_width(opt.cbor_float_width.unwrap_or_default())
+            .cbor_self_describe(opt.cbor_self_describe);
+
+        return size_report(&ir, &options);
+    }
+
+    if opt.minify_keys {
+        key_report(&ir);
+
+        return Ok(());
+    }
+
+    if opt.interactive {
+        #[cfg(feature = "interactive")]
+        return interactive::explore(&ir, &write_options(&opt));
+        #[cfg(not(feature = "interactive"))]
+        bail!("`--interactive` requires dsconv built with the `interactive` feature");
+    }
+
+    let output_format = if opt.infer_schema {
+        ir = schema::infer(&ir)
+            .try_into()
+            .context("Failed to convert from a JSON value")?;
+
+        Some(Format::Json)
+    } else if opt.stats {
+        ir = stats::profile(&ir)
+            .try_into()
+            .context("Failed to convert from a JSON value")?;
+
+        Some(Format::Json)
+    } else {
+        opt.output_format()
+    };
+    let output_format = output_format.ok_or(FormatError::Undeterminable("output"))?;
+    let merge_target = opt
+        .output
+        .as_ref()
+        .filter(|file| opt.merge_into && file.exists());
+    let existing = match merge_target {
+        Some(file) => {
+            let reader = File::open(file)
+                .map(BufReader::new)
+                .with_context(|| format!("Failed to read bytes from {}", file.display()))?;
+            let existing = dsconv::io::read_value(output_format, reader, &read_options(&opt))
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+
+            Some(existing)
         }
-        Some(Format::Yaml) => serde_yaml::to_string(&Yaml::from(ir))
-            .context("Failed to serialize to a YAML string")?
-            .into_bytes(),
-        _ => bail!("Unable to determine output format"),
+        None => None,
     };
+    let options = write_options(&opt).merge_into(existing);
+    ir = transform::apply_output(ir, output_format, &options, &audit);
+    if opt.warn_number_loss || opt.fail_on_lossy {
+        let tracker = lossy::track(&ir, output_format, &options);
+        if opt.warn_number_loss {
+            for loss in tracker.numbers() {
+                warn(&opt, loss);
+            }
+        }
+        if opt.fail_on_lossy && !tracker.losses().is_empty() {
+            return Err(LossyError(tracker.losses().to_vec()).into());
+        }
+    }
+    if let (Some(file), Some(types)) = (&opt.types_out, types) {
+        let types =
+            serde_json::to_string_pretty(&types).context("Failed to serialize to a JSON string")?;
+        fs::write(file, types + "\n")
+            .with_context(|| format!("Failed to write to {}", file.display()))?;
+    }
+
+    if let Some(ref dir) = opt.output_dir {
+        return explode(&opt, ir, output_format, &options, dir);
+    }
+
+    let output = render(&opt, &ir, output_format, &options)?;
+    let is_binary_output = output_format.is_binary();
+
+    if let Some(ref file) = opt.assert_equal_to {
+        let expected = fs::read(file)
+            .with_context(|| format!("Failed to read bytes from {}", file.display()))?;
+
+        return golden::compare(&expected, &output)
+            .with_context(|| format!("The output differs from {}", file.display()));
+    }
 
     if let Some(ref file) = opt.output {
         fs::write(file, output)
             .with_context(|| format!("Failed to write to {}", file.display()))?;
     } else {
+        let is_escaped_output = opt.escape_control
+            && !is_binary_output
+            && opt.encode_output.is_none()
+            && opt.output_encoding.is_utf8()
+            && atty::is(atty::Stream::Stdout);
+        let output = if is_escaped_output {
+            inspect::escape_control(&output)
+        } else {
+            output
+        };
         let is_colored_output = match opt.color {
+            _ if opt.encode_output.is_some() => false,
+            _ if !opt.output_encoding.is_utf8() => false,
+            _ if matches!(
+                output_format,
+                Format::JsonSeq | Format::Lines | Format::Reg | Format::UrlEncoded
+            ) =>
+            {
+                false
+            }
             Color::Auto if atty::is(atty::Stream::Stdout) => true,
             Color::Always => true,
             _ => false,
         };
         if is_colored_output {
-            let language = Format::from(opt.to.expect("Unable to determine output format"));
-            ensure!(
-                !matches!(language, Format::Cbor | Format::MessagePack),
-                "{} cannot colored output",
-                language
-            );
+            ensure!(!is_binary_output, "{} cannot colored output", output_format);
 
-            let language = language.to_string();
+            // bat does not have a syntax of RON, but it is close to Rust.
+            let language = match output_format {
+                Format::CHeader => "C".to_string(),
+                Format::Env => "Bash".to_string(),
+                Format::Jsonc | Format::Ndjson => "JSON".to_string(),
+                Format::Ron => "Rust".to_string(),
+                language => language.to_string(),
+            };
             PrettyPrinter::new()
                 .input_from_bytes(&output)
                 .language(&language)
@@ -215,3 +320,604 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Read the input files, or stdin if no files are specified, into a value.
+fn read_inputs(opt: &Opt) -> Result<Value> {
+    if let Some(records) = read_limited_records(opt)? {
+        return Ok(records);
+    }
+
+    let max_file_size = opt.max_file_size.unwrap_or(u64::MAX);
+    let inputs = if opt.input.is_empty() {
+        let input = if atty::is(atty::Stream::Stdin) {
+            dialoguer::Input::<String>::with_theme(&ColorfulTheme::default())
+                .with_prompt("Input")
+                .interact()
+                .context("Failed to read a string from stdin")?
+                .into_bytes()
+        } else {
+            progress::read_to_end(
+                io::stdin().take(max_file_size.saturating_add(1)),
+                None,
+                opt.quiet,
+            )
+            .context("Failed to read bytes from stdin")?
+        };
+        ensure!(
+            input.len() as u64 <= max_file_size,
+            "The input exceeds the maximum size of {} bytes",
+            max_file_size
+        );
+
+        vec![input]
+    } else {
+        ensure!(
+            opt.input.len() == 1 || opt.collect || opt.concat,
+            "Multiple input files require `--collect` or `--concat`"
+        );
+
+        opt.input
+            .iter()
+            .map(|file| {
+                let context = || format!("Failed to read bytes from {}", file.display());
+
+                let f = File::open(file).with_context(context)?;
+                let len = f.metadata().with_context(context)?.len();
+                ensure!(
+                    len <= max_file_size,
+                    "{} exceeds the maximum size of {} bytes",
+                    file.display(),
+                    max_file_size
+                );
+
+                progress::read_to_end(
+                    f.take(max_file_size.saturating_add(1)),
+                    Some(len),
+                    opt.quiet,
+                )
+                .with_context(context)
+                .and_then(|input| {
+                    ensure!(
+                        input.len() as u64 <= max_file_size,
+                        "{} exceeds the maximum size of {} bytes",
+                        file.display(),
+                        max_file_size
+                    );
+
+                    Ok(input)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let inputs = match opt.decode_input {
+        Some(encoding) => inputs
+            .iter()
+            .map(|input| {
+                encoding
+                    .decode(input)
+                    .with_context(|| format!("Failed to decode the input from {}", encoding))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => inputs,
+    };
+
+    let input_formats = inputs
+        .iter()
+        .enumerate()
+        .map(|(n, input)| {
+            known_input_format(opt, opt.input.get(n))
+                .or_else(|| dsconv::detect::sniff_binary(input))
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(from) = input_formats[0] {
+        explain(opt, from);
+    }
+
+    let options = read_options(opt);
+    // The root array is cut by `--skip` and `--limit` later, so no more records
+    // than both are needed.
+    let record_limit = opt
+        .limit
+        .filter(|_| !opt.collect)
+        .map(|limit| limit.saturating_add(opt.skip.unwrap_or_default()));
+    let mut documents = Vec::new();
+    for (input, format) in inputs.iter().zip(input_formats) {
+        let format = format.ok_or(FormatError::Undeterminable("input"))?;
+        if let (Format::Yaml, true) = (format, opt.tolerant_yaml) {
+            // Errors are reported when the input is deserialized.
+            let found = str::from_utf8(input)
+                .ok()
+                .and_then(|input| yaml::find_ambiguous_bools(input).ok());
+            for found in found.unwrap_or_default() {
+                warn(opt, found);
+            }
+        }
+        if format.is_record_oriented() {
+            let (records, malformed) = dsconv::io::read_records(
+                format,
+                input.as_slice(),
+                opt.on_parse_error,
+                record_limit,
+                &options,
+            )?;
+            report_malformed(opt, malformed);
+            documents.push(records);
+        } else if opt.collect {
+            documents.extend(dsconv::io::read_documents(
+                format,
+                input.as_slice(),
+                &options,
+            )?);
+        } else {
+            documents.push(dsconv::io::read_value(format, input.as_slice(), &options)?);
+        }
+    }
+    if opt.collect {
+        return Ok(Value::Array(documents));
+    }
+    if opt.concat {
+        let mut concatenated = Vec::new();
+        for (i, document) in documents.into_iter().enumerate() {
+            match document {
+                Value::Array(arr) => concatenated.extend(arr),
+                value => bail!(
+                    "`--concat` requires an array at the root, but the root of {} is {}",
+                    opt.input
+                        .get(i)
+                        .map_or_else(|| "stdin".to_string(), |f| f.display().to_string()),
+                    value.type_name()
+                ),
+            }
+        }
+
+        return Ok(Value::Array(concatenated));
+    }
+
+    Ok(documents.remove(0))
+}
+
+/// Read the records of a single input only up to `--skip` and `--limit`,
+/// leaving the rest of the input unread.
+///
+/// Returns `None` if the input has to be read as a whole, such as when the
+/// input format is not record-oriented or is not known before reading.
+fn read_limited_records(opt: &Opt) -> Result<Option<Value>> {
+    let limit = match opt.limit {
+        Some(limit) if opt.input.len() <= 1 && !opt.collect && opt.decode_input.is_none() => {
+            limit.saturating_add(opt.skip.unwrap_or_default())
+        }
+        _ => return Ok(None),
+    };
+    let format = match known_input_format(opt, opt.input.first()) {
+        Some(format) if format.is_record_oriented() => format,
+        _ => return Ok(None),
+    };
+    if opt.input.is_empty() && atty::is(atty::Stream::Stdin) {
+        return Ok(None);
+    }
+
+    explain(opt, format);
+    let (reader, _) = input::open(
+        opt.input.first().map(PathBuf::as_path),
+        opt.max_file_size.unwrap_or(u64::MAX),
+    )?;
+    let (records, malformed) = dsconv::io::read_records(
+        format,
+        BufReader::new(reader),
+        opt.on_parse_error,
+        Some(limit),
+        &read_options(opt),
+    )?;
+    report_malformed(opt, malformed);
+
+    Ok(Some(records))
+}
+
+/// Determine the input format from `--from` or the extension of `file`.
+fn known_input_format(opt: &Opt, file: Option<&PathBuf>) -> Option<Format> {
+    opt.from.map(Format::from).or_else(|| {
+        file.and_then(|f| {
+            f.extension()
+                .and_then(OsStr::to_str)
+                .and_then(|e| e.parse().ok())
+        })
+    })
+}
+
+/// Print the known lossy conversions from `from` to the output format, if
+/// `--explain` is specified.
+fn explain(opt: &Opt, from: Format) {
+    if let (true, Some(to)) = (opt.explain, opt.output_format()) {
+        let notes = from.lossy_conversions(to);
+        if notes.is_empty() {
+            eprintln!("{} to {}: no known lossy conversions", from, to);
+        } else {
+            eprintln!("{} to {}:", from, to);
+            for note in notes {
+                eprintln!("  - {}", note);
+            }
+        }
+    }
+}
+
+/// Write the value in `output_format`, and apply the options which process
+/// the written bytes.
+fn render(opt: &Opt, ir: &Value, output_format: Format, options: &Options) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    if let Some(ref text) = opt.header_comment {
+        let prefix = output_format
+            .comment_prefix()
+            .with_context(|| format!("{} does not support comments", output_format))?;
+        for line in text.lines() {
+            let comment = format!("{} {}", prefix, line);
+            output.extend(comment.trim_end().as_bytes());
+            output.push(b'\n');
+        }
+    }
+    dsconv::io::write_value(output_format, ir, &mut output, options)?;
+
+    let output = match opt.pipe_through {
+        Some(ref command) => pipe_through(command, output)?,
+        None => output,
+    };
+
+    let output = if output_format.is_binary() || (opt.output_encoding.is_utf8() && !opt.bom) {
+        output
+    } else {
+        let str = String::from_utf8(output).context("The output is not valid UTF-8")?;
+        opt.output_encoding.encode(&str, opt.bom).with_context(|| {
+            format!(
+                "The output contains characters which {} cannot represent",
+                opt.output_encoding
+            )
+        })?
+    };
+
+    let output = match opt.encode_output {
+        Some(encoding) => format!("{}\n", encoding.encode(&output)).into_bytes(),
+        None => output,
+    };
+
+    Ok(output)
+}
+
+/// Write each entry of the root map to its own file in `dir`.
+fn explode(
+    opt: &Opt,
+    ir: Value,
+    output_format: Format,
+    options: &Options,
+    dir: &Path,
+) -> Result<()> {
+    let map = match ir {
+        Value::Map(map) => map,
+        value => bail!(
+            "`--explode` requires a map at the root, but the root is {}",
+            value.type_name()
+        ),
+    };
+
+    // Check all the keys before writing anything, so that no files are left
+    // behind on error.
+    if let Some(key) = map
+        .keys()
+        .find(|k| matches!(k.as_str(), "" | "." | "..") || k.contains(|c| matches!(c, '/' | '\\')))
+    {
+        bail!("{:?} cannot be used as a filename", key);
+    }
+    let outputs = map
+        .iter()
+        .map(|(key, value)| {
+            render(opt, value, output_format, options)
+                .map(|output| (key, output))
+                .with_context(|| format!("Failed to write the value of {:?}", key))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    for (key, output) in outputs {
+        let file = dir.join(format!("{}.{}", key, output_format.extension()));
+        fs::write(&file, output)
+            .with_context(|| format!("Failed to write to {}", file.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Split `<POINTER>=<VALUE>` of `--set`.
+///
+/// Since a JSON Pointer can contain `=`, the first `=` which is followed by a
+/// valid JSON value separates them.
+fn parse_assignment(assignment: &str) -> Result<(&str, Json)> {
+    let first = assignment
+        .find('=')
+        .with_context(|| format!("`--set` requires <POINTER>=<VALUE>: {}", assignment))?;
+    for (i, _) in assignment.match_indices('=') {
+        if let Ok(value) = serde_json::from_str(&assignment[i + 1..]) {
+            return Ok((&assignment[..i], value));
+        }
+    }
+
+    let (pointer, json) = (&assignment[..first], &assignment[first + 1..]);
+    let value = serde_json::from_str(json)
+        .with_context(|| format!("Failed to parse the value for {} as JSON", pointer))?;
+
+    Ok((pointer, value))
+}
+
+/// Run `command` by the shell, feeding `input` to its stdin, and return its
+/// stdout.
+fn pipe_through(command: &str, input: Vec<u8>) -> Result<Vec<u8>> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut child = process::Command::new(shell)
+        .args([flag, command])
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run `{}`", command))?;
+
+    // Write from another thread so that a command which produces output
+    // before reading all of its input does not deadlock.
+    let mut stdin = child.stdin.take().expect("stdin of the command is piped");
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let result = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to wait for `{}`", command))?;
+    // A command which exits without reading all of its input breaks the pipe,
+    // so the exit status is checked first.
+    let written = writer.join().expect("Failed to join the writer thread");
+    ensure!(
+        result.status.success(),
+        "`{}` exited with {}",
+        command,
+        result.status
+    );
+    written.with_context(|| format!("Failed to write to stdin of `{}`", command))?;
+
+    Ok(result.stdout)
+}
+
+/// Print the detected format of each input.
+///
+/// The content is read only if the format cannot be determined from the
+/// filename extension.
+fn detect_only(opt: &Opt) -> Result<()> {
+    let max_file_size = opt.max_file_size.unwrap_or(u64::MAX);
+    if opt.input.is_empty() {
+        let format = dsconv::detect::sniff_reader(input::open(None, max_file_size)?.0)
+            .context("Failed to read bytes from stdin")?
+            .ok_or(FormatError::Undeterminable("input"))?;
+        println!("{}", format);
+
+        return Ok(());
+    }
+
+    for file in &opt.input {
+        let format = match file
+            .extension()
+            .and_then(OsStr::to_str)
+            .and_then(|e| e.parse::<Format>().ok())
+        {
+            Some(format) => Some(format),
+            None => dsconv::detect::sniff_reader(input::open(Some(file), max_file_size)?.0)
+                .with_context(|| format!("Failed to read bytes from {}", file.display()))?,
+        };
+        let format = format.ok_or(FormatError::Undeterminable("input"))?;
+
+        if opt.input.len() == 1 {
+            println!("{}", format);
+        } else {
+            println!("{}: {}", file.display(), format);
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the options which affect how the input is read.
+fn read_options(opt: &Opt) -> Options {
+    Options::new()
+        .on_key_conflict(opt.on_key_conflict)
+        .lossy_utf8(opt.lossy_utf8)
+        .byte_strings(opt.bytes)
+        .preserve_cbor_bignum(opt.preserve_cbor_bignum)
+        .preserve_json_decimal(opt.preserve_json_decimal)
+        .cbor_strict_undefined(opt.cbor_strict_undefined)
+}
+
+/// Get the options which select the transforms.
+fn transform_options(opt: &Opt) -> Result<Options> {
+    let pairs = |args: &[String]| -> Vec<_> {
+        args.chunks(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect()
+    };
+    let set = opt
+        .set
+        .iter()
+        .map(|assignment| {
+            let (pointer, value) = parse_assignment(assignment)?;
+            let value: Value = value
+                .try_into()
+                .with_context(|| format!("Failed to convert the value for {}", pointer))?;
+
+            Ok((pointer.to_string(), value))
+        })
+        .collect::<Result<_>>()?;
+
+    let options = Options::new()
+        .on_key_conflict(opt.on_key_conflict)
+        .transform_order(opt.transform_order.clone())
+        .group_by(opt.group_by.clone())
+        .drop_group_key(opt.drop_group_key)
+        .ungroup(opt.ungroup.clone())
+        .key_case(opt.key_case())
+        .strip_prefix(opt.strip_prefix.clone())
+        .strip_prefix_recursive(opt.strip_prefix_recursive)
+        .key_order(opt.key_order.clone())
+        .dedupe_array(opt.dedupe_array)
+        .dedupe_array_at(opt.dedupe_array_at.clone())
+        .sort_arrays(opt.sort_arrays)
+        .sort_arrays_at(opt.sort_arrays_at.clone())
+        .trim_strings(opt.trim_strings)
+        .collapse_whitespace(opt.collapse_whitespace)
+        .trim_keys(opt.trim_keys)
+        .replace(pairs(&opt.replace))
+        .replace_regex(pairs(&opt.replace_regex))
+        .replace_keys(opt.replace_keys)
+        .max_string_length(opt.max_string_length)
+        .stringify_under(opt.stringify_under.clone())
+        .coerce_bools(opt.coerce_bools)
+        .true_tokens(opt.true_tokens.clone())
+        .false_tokens(opt.false_tokens.clone())
+        .parse_radix(opt.parse_radix)
+        .datetime_tz(opt.datetime_tz)
+        .null_as(opt.null_as.clone())
+        .flatten_arrays(opt.flatten_arrays.map(Option::unwrap_or_default))
+        .arrays_as_maps(opt.arrays_as_maps)
+        .maps_as_arrays(opt.maps_as_arrays)
+        .delete(opt.delete.clone())
+        .delete_missing_ok(opt.delete_missing_ok)
+        .set(set);
+
+    Ok(options)
+}
+
+/// Get the options which affect how the output is written.
+fn write_options(opt: &Opt) -> Options {
+    Options::new()
+        .pretty(opt.pretty.map_or(false, |p| p.unwrap_or(true)))
+        .compact_scalar_arrays(opt.compact_scalar_arrays)
+        .jcs(opt.is_jcs())
+        .float_format(opt.float_format.unwrap_or_default())
+        .cbor_float_width(opt.cbor_float_width.unwrap_or_default())
+        .cbor_self_describe(opt.cbor_self_describe)
+        .ron_depth_limit(opt.ron_depth_limit)
+        .toml_stringify_mixed_arrays(opt.toml_stringify_mixed_arrays)
+        .toml_literal_strings(opt.toml_literal_strings)
+        .toml_array_wrap(opt.toml_array_wrap)
+        .yaml_indent(opt.yaml_indent.get())
+        .yaml_tags(opt.yaml_tags)
+        .yaml_literal_block(opt.yaml_literal_block)
+        .env_prefix(opt.to_env.clone().flatten().unwrap_or_default())
+        .env_separator(opt.env_separator.as_str())
+        .sort_keys(opt.sort_scope())
+        .wrap(opt.wrap.clone())
+        .js_safe_integers(opt.js_safe_integers)
+}
+
+/// Print the size of the value in each binary output format.
+fn size_report(ir: &Value, options: &Options) -> Result<()> {
+    println!("{:<11} {:>10}", "FORMAT", "BYTES");
+    for format in OutputFormat::value_variants()
+        .iter()
+        .map(|f| Format::from(*f))
+        // Parquet only holds an array of maps, so it cannot encode every value.
+        .filter(|f| f.is_binary() && !matches!(f, Format::Parquet))
+    {
+        let mut buf = Vec::new();
+        dsconv::io::write_value(format, ir, &mut buf, options)?;
+        println!("{:<11} {:>10}", format.to_string(), buf.len());
+    }
+
+    Ok(())
+}
+
+/// Print how many bytes sharing repeated keys would save in CBOR.
+fn key_report(ir: &Value) {
+    let stats = cbor::key_stats(ir);
+    println!("{:<12} {:>10}", "KEYS", stats.count);
+    println!("{:<12} {:>10}", "DISTINCT", stats.distinct);
+    println!("{:<12} {:>10}", "KEY BYTES", stats.bytes);
+    println!("{:<12} {:>10}", "SHARED BYTES", stats.shared_bytes);
+    println!(
+        "{:<12} {:>10}",
+        "SAVED BYTES",
+        stats.bytes.saturating_sub(stats.shared_bytes)
+    );
+}
+
+/// Convert CSV or TSV to NDJSON one record at a time.
+fn stream(opt: &Opt) -> Result<()> {
+    ensure!(
+        opt.input.len() <= 1,
+        "`--stream` does not support multiple input files"
+    );
+
+    let delimiter = match (
+        known_input_format(opt, opt.input.first()),
+        opt.output_format(),
+    ) {
+        (Some(Format::Csv), Some(Format::Ndjson)) => b',',
+        (Some(Format::Tsv), Some(Format::Ndjson)) => b'\t',
+        _ => bail!("`--stream` is only available for CSV or TSV to NDJSON"),
+    };
+
+    let (reader, len) = input::open(
+        opt.input.first().map(PathBuf::as_path),
+        opt.max_file_size.unwrap_or(u64::MAX),
+    )?;
+    let reader = progress::wrap_read(reader, len, opt.quiet);
+    let reader: Box<dyn Read> = match opt.decode_input {
+        Some(encoding) => Box::new(input::Decode::new(reader, encoding)),
+        None => reader,
+    };
+    let mut writer: Box<dyn Write> = match opt.output {
+        Some(ref file) => {
+            Box::new(BufWriter::new(File::create(file).with_context(|| {
+                format!("Failed to write to {}", file.display())
+            })?))
+        }
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+
+    let malformed = dsconv::tabular::for_each_record(
+        reader,
+        delimiter,
+        opt.skip.unwrap_or_default(),
+        opt.limit,
+        opt.on_parse_error,
+        opt.on_key_conflict,
+        |record| -> Result<()> {
+            let obj: Json = record
+                .try_into()
+                .context("Failed to convert to a JSON value")?;
+            serde_json::to_writer(&mut writer, &obj)
+                .context("Failed to serialize to a NDJSON string")?;
+            writer.write_all(b"\n").context("Failed to write a record")
+        },
+    )
+    .context("Failed to read records")?;
+    report_malformed(opt, malformed);
+    writer.flush().context("Failed to write records")
+}
+
+/// Print the warning to stderr unless `--quiet` is specified.
+fn warn(opt: &Opt, warning: impl fmt::Display) {
+    if !opt.quiet {
+        eprintln!("Warning: {}", warning);
+    }
+}
+
+/// Warn about the number of malformed records.
+fn report_malformed(opt: &Opt, count: usize) {
+    match (count, opt.on_parse_error) {
+        (0, _) | (_, ParseErrorPolicy::Abort) => (),
+        (count, ParseErrorPolicy::Skip) => {
+            warn(opt, format_args!("Skipped {} malformed records", count));
+        }
+        (count, ParseErrorPolicy::Null) => {
+            warn(
+                opt,
+                format_args!("Replaced {} malformed records with null", count),
+            );
+        }
+    }
+}
@@ -0,0 +1,388 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use indexmap::IndexMap;
+
+use crate::value::Value;
+
+/// The error type for parsing an nginx configuration.
+#[derive(Debug)]
+pub struct ParseError {
+    /// What was expected or found.
+    pub message: String,
+    /// The line number where the error occurred, starting from 1.
+    pub line: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}", self.message, self.line)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parse an nginx configuration into a map.
+///
+/// A simple directive becomes an entry whose value is `null` without
+/// arguments, a string with one argument, or an array of strings with more
+/// arguments. A block directive becomes a map of its directives, which is
+/// nested in a map keyed by the arguments joined with a space if it has any
+/// (e.g. `location / { ... }` becomes `{"location":{"/":{...}}}`). Repeated
+/// directives are collected into an array.
+pub fn from_str(input: &str) -> Result<Value, ParseError> {
+    let mut parser = Parser {
+        chars: input.chars().peekable(),
+        line: 1,
+    };
+
+    parser.block(false)
+}
+
+/// How the values of a directive in a block have been collected.
+#[derive(Clone, Copy)]
+enum Slot {
+    /// The directive has appeared once.
+    Single,
+    /// The directive has appeared more than once, and the value is an array
+    /// of the values of each occurrence.
+    Repeated,
+    /// The directive is a block with arguments, and the value is a map keyed
+    /// by the arguments.
+    Blocks,
+}
+
+enum Token {
+    Word(String),
+    Semicolon,
+    Open,
+    Close,
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            message: message.into(),
+            line: self.line,
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+
+        c
+    }
+
+    /// Skip whitespace and comments.
+    fn skip_whitespace(&mut self) {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.next();
+                }
+                Some('#') => while !matches!(self.next(), Some('\n') | None) {},
+                _ => return,
+            }
+        }
+    }
+
+    fn token(&mut self) -> Result<Option<Token>, ParseError> {
+        self.skip_whitespace();
+        let token = match self.chars.peek() {
+            None => return Ok(None),
+            Some(';') => Token::Semicolon,
+            Some('{') => Token::Open,
+            Some('}') => Token::Close,
+            Some(&quote @ ('"' | '\'')) => {
+                self.next();
+                return self.quoted_string(quote).map(|s| Some(Token::Word(s)));
+            }
+            Some(_) => return self.word().map(|s| Some(Token::Word(s))),
+        };
+        self.next();
+
+        Ok(Some(token))
+    }
+
+    /// Parse an unquoted argument, where `${...}` may contain braces and a
+    /// backslash only escapes quotes and itself.
+    fn word(&mut self) -> Result<String, ParseError> {
+        let mut str = String::new();
+
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                ';' | '{' | '}' | '"' | '\'' => {
+                    if c == '{' && str.ends_with('$') {
+                        while let Some(c) = self.next() {
+                            str.push(c);
+                            if c == '}' {
+                                break;
+                            }
+                        }
+                        if !str.ends_with('}') {
+                            return Err(self.error("Unterminated variable"));
+                        }
+                        continue;
+                    }
+                    break;
+                }
+                '\\' => {
+                    self.next();
+                    match self.next() {
+                        Some(c @ ('"' | '\'' | '\\')) => str.push(c),
+                        Some(c) => {
+                            str.push('\\');
+                            str.push(c);
+                        }
+                        None => return Err(self.error("Unexpected end of input")),
+                    }
+                    continue;
+                }
+                c if c.is_whitespace() => break,
+                c => str.push(c),
+            }
+            self.next();
+        }
+
+        Ok(str)
+    }
+
+    fn quoted_string(&mut self, quote: char) -> Result<String, ParseError> {
+        let mut str = String::new();
+
+        loop {
+            match self.next() {
+                Some(c) if c == quote => break,
+                Some('\\') => match self.next() {
+                    Some('n') => str.push('\n'),
+                    Some('r') => str.push('\r'),
+                    Some('t') => str.push('\t'),
+                    Some(c) if c == quote || c == '\\' => str.push(c),
+                    Some(c) => {
+                        str.push('\\');
+                        str.push(c);
+                    }
+                    None => return Err(self.error("Unterminated string")),
+                },
+                Some(c) => str.push(c),
+                None => return Err(self.error("Unterminated string")),
+            }
+        }
+
+        Ok(str)
+    }
+
+    /// Parse directives until `}` if `nested`, or the end of input otherwise.
+    fn block(&mut self, nested: bool) -> Result<Value, ParseError> {
+        let mut map = IndexMap::new();
+        let mut slots = HashMap::new();
+        // The slots of the blocks with arguments, keyed by the directive.
+        let mut block_slots = HashMap::<_, HashMap<_, _>>::new();
+
+        loop {
+            let name = match self.token()? {
+                Some(Token::Word(name)) => name,
+                Some(Token::Close) if nested => break,
+                None if !nested => break,
+                Some(Token::Close) => return Err(self.error("Unexpected `}`")),
+                Some(Token::Semicolon) => return Err(self.error("Unexpected `;`")),
+                Some(Token::Open) => return Err(self.error("Unexpected `{`")),
+                None => return Err(self.error("Unterminated block")),
+            };
+
+            let mut args = Vec::new();
+            let value = loop {
+                match self.token()? {
+                    Some(Token::Word(arg)) => args.push(arg),
+                    Some(Token::Semicolon) => {
+                        break match args.len() {
+                            0 => Value::Null,
+                            1 => Value::String(args.remove(0)),
+                            _ => Value::Array(args.drain(..).map(Value::String).collect()),
+                        };
+                    }
+                    Some(Token::Open) => break self.block(true)?,
+                    Some(Token::Close) => return Err(self.error("Unexpected `}`")),
+                    None => return Err(self.error("Expected `;` or `{`")),
+                }
+            };
+
+            if args.is_empty() {
+                insert(&mut map, &mut slots, name, value);
+                continue;
+            }
+            let key = args.join(" ");
+            match (map.get_mut(&name), slots.get(&name)) {
+                (Some(Value::Map(blocks)), Some(Slot::Blocks)) => {
+                    let slots = block_slots.entry(name).or_default();
+                    insert(blocks, slots, key, value);
+                }
+                (Some(_), _) => insert(&mut map, &mut slots, name, value),
+                (None, _) => {
+                    let mut blocks = IndexMap::new();
+                    insert(
+                        &mut blocks,
+                        block_slots.entry(name.clone()).or_default(),
+                        key,
+                        value,
+                    );
+                    map.insert(name.clone(), Value::Map(blocks));
+                    slots.insert(name, Slot::Blocks);
+                }
+            }
+        }
+
+        Ok(Value::Map(map))
+    }
+}
+
+/// Insert the value of a directive, collecting the values of a repeated one
+/// into an array.
+fn insert(
+    map: &mut IndexMap<String, Value>,
+    slots: &mut HashMap<String, Slot>,
+    name: String,
+    value: Value,
+) {
+    match (map.get_mut(&name), slots.get(&name).copied()) {
+        (Some(Value::Array(values)), Some(Slot::Repeated)) => values.push(value),
+        (Some(existing), _) => {
+            let first = std::mem::replace(existing, Value::Null);
+            *existing = Value::Array(vec![first, value]);
+            slots.insert(name, Slot::Repeated);
+        }
+        (None, _) => {
+            map.insert(name.clone(), value);
+            slots.insert(name, Slot::Single);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    fn string(str: &str) -> Value {
+        Value::String(str.to_string())
+    }
+
+    #[test]
+    fn directives() {
+        let input = r#"
+            # A comment
+            user nginx;
+            worker_processes auto;
+            events { worker_connections 1024; }
+            http {
+                server {
+                    listen 80;
+                    listen 443 ssl;
+                    server_name example.com "www.example.com";
+                    location / { root /var/www; }
+                    location ~ \.php$ { return 404; }
+                    location / { try_files $uri ${uri}/ =404; }
+                    ip_hash;
+                }
+                server { listen 8080; }
+            }
+        "#;
+
+        assert_eq!(
+            from_str(input).unwrap(),
+            map(vec![
+                ("user", string("nginx")),
+                ("worker_processes", string("auto")),
+                ("events", map(vec![("worker_connections", string("1024"))])),
+                (
+                    "http",
+                    map(vec![(
+                        "server",
+                        Value::Array(vec![
+                            map(vec![
+                                (
+                                    "listen",
+                                    Value::Array(vec![
+                                        string("80"),
+                                        Value::Array(vec![string("443"), string("ssl")]),
+                                    ])
+                                ),
+                                (
+                                    "server_name",
+                                    Value::Array(vec![
+                                        string("example.com"),
+                                        string("www.example.com"),
+                                    ])
+                                ),
+                                (
+                                    "location",
+                                    map(vec![
+                                        (
+                                            "/",
+                                            Value::Array(vec![
+                                                map(vec![("root", string("/var/www"))]),
+                                                map(vec![(
+                                                    "try_files",
+                                                    Value::Array(vec![
+                                                        string("$uri"),
+                                                        string("${uri}/"),
+                                                        string("=404"),
+                                                    ])
+                                                )]),
+                                            ])
+                                        ),
+                                        ("~ \\.php$", map(vec![("return", string("404"))])),
+                                    ])
+                                ),
+                                ("ip_hash", Value::Null),
+                            ]),
+                            map(vec![("listen", string("8080"))]),
+                        ])
+                    )])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(from_str("").unwrap(), map(vec![]));
+        assert_eq!(from_str("# only a comment\n").unwrap(), map(vec![]));
+    }
+
+    #[test]
+    fn invalid() {
+        let err = from_str("http {\n  listen 80\n}").unwrap_err();
+        assert_eq!(err.to_string(), "Unexpected `}` at line 3");
+        assert!(from_str("http {").is_err());
+        assert!(from_str("}").is_err());
+        assert!(from_str("listen 80").is_err());
+        assert!(from_str("; listen 80;").is_err());
+        assert!(from_str("root \"/var;").is_err());
+    }
+}
@@ -0,0 +1,389 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use indexmap::IndexMap;
+
+use crate::value::{KeyConflict, Value};
+
+/// The error type for parsing an OpenStep property list.
+#[derive(Debug)]
+pub struct ParseError {
+    /// What was expected or found.
+    pub message: String,
+    /// The line number where the error occurred, starting from 1.
+    pub line: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}", self.message, self.line)
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parse an OpenStep property list into a value.
+///
+/// Dictionaries become maps, arrays become arrays, and quoted and unquoted
+/// strings become strings. Data blocks become Base64 strings. The root may
+/// also be a sequence of `key = value;` pairs without braces, as in `.strings`
+/// files. Duplicate keys are handled by `on_key_conflict`.
+pub fn from_str(input: &str, on_key_conflict: KeyConflict) -> Result<Value, ParseError> {
+    let mut parser = Parser {
+        chars: input.chars().peekable(),
+        line: 1,
+        on_key_conflict,
+    };
+
+    parser.skip_whitespace()?;
+    let value = match parser.chars.peek() {
+        None => Value::Map(IndexMap::new()),
+        Some('{' | '(' | '<') => parser.value()?,
+        Some(_) => {
+            let value = parser.value()?;
+            parser.skip_whitespace()?;
+            match (value, parser.chars.peek()) {
+                (Value::String(key), Some('=')) => parser.entries(Some(key), None)?,
+                (value, _) => value,
+            }
+        }
+    };
+
+    parser.skip_whitespace()?;
+    match parser.chars.peek() {
+        None => Ok(value),
+        Some(&c) => Err(parser.error(format!("Unexpected `{}`", c))),
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    on_key_conflict: KeyConflict,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            message: message.into(),
+            line: self.line,
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+
+        c
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        self.skip_whitespace()?;
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(self.error(format!("Expected `{}`, found `{}`", expected, c))),
+            None => Err(self.error(format!("Expected `{}`, found end of input", expected))),
+        }
+    }
+
+    /// Skip whitespace and comments.
+    fn skip_whitespace(&mut self) -> Result<(), ParseError> {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.next();
+                }
+                Some('/') => {
+                    let mut lookahead = self.chars.clone();
+                    lookahead.next();
+                    match lookahead.next() {
+                        Some('/') => while !matches!(self.next(), Some('\n') | None) {},
+                        Some('*') => {
+                            self.next();
+                            self.next();
+                            let mut prev = None;
+                            loop {
+                                match self.next() {
+                                    Some('/') if prev == Some('*') => break,
+                                    Some(c) => prev = Some(c),
+                                    None => return Err(self.error("Unterminated comment")),
+                                }
+                            }
+                        }
+                        _ => return Ok(()),
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace()?;
+        match self.chars.peek() {
+            Some('{') => {
+                self.next();
+                self.entries(None, Some('}'))
+            }
+            Some('(') => {
+                self.next();
+                self.array()
+            }
+            Some('<') => {
+                self.next();
+                self.data()
+            }
+            Some('"') => {
+                self.next();
+                self.quoted_string().map(Value::String)
+            }
+            Some(&c) if is_unquoted(c) => {
+                let mut str = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !is_unquoted(c) {
+                        break;
+                    }
+                    str.push(c);
+                    self.next();
+                }
+
+                Ok(Value::String(str))
+            }
+            Some(&c) => Err(self.error(format!("Unexpected `{}`", c))),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    /// Parse `key = value;` pairs until `close`, or the end of input if
+    /// `close` is `None`.
+    fn entries(&mut self, first: Option<String>, close: Option<char>) -> Result<Value, ParseError> {
+        let mut map = IndexMap::new();
+        let mut key = first;
+
+        loop {
+            let k = match key.take() {
+                Some(k) => k,
+                None => {
+                    self.skip_whitespace()?;
+                    match (self.chars.peek(), close) {
+                        (Some(&c), Some(close)) if c == close => {
+                            self.next();
+                            break;
+                        }
+                        (None, None) => break,
+                        _ => (),
+                    }
+                    match self.value()? {
+                        Value::String(k) => k,
+                        _ => return Err(self.error("Expected a string as the key")),
+                    }
+                }
+            };
+            self.expect('=')?;
+            let v = self.value()?;
+            self.expect(';')?;
+
+            self.on_key_conflict
+                .insert(&mut map, k, v)
+                .map_err(|k| self.error(format!("The key is duplicated: {}", k)))?;
+        }
+
+        Ok(Value::Map(map))
+    }
+
+    fn array(&mut self) -> Result<Value, ParseError> {
+        let mut arr = Vec::new();
+
+        loop {
+            self.skip_whitespace()?;
+            if self.chars.peek() == Some(&')') {
+                self.next();
+                break;
+            }
+            arr.push(self.value()?);
+
+            self.skip_whitespace()?;
+            match self.next() {
+                Some(',') => (),
+                Some(')') => break,
+                Some(c) => return Err(self.error(format!("Expected `,` or `)`, found `{}`", c))),
+                None => return Err(self.error("Unterminated array")),
+            }
+        }
+
+        Ok(Value::Array(arr))
+    }
+
+    fn data(&mut self) -> Result<Value, ParseError> {
+        let mut hex = String::new();
+
+        loop {
+            match self.next() {
+                Some('>') => break,
+                Some(c) if c.is_ascii_hexdigit() => hex.push(c.to_ascii_lowercase()),
+                Some(c) if c.is_whitespace() => (),
+                Some(c) => return Err(self.error(format!("Invalid character in data: `{}`", c))),
+                None => return Err(self.error("Unterminated data")),
+            }
+        }
+
+        let bytes = data_encoding::HEXLOWER
+            .decode(hex.as_bytes())
+            .map_err(|_| self.error("Odd number of hexadecimal digits in data"))?;
+
+        Ok(Value::String(data_encoding::BASE64.encode(&bytes)))
+    }
+
+    fn quoted_string(&mut self) -> Result<String, ParseError> {
+        let mut str = String::new();
+
+        loop {
+            match self.next() {
+                Some('"') => break,
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('a') => '\u{7}',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('v') => '\u{b}',
+                        Some('U') => self.escaped_code(16, 4)?,
+                        Some(c @ '0'..='7') => {
+                            let mut code = c.to_digit(8).unwrap_or_default();
+                            for _ in 0..2 {
+                                match self.chars.peek().and_then(|c| c.to_digit(8)) {
+                                    Some(digit) => {
+                                        code = code * 8 + digit;
+                                        self.next();
+                                    }
+                                    None => break,
+                                }
+                            }
+                            char::from_u32(code)
+                                .ok_or_else(|| self.error("Invalid octal escape"))?
+                        }
+                        Some(c) => c,
+                        None => return Err(self.error("Unterminated string")),
+                    };
+                    str.push(c);
+                }
+                Some(c) => str.push(c),
+                None => return Err(self.error("Unterminated string")),
+            }
+        }
+
+        Ok(str)
+    }
+
+    fn escaped_code(&mut self, radix: u32, len: usize) -> Result<char, ParseError> {
+        let mut code = 0;
+        for _ in 0..len {
+            let digit = self
+                .next()
+                .and_then(|c| c.to_digit(radix))
+                .ok_or_else(|| self.error("Invalid Unicode escape"))?;
+            code = code * radix + digit;
+        }
+
+        char::from_u32(code).ok_or_else(|| self.error("Invalid Unicode escape"))
+    }
+}
+
+/// Returns `true` if the character can appear in an unquoted string.
+const fn is_unquoted(c: char) -> bool {
+    matches!(c, 'A'..='Z' | 'a'..='z' | '0'..='9' | '_' | '$' | '+' | '/' | ':' | '.' | '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    fn string(str: &str) -> Value {
+        Value::String(str.to_string())
+    }
+
+    #[test]
+    fn dictionary() {
+        let input = r#"
+            // A comment
+            {
+                name = "dsconv";
+                version = 1.0;
+                /* Another comment */
+                tags = (a, "b c", );
+                empty = {};
+                data = <0001 ff>;
+            }
+        "#;
+
+        assert_eq!(
+            from_str(input, KeyConflict::Error).unwrap(),
+            map(vec![
+                ("name", string("dsconv")),
+                ("version", string("1.0")),
+                ("tags", Value::Array(vec![string("a"), string("b c")])),
+                ("empty", map(vec![])),
+                ("data", string("AAH/")),
+            ])
+        );
+    }
+
+    #[test]
+    fn strings_file() {
+        assert_eq!(
+            from_str("\"a\" = \"\\U00e9\\n\\101\";\nb = c;\n", KeyConflict::Error).unwrap(),
+            map(vec![("a", string("\u{e9}\nA")), ("b", string("c"))])
+        );
+        assert_eq!(from_str("", KeyConflict::Error).unwrap(), map(vec![]));
+        assert_eq!(from_str("abc", KeyConflict::Error).unwrap(), string("abc"));
+    }
+
+    #[test]
+    fn invalid() {
+        let err = from_str("{\n  a = b\n}", KeyConflict::Error).unwrap_err();
+        assert_eq!(err.to_string(), "Expected `;`, found `}` at line 3");
+        assert!(from_str("(a b)", KeyConflict::Error).is_err());
+        assert!(from_str("<abc>", KeyConflict::Error).is_err());
+        assert!(from_str("\"abc", KeyConflict::Error).is_err());
+        assert!(from_str("{} x", KeyConflict::Error).is_err());
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let input = "{\n  a = b;\n  a = c;\n}";
+
+        assert_eq!(
+            from_str(input, KeyConflict::Error).unwrap_err().to_string(),
+            "The key is duplicated: a at line 3"
+        );
+        assert_eq!(
+            from_str(input, KeyConflict::First).unwrap(),
+            map(vec![("a", string("b"))])
+        );
+        assert_eq!(
+            from_str(input, KeyConflict::Last).unwrap(),
+            map(vec![("a", string("c"))])
+        );
+    }
+}
@@ -0,0 +1,683 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::mem;
+use std::sync::Arc;
+
+use ::parquet::basic::{Compression, ConvertedType, Repetition, Type as PhysicalType};
+use ::parquet::column::writer::ColumnWriter;
+use ::parquet::data_type::{ByteArray, Decimal};
+use ::parquet::file::properties::WriterProperties;
+use ::parquet::file::reader::{FileReader, SerializedFileReader};
+use ::parquet::file::writer::{FileWriter, InMemoryWriteableCursor, SerializedFileWriter};
+use ::parquet::record::{Field, Row};
+use ::parquet::schema::types::{Type, TypePtr};
+use ::parquet::util::cursor::SliceableCursor;
+use anyhow::{bail, ensure, Context, Result};
+use chrono::{Duration, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use indexmap::IndexMap;
+
+use crate::convert::{push_pointer_token, ConvertError};
+use crate::value::Value;
+use crate::Options;
+
+/// Read the rows of a Parquet file into an array of maps.
+///
+/// Groups become maps, and lists and maps become arrays and maps. Dates and
+/// timestamps become strings of RFC 3339 in UTC, and decimals become strings
+/// of their exact values. Binary values which are not strings are converted as
+/// `byte_strings` of `options` selects.
+///
+/// The reader seeks in the whole file from its footer, so `input` is taken by
+/// value to be shared with it instead of being copied.
+pub fn from_vec(input: Vec<u8>, options: &Options) -> Result<Value> {
+    let reader = SerializedFileReader::new(SliceableCursor::new(input))
+        .context("Failed to read the metadata")?;
+    let rows = reader
+        .get_row_iter(None)
+        .context("Failed to read the rows")?;
+    let arr: Result<Vec<_>, _> = rows.map(|row| from_row(&row, options)).collect();
+
+    Ok(Value::Array(arr?))
+}
+
+fn from_row(row: &Row, options: &Options) -> Result<Value, ConvertError> {
+    let mut obj = IndexMap::with_capacity(row.len());
+    for (k, v) in row.get_column_iter() {
+        obj.insert(k.clone(), from_field(v, options)?);
+    }
+
+    Ok(Value::Map(obj))
+}
+
+fn from_field(field: &Field, options: &Options) -> Result<Value, ConvertError> {
+    let value = match field {
+        Field::Null => Value::Null,
+        Field::Bool(bool) => Value::Bool(*bool),
+        Field::Byte(int) => Value::Integer(i64::from(*int).into()),
+        Field::Short(int) => Value::Integer(i64::from(*int).into()),
+        Field::Int(int) => Value::Integer(i64::from(*int).into()),
+        Field::Long(int) => Value::Integer((*int).into()),
+        Field::UByte(uint) => Value::Integer(u64::from(*uint).into()),
+        Field::UShort(uint) => Value::Integer(u64::from(*uint).into()),
+        Field::UInt(uint) => Value::Integer(u64::from(*uint).into()),
+        Field::ULong(uint) => Value::Integer((*uint).into()),
+        Field::Float(float) => Value::Float(f64::from(*float)),
+        Field::Double(float) => Value::Float(*float),
+        Field::Decimal(decimal) => Value::String(decimal_to_string(decimal)),
+        Field::Str(str) => Value::String(str.clone()),
+        Field::Bytes(bytes) => Value::String(
+            options
+                .byte_strings
+                .encode(bytes.data())
+                .ok_or(ConvertError::ByteStringUnsupported)?,
+        ),
+        // The days are stored as a signed 32-bit integer, and any of them is a
+        // valid date of Parquet.
+        Field::Date(days) => {
+            let days = i64::from(*days as i32);
+            let date = NaiveDate::from_ymd(1970, 1, 1)
+                .checked_add_signed(Duration::days(days))
+                .ok_or_else(|| ConvertError::IntegerOutOfRange(days.into()))?;
+            Value::String(date.to_string())
+        }
+        Field::TimestampMillis(millis) => {
+            let millis = *millis as i64;
+            timestamp(millis.div_euclid(1000), millis.rem_euclid(1000) * 1_000_000)
+                .ok_or_else(|| ConvertError::IntegerOutOfRange(millis.into()))?
+        }
+        Field::TimestampMicros(micros) => {
+            let micros = *micros as i64;
+            timestamp(
+                micros.div_euclid(1_000_000),
+                micros.rem_euclid(1_000_000) * 1000,
+            )
+            .ok_or_else(|| ConvertError::IntegerOutOfRange(micros.into()))?
+        }
+        Field::Group(row) => from_row(row, options)?,
+        Field::ListInternal(list) => {
+            let arr: Result<Vec<_>, _> = list
+                .elements()
+                .iter()
+                .map(|v| from_field(v, options))
+                .collect();
+
+            Value::Array(arr?)
+        }
+        Field::MapInternal(map) => {
+            let mut obj = IndexMap::with_capacity(map.len());
+            for (k, v) in map.entries() {
+                let k = match k {
+                    Field::Str(str) => str.clone(),
+                    _ => return Err(ConvertError::NonStringKey),
+                };
+                let v = from_field(v, options)?;
+
+                options
+                    .on_key_conflict
+                    .insert(&mut obj, k, v)
+                    .map_err(ConvertError::DuplicateKey)?;
+            }
+
+            Value::Map(obj)
+        }
+    };
+
+    Ok(value)
+}
+
+/// Format the timestamp as RFC 3339 in UTC.
+///
+/// Returns `None` if the timestamp is out of the range of `chrono`.
+fn timestamp(secs: i64, nsecs: i64) -> Option<Value> {
+    let datetime = NaiveDateTime::from_timestamp_opt(secs, nsecs as u32)?;
+
+    Some(Value::String(
+        Utc.from_utc_datetime(&datetime)
+            .to_rfc3339_opts(SecondsFormat::AutoSi, true),
+    ))
+}
+
+/// Format the decimal exactly, without an exponent (e.g. `-0.05`).
+fn decimal_to_string(decimal: &Decimal) -> String {
+    // The unscaled value is a big-endian two's complement integer of any
+    // length, so its magnitude is divided by 10 byte by byte.
+    let bytes = decimal.data();
+    let is_negative = bytes.first().map_or(false, |b| b & 0x80 != 0);
+    let mut magnitude: Vec<_> = if is_negative {
+        bytes.iter().map(|b| !b).collect()
+    } else {
+        bytes.to_vec()
+    };
+    if is_negative {
+        for b in magnitude.iter_mut().rev() {
+            let (sum, carry) = b.overflowing_add(1);
+            *b = sum;
+            if !carry {
+                break;
+            }
+        }
+    }
+
+    let mut digits = Vec::new();
+    while magnitude.iter().any(|b| *b != 0) {
+        let mut rem = 0;
+        for b in &mut magnitude {
+            let cur = (rem << 8) | u16::from(*b);
+            *b = (cur / 10) as u8;
+            rem = cur % 10;
+        }
+        digits.push(b'0' + rem as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+    digits.reverse();
+    let digits = String::from_utf8(digits).expect("The digits should be ASCII");
+
+    let sign = if is_negative { "-" } else { "" };
+    let scale = decimal.scale().max(0) as usize;
+    if scale == 0 {
+        return format!("{}{}", sign, digits);
+    }
+
+    let digits = format!("{:0>width$}", digits, width = scale + 1);
+    let (int, frac) = digits.split_at(digits.len() - scale);
+    format!("{}{}.{}", sign, int, frac)
+}
+
+/// The type of a column, which is inferred from the values in the rows.
+enum Node {
+    /// Only nulls appear so far.
+    Null,
+    Bool,
+    Integer,
+    Float,
+    String,
+    List(Box<Node>),
+    Group(IndexMap<String, Node>),
+}
+
+/// The values and the levels of a leaf column.
+struct Column {
+    values: Values,
+    definition_levels: Vec<i16>,
+    repetition_levels: Vec<i16>,
+}
+
+enum Values {
+    Bool(Vec<bool>),
+    Int64(Vec<i64>),
+    Double(Vec<f64>),
+    ByteArray(Vec<ByteArray>),
+}
+
+impl Node {
+    /// Widen the type to accept `value` at the JSON Pointer `pointer`.
+    ///
+    /// Integers are accepted by a column of floats, and the column of integers
+    /// becomes a column of floats when a float appears.
+    fn accept(&mut self, value: &Value, pointer: &str) -> Result<()> {
+        if let Value::Null = value {
+            return Ok(());
+        }
+        if matches!(self, Self::Null) {
+            *self = match value {
+                Value::Null => unreachable!(),
+                Value::Bool(_) => Self::Bool,
+                Value::Integer(_) => Self::Integer,
+                Value::Float(_) => Self::Float,
+                Value::String(_) => Self::String,
+                Value::Array(_) => Self::List(Box::new(Self::Null)),
+                Value::Map(_) => Self::Group(IndexMap::new()),
+            };
+        }
+        if matches!((&*self, value), (Self::Integer, Value::Float(_))) {
+            *self = Self::Float;
+        }
+
+        match (self, value) {
+            (Self::Bool, Value::Bool(_))
+            | (Self::Float, Value::Float(_))
+            | (Self::String, Value::String(_)) => {}
+            (Self::Integer | Self::Float, Value::Integer(int)) => {
+                if int.as_i64().is_none() {
+                    return Err(ConvertError::IntegerOutOfRange(int.clone()).into());
+                }
+            }
+            (Self::List(elem), Value::Array(arr)) => {
+                for (i, v) in arr.iter().enumerate() {
+                    elem.accept(v, &format!("{}/{}", pointer, i))?;
+                }
+            }
+            (Self::Group(fields), Value::Map(map)) => {
+                for (k, v) in map {
+                    let mut pointer = pointer.to_string();
+                    push_pointer_token(&mut pointer, k);
+                    fields
+                        .entry(k.clone())
+                        .or_insert(Self::Null)
+                        .accept(v, &pointer)?;
+                }
+            }
+            _ => bail!(
+                "The value at {} does not have the same type as the other values of the column",
+                pointer
+            ),
+        }
+
+        Ok(())
+    }
+
+    /// Build the type of the field `name` in the schema.
+    fn to_type(&self, name: &str) -> Result<TypePtr> {
+        let primitive = |physical_type| {
+            Type::primitive_type_builder(name, physical_type).with_repetition(Repetition::OPTIONAL)
+        };
+        let ty = match self {
+            Self::Null | Self::String => primitive(PhysicalType::BYTE_ARRAY)
+                .with_converted_type(ConvertedType::UTF8)
+                .build(),
+            Self::Bool => primitive(PhysicalType::BOOLEAN).build(),
+            Self::Integer => primitive(PhysicalType::INT64).build(),
+            Self::Float => primitive(PhysicalType::DOUBLE).build(),
+            Self::List(elem) => {
+                let list = Type::group_type_builder("list")
+                    .with_repetition(Repetition::REPEATED)
+                    .with_fields(&mut vec![elem.to_type("element")?])
+                    .build()?;
+
+                Type::group_type_builder(name)
+                    .with_repetition(Repetition::OPTIONAL)
+                    .with_converted_type(ConvertedType::LIST)
+                    .with_fields(&mut vec![Arc::new(list)])
+                    .build()
+            }
+            Self::Group(fields) => {
+                ensure!(
+                    !fields.is_empty(),
+                    "The column {} only has empty maps, which Parquet cannot write",
+                    name
+                );
+
+                Type::group_type_builder(name)
+                    .with_repetition(Repetition::OPTIONAL)
+                    .with_fields(&mut Self::to_types(fields)?)
+                    .build()
+            }
+        };
+
+        Ok(Arc::new(ty?))
+    }
+
+    fn to_types(fields: &IndexMap<String, Self>) -> Result<Vec<TypePtr>> {
+        fields.iter().map(|(k, v)| v.to_type(k)).collect()
+    }
+
+    /// Append the leaf columns under this node in the order of the schema.
+    fn columns(&self, columns: &mut Vec<Column>) {
+        let values = match self {
+            Self::Null | Self::String => Values::ByteArray(Vec::new()),
+            Self::Bool => Values::Bool(Vec::new()),
+            Self::Integer => Values::Int64(Vec::new()),
+            Self::Float => Values::Double(Vec::new()),
+            Self::List(elem) => {
+                elem.columns(columns);
+                return;
+            }
+            Self::Group(fields) => {
+                fields.values().for_each(|v| v.columns(columns));
+                return;
+            }
+        };
+
+        columns.push(Column {
+            values,
+            definition_levels: Vec::new(),
+            repetition_levels: Vec::new(),
+        });
+    }
+
+    fn leaf_count(&self) -> usize {
+        match self {
+            Self::List(elem) => elem.leaf_count(),
+            Self::Group(fields) => fields.values().map(Self::leaf_count).sum(),
+            _ => 1,
+        }
+    }
+
+    /// Append `value` to `columns`, which are the leaf columns under this node.
+    ///
+    /// `definition_level` is that of the parent, `repetition_level` is that of
+    /// the first leaf value, and `depth` is the number of the lists which
+    /// contain this node.
+    fn shred(
+        &self,
+        value: Option<&Value>,
+        columns: &mut [Column],
+        definition_level: i16,
+        repetition_level: i16,
+        depth: i16,
+    ) {
+        let push_null = |columns: &mut [Column], definition_level| {
+            for column in columns {
+                column.definition_levels.push(definition_level);
+                column.repetition_levels.push(repetition_level);
+            }
+        };
+
+        match (self, value) {
+            (_, None | Some(Value::Null)) => push_null(columns, definition_level),
+            (Self::Group(fields), Some(Value::Map(map))) => {
+                Self::shred_fields(
+                    fields,
+                    map,
+                    columns,
+                    definition_level + 1,
+                    repetition_level,
+                    depth,
+                );
+            }
+            // The optional list, the repeated group and the optional element.
+            (Self::List(elem), Some(Value::Array(arr))) => {
+                if arr.is_empty() {
+                    push_null(columns, definition_level + 1);
+                }
+                for (i, v) in arr.iter().enumerate() {
+                    let repetition_level = if i == 0 { repetition_level } else { depth + 1 };
+                    elem.shred(
+                        Some(v),
+                        columns,
+                        definition_level + 2,
+                        repetition_level,
+                        depth + 1,
+                    );
+                }
+            }
+            (_, Some(value)) => {
+                let column = &mut columns[0];
+                match (&mut column.values, value) {
+                    (Values::Bool(values), Value::Bool(bool)) => values.push(*bool),
+                    (Values::Int64(values), Value::Integer(int)) => {
+                        values.push(int.as_i64().expect("The integer should be checked"));
+                    }
+                    (Values::Double(values), Value::Float(float)) => values.push(*float),
+                    (Values::Double(values), Value::Integer(int)) => {
+                        values.push(int.as_i64().expect("The integer should be checked") as f64);
+                    }
+                    (Values::ByteArray(values), Value::String(str)) => {
+                        values.push(ByteArray::from(str.as_str()));
+                    }
+                    _ => unreachable!("The value should be accepted by the column"),
+                }
+                column.definition_levels.push(definition_level + 1);
+                column.repetition_levels.push(repetition_level);
+            }
+        }
+    }
+
+    fn shred_fields(
+        fields: &IndexMap<String, Self>,
+        map: &IndexMap<String, Value>,
+        mut columns: &mut [Column],
+        definition_level: i16,
+        repetition_level: i16,
+        depth: i16,
+    ) {
+        for (k, node) in fields {
+            let (head, tail) = mem::take(&mut columns).split_at_mut(node.leaf_count());
+            node.shred(map.get(k), head, definition_level, repetition_level, depth);
+            columns = tail;
+        }
+    }
+}
+
+impl Column {
+    fn write(&self, writer: &mut ColumnWriter) -> Result<()> {
+        let levels = (
+            Some(self.definition_levels.as_slice()),
+            Some(self.repetition_levels.as_slice()),
+        );
+        match (&self.values, writer) {
+            (Values::Bool(values), ColumnWriter::BoolColumnWriter(w)) => {
+                w.write_batch(values, levels.0, levels.1)
+            }
+            (Values::Int64(values), ColumnWriter::Int64ColumnWriter(w)) => {
+                w.write_batch(values, levels.0, levels.1)
+            }
+            (Values::Double(values), ColumnWriter::DoubleColumnWriter(w)) => {
+                w.write_batch(values, levels.0, levels.1)
+            }
+            (Values::ByteArray(values), ColumnWriter::ByteArrayColumnWriter(w)) => {
+                w.write_batch(values, levels.0, levels.1)
+            }
+            _ => unreachable!("The column should follow the schema"),
+        }?;
+
+        Ok(())
+    }
+}
+
+/// Write an array of maps as the rows of a Parquet file.
+///
+/// The schema is inferred from the rows, and every column is optional. Maps
+/// and arrays become groups and lists, and a column which only has nulls is
+/// written as strings. Integers in a column which also has floats are written
+/// as floats. Other columns whose values have different types cause an error.
+pub fn to_vec(value: &Value) -> Result<Vec<u8>> {
+    let rows = match value {
+        Value::Array(rows) => rows,
+        _ => bail!("Parquet requires an array of maps at the root"),
+    };
+
+    let mut root = Node::Group(IndexMap::new());
+    for (i, row) in rows.iter().enumerate() {
+        ensure!(
+            matches!(row, Value::Map(_)),
+            "The element at index {} is not a map",
+            i
+        );
+        root.accept(row, &format!("/{}", i))?;
+    }
+    let fields = match root {
+        Node::Group(fields) => fields,
+        _ => unreachable!(),
+    };
+    ensure!(!fields.is_empty(), "Parquet requires at least one column");
+
+    let schema = Type::group_type_builder("schema")
+        .with_fields(&mut Node::to_types(&fields)?)
+        .build()
+        .context("Failed to build the schema")?;
+    let mut columns = Vec::new();
+    fields.values().for_each(|v| v.columns(&mut columns));
+    for row in rows {
+        if let Value::Map(map) = row {
+            Node::shred_fields(&fields, map, &mut columns, 0, 0, 0);
+        }
+    }
+
+    // The dictionary encoder of `parquet` reads unaligned memory.
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_dictionary_enabled(false)
+        .build();
+    let cursor = InMemoryWriteableCursor::default();
+    let mut writer =
+        SerializedFileWriter::new(cursor.clone(), Arc::new(schema), Arc::new(properties))?;
+    let mut row_group = writer.next_row_group()?;
+    let mut columns = columns.iter();
+    while let Some(mut column_writer) = row_group.next_column()? {
+        columns
+            .next()
+            .expect("The columns should follow the schema")
+            .write(&mut column_writer)?;
+        row_group.close_column(column_writer)?;
+    }
+    writer.close_row_group(row_group)?;
+    writer.close()?;
+
+    Ok(cursor.data())
+}
+
+#[cfg(test)]
+mod tests {
+    use ::parquet::schema::parser::parse_message_type;
+
+    use super::*;
+    use crate::value::ByteStringMode;
+
+    /// Write a Parquet file of two rows.
+    fn write() -> Vec<u8> {
+        let schema = "
+            message schema {
+                REQUIRED INT32 id;
+                OPTIONAL BYTE_ARRAY name (UTF8);
+                OPTIONAL GROUP tags (LIST) {
+                    REPEATED GROUP list {
+                        OPTIONAL INT64 element;
+                    }
+                }
+                REQUIRED INT32 day (DATE);
+                REQUIRED INT64 at (TIMESTAMP_MILLIS);
+                REQUIRED INT32 price (DECIMAL(5, 2));
+            }
+        ";
+        let schema = Arc::new(parse_message_type(schema).unwrap());
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_dictionary_enabled(false)
+            .build();
+        let cursor = InMemoryWriteableCursor::default();
+        let mut writer =
+            SerializedFileWriter::new(cursor.clone(), schema, Arc::new(properties)).unwrap();
+
+        let mut row_group = writer.next_row_group().unwrap();
+        let mut index = 0;
+        while let Some(mut column) = row_group.next_column().unwrap() {
+            match (index, &mut column) {
+                (0, ColumnWriter::Int32ColumnWriter(w)) => {
+                    w.write_batch(&[1, 2], None, None).unwrap();
+                }
+                (1, ColumnWriter::ByteArrayColumnWriter(w)) => {
+                    w.write_batch(&[ByteArray::from("a")], Some(&[1, 0]), None)
+                        .unwrap();
+                }
+                // `[1, null]` and `[]`.
+                (2, ColumnWriter::Int64ColumnWriter(w)) => {
+                    w.write_batch(&[1], Some(&[3, 2, 1]), Some(&[0, 1, 0]))
+                        .unwrap();
+                }
+                (3, ColumnWriter::Int32ColumnWriter(w)) => {
+                    w.write_batch(&[0, -1], None, None).unwrap();
+                }
+                (4, ColumnWriter::Int64ColumnWriter(w)) => {
+                    w.write_batch(&[1_500, -1], None, None).unwrap();
+                }
+                (5, ColumnWriter::Int32ColumnWriter(w)) => {
+                    w.write_batch(&[12345, -5], None, None).unwrap();
+                }
+                _ => unreachable!(),
+            }
+            row_group.close_column(column).unwrap();
+            index += 1;
+        }
+        writer.close_row_group(row_group).unwrap();
+        writer.close().unwrap();
+
+        cursor.data()
+    }
+
+    #[test]
+    fn read() {
+        let value = from_vec(write(), &Options::default()).unwrap();
+        let json = serde_json::to_string(&serde_json::Value::try_from(value).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"[{"id":1,"name":"a","tags":[1,null],"day":"1970-01-01","#,
+                r#""at":"1970-01-01T00:00:01.500Z","price":"123.45"},"#,
+                r#"{"id":2,"name":null,"tags":[],"day":"1969-12-31","#,
+                r#""at":"1969-12-31T23:59:59.999Z","price":"-0.05"}]"#
+            )
+        );
+
+        assert!(from_vec(b"PAR1".to_vec(), &Options::default()).is_err());
+    }
+
+    #[test]
+    fn read_field() {
+        assert!(matches!(
+            from_field(&Field::Date(i32::MAX as u32), &Options::default()),
+            Err(ConvertError::IntegerOutOfRange(_))
+        ));
+        assert!(matches!(
+            from_field(&Field::Date(i32::MIN as u32), &Options::default()),
+            Err(ConvertError::IntegerOutOfRange(_))
+        ));
+        assert!(matches!(
+            from_field(
+                &Field::Bytes(ByteArray::from(vec![0xff])),
+                &Options::default()
+            ),
+            Err(ConvertError::ByteStringUnsupported)
+        ));
+        assert_eq!(
+            from_field(
+                &Field::Bytes(ByteArray::from(vec![0xff])),
+                &Options::new().byte_strings(ByteStringMode::Base64)
+            )
+            .unwrap(),
+            Value::String("/w==".to_string())
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = r#"[
+            {"id": 1, "name": "a", "score": 1, "tags": [["x"], []], "point": {"x": 1, "y": null}},
+            {"id": 2, "score": 2.5, "tags": null, "point": {"x": -1}, "flag": true},
+            {"id": 3, "name": null, "score": null, "tags": [null, ["y", null]], "point": null}
+        ]"#;
+        let output = from_vec(to_vec(&json(input)).unwrap(), &Options::default()).unwrap();
+        let json = serde_json::to_string(&serde_json::Value::try_from(output).unwrap()).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"[{"id":1,"name":"a","score":1.0,"tags":[["x"],[]],"#,
+                r#""point":{"x":1,"y":null},"flag":null},"#,
+                r#"{"id":2,"name":null,"score":2.5,"tags":null,"#,
+                r#""point":{"x":-1,"y":null},"flag":true},"#,
+                r#"{"id":3,"name":null,"score":null,"tags":[null,["y",null]],"#,
+                r#""point":null,"flag":null}]"#
+            )
+        );
+    }
+
+    fn json(input: &str) -> Value {
+        serde_json::from_str::<serde_json::Value>(input)
+            .unwrap()
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn write_invalid() {
+        assert!(to_vec(&json(r#"{"a": 1}"#)).is_err());
+        assert!(to_vec(&json("[1]")).is_err());
+        assert!(to_vec(&json("[]")).is_err());
+        assert!(to_vec(&json(r#"[{"a": {}}]"#)).is_err());
+        assert_eq!(
+            to_vec(&json(r#"[{"a": 1}, {"a": [1]}]"#))
+                .unwrap_err()
+                .to_string(),
+            "The value at /1/a does not have the same type as the other values of the column"
+        );
+        assert!(to_vec(&json(r#"[{"a": 18446744073709551615}]"#))
+            .unwrap_err()
+            .is::<ConvertError>());
+    }
+}
@@ -0,0 +1,48 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::io::{self, Read};
+
+/// Wrap the reader to show the progress of reading on stderr.
+///
+/// The progress is shown only when stderr is a terminal and `quiet` is
+/// `false`, and is cleared when the reader is dropped. `len` is the total
+/// number of bytes, if known.
+#[cfg(feature = "progress")]
+pub fn wrap_read<'a>(reader: impl Read + 'a, len: Option<u64>, quiet: bool) -> Box<dyn Read + 'a> {
+    use indicatif::{ProgressBar, ProgressStyle};
+
+    if quiet || !atty::is(atty::Stream::Stderr) {
+        return Box::new(reader);
+    }
+
+    let bar = match len {
+        Some(len) => ProgressBar::new(len).with_style(
+            ProgressStyle::default_bar()
+                .template("{bytes}/{total_bytes} [{bar:40}] {bytes_per_sec} ({eta})"),
+        ),
+        None => ProgressBar::new_spinner().with_style(
+            ProgressStyle::default_spinner().template("{spinner} {bytes} {bytes_per_sec}"),
+        ),
+    };
+    Box::new(bar.wrap_read(reader))
+}
+
+/// Return the reader as is.
+#[cfg(not(feature = "progress"))]
+pub fn wrap_read<'a>(reader: impl Read + 'a, _: Option<u64>, _: bool) -> Box<dyn Read + 'a> {
+    Box::new(reader)
+}
+
+/// Read all bytes from the reader, showing the progress on stderr.
+///
+/// See [`wrap_read`] for when the progress is shown.
+pub fn read_to_end(reader: impl Read, len: Option<u64>, quiet: bool) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    wrap_read(reader, len, quiet).read_to_end(&mut buf)?;
+
+    Ok(buf)
+}
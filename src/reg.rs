@@ -0,0 +1,190 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::fmt::Write;
+
+use crate::convert::ConvertError;
+use crate::lossy::Tracker;
+use crate::value::Value;
+
+const HEADER: &str = "Windows Registry Editor Version 5.00";
+
+/// Convert a map of registry keys into a Windows Registry file.
+///
+/// Each key of the root map is the path of a registry key, and its value is a
+/// map from value names to values. An empty value name is the default value.
+/// Strings become `REG_SZ`, integers become `REG_DWORD` or `REG_QWORD`,
+/// booleans become `REG_DWORD`, arrays of bytes become `REG_BINARY` and nulls
+/// delete the value or the key.
+///
+/// Lines are terminated by CRLF.
+pub fn to_string(value: &Value) -> Result<String, ConvertError> {
+    to_string_tracked(value, &mut Tracker::new())
+}
+
+/// Convert a map of registry keys into a Windows Registry file like
+/// `to_string`, reporting the values whose type changes to `tracker`.
+pub(crate) fn to_string_tracked(
+    value: &Value,
+    tracker: &mut Tracker,
+) -> Result<String, ConvertError> {
+    let map = match value {
+        Value::Map(map) => map,
+        _ => return Err(ConvertError::NonMapRoot),
+    };
+
+    let mut output = format!("{}\r\n", HEADER);
+    for (path, values) in map {
+        let values = match values {
+            Value::Map(values) => values,
+            Value::Null => {
+                write!(output, "\r\n[-{}]\r\n", path).unwrap();
+                continue;
+            }
+            _ => return Err(ConvertError::UnsupportedValue(path.clone())),
+        };
+
+        write!(output, "\r\n[{}]\r\n", path).unwrap();
+        for (name, value) in values {
+            let data = tracker
+                .nest(path, |tracker| {
+                    tracker.nest(name, |tracker| data(value, tracker))
+                })
+                .ok_or_else(|| ConvertError::UnsupportedValue(format!("{}\\{}", path, name)))?;
+            if name.is_empty() {
+                write!(output, "@={}\r\n", data).unwrap();
+            } else {
+                write!(output, "{}={}\r\n", quote(name), data).unwrap();
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+fn quote(str: &str) -> String {
+    format!("\"{}\"", str.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn hex<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> String {
+    bytes
+        .into_iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Format the data of a registry value with its type prefix.
+fn data(value: &Value, tracker: &mut Tracker) -> Option<String> {
+    match value {
+        Value::Null => Some("-".to_string()),
+        Value::Bool(bool) => {
+            tracker.report("the boolean is converted to a DWORD");
+            Some(format!("dword:{:08x}", u32::from(*bool)))
+        }
+        Value::Integer(int) => match int.as_u64()? {
+            uint if uint <= u32::MAX.into() => Some(format!("dword:{:08x}", uint)),
+            uint => {
+                tracker.report("the integer is converted to a QWORD");
+                Some(format!("hex(b):{}", hex(&uint.to_le_bytes())))
+            }
+        },
+        Value::String(str) => Some(quote(str)),
+        Value::Array(arr) => {
+            let bytes = arr
+                .iter()
+                .map(|v| match v {
+                    Value::Integer(int) => int.as_u64().and_then(|b| u8::try_from(b).ok()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+
+            Some(format!("hex:{}", hex(&bytes)))
+        }
+        Value::Float(_) | Value::Map(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn map_to_reg() {
+        let value = map(vec![
+            (
+                r"HKEY_CURRENT_USER\Software\dsconv",
+                map(vec![
+                    ("", Value::String("default".to_string())),
+                    ("Path", Value::String(r#"C:\"a""#.to_string())),
+                    ("Count", Value::Integer(42_u64.into())),
+                    ("Large", Value::Integer(u64::MAX.into())),
+                    ("Enabled", Value::Bool(true)),
+                    (
+                        "Data",
+                        Value::Array(vec![
+                            Value::Integer(1_u64.into()),
+                            Value::Integer(255_u64.into()),
+                        ]),
+                    ),
+                    ("Old", Value::Null),
+                ]),
+            ),
+            (r"HKEY_CURRENT_USER\Software\Old", Value::Null),
+        ]);
+
+        assert_eq!(
+            to_string(&value).unwrap(),
+            "Windows Registry Editor Version 5.00\r\n\
+             \r\n\
+             [HKEY_CURRENT_USER\\Software\\dsconv]\r\n\
+             @=\"default\"\r\n\
+             \"Path\"=\"C:\\\\\\\"a\\\"\"\r\n\
+             \"Count\"=dword:0000002a\r\n\
+             \"Large\"=hex(b):ff,ff,ff,ff,ff,ff,ff,ff\r\n\
+             \"Enabled\"=dword:00000001\r\n\
+             \"Data\"=hex:01,ff\r\n\
+             \"Old\"=-\r\n\
+             \r\n\
+             [-HKEY_CURRENT_USER\\Software\\Old]\r\n"
+        );
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(matches!(
+            to_string(&Value::Array(vec![])),
+            Err(ConvertError::NonMapRoot)
+        ));
+        assert!(matches!(
+            to_string(&map(vec![("HKEY_CURRENT_USER", Value::Bool(true))])),
+            Err(ConvertError::UnsupportedValue(path)) if path == "HKEY_CURRENT_USER"
+        ));
+        assert!(matches!(
+            to_string(&map(vec![(
+                "HKEY_CURRENT_USER",
+                map(vec![("a", Value::Float(0.5))])
+            )])),
+            Err(ConvertError::UnsupportedValue(path)) if path == "HKEY_CURRENT_USER\\a"
+        ));
+        assert!(matches!(
+            to_string(&map(vec![(
+                "HKEY_CURRENT_USER",
+                map(vec![("a", Value::Integer((-1_i64).into()))])
+            )])),
+            Err(ConvertError::UnsupportedValue(_))
+        ));
+    }
+}
@@ -0,0 +1,203 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use indexmap::IndexMap;
+use serde_json::{json, Map, Value as Json};
+
+use crate::value::Value;
+
+const DRAFT_07: &str = "http://json-schema.org/draft-07/schema#";
+
+#[derive(Default)]
+struct Schema {
+    types: Vec<&'static str>,
+    items: Option<Box<Schema>>,
+    properties: Option<IndexMap<String, Schema>>,
+    required: Vec<String>,
+}
+
+impl Schema {
+    fn of(value: &Value) -> Self {
+        let mut schema = Self::default();
+        match value {
+            Value::Null => schema.types.push("null"),
+            Value::Bool(_) => schema.types.push("boolean"),
+            Value::Integer(_) => schema.types.push("integer"),
+            Value::Float(_) => schema.types.push("number"),
+            Value::String(_) => schema.types.push("string"),
+            Value::Array(arr) => {
+                schema.types.push("array");
+                schema.items = arr.iter().map(Self::of).reduce(Self::merge).map(Box::new);
+            }
+            Value::Map(map) => {
+                schema.types.push("object");
+                schema.properties =
+                    Some(map.iter().map(|(k, v)| (k.clone(), Self::of(v))).collect());
+                schema.required = map.keys().cloned().collect();
+            }
+        }
+
+        schema
+    }
+
+    fn merge(mut self, other: Self) -> Self {
+        for ty in other.types {
+            if !self.types.contains(&ty) {
+                self.types.push(ty);
+            }
+        }
+
+        self.items = match (self.items, other.items) {
+            (Some(a), Some(b)) => Some(Box::new(a.merge(*b))),
+            (a, b) => a.or(b),
+        };
+
+        match (&mut self.properties, other.properties) {
+            (Some(properties), Some(others)) => {
+                self.required.retain(|k| other.required.contains(k));
+                for (key, schema) in others {
+                    let merged = match properties.remove(&key) {
+                        Some(existing) => existing.merge(schema),
+                        None => schema,
+                    };
+                    properties.insert(key, merged);
+                }
+            }
+            (properties @ None, Some(others)) => {
+                *properties = Some(others);
+                self.required = other.required;
+            }
+            _ => (),
+        }
+
+        self
+    }
+
+    fn into_json(self) -> Json {
+        let mut types: Vec<_> = self.types;
+        if types.contains(&"number") {
+            types.retain(|&t| t != "integer");
+        }
+
+        let mut obj = Map::new();
+        if let [ty] = types.as_slice() {
+            obj.insert("type".to_string(), json!(ty));
+        } else {
+            obj.insert("type".to_string(), json!(types));
+        }
+        if let Some(items) = self.items {
+            obj.insert("items".to_string(), items.into_json());
+        }
+        if let Some(properties) = self.properties {
+            obj.insert(
+                "properties".to_string(),
+                Json::Object(
+                    properties
+                        .into_iter()
+                        .map(|(k, v)| (k, v.into_json()))
+                        .collect(),
+                ),
+            );
+            if !self.required.is_empty() {
+                obj.insert("required".to_string(), json!(self.required));
+            }
+        }
+
+        Json::Object(obj)
+    }
+}
+
+/// Infer a JSON Schema (draft-07) which describes the value.
+///
+/// The elements of an array are described by a single schema. A key of a map
+/// is required if it is present in all maps described by the schema.
+pub fn infer(value: &Value) -> Json {
+    let mut obj = Map::new();
+    obj.insert("$schema".to_string(), json!(DRAFT_07));
+    if let Json::Object(schema) = Schema::of(value).into_json() {
+        obj.extend(schema);
+    }
+
+    Json::Object(obj)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn scalar() {
+        assert_eq!(
+            infer(&Value::Null),
+            json!({"type": "null", "$schema": DRAFT_07})
+        );
+        assert_eq!(
+            infer(&Value::Array(vec![])),
+            json!({"type": "array", "$schema": DRAFT_07})
+        );
+    }
+
+    #[test]
+    fn array_of_maps() {
+        let value = Value::Array(vec![
+            map(vec![
+                ("id", Value::Integer(1_u64.into())),
+                ("name", Value::String("a".to_string())),
+                ("tags", Value::Array(vec![Value::String("x".to_string())])),
+            ]),
+            map(vec![
+                ("id", Value::Float(1.5)),
+                ("tags", Value::Array(vec![])),
+                ("extra", Value::Null),
+            ]),
+        ]);
+
+        assert_eq!(
+            infer(&value),
+            json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": {"type": "number"},
+                        "name": {"type": "string"},
+                        "tags": {"type": "array", "items": {"type": "string"}},
+                        "extra": {"type": "null"}
+                    },
+                    "required": ["id", "tags"]
+                },
+                "$schema": DRAFT_07
+            })
+        );
+    }
+
+    #[test]
+    fn mixed_types() {
+        let value = Value::Array(vec![
+            Value::String("a".to_string()),
+            Value::Null,
+            Value::String("b".to_string()),
+        ]);
+
+        assert_eq!(
+            infer(&value),
+            json!({
+                "type": "array",
+                "items": {"type": ["string", "null"]},
+                "$schema": DRAFT_07
+            })
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+use serde_json::{json, Map, Value as Json};
+
+use crate::value::Value;
+
+#[derive(Default)]
+struct Stats<'a> {
+    nodes: usize,
+    depth: usize,
+    types: IndexMap<&'static str, usize>,
+    array_lengths: Vec<usize>,
+    keys: HashSet<&'a str>,
+    string_bytes: usize,
+}
+
+impl<'a> Stats<'a> {
+    fn visit(&mut self, value: &'a Value, depth: usize) {
+        self.nodes += 1;
+        *self.types.entry(value.type_name()).or_default() += 1;
+
+        match value {
+            Value::String(str) => self.string_bytes += str.len(),
+            Value::Array(arr) => {
+                self.depth = self.depth.max(depth + 1);
+                self.array_lengths.push(arr.len());
+                for v in arr {
+                    self.visit(v, depth + 1);
+                }
+            }
+            Value::Map(map) => {
+                self.depth = self.depth.max(depth + 1);
+                for (k, v) in map {
+                    self.keys.insert(k);
+                    self.visit(v, depth + 1);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Profile the structure of the value.
+///
+/// The result is a JSON object of the number of values (`nodes`), the
+/// maximum nesting of arrays and maps (`depth`), the number of values of each
+/// type (`types`), the minimum, maximum and average lengths of arrays
+/// (`array_length`, which is `null` if there are no arrays), the number of
+/// distinct map keys (`unique_keys`), and the total size of strings in bytes
+/// excluding keys (`string_bytes`).
+pub fn profile(value: &Value) -> Json {
+    let mut stats = Stats::default();
+    for ty in [
+        "null", "boolean", "integer", "float", "string", "array", "map",
+    ] {
+        stats.types.insert(ty, 0);
+    }
+    stats.visit(value, 0);
+
+    let lengths = &stats.array_lengths;
+    let array_length = match (lengths.iter().min(), lengths.iter().max()) {
+        (Some(min), Some(max)) => json!({
+            "min": min,
+            "max": max,
+            "avg": lengths.iter().sum::<usize>() as f64 / lengths.len() as f64,
+        }),
+        _ => Json::Null,
+    };
+
+    let types: Map<_, _> = stats
+        .types
+        .into_iter()
+        .map(|(ty, count)| (ty.to_string(), json!(count)))
+        .collect();
+
+    json!({
+        "nodes": stats.nodes,
+        "depth": stats.depth,
+        "types": types,
+        "array_length": array_length,
+        "unique_keys": stats.keys.len(),
+        "string_bytes": stats.string_bytes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested() {
+        let value: Value = json!({"a": [1, "xy", [true]], "b": {"a": null, "c": 1.5}})
+            .try_into()
+            .unwrap();
+
+        assert_eq!(
+            profile(&value),
+            json!({
+                "nodes": 9,
+                "depth": 3,
+                "types": {
+                    "null": 1,
+                    "boolean": 1,
+                    "integer": 1,
+                    "float": 1,
+                    "string": 1,
+                    "array": 2,
+                    "map": 2,
+                },
+                "array_length": {"min": 1, "max": 3, "avg": 2.0},
+                "unique_keys": 3,
+                "string_bytes": 2,
+            })
+        );
+    }
+
+    #[test]
+    fn scalar() {
+        let stats = profile(&Value::String("abc".to_string()));
+
+        assert_eq!(stats["nodes"], 1);
+        assert_eq!(stats["depth"], 0);
+        assert_eq!(stats["array_length"], Json::Null);
+        assert_eq!(stats["string_bytes"], 3);
+    }
+}
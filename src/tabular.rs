@@ -0,0 +1,341 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::collections::HashSet;
+use std::io::{self, Read};
+
+use csv::{ErrorKind, ReaderBuilder, StringRecord};
+use indexmap::IndexMap;
+
+use crate::value::{KeyConflict, ParseErrorPolicy, Value};
+use crate::ConvertError;
+
+/// The result of reading a record.
+enum Record {
+    Read,
+    Malformed,
+    End,
+}
+
+/// Read records of CSV one at a time, calling `f` with each record as a map.
+///
+/// The first record is the header, which is used as the keys of the maps.
+/// Fields are always strings. Only one record is kept in memory at a time.
+/// The first `skip` records after the header are discarded without being
+/// converted. If `limit` is specified, reading stops after that many records.
+/// Records with the wrong number of fields or invalid UTF-8 are handled by
+/// `on_parse_error`, and duplicate headers are handled by `on_key_conflict`.
+///
+/// Returns the number of malformed records which are skipped or replaced with
+/// null.
+pub fn for_each_record<R, E, F>(
+    reader: R,
+    delimiter: u8,
+    skip: usize,
+    limit: Option<usize>,
+    on_parse_error: ParseErrorPolicy,
+    on_key_conflict: KeyConflict,
+    mut f: F,
+) -> Result<usize, E>
+where
+    R: Read,
+    E: From<csv::Error>,
+    F: FnMut(Value) -> Result<(), E>,
+{
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(reader);
+    let headers = reader.headers()?.clone();
+    if on_key_conflict == KeyConflict::Error {
+        let mut seen = HashSet::new();
+        if let Some(key) = headers.iter().find(|k| !seen.insert(*k)) {
+            let err = io::Error::new(
+                io::ErrorKind::InvalidData,
+                ConvertError::DuplicateKey(key.to_string()),
+            );
+            return Err(csv::Error::from(err).into());
+        }
+    }
+
+    let mut read = |record: &mut StringRecord| match reader.read_record(record) {
+        Ok(true) => Ok(Record::Read),
+        Ok(false) => Ok(Record::End),
+        Err(err)
+            if on_parse_error != ParseErrorPolicy::Abort
+                && matches!(
+                    err.kind(),
+                    ErrorKind::UnequalLengths { .. } | ErrorKind::Utf8 { .. }
+                ) =>
+        {
+            Ok(Record::Malformed)
+        }
+        Err(err) => Err(err),
+    };
+
+    let mut record = StringRecord::new();
+    for _ in 0..skip {
+        if let Record::End = read(&mut record)? {
+            return Ok(0);
+        }
+    }
+    let (mut count, mut malformed) = (0, 0);
+    while limit.map_or(true, |limit| count < limit) {
+        match read(&mut record)? {
+            Record::Read => {
+                count += 1;
+                let mut map = IndexMap::with_capacity(headers.len());
+                for (k, v) in headers.iter().zip(record.iter()) {
+                    // Duplicate headers are already rejected if the policy is
+                    // `Error`.
+                    let _ = on_key_conflict.insert(
+                        &mut map,
+                        k.to_string(),
+                        Value::String(v.to_string()),
+                    );
+                }
+                f(Value::Map(map))?;
+            }
+            Record::Malformed => {
+                malformed += 1;
+                if on_parse_error == ParseErrorPolicy::Null {
+                    count += 1;
+                    f(Value::Null)?;
+                }
+            }
+            Record::End => break,
+        }
+    }
+
+    Ok(malformed)
+}
+
+/// Read CSV into an array of maps.
+///
+/// The first record is the header, which is used as the keys of the maps.
+/// Fields are always strings. Malformed records are handled by
+/// `on_parse_error`, and duplicate headers are handled by `on_key_conflict`.
+///
+/// Returns the array and the number of malformed records.
+pub fn from_reader(
+    reader: impl Read,
+    delimiter: u8,
+    on_parse_error: ParseErrorPolicy,
+    on_key_conflict: KeyConflict,
+) -> Result<(Value, usize), csv::Error> {
+    let mut arr = Vec::new();
+    let malformed = for_each_record(
+        reader,
+        delimiter,
+        0,
+        None,
+        on_parse_error,
+        on_key_conflict,
+        |record| {
+            arr.push(record);
+
+            Ok::<_, csv::Error>(())
+        },
+    )?;
+
+    Ok((Value::Array(arr), malformed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: Vec<(&str, &str)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            from_reader(
+                "a,b\n1,\"x,y\"\n2,\n".as_bytes(),
+                b',',
+                ParseErrorPolicy::Abort,
+                KeyConflict::Error
+            )
+            .unwrap()
+            .0,
+            Value::Array(vec![
+                map(vec![("a", "1"), ("b", "x,y")]),
+                map(vec![("a", "2"), ("b", "")]),
+            ])
+        );
+        assert_eq!(
+            from_reader(
+                "a\n".as_bytes(),
+                b',',
+                ParseErrorPolicy::Abort,
+                KeyConflict::Error
+            )
+            .unwrap()
+            .0,
+            Value::Array(vec![])
+        );
+        assert!(from_reader(
+            "a,b\n1\n".as_bytes(),
+            b',',
+            ParseErrorPolicy::Abort,
+            KeyConflict::Error
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn limit() {
+        let mut records = Vec::new();
+        for_each_record(
+            "a\n1\n2\n3\n".as_bytes(),
+            b',',
+            0,
+            Some(2),
+            ParseErrorPolicy::Abort,
+            KeyConflict::Error,
+            |record| {
+                records.push(record);
+
+                Ok::<_, csv::Error>(())
+            },
+        )
+        .unwrap();
+        assert_eq!(records, vec![map(vec![("a", "1")]), map(vec![("a", "2")])]);
+    }
+
+    #[test]
+    fn skip() {
+        let mut records = Vec::new();
+        for_each_record(
+            "a\n1\n2\n3\n".as_bytes(),
+            b',',
+            1,
+            Some(1),
+            ParseErrorPolicy::Abort,
+            KeyConflict::Error,
+            |record| {
+                records.push(record);
+
+                Ok::<_, csv::Error>(())
+            },
+        )
+        .unwrap();
+        assert_eq!(records, vec![map(vec![("a", "2")])]);
+        for_each_record(
+            "a\n1\n".as_bytes(),
+            b',',
+            5,
+            None,
+            ParseErrorPolicy::Abort,
+            KeyConflict::Error,
+            |_| -> Result<(), csv::Error> {
+                panic!("no records should be read");
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn malformed() {
+        let input = "a\n1\n2,3\n4\n";
+
+        assert!(from_reader(
+            input.as_bytes(),
+            b',',
+            ParseErrorPolicy::Abort,
+            KeyConflict::Error
+        )
+        .is_err());
+        assert_eq!(
+            from_reader(
+                input.as_bytes(),
+                b',',
+                ParseErrorPolicy::Skip,
+                KeyConflict::Error
+            )
+            .unwrap(),
+            (
+                Value::Array(vec![map(vec![("a", "1")]), map(vec![("a", "4")])]),
+                1
+            )
+        );
+        assert_eq!(
+            from_reader(
+                input.as_bytes(),
+                b',',
+                ParseErrorPolicy::Null,
+                KeyConflict::Error
+            )
+            .unwrap(),
+            (
+                Value::Array(vec![
+                    map(vec![("a", "1")]),
+                    Value::Null,
+                    map(vec![("a", "4")])
+                ]),
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn tsv() {
+        assert_eq!(
+            from_reader(
+                "a\tb\n1\t2\n".as_bytes(),
+                b'\t',
+                ParseErrorPolicy::Abort,
+                KeyConflict::Error
+            )
+            .unwrap()
+            .0,
+            Value::Array(vec![map(vec![("a", "1"), ("b", "2")])])
+        );
+    }
+
+    #[test]
+    fn duplicate_headers() {
+        let input = "a,b,a\n1,2,3\n";
+
+        assert!(from_reader(
+            input.as_bytes(),
+            b',',
+            ParseErrorPolicy::Abort,
+            KeyConflict::Error
+        )
+        .unwrap_err()
+        .to_string()
+        .contains("The key is duplicated: a"));
+        assert_eq!(
+            from_reader(
+                input.as_bytes(),
+                b',',
+                ParseErrorPolicy::Abort,
+                KeyConflict::First
+            )
+            .unwrap()
+            .0,
+            Value::Array(vec![map(vec![("a", "1"), ("b", "2")])])
+        );
+        assert_eq!(
+            from_reader(
+                input.as_bytes(),
+                b',',
+                ParseErrorPolicy::Abort,
+                KeyConflict::Last
+            )
+            .unwrap()
+            .0,
+            Value::Array(vec![map(vec![("a", "3"), ("b", "2")])])
+        );
+    }
+}
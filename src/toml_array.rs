@@ -0,0 +1,213 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use serde::ser::Error;
+use toml::value::Table;
+use toml::Value as Toml;
+
+/// The key used to serialize a single value.
+const KEY: &str = "x";
+
+/// Return `true` if the value is written as a subtable or an array of tables.
+fn is_section(value: &Toml) -> bool {
+    match value {
+        Toml::Table(_) => true,
+        Toml::Array(arr) => !arr.is_empty() && arr.iter().all(Toml::is_table),
+        _ => false,
+    }
+}
+
+/// Serialize a table which has the only entry of `key` and `value` by
+/// `serialize`.
+fn serialize_entry<F>(key: &str, value: &Toml, serialize: &F) -> Result<String, toml::ser::Error>
+where
+    F: Fn(&Toml) -> Result<String, toml::ser::Error>,
+{
+    let mut table = Table::new();
+    table.insert(key.to_string(), value.clone());
+
+    serialize(&Toml::Table(table))
+}
+
+/// Serialize a value which is not a table by `serialize`.
+fn serialize_value<F>(value: &Toml, serialize: &F) -> Result<String, toml::ser::Error>
+where
+    F: Fn(&Toml) -> Result<String, toml::ser::Error>,
+{
+    let str = serialize_entry(KEY, value, serialize)?;
+
+    str.strip_prefix(&format!("{} = ", KEY))
+        .map(|s| s.trim_end_matches('\n').to_string())
+        .ok_or_else(|| toml::ser::Error::custom("Unexpected output of the serializer"))
+}
+
+/// Serialize a key by `serialize`, quoting it if necessary.
+fn serialize_key<F>(key: &str, serialize: &F) -> Result<String, toml::ser::Error>
+where
+    F: Fn(&Toml) -> Result<String, toml::ser::Error>,
+{
+    let str = serialize_entry(key, &Toml::Boolean(true), serialize)?;
+
+    str.strip_suffix(" = true\n")
+        .map(str::to_string)
+        .ok_or_else(|| toml::ser::Error::custom("Unexpected output of the serializer"))
+}
+
+/// Write the header of a subtable or an array of tables.
+fn write_header(buf: &mut String, path: &[String], array: bool) {
+    if !buf.is_empty() {
+        buf.push('\n');
+    }
+    let (open, close) = if array { ("[[", "]]") } else { ("[", "]") };
+    buf.push_str(open);
+    buf.push_str(&path.join("."));
+    buf.push_str(close);
+    buf.push('\n');
+}
+
+/// Write the entries of `table` whose path is `path` to `buf`.
+///
+/// The values are written first, and then the subtables and the arrays of
+/// tables, each of them in the order of `table`.
+fn write_table<F>(
+    buf: &mut String,
+    path: &mut Vec<String>,
+    table: &Table,
+    max: usize,
+    serialize: &F,
+) -> Result<(), toml::ser::Error>
+where
+    F: Fn(&Toml) -> Result<String, toml::ser::Error>,
+{
+    for (key, value) in table.iter().filter(|(_, v)| !is_section(v)) {
+        match value {
+            Toml::Array(arr) if arr.len() > max && !arr.iter().any(Toml::is_table) => {
+                buf.push_str(&serialize_key(key, serialize)?);
+                buf.push_str(" = [\n");
+                for elem in arr {
+                    buf.push_str("    ");
+                    buf.push_str(&serialize_value(elem, serialize)?);
+                    buf.push_str(",\n");
+                }
+                buf.push_str("]\n");
+            }
+            value => buf.push_str(&serialize_entry(key, value, serialize)?),
+        }
+    }
+    for (key, value) in table.iter().filter(|(_, v)| is_section(v)) {
+        path.push(serialize_key(key, serialize)?);
+        match value {
+            Toml::Table(table) => {
+                // A table which only has subtables does not need its header.
+                if table.is_empty() || !table.values().all(is_section) {
+                    write_header(buf, path, false);
+                }
+                write_table(buf, path, table, max, serialize)?;
+            }
+            Toml::Array(arr) => {
+                for table in arr.iter().filter_map(Toml::as_table) {
+                    write_header(buf, path, true);
+                    write_table(buf, path, table, max, serialize)?;
+                }
+            }
+            _ => unreachable!(),
+        }
+        path.pop();
+    }
+
+    Ok(())
+}
+
+/// Serialize the table by `serialize`, writing arrays longer than `max`
+/// elements one element per line.
+///
+/// Only arrays which are values of tables are wrapped, since others are
+/// written inline. `serialize` must write arrays inline.
+pub fn to_string<F>(value: &Toml, max: usize, serialize: F) -> Result<String, toml::ser::Error>
+where
+    F: Fn(&Toml) -> Result<String, toml::ser::Error>,
+{
+    let table = value
+        .as_table()
+        .ok_or_else(|| toml::ser::Error::custom("TOML requires a table at the root"))?;
+
+    let mut buf = String::new();
+    write_table(&mut buf, &mut Vec::new(), table, max, &serialize)?;
+
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap() {
+        let value: Toml = toml::from_str(concat!(
+            "short = [1, 2]\n",
+            "long = [\"a\", \"b\", \"c\"]\n",
+            "[[t]]\n",
+            "nested = [[1], [2], [3]]\n"
+        ))
+        .unwrap();
+
+        assert_eq!(
+            to_string(&value, 2, toml::to_string).unwrap(),
+            concat!(
+                "short = [1, 2]\n",
+                "long = [\n",
+                "    \"a\",\n",
+                "    \"b\",\n",
+                "    \"c\",\n",
+                "]\n",
+                "\n",
+                "[[t]]\n",
+                "nested = [\n",
+                "    [1],\n",
+                "    [2],\n",
+                "    [3],\n",
+                "]\n"
+            )
+        );
+        assert_eq!(
+            toml::from_str::<Toml>(&to_string(&value, 0, toml::to_string).unwrap()).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn nul() {
+        let value: Toml = toml::from_str(concat!(
+            "a = [\"\\u0000dsconv-array-0\\u0000\", \"b\"]\n",
+            "c = \"\\u0000dsconv-array-0\\u0000\"\n"
+        ))
+        .unwrap();
+
+        assert_eq!(
+            toml::from_str::<Toml>(&to_string(&value, 1, toml::to_string).unwrap()).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn quoted_keys() {
+        let value: Toml =
+            toml::from_str(concat!("\"a b\" = [1, 2]\n", "[\"c.d\".e]\n", "f = 1\n")).unwrap();
+
+        assert_eq!(
+            to_string(&value, 1, toml::to_string).unwrap(),
+            concat!(
+                "\"a b\" = [\n",
+                "    1,\n",
+                "    2,\n",
+                "]\n",
+                "\n",
+                "[\"c.d\".e]\n",
+                "f = 1\n"
+            )
+        );
+    }
+}
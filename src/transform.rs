@@ -0,0 +1,2024 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::mem;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use chrono::DateTime;
+use indexmap::IndexMap;
+use num_bigint::BigInt;
+use regex::Regex;
+
+use crate::convert::push_pointer_token;
+use crate::value::{Depth, Format, KeyCase, KeyConflict, TimeZone, Transform, Value};
+use crate::Options;
+
+/// Convert a scalar to a string.
+///
+/// Returns `None` if the value is an array or a map.
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some("null".to_string()),
+        Value::Bool(bool) => Some(bool.to_string()),
+        Value::Integer(int) => Some(int.to_string()),
+        Value::Float(float) => Some(float.to_string()),
+        Value::String(str) => Some(str.clone()),
+        Value::Array(_) | Value::Map(_) => None,
+    }
+}
+
+/// Reshape an array of maps into a map keyed by the value of `key` of each
+/// element.
+///
+/// If `drop_key` is `true`, `key` is removed from each element.
+pub fn group_by(
+    value: Value,
+    key: &str,
+    drop_key: bool,
+    on_conflict: KeyConflict,
+) -> Result<Value> {
+    let arr = match value {
+        Value::Array(arr) => arr,
+        _ => bail!("The root is not an array"),
+    };
+
+    let mut map = IndexMap::with_capacity(arr.len());
+    for (i, elem) in arr.into_iter().enumerate() {
+        let mut obj = match elem {
+            Value::Map(obj) => obj,
+            _ => bail!("The element at index {} is not a map", i),
+        };
+
+        let group = if drop_key {
+            obj.shift_remove(key)
+        } else {
+            obj.get(key).cloned()
+        };
+        let group = group.with_context(|| {
+            format!("The element at index {} does not have the key: {}", i, key)
+        })?;
+        let group = scalar_to_string(&group)
+            .with_context(|| format!("The key of the element at index {} is not a scalar", i))?;
+
+        on_conflict
+            .insert(&mut map, group, Value::Map(obj))
+            .map_err(|k| anyhow!("The key is duplicated: {}", k))?;
+    }
+
+    Ok(Value::Map(map))
+}
+
+/// Expand a map of maps into an array of maps, storing the key of each entry
+/// as `key` of the element.
+pub fn ungroup(value: Value, key: &str) -> Result<Value> {
+    let map = match value {
+        Value::Map(map) => map,
+        _ => bail!("The root is not a map"),
+    };
+
+    let mut arr = Vec::with_capacity(map.len());
+    for (group, elem) in map {
+        let obj = match elem {
+            Value::Map(obj) => obj,
+            _ => bail!("The value of {} is not a map", group),
+        };
+        if obj.contains_key(key) {
+            bail!("The value of {} already has the key: {}", group, key);
+        }
+
+        let mut elem = IndexMap::with_capacity(obj.len() + 1);
+        elem.insert(key.to_string(), Value::String(group));
+        elem.extend(obj);
+        arr.push(Value::Map(elem));
+    }
+
+    Ok(Value::Array(arr))
+}
+
+/// Rename keys of maps by `rename`, and merge the keys which become the same.
+///
+/// `rename` returns `None` if the key is not renamed. If `recursive` is
+/// `false`, only the keys of the root map are renamed.
+///
+/// Returns the number of renamed keys.
+fn rename_keys<F>(
+    value: &mut Value,
+    rename: &F,
+    recursive: bool,
+    on_conflict: KeyConflict,
+) -> Result<usize>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    match value {
+        Value::Array(arr) if recursive => arr
+            .iter_mut()
+            .map(|v| rename_keys(v, rename, recursive, on_conflict))
+            .sum(),
+        Value::Map(map) => {
+            let mut count = 0;
+            let mut renamed = IndexMap::with_capacity(map.len());
+            for (key, mut elem) in mem::take(map) {
+                if recursive {
+                    count += rename_keys(&mut elem, rename, recursive, on_conflict)?;
+                }
+
+                let key = match rename(&key) {
+                    Some(new_key) => {
+                        count += 1;
+
+                        new_key
+                    }
+                    None => key,
+                };
+                on_conflict
+                    .insert(&mut renamed, key, elem)
+                    .map_err(|key| anyhow!("The key is duplicated after renaming: {}", key))?;
+            }
+            *map = renamed;
+
+            Ok(count)
+        }
+        _ => Ok(0),
+    }
+}
+
+/// Convert keys of maps to `case`, and merge the keys which become the same.
+///
+/// The merged keys are handled by `on_conflict`.
+///
+/// Returns the number of converted keys.
+pub fn fold_keys(value: &mut Value, case: KeyCase, on_conflict: KeyConflict) -> Result<usize> {
+    let rename = |key: &str| Some(case.convert(key)).filter(|k| k != key);
+
+    rename_keys(value, &rename, true, on_conflict)
+}
+
+/// Remove `prefix` from keys of maps.
+///
+/// Keys which do not start with `prefix` are left as they are. If `recursive`
+/// is `false`, only the keys of the root map are changed. The keys which become
+/// the same are handled by `on_conflict`.
+///
+/// Returns the number of changed keys.
+pub fn strip_prefix(
+    value: &mut Value,
+    prefix: &str,
+    recursive: bool,
+    on_conflict: KeyConflict,
+) -> Result<usize> {
+    let rename = |key: &str| key.strip_prefix(prefix).map(str::to_string);
+
+    rename_keys(value, &rename, recursive, on_conflict)
+}
+
+/// Move the keys of maps to the front in the order of `keys`.
+///
+/// Keys which are not in `keys` follow in the original order.
+///
+/// Returns the number of maps which have any of `keys`.
+pub fn order_keys<T: AsRef<str>>(value: &mut Value, keys: &[T]) -> usize {
+    match value {
+        Value::Array(arr) => arr.iter_mut().map(|v| order_keys(v, keys)).sum(),
+        Value::Map(map) => {
+            let mut count: usize = map.values_mut().map(|v| order_keys(v, keys)).sum();
+
+            let mut ordered = IndexMap::with_capacity(map.len());
+            for key in keys {
+                if let Some((key, value)) = map.shift_remove_entry(key.as_ref()) {
+                    ordered.insert(key, value);
+                }
+            }
+            if !ordered.is_empty() {
+                count += 1;
+            }
+            ordered.extend(mem::take(map));
+            *map = ordered;
+
+            count
+        }
+        _ => 0,
+    }
+}
+
+/// Sort the keys of all maps lexically.
+///
+/// Returns the number of maps whose order of keys changed.
+pub fn sort_keys(value: &mut Value) -> usize {
+    match value {
+        Value::Array(arr) => arr.iter_mut().map(sort_keys).sum(),
+        Value::Map(map) => {
+            let mut count: usize = map.values_mut().map(sort_keys).sum();
+
+            if !map.keys().zip(map.keys().skip(1)).all(|(a, b)| a <= b) {
+                map.sort_keys();
+                count += 1;
+            }
+
+            count
+        }
+        _ => 0,
+    }
+}
+
+/// Find the keys which do not exist in the map at the JSON Pointer.
+pub fn missing_keys<T: AsRef<str>>(
+    value: &Value,
+    pointer: &str,
+    keys: &[T],
+) -> Result<Vec<String>> {
+    match value.pointer(pointer) {
+        Some(Value::Map(map)) => Ok(keys
+            .iter()
+            .map(AsRef::as_ref)
+            .filter(|k| !map.contains_key(*k))
+            .map(str::to_string)
+            .collect()),
+        Some(_) => bail!("The value is not a map: {}", pointer),
+        None => bail!("The value does not exist: {}", pointer),
+    }
+}
+
+/// Replace substrings of strings by `replace`.
+///
+/// `replace` returns the replaced string and the number of replacements, or
+/// `None` if nothing is replaced. If `keys` is `true`, keys of maps are also
+/// replaced, and the keys which become the same are handled by `on_conflict`.
+///
+/// Returns the number of replacements.
+pub fn replace_strings<F>(
+    value: &mut Value,
+    replace: &F,
+    keys: bool,
+    on_conflict: KeyConflict,
+) -> Result<usize>
+where
+    F: Fn(&str) -> Option<(String, usize)>,
+{
+    let mut count = replace_values(value, replace);
+    if keys {
+        let replaced = Cell::new(0);
+        let rename = |key: &str| {
+            replace(key).map(|(key, n)| {
+                replaced.set(replaced.get() + n);
+
+                key
+            })
+        };
+        rename_keys(value, &rename, true, on_conflict)?;
+        count += replaced.get();
+    }
+
+    Ok(count)
+}
+
+fn replace_values<F>(value: &mut Value, replace: &F) -> usize
+where
+    F: Fn(&str) -> Option<(String, usize)>,
+{
+    match value {
+        Value::String(str) => match replace(str) {
+            Some((replaced, count)) => {
+                *str = replaced;
+
+                count
+            }
+            None => 0,
+        },
+        Value::Array(arr) => arr.iter_mut().map(|v| replace_values(v, replace)).sum(),
+        Value::Map(map) => map.values_mut().map(|v| replace_values(v, replace)).sum(),
+        _ => 0,
+    }
+}
+
+/// Remove leading and trailing whitespace from strings.
+///
+/// If `collapse` is `true`, each run of whitespace inside strings is also
+/// replaced with a single space. If `keys` is `true`, keys of maps are also
+/// trimmed, and the keys which become the same are handled by `on_conflict`.
+///
+/// Returns the number of changed strings.
+pub fn trim_strings(
+    value: &mut Value,
+    collapse: bool,
+    keys: bool,
+    on_conflict: KeyConflict,
+) -> Result<usize> {
+    let trim = |str: &str| {
+        let trimmed = if collapse {
+            str.split_whitespace().collect::<Vec<_>>().join(" ")
+        } else {
+            str.trim().to_string()
+        };
+
+        Some((trimmed, 1)).filter(|(trimmed, _)| trimmed != str)
+    };
+
+    replace_strings(value, &trim, keys, on_conflict)
+}
+
+/// Truncate strings longer than `max` Unicode scalar values, appending an
+/// ellipsis.
+///
+/// Returns the number of truncated strings.
+pub fn truncate_strings(value: &mut Value, max: usize) -> usize {
+    match value {
+        Value::String(str) => match str.char_indices().nth(max) {
+            Some((end, _)) => {
+                str.truncate(end);
+                str.push('\u{2026}');
+
+                1
+            }
+            None => 0,
+        },
+        Value::Array(arr) => arr.iter_mut().map(|v| truncate_strings(v, max)).sum(),
+        Value::Map(map) => map.values_mut().map(|v| truncate_strings(v, max)).sum(),
+        _ => 0,
+    }
+}
+
+/// The largest integer which JavaScript represents exactly (`2^53 - 1`).
+pub const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Convert integers which JavaScript cannot represent exactly to strings.
+///
+/// Integers whose absolute value is greater than `MAX_SAFE_INTEGER` are
+/// converted.
+///
+/// Returns the number of converted integers.
+pub fn stringify_unsafe_integers(value: &mut Value) -> usize {
+    match value {
+        Value::Integer(int) => {
+            let is_safe = match int.as_i64() {
+                Some(sint) => sint.unsigned_abs() <= MAX_SAFE_INTEGER,
+                None => false,
+            };
+            if is_safe {
+                return 0;
+            }
+            *value = Value::String(int.to_string());
+
+            1
+        }
+        Value::Array(arr) => arr.iter_mut().map(stringify_unsafe_integers).sum(),
+        Value::Map(map) => map.values_mut().map(stringify_unsafe_integers).sum(),
+        _ => 0,
+    }
+}
+
+/// Convert booleans, integers and floats to strings.
+///
+/// Returns the number of converted values.
+pub fn stringify_scalars(value: &mut Value) -> usize {
+    match value {
+        Value::Bool(_) | Value::Integer(_) | Value::Float(_) => {
+            *value = Value::String(scalar_to_string(value).expect("The value is not a scalar"));
+
+            1
+        }
+        Value::Array(arr) => arr.iter_mut().map(stringify_scalars).sum(),
+        Value::Map(map) => map.values_mut().map(stringify_scalars).sum(),
+        Value::Null | Value::String(_) => 0,
+    }
+}
+
+/// Convert booleans, integers and floats under the JSON Pointer to strings.
+///
+/// Returns the number of converted values.
+pub fn stringify_under(value: &mut Value, pointer: &str) -> Result<usize> {
+    let target = value
+        .pointer_mut(pointer)
+        .with_context(|| format!("The value does not exist: {}", pointer))?;
+
+    Ok(stringify_scalars(target))
+}
+
+/// Describe the location of the JSON Pointer in an error message.
+fn location(path: &str) -> &str {
+    if path.is_empty() {
+        "(root)"
+    } else {
+        path
+    }
+}
+
+/// Get the element of the array at `token`, which is the index of it.
+fn element<'a>(arr: &'a mut [Value], token: &str, path: &str) -> Result<&'a mut Value> {
+    let len = arr.len();
+
+    token
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| arr.get_mut(i))
+        .with_context(|| {
+            format!(
+                "The index {:?} is out of range of the array of length {} at {}",
+                token,
+                len,
+                location(path)
+            )
+        })
+}
+
+/// Set `new` at the JSON Pointer, creating intermediate maps as needed.
+///
+/// Existing elements of arrays can be replaced, but new elements cannot be
+/// created. It is an error if a scalar is on the path.
+pub fn set_pointer(value: &mut Value, pointer: &str, new: Value) -> Result<()> {
+    if pointer.is_empty() {
+        *value = new;
+
+        return Ok(());
+    }
+
+    let tokens = pointer
+        .strip_prefix('/')
+        .with_context(|| format!("The JSON Pointer does not start with `/`: {}", pointer))?
+        .split('/')
+        .map(|t| t.replace("~1", "/").replace("~0", "~"))
+        .collect::<Vec<_>>();
+    let (last, parents) = tokens.split_last().unwrap();
+
+    let mut target = value;
+    let mut path = String::new();
+    for token in parents {
+        target = match target {
+            Value::Map(map) => map
+                .entry(token.clone())
+                .or_insert_with(|| Value::Map(IndexMap::new())),
+            Value::Array(arr) => element(arr, token, &path)?,
+            value => bail!(
+                "The value at {} is {}, which cannot have children",
+                location(&path),
+                value.type_name()
+            ),
+        };
+        push_pointer_token(&mut path, token);
+    }
+    match target {
+        Value::Map(map) => {
+            map.insert(last.clone(), new);
+        }
+        Value::Array(arr) => *element(arr, last, &path)? = new,
+        value => bail!(
+            "The value at {} is {}, which cannot have children",
+            location(&path),
+            value.type_name()
+        ),
+    }
+
+    Ok(())
+}
+
+/// Remove the value at the JSON Pointer from its parent map or array.
+///
+/// Subsequent elements of an array are shifted. Returns `false` if the value
+/// does not exist.
+pub fn delete_pointer(value: &mut Value, pointer: &str) -> Result<bool> {
+    let (parent, token) = match pointer.rfind('/') {
+        Some(pos) if pointer.starts_with('/') => (&pointer[..pos], &pointer[pos + 1..]),
+        _ if pointer.is_empty() => bail!("The root cannot be deleted"),
+        _ => bail!("The JSON Pointer does not start with `/`: {}", pointer),
+    };
+    let token = token.replace("~1", "/").replace("~0", "~");
+
+    let deleted = match value.pointer_mut(parent) {
+        Some(Value::Map(map)) => map.shift_remove(&token).is_some(),
+        Some(Value::Array(arr)) => match token.parse::<usize>() {
+            Ok(i) if i < arr.len() => {
+                arr.remove(i);
+
+                true
+            }
+            _ => false,
+        },
+        _ => false,
+    };
+
+    Ok(deleted)
+}
+
+/// Remove elements of arrays which are equal to an earlier element.
+///
+/// The first occurrences are kept in the original order.
+fn dedupe(arr: &mut Vec<Value>) -> usize {
+    let len = arr.len();
+    let is_first: Vec<_> = {
+        let mut seen = HashSet::with_capacity(len);
+        arr.iter().map(|elem| seen.insert(elem)).collect()
+    };
+    let mut is_first = is_first.into_iter();
+    arr.retain(|_| is_first.next() == Some(true));
+
+    len - arr.len()
+}
+
+/// Remove duplicate elements from all arrays.
+///
+/// Nested arrays are deduplicated before their parents.
+///
+/// Returns the number of removed elements.
+pub fn dedupe_arrays(value: &mut Value) -> usize {
+    match value {
+        Value::Array(arr) => {
+            let count: usize = arr.iter_mut().map(dedupe_arrays).sum();
+
+            count + dedupe(arr)
+        }
+        Value::Map(map) => map.values_mut().map(dedupe_arrays).sum(),
+        _ => 0,
+    }
+}
+
+/// Remove duplicate elements from the array at the JSON Pointer.
+///
+/// Returns the number of removed elements.
+pub fn dedupe_array_at(value: &mut Value, pointer: &str) -> Result<usize> {
+    match value.pointer_mut(pointer) {
+        Some(Value::Array(arr)) => Ok(dedupe(arr)),
+        Some(_) => bail!("The value is not an array: {}", pointer),
+        None => bail!("The value does not exist: {}", pointer),
+    }
+}
+
+/// Sort the elements of all arrays in the order of `Value`.
+///
+/// Nested arrays are sorted before their parents, so the result does not
+/// depend on the original order of any array.
+///
+/// Returns the number of arrays whose order is changed.
+pub fn sort_arrays(value: &mut Value) -> usize {
+    match value {
+        Value::Array(arr) => {
+            let count: usize = arr.iter_mut().map(sort_arrays).sum();
+            let is_sorted = arr.windows(2).all(|w| w[0] <= w[1]);
+            if is_sorted {
+                return count;
+            }
+            arr.sort();
+
+            count + 1
+        }
+        Value::Map(map) => map.values_mut().map(sort_arrays).sum(),
+        _ => 0,
+    }
+}
+
+/// Sort the elements of the array at the JSON Pointer and all arrays in it in
+/// the order of `Value`.
+///
+/// Returns the number of arrays whose order is changed.
+pub fn sort_arrays_at(value: &mut Value, pointer: &str) -> Result<usize> {
+    match value.pointer_mut(pointer) {
+        Some(arr @ Value::Array(_)) => Ok(sort_arrays(arr)),
+        Some(_) => bail!("The value is not an array: {}", pointer),
+        None => bail!("The value does not exist: {}", pointer),
+    }
+}
+
+/// Merge `overlay` into `base` recursively.
+///
+/// Maps are merged key by key. Otherwise, including arrays, the value of
+/// `overlay` replaces the value of `base`. New keys are appended in the order
+/// of `overlay`.
+///
+/// Returns the number of values of `base` which are replaced.
+pub fn merge(base: &mut Value, overlay: Value) -> usize {
+    match (base, overlay) {
+        (Value::Map(base), Value::Map(overlay)) => overlay
+            .into_iter()
+            .map(|(k, v)| match base.get_mut(&k) {
+                Some(existing) => merge(existing, v),
+                None => {
+                    base.insert(k, v);
+
+                    0
+                }
+            })
+            .sum(),
+        (base, overlay) => {
+            *base = overlay;
+
+            1
+        }
+    }
+}
+
+/// Replace nulls with the string.
+///
+/// Returns the number of replaced values.
+pub fn replace_nulls(value: &mut Value, str: &str) -> usize {
+    match value {
+        Value::Null => {
+            *value = Value::String(str.to_string());
+
+            1
+        }
+        Value::Array(arr) => arr.iter_mut().map(|v| replace_nulls(v, str)).sum(),
+        Value::Map(map) => map.values_mut().map(|v| replace_nulls(v, str)).sum(),
+        _ => 0,
+    }
+}
+
+/// The default tokens which `coerce_bools` converts to `true`.
+pub const TRUE_TOKENS: [&str; 4] = ["true", "yes", "on", "1"];
+
+/// The default tokens which `coerce_bools` converts to `false`.
+pub const FALSE_TOKENS: [&str; 4] = ["false", "no", "off", "0"];
+
+/// Convert strings which match the tokens to booleans.
+///
+/// The tokens are compared case-insensitively with the whole string. Keys of
+/// maps are left as they are.
+///
+/// Returns the number of converted values.
+pub fn coerce_bools<T: AsRef<str>>(
+    value: &mut Value,
+    true_tokens: &[T],
+    false_tokens: &[T],
+) -> usize {
+    match value {
+        Value::String(str) => {
+            let matches =
+                |tokens: &[T]| tokens.iter().any(|t| t.as_ref().eq_ignore_ascii_case(str));
+            if matches(true_tokens) {
+                *value = Value::Bool(true);
+
+                1
+            } else if matches(false_tokens) {
+                *value = Value::Bool(false);
+
+                1
+            } else {
+                0
+            }
+        }
+        Value::Array(arr) => arr
+            .iter_mut()
+            .map(|v| coerce_bools(v, true_tokens, false_tokens))
+            .sum(),
+        Value::Map(map) => map
+            .values_mut()
+            .map(|v| coerce_bools(v, true_tokens, false_tokens))
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// Parse a string with a radix prefix as an integer.
+fn parse_radix_integer(str: &str) -> Option<Value> {
+    let (negative, str) = match str.strip_prefix('-') {
+        Some(str) => (true, str),
+        None => (false, str),
+    };
+    let radix = match str.get(..2)?.to_ascii_lowercase().as_str() {
+        "0x" => 16,
+        "0o" => 8,
+        "0b" => 2,
+        _ => return None,
+    };
+    let digits = &str[2..];
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+
+    let int = BigInt::parse_bytes(digits.as_bytes(), radix)?;
+    Some(Value::Integer(if negative { -int } else { int }.into()))
+}
+
+/// Convert strings of hexadecimal, octal and binary integers to integers.
+///
+/// Only strings with the prefix `0x`, `0o` or `0b` (optionally preceded by
+/// `-`) are converted. Integers of any size are converted.
+///
+/// Returns the number of converted values.
+pub fn parse_radix(value: &mut Value) -> usize {
+    match value {
+        Value::String(str) => match parse_radix_integer(str) {
+            Some(int) => {
+                *value = int;
+
+                1
+            }
+            None => 0,
+        },
+        Value::Array(arr) => arr.iter_mut().map(parse_radix).sum(),
+        Value::Map(map) => map.values_mut().map(parse_radix).sum(),
+        _ => 0,
+    }
+}
+
+/// Convert strings of RFC 3339 datetimes with an offset into `tz`.
+///
+/// Local datetimes, dates and times, which do not have an offset, are left as
+/// they are.
+///
+/// Returns the number of converted strings.
+pub fn convert_datetimes(value: &mut Value, tz: TimeZone) -> usize {
+    match value {
+        Value::String(str) => match DateTime::parse_from_rfc3339(str) {
+            Ok(datetime) => {
+                let converted = tz.convert(datetime);
+                if converted == *str {
+                    return 0;
+                }
+                *str = converted;
+
+                1
+            }
+            Err(_) => 0,
+        },
+        Value::Array(arr) => arr.iter_mut().map(|v| convert_datetimes(v, tz)).sum(),
+        Value::Map(map) => map.values_mut().map(|v| convert_datetimes(v, tz)).sum(),
+        _ => 0,
+    }
+}
+
+/// Concatenate the elements of nested arrays into the outer array.
+///
+/// Returns the number of flattened arrays.
+fn flatten_into(arr: Vec<Value>, depth: Depth, flat: &mut Vec<Value>) -> usize {
+    let mut count = 0;
+    for value in arr {
+        count += match (value, depth) {
+            (Value::Array(inner), Depth::Full) => 1 + flatten_into(inner, depth, flat),
+            (Value::Array(inner), Depth::Limited(n)) if n > 0 => {
+                1 + flatten_into(inner, Depth::Limited(n - 1), flat)
+            }
+            (value, _) => {
+                flat.push(value);
+
+                0
+            }
+        };
+    }
+
+    count
+}
+
+/// Flatten nested arrays up to `depth` levels.
+///
+/// This applies to the root array and the arrays in maps. Maps and scalars are
+/// left as they are.
+///
+/// Returns the number of flattened arrays.
+pub fn flatten_arrays(value: &mut Value, depth: Depth) -> usize {
+    match value {
+        Value::Array(arr) => {
+            let mut flat = Vec::with_capacity(arr.len());
+            let mut count = flatten_into(mem::take(arr), depth, &mut flat);
+            for value in &mut flat {
+                if let Value::Map(_) = value {
+                    count += flatten_arrays(value, depth);
+                }
+            }
+            *arr = flat;
+
+            count
+        }
+        Value::Map(map) => map.values_mut().map(|v| flatten_arrays(v, depth)).sum(),
+        _ => 0,
+    }
+}
+
+/// Convert arrays into maps keyed by the indices of the elements.
+///
+/// Nested arrays are converted before their parents.
+///
+/// Returns the number of converted arrays.
+pub fn arrays_to_maps(value: &mut Value) -> usize {
+    match value {
+        Value::Array(arr) => {
+            let count = arr.iter_mut().map(arrays_to_maps).sum::<usize>();
+            let map = mem::take(arr)
+                .into_iter()
+                .enumerate()
+                .map(|(i, v)| (i.to_string(), v))
+                .collect();
+            *value = Value::Map(map);
+
+            count + 1
+        }
+        Value::Map(map) => map.values_mut().map(arrays_to_maps).sum(),
+        _ => 0,
+    }
+}
+
+/// Convert maps whose keys are the indices from `0` into arrays.
+///
+/// The keys may be in any order, but must be exactly `0` to `n - 1` without
+/// leading zeros. Empty maps are left as they are. Nested maps are converted
+/// before their parents.
+///
+/// Returns the number of converted maps.
+pub fn maps_to_arrays(value: &mut Value) -> usize {
+    match value {
+        Value::Array(arr) => arr.iter_mut().map(maps_to_arrays).sum(),
+        Value::Map(map) => {
+            let count = map.values_mut().map(maps_to_arrays).sum::<usize>();
+            let is_indexed =
+                !map.is_empty() && (0..map.len()).all(|i| map.contains_key(i.to_string().as_str()));
+            if !is_indexed {
+                return count;
+            }
+            let arr = (0..map.len())
+                .filter_map(|i| map.remove(i.to_string().as_str()))
+                .collect();
+            *value = Value::Array(arr);
+
+            count + 1
+        }
+        _ => 0,
+    }
+}
+
+/// Apply the transforms enabled by `options` to the value, in the order of
+/// `transform_order` followed by the default order.
+///
+/// `audit` is called with a summary of each transform which is applied.
+pub fn apply(mut value: Value, options: &Options, audit: &dyn Fn(String)) -> Result<Value> {
+    let order = match Transform::order(&options.transform_order) {
+        Ok(order) => order,
+        Err(step) => bail!(
+            "{} is specified more than once in the transform order",
+            step
+        ),
+    };
+    for step in order {
+        value = apply_step(step, value, options, audit)?;
+    }
+
+    Ok(value)
+}
+
+/// Apply the transforms enabled by `options` which depend on the output
+/// format: merging into `merge_into`, sorting keys, wrapping a scalar at the
+/// root and converting unsafe integers of JavaScript to strings, in this
+/// order.
+///
+/// `audit` is called with a summary of each transform which is applied.
+pub fn apply_output(
+    mut value: Value,
+    format: Format,
+    options: &Options,
+    audit: &dyn Fn(String),
+) -> Value {
+    if let Some(ref base) = options.merge_into {
+        let mut base = base.clone();
+        let count = merge(&mut base, value);
+        value = base;
+        audit(format!("merge-into: replaced {} values", count));
+    }
+    if options
+        .sort_keys
+        .map_or(false, |scope| scope.includes(format))
+    {
+        let count = sort_keys(&mut value);
+        audit(format!("sort-keys: sorted {} maps", count));
+    }
+    if let (Some(key), Some(root), true) = (
+        options.wrap.as_deref(),
+        format.required_root(),
+        value.is_scalar(),
+    ) {
+        value = root.wrap(key, value);
+        audit(format!("wrap: wrapped the root in {}", root));
+    }
+    if options.js_safe_integers
+        && matches!(
+            format,
+            Format::Json | Format::Jsonc | Format::JsonSeq | Format::Ndjson
+        )
+    {
+        let count = stringify_unsafe_integers(&mut value);
+        audit(format!("js-safe-integers: converted {} integers", count));
+    }
+
+    value
+}
+
+/// Apply the transform of `step` to the value if it is enabled by `options`.
+fn apply_step(
+    step: Transform,
+    mut value: Value,
+    options: &Options,
+    audit: &dyn Fn(String),
+) -> Result<Value> {
+    match step {
+        Transform::GroupBy => {
+            if let Some(ref key) = options.group_by {
+                value = group_by(value, key, options.drop_group_key, options.on_key_conflict)
+                    .context("Failed to group the array")?;
+                if let Value::Map(ref map) = value {
+                    audit(format!("group-by: grouped into {} entries", map.len()));
+                }
+            }
+        }
+        Transform::Ungroup => {
+            if let Some(ref key) = options.ungroup {
+                value = ungroup(value, key).context("Failed to ungroup the map")?;
+                if let Value::Array(ref arr) = value {
+                    audit(format!("ungroup: expanded into {} elements", arr.len()));
+                }
+            }
+        }
+        Transform::FoldKeys => {
+            if let Some(case) = options.key_case {
+                let count = fold_keys(&mut value, case, options.on_key_conflict)
+                    .context("Failed to fold the keys")?;
+                audit(format!("fold-keys: converted {} keys", count));
+            }
+        }
+        Transform::StripPrefix => {
+            if let Some(ref prefix) = options.strip_prefix {
+                let count = strip_prefix(
+                    &mut value,
+                    prefix,
+                    options.strip_prefix_recursive,
+                    options.on_key_conflict,
+                )
+                .context("Failed to strip the prefix from the keys")?;
+                audit(format!("strip-prefix: changed {} keys", count));
+            }
+        }
+        Transform::KeyOrder => {
+            if !options.key_order.is_empty() {
+                let count = order_keys(&mut value, &options.key_order);
+                audit(format!("key-order: reordered {} maps", count));
+            }
+        }
+        Transform::DedupeArray => {
+            if options.dedupe_array {
+                let count = dedupe_arrays(&mut value);
+                audit(format!("dedupe-array: removed {} elements", count));
+            }
+            for pointer in &options.dedupe_array_at {
+                let count = dedupe_array_at(&mut value, pointer)
+                    .context("Failed to remove duplicate elements")?;
+                audit(format!(
+                    "dedupe-array-at: removed {} elements from {}",
+                    count, pointer
+                ));
+            }
+        }
+        Transform::SortArrays => {
+            if options.sort_arrays {
+                let count = sort_arrays(&mut value);
+                audit(format!("sort-arrays: reordered {} arrays", count));
+            }
+            for pointer in &options.sort_arrays_at {
+                let count =
+                    sort_arrays_at(&mut value, pointer).context("Failed to sort an array")?;
+                audit(format!(
+                    "sort-arrays-at: reordered {} arrays at {}",
+                    count, pointer
+                ));
+            }
+        }
+        Transform::TrimStrings => {
+            if options.trim_strings {
+                let count = trim_strings(
+                    &mut value,
+                    options.collapse_whitespace,
+                    options.trim_keys,
+                    options.on_key_conflict,
+                )
+                .context("Failed to trim strings")?;
+                audit(format!("trim-strings: trimmed {} strings", count));
+            }
+        }
+        Transform::Replace => {
+            for (from, to) in &options.replace {
+                ensure!(!from.is_empty(), "The string to replace is empty");
+                let replace = |str: &str| match str.matches(from.as_str()).count() {
+                    0 => None,
+                    count => Some((str.replace(from.as_str(), to), count)),
+                };
+                let count = replace_strings(
+                    &mut value,
+                    &replace,
+                    options.replace_keys,
+                    options.on_key_conflict,
+                )
+                .context("Failed to replace strings")?;
+                audit(format!(
+                    "replace: replaced {} occurrences of {:?}",
+                    count, from
+                ));
+            }
+        }
+        Transform::ReplaceRegex => {
+            for (pattern, to) in &options.replace_regex {
+                let regex = Regex::new(pattern)
+                    .with_context(|| format!("Invalid regular expression: {}", pattern))?;
+                let replace = |str: &str| match regex.find_iter(str).count() {
+                    0 => None,
+                    count => Some((regex.replace_all(str, to.as_str()).into_owned(), count)),
+                };
+                let count = replace_strings(
+                    &mut value,
+                    &replace,
+                    options.replace_keys,
+                    options.on_key_conflict,
+                )
+                .context("Failed to replace strings")?;
+                audit(format!(
+                    "replace-regex: replaced {} matches of {:?}",
+                    count, pattern
+                ));
+            }
+        }
+        Transform::MaxStringLength => {
+            if let Some(max) = options.max_string_length {
+                let count = truncate_strings(&mut value, max);
+                audit(format!("max-string-length: truncated {} strings", count));
+            }
+        }
+        Transform::StringifyUnder => {
+            for pointer in &options.stringify_under {
+                let count = stringify_under(&mut value, pointer)
+                    .context("Failed to convert values to strings")?;
+                audit(format!(
+                    "stringify-under: converted {} values under {}",
+                    count, pointer
+                ));
+            }
+        }
+        Transform::CoerceBools => {
+            if options.coerce_bools {
+                let true_tokens: Vec<_> = if options.true_tokens.is_empty() {
+                    TRUE_TOKENS.to_vec()
+                } else {
+                    options.true_tokens.iter().map(String::as_str).collect()
+                };
+                let false_tokens: Vec<_> = if options.false_tokens.is_empty() {
+                    FALSE_TOKENS.to_vec()
+                } else {
+                    options.false_tokens.iter().map(String::as_str).collect()
+                };
+                let count = coerce_bools(&mut value, &true_tokens, &false_tokens);
+                audit(format!("coerce-bools: converted {} strings", count));
+            }
+        }
+        Transform::ParseRadix => {
+            if options.parse_radix {
+                let count = parse_radix(&mut value);
+                audit(format!("parse-radix: converted {} strings", count));
+            }
+        }
+        Transform::DatetimeTz => {
+            if let Some(tz) = options.datetime_tz {
+                let count = convert_datetimes(&mut value, tz);
+                audit(format!("datetime-tz: converted {} datetimes", count));
+            }
+        }
+        Transform::NullAs => {
+            if let Some(ref str) = options.null_as {
+                let count = replace_nulls(&mut value, str);
+                audit(format!("null-as: replaced {} nulls", count));
+            }
+        }
+        Transform::FlattenArrays => {
+            if let Some(depth) = options.flatten_arrays {
+                let count = flatten_arrays(&mut value, depth);
+                audit(format!("flatten-arrays: flattened {} arrays", count));
+            }
+        }
+        Transform::ArraysAsMaps => {
+            if options.arrays_as_maps {
+                let count = arrays_to_maps(&mut value);
+                audit(format!("arrays-as-maps: converted {} arrays", count));
+            }
+        }
+        Transform::MapsAsArrays => {
+            if options.maps_as_arrays {
+                let count = maps_to_arrays(&mut value);
+                audit(format!("maps-as-arrays: converted {} maps", count));
+            }
+        }
+        Transform::Delete => {
+            let mut count = 0;
+            for pointer in &options.delete {
+                if delete_pointer(&mut value, pointer)? {
+                    count += 1;
+                } else {
+                    ensure!(
+                        options.delete_missing_ok,
+                        "The value does not exist: {}",
+                        pointer
+                    );
+                }
+            }
+            if !options.delete.is_empty() {
+                audit(format!("delete: deleted {} values", count));
+            }
+        }
+        Transform::Set => {
+            for (pointer, new) in &options.set {
+                set_pointer(&mut value, pointer, new.clone())
+                    .with_context(|| format!("Failed to set the value at {}", pointer))?;
+            }
+            if !options.set.is_empty() {
+                audit(format!("set: set {} values", options.set.len()));
+            }
+        }
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::value::SortScope;
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn group() {
+        let arr = Value::Array(vec![
+            map(vec![("id", Value::String("a".to_string()))]),
+            map(vec![("id", Value::Integer(1_u64.into()))]),
+        ]);
+
+        assert_eq!(
+            group_by(arr.clone(), "id", false, KeyConflict::Error).unwrap(),
+            map(vec![
+                ("a", map(vec![("id", Value::String("a".to_string()))])),
+                ("1", map(vec![("id", Value::Integer(1_u64.into()))])),
+            ])
+        );
+        assert_eq!(
+            group_by(arr, "id", true, KeyConflict::Error).unwrap(),
+            map(vec![("a", map(vec![])), ("1", map(vec![]))])
+        );
+    }
+
+    #[test]
+    fn group_duplicate() {
+        let arr = Value::Array(vec![
+            map(vec![
+                ("id", Value::String("a".to_string())),
+                ("n", Value::Integer(0_u64.into())),
+            ]),
+            map(vec![
+                ("id", Value::String("a".to_string())),
+                ("n", Value::Integer(1_u64.into())),
+            ]),
+        ]);
+
+        assert!(group_by(arr.clone(), "id", true, KeyConflict::Error).is_err());
+        assert_eq!(
+            group_by(arr.clone(), "id", true, KeyConflict::First).unwrap(),
+            map(vec![("a", map(vec![("n", Value::Integer(0_u64.into()))]))])
+        );
+        assert_eq!(
+            group_by(arr, "id", true, KeyConflict::Last).unwrap(),
+            map(vec![("a", map(vec![("n", Value::Integer(1_u64.into()))]))])
+        );
+    }
+
+    #[test]
+    fn group_invalid() {
+        assert!(group_by(Value::Null, "id", false, KeyConflict::Error).is_err());
+        assert!(group_by(
+            Value::Array(vec![Value::Null]),
+            "id",
+            false,
+            KeyConflict::Error
+        )
+        .is_err());
+        assert!(group_by(
+            Value::Array(vec![map(vec![])]),
+            "id",
+            false,
+            KeyConflict::Error
+        )
+        .is_err());
+        assert!(group_by(
+            Value::Array(vec![map(vec![("id", Value::Array(vec![]))])]),
+            "id",
+            false,
+            KeyConflict::Error
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn ungroup_map() {
+        let grouped = map(vec![
+            ("a", map(vec![("n", Value::Integer(0_u64.into()))])),
+            ("b", map(vec![])),
+        ]);
+
+        assert_eq!(
+            ungroup(grouped.clone(), "id").unwrap(),
+            Value::Array(vec![
+                map(vec![
+                    ("id", Value::String("a".to_string())),
+                    ("n", Value::Integer(0_u64.into())),
+                ]),
+                map(vec![("id", Value::String("b".to_string()))]),
+            ])
+        );
+        assert_eq!(
+            group_by(
+                ungroup(grouped.clone(), "id").unwrap(),
+                "id",
+                true,
+                KeyConflict::Error
+            )
+            .unwrap(),
+            grouped
+        );
+    }
+
+    #[test]
+    fn ungroup_invalid() {
+        assert!(ungroup(Value::Array(vec![]), "id").is_err());
+        assert!(ungroup(map(vec![("a", Value::Null)]), "id").is_err());
+        assert!(ungroup(map(vec![("a", map(vec![("id", Value::Null)]))]), "id").is_err());
+    }
+
+    #[test]
+    fn replace() {
+        let replace = |str: &str| match str.matches('a').count() {
+            0 => None,
+            count => Some((str.replace('a', "x"), count)),
+        };
+
+        let mut value = map(vec![
+            ("a", Value::String("banana".to_string())),
+            ("b", Value::Array(vec![Value::String("abc".to_string())])),
+        ]);
+        assert_eq!(
+            replace_strings(&mut value, &replace, false, KeyConflict::Error).unwrap(),
+            4
+        );
+        assert_eq!(
+            value,
+            map(vec![
+                ("a", Value::String("bxnxnx".to_string())),
+                ("b", Value::Array(vec![Value::String("xbc".to_string())])),
+            ])
+        );
+
+        assert_eq!(
+            replace_strings(&mut value, &replace, true, KeyConflict::Error).unwrap(),
+            1
+        );
+        assert_eq!(
+            value,
+            map(vec![
+                ("x", Value::String("bxnxnx".to_string())),
+                ("b", Value::Array(vec![Value::String("xbc".to_string())])),
+            ])
+        );
+
+        let mut value = map(vec![("a", Value::Null), ("x", Value::Null)]);
+        assert!(replace_strings(&mut value, &replace, true, KeyConflict::Error).is_err());
+    }
+
+    #[test]
+    fn dedupe() {
+        let tags = || {
+            Value::Array(vec![
+                Value::String("a".to_string()),
+                Value::String("b".to_string()),
+                Value::String("a".to_string()),
+            ])
+        };
+        let mut value = map(vec![
+            ("tags", tags()),
+            ("nested", Value::Array(vec![tags(), tags(), Value::Null])),
+        ]);
+
+        assert_eq!(dedupe_array_at(&mut value, "/nested").unwrap(), 1);
+        assert_eq!(
+            value,
+            map(vec![
+                ("tags", tags()),
+                ("nested", Value::Array(vec![tags(), Value::Null])),
+            ])
+        );
+        assert!(dedupe_array_at(&mut value, "/nested/1").is_err());
+        assert!(dedupe_array_at(&mut value, "/missing").is_err());
+
+        assert_eq!(dedupe_arrays(&mut value), 2);
+        let unique = Value::Array(vec![
+            Value::String("a".to_string()),
+            Value::String("b".to_string()),
+        ]);
+        assert_eq!(
+            value,
+            map(vec![
+                ("tags", unique.clone()),
+                ("nested", Value::Array(vec![unique, Value::Null])),
+            ])
+        );
+    }
+
+    #[test]
+    fn sort_array_elements() {
+        let mut value = map(vec![
+            (
+                "a",
+                Value::Array(vec![
+                    Value::String("b".to_string()),
+                    Value::Integer(2_u64.into()),
+                    Value::Float(1.5),
+                    Value::Null,
+                ]),
+            ),
+            (
+                "b",
+                Value::Array(vec![
+                    Value::Array(vec![Value::Bool(true), Value::Bool(false)]),
+                    Value::Array(vec![Value::Bool(false)]),
+                ]),
+            ),
+        ]);
+
+        assert_eq!(sort_arrays_at(&mut value, "/b").unwrap(), 2);
+        assert_eq!(
+            value.pointer("/b").unwrap(),
+            &Value::Array(vec![
+                Value::Array(vec![Value::Bool(false)]),
+                Value::Array(vec![Value::Bool(false), Value::Bool(true)]),
+            ])
+        );
+        assert!(sort_arrays_at(&mut value, "/b/0/0").is_err());
+        assert!(sort_arrays_at(&mut value, "/missing").is_err());
+
+        assert_eq!(sort_arrays(&mut value), 1);
+        assert_eq!(
+            value.pointer("/a").unwrap(),
+            &Value::Array(vec![
+                Value::Null,
+                Value::Float(1.5),
+                Value::Integer(2_u64.into()),
+                Value::String("b".to_string()),
+            ])
+        );
+        assert_eq!(sort_arrays(&mut value), 0);
+    }
+
+    #[test]
+    fn trim() {
+        let mut value = map(vec![
+            (" a ", Value::String(" x  y\t".to_string())),
+            ("b", Value::Array(vec![Value::String("z".to_string())])),
+        ]);
+
+        assert_eq!(
+            trim_strings(&mut value.clone(), false, false, KeyConflict::Error).unwrap(),
+            1
+        );
+        assert_eq!(
+            trim_strings(&mut value, true, true, KeyConflict::Error).unwrap(),
+            2
+        );
+        assert_eq!(
+            value,
+            map(vec![
+                ("a", Value::String("x y".to_string())),
+                ("b", Value::Array(vec![Value::String("z".to_string())])),
+            ])
+        );
+
+        let mut value = map(vec![("a", Value::Null), ("a ", Value::Null)]);
+        assert!(trim_strings(&mut value, false, true, KeyConflict::Error).is_err());
+    }
+
+    #[test]
+    fn truncate() {
+        let mut value = map(vec![
+            ("a", Value::String("\u{3042}\u{3044}\u{3046}".to_string())),
+            ("b", Value::Array(vec![Value::String("ab".to_string())])),
+        ]);
+
+        assert_eq!(truncate_strings(&mut value, 2), 1);
+        assert_eq!(
+            value,
+            map(vec![
+                ("a", Value::String("\u{3042}\u{3044}\u{2026}".to_string())),
+                ("b", Value::Array(vec![Value::String("ab".to_string())])),
+            ])
+        );
+
+        let mut value = Value::String("abc".to_string());
+        assert_eq!(truncate_strings(&mut value, 0), 1);
+        assert_eq!(value, Value::String("\u{2026}".to_string()));
+    }
+
+    #[test]
+    fn unsafe_integers() {
+        let mut value = Value::Array(vec![
+            Value::Integer(MAX_SAFE_INTEGER.into()),
+            Value::Integer((MAX_SAFE_INTEGER + 1).into()),
+            Value::Integer((-(MAX_SAFE_INTEGER as i64)).into()),
+            Value::Integer((-(MAX_SAFE_INTEGER as i64) - 1).into()),
+            Value::Integer(u64::MAX.into()),
+            Value::Float(1e300),
+        ]);
+
+        assert_eq!(stringify_unsafe_integers(&mut value), 3);
+        assert_eq!(
+            value,
+            Value::Array(vec![
+                Value::Integer(MAX_SAFE_INTEGER.into()),
+                Value::String("9007199254740992".to_string()),
+                Value::Integer((-(MAX_SAFE_INTEGER as i64)).into()),
+                Value::String("-9007199254740992".to_string()),
+                Value::String("18446744073709551615".to_string()),
+                Value::Float(1e300),
+            ])
+        );
+    }
+
+    #[test]
+    fn stringify() {
+        let mut value = map(vec![
+            (
+                "labels",
+                map(vec![
+                    ("a", Value::Bool(true)),
+                    ("b", Value::Integer(1_u64.into())),
+                    ("c", Value::Float(1.5)),
+                    ("d", Value::Null),
+                ]),
+            ),
+            ("replicas", Value::Integer(1_u64.into())),
+        ]);
+
+        assert_eq!(stringify_under(&mut value, "/labels").unwrap(), 3);
+        assert_eq!(
+            value,
+            map(vec![
+                (
+                    "labels",
+                    map(vec![
+                        ("a", Value::String("true".to_string())),
+                        ("b", Value::String("1".to_string())),
+                        ("c", Value::String("1.5".to_string())),
+                        ("d", Value::Null),
+                    ]),
+                ),
+                ("replicas", Value::Integer(1_u64.into())),
+            ])
+        );
+        assert!(stringify_under(&mut value, "/annotations").is_err());
+        assert!(stringify_under(&mut value, "labels").is_err());
+    }
+
+    #[test]
+    fn datetimes() {
+        let string = |str: &str| Value::String(str.to_string());
+        let mut value = map(vec![
+            ("created", string("1979-05-27T07:32:00-07:00")),
+            (
+                "times",
+                Value::Array(vec![
+                    string("1979-05-27T14:32:00Z"),
+                    string("1979-05-27T07:32:00"),
+                    string("1979-05-27"),
+                    string("07:32:00"),
+                ]),
+            ),
+        ]);
+
+        assert_eq!(convert_datetimes(&mut value, TimeZone::Utc), 1);
+        assert_eq!(
+            value,
+            map(vec![
+                ("created", string("1979-05-27T14:32:00Z")),
+                (
+                    "times",
+                    Value::Array(vec![
+                        string("1979-05-27T14:32:00Z"),
+                        string("1979-05-27T07:32:00"),
+                        string("1979-05-27"),
+                        string("07:32:00"),
+                    ]),
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn set() {
+        let int = |n: u64| Value::Integer(n.into());
+        let mut value = map(vec![("a", Value::Array(vec![int(1), int(2)]))]);
+
+        set_pointer(
+            &mut value,
+            "/metadata/version",
+            Value::String("1.2.3".to_string()),
+        )
+        .unwrap();
+        set_pointer(&mut value, "/a/1", map(vec![])).unwrap();
+        set_pointer(&mut value, "/a/1/b~1c", int(3)).unwrap();
+        assert_eq!(
+            value,
+            map(vec![
+                ("a", Value::Array(vec![int(1), map(vec![("b/c", int(3))])])),
+                (
+                    "metadata",
+                    map(vec![("version", Value::String("1.2.3".to_string()))])
+                ),
+            ])
+        );
+
+        assert_eq!(
+            set_pointer(&mut value, "/a/2", Value::Null)
+                .unwrap_err()
+                .to_string(),
+            "The index \"2\" is out of range of the array of length 2 at /a"
+        );
+        assert_eq!(
+            set_pointer(&mut value, "/a/0/b", Value::Null)
+                .unwrap_err()
+                .to_string(),
+            "The value at /a/0 is integer, which cannot have children"
+        );
+        assert!(set_pointer(&mut value, "a", Value::Null).is_err());
+
+        set_pointer(&mut value, "", Value::Null).unwrap();
+        assert_eq!(value, Value::Null);
+    }
+
+    #[test]
+    fn delete() {
+        let int = |n: u64| Value::Integer(n.into());
+        let mut value = map(vec![
+            ("a", Value::Array(vec![int(1), int(2), int(3)])),
+            ("b/c", map(vec![("d", int(4)), ("e", int(5))])),
+        ]);
+
+        assert!(delete_pointer(&mut value, "/a/0").unwrap());
+        assert!(delete_pointer(&mut value, "/b~1c/d").unwrap());
+        assert!(!delete_pointer(&mut value, "/a/2").unwrap());
+        assert!(!delete_pointer(&mut value, "/f/g").unwrap());
+        assert!(!delete_pointer(&mut value, "/a/0/h").unwrap());
+        assert_eq!(
+            value,
+            map(vec![
+                ("a", Value::Array(vec![int(2), int(3)])),
+                ("b/c", map(vec![("e", int(5))])),
+            ])
+        );
+
+        assert!(delete_pointer(&mut value, "").is_err());
+        assert!(delete_pointer(&mut value, "a").is_err());
+        assert!(delete_pointer(&mut value, "a/0").is_err());
+    }
+
+    #[test]
+    fn flatten() {
+        let int = |n: u64| Value::Integer(n.into());
+        let nested = || {
+            Value::Array(vec![
+                Value::Array(vec![int(1), Value::Array(vec![int(2)])]),
+                int(3),
+                map(vec![("a", Value::Array(vec![Value::Array(vec![int(4)])]))]),
+            ])
+        };
+
+        let mut value = nested();
+        assert_eq!(flatten_arrays(&mut value, Depth::Limited(1)), 2);
+        assert_eq!(
+            value,
+            Value::Array(vec![
+                int(1),
+                Value::Array(vec![int(2)]),
+                int(3),
+                map(vec![("a", Value::Array(vec![int(4)]))]),
+            ])
+        );
+
+        let mut value = nested();
+        assert_eq!(flatten_arrays(&mut value, Depth::Full), 3);
+        assert_eq!(
+            value,
+            Value::Array(vec![
+                int(1),
+                int(2),
+                int(3),
+                map(vec![("a", Value::Array(vec![int(4)]))]),
+            ])
+        );
+
+        let mut value = nested();
+        assert_eq!(flatten_arrays(&mut value, Depth::Limited(0)), 0);
+        assert_eq!(value, nested());
+    }
+
+    #[test]
+    fn depth() {
+        assert_eq!("2".parse::<Depth>().unwrap(), Depth::Limited(2));
+        assert_eq!("FULL".parse::<Depth>().unwrap(), Depth::Full);
+        assert!("-1".parse::<Depth>().is_err());
+    }
+
+    #[test]
+    fn coerce() {
+        let mut value = map(vec![
+            ("a", Value::String("Yes".to_string())),
+            ("b", Value::String("OFF".to_string())),
+            ("c", Value::Array(vec![Value::String("1".to_string())])),
+            ("d", Value::String("y".to_string())),
+            ("e", Value::Integer(0_u64.into())),
+        ]);
+
+        assert_eq!(coerce_bools(&mut value, &TRUE_TOKENS, &FALSE_TOKENS), 3);
+        assert_eq!(
+            value,
+            map(vec![
+                ("a", Value::Bool(true)),
+                ("b", Value::Bool(false)),
+                ("c", Value::Array(vec![Value::Bool(true)])),
+                ("d", Value::String("y".to_string())),
+                ("e", Value::Integer(0_u64.into())),
+            ])
+        );
+
+        let mut value = Value::String("y".to_string());
+        assert_eq!(coerce_bools(&mut value, &["y"], &["n"]), 1);
+        assert_eq!(value, Value::Bool(true));
+    }
+
+    #[test]
+    fn radix() {
+        let mut value = Value::Array(vec![
+            Value::String("0xFF".to_string()),
+            Value::String("0o17".to_string()),
+            Value::String("0B1010".to_string()),
+            Value::String("-0x10".to_string()),
+            Value::String("0x".to_string()),
+            Value::String("0xG".to_string()),
+            Value::String("255".to_string()),
+            Value::String("0x1_0".to_string()),
+            Value::String("0x10000000000000000".to_string()),
+            Value::String("-0b1".to_string()),
+        ]);
+
+        assert_eq!(parse_radix(&mut value), 6);
+        assert_eq!(
+            value,
+            Value::Array(vec![
+                Value::Integer(255_u64.into()),
+                Value::Integer(15_u64.into()),
+                Value::Integer(10_u64.into()),
+                Value::Integer((-16_i64).into()),
+                Value::String("0x".to_string()),
+                Value::String("0xG".to_string()),
+                Value::String("255".to_string()),
+                Value::String("0x1_0".to_string()),
+                Value::Integer((BigInt::from(u64::MAX) + 1_u32).into()),
+                Value::Integer((-1_i64).into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn fold() {
+        let value = map(vec![
+            ("Host", Value::Integer(0_u64.into())),
+            ("host", Value::Integer(1_u64.into())),
+            ("Sub", Value::Array(vec![map(vec![("A", Value::Null)])])),
+        ]);
+
+        let mut folded = value.clone();
+        assert_eq!(
+            fold_keys(&mut folded, KeyCase::Lower, KeyConflict::First).unwrap(),
+            3
+        );
+        assert_eq!(
+            folded,
+            map(vec![
+                ("host", Value::Integer(0_u64.into())),
+                ("sub", Value::Array(vec![map(vec![("a", Value::Null)])])),
+            ])
+        );
+
+        let mut folded = value.clone();
+        fold_keys(&mut folded, KeyCase::Upper, KeyConflict::Last).unwrap();
+        assert_eq!(
+            folded,
+            map(vec![
+                ("HOST", Value::Integer(1_u64.into())),
+                ("SUB", Value::Array(vec![map(vec![("A", Value::Null)])])),
+            ])
+        );
+
+        let mut folded = value;
+        assert!(fold_keys(&mut folded, KeyCase::Lower, KeyConflict::Error).is_err());
+    }
+
+    #[test]
+    fn strip() {
+        let value = map(vec![
+            ("APP_HOST", Value::Integer(0_u64.into())),
+            ("HOST", Value::Integer(1_u64.into())),
+            ("APP_SUB", map(vec![("APP_A", Value::Null)])),
+        ]);
+
+        let mut stripped = value.clone();
+        assert_eq!(
+            strip_prefix(&mut stripped, "APP_", false, KeyConflict::Last).unwrap(),
+            2
+        );
+        assert_eq!(
+            stripped,
+            map(vec![
+                ("HOST", Value::Integer(1_u64.into())),
+                ("SUB", map(vec![("APP_A", Value::Null)])),
+            ])
+        );
+
+        let mut stripped = value.clone();
+        assert_eq!(
+            strip_prefix(&mut stripped, "APP_", true, KeyConflict::First).unwrap(),
+            3
+        );
+        assert_eq!(
+            stripped,
+            map(vec![
+                ("HOST", Value::Integer(0_u64.into())),
+                ("SUB", map(vec![("A", Value::Null)])),
+            ])
+        );
+
+        let mut stripped = value;
+        assert!(strip_prefix(&mut stripped, "APP_", false, KeyConflict::Error).is_err());
+    }
+
+    #[test]
+    fn null_as() {
+        let mut value = map(vec![
+            ("a", Value::Null),
+            ("b", Value::Array(vec![Value::Null, Value::Bool(false)])),
+        ]);
+
+        assert_eq!(replace_nulls(&mut value, "NULL"), 2);
+        assert_eq!(
+            value,
+            map(vec![
+                ("a", Value::String("NULL".to_string())),
+                (
+                    "b",
+                    Value::Array(vec![Value::String("NULL".to_string()), Value::Bool(false)])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn order() {
+        let mut value = Value::Array(vec![
+            map(vec![
+                ("spec", map(vec![("b", Value::Null), ("kind", Value::Null)])),
+                ("kind", Value::Null),
+                ("apiVersion", Value::Null),
+            ]),
+            Value::Null,
+        ]);
+
+        assert_eq!(order_keys(&mut value, &["apiVersion", "kind"]), 2);
+        assert_eq!(
+            value,
+            Value::Array(vec![
+                map(vec![
+                    ("apiVersion", Value::Null),
+                    ("kind", Value::Null),
+                    ("spec", map(vec![("kind", Value::Null), ("b", Value::Null)])),
+                ]),
+                Value::Null,
+            ])
+        );
+    }
+
+    #[test]
+    fn sort() {
+        let mut value = Value::Array(vec![
+            map(vec![
+                ("b", map(vec![("a", Value::Null), ("b", Value::Null)])),
+                ("a", map(vec![("d", Value::Null), ("c", Value::Null)])),
+            ]),
+            Value::Null,
+        ]);
+
+        assert_eq!(sort_keys(&mut value), 2);
+        assert_eq!(
+            value,
+            Value::Array(vec![
+                map(vec![
+                    ("a", map(vec![("c", Value::Null), ("d", Value::Null)])),
+                    ("b", map(vec![("a", Value::Null), ("b", Value::Null)])),
+                ]),
+                Value::Null,
+            ])
+        );
+    }
+
+    #[test]
+    fn missing() {
+        let value = map(vec![
+            ("a", Value::Null),
+            ("b", map(vec![("c", Value::Null)])),
+        ]);
+
+        assert_eq!(
+            missing_keys(&value, "", &["a", "x", "c"]).unwrap(),
+            ["x", "c"]
+        );
+        assert!(missing_keys(&value, "/b", &["c"]).unwrap().is_empty());
+        assert!(missing_keys(&value, "/a", &["c"]).is_err());
+        assert!(missing_keys(&value, "/d", &["c"]).is_err());
+    }
+
+    #[test]
+    fn merge_maps() {
+        let mut value = map(vec![
+            ("a", Value::Integer(1_i64.into())),
+            (
+                "b",
+                map(vec![
+                    ("c", Value::Bool(true)),
+                    ("d", Value::Array(vec![Value::Null])),
+                ]),
+            ),
+        ]);
+        let overlay = map(vec![
+            (
+                "b",
+                map(vec![
+                    ("d", Value::Array(vec![])),
+                    ("e", Value::String("x".to_string())),
+                ]),
+            ),
+            ("f", Value::Null),
+        ]);
+        assert_eq!(merge(&mut value, overlay), 1);
+        assert_eq!(
+            value,
+            map(vec![
+                ("a", Value::Integer(1_i64.into())),
+                (
+                    "b",
+                    map(vec![
+                        ("c", Value::Bool(true)),
+                        ("d", Value::Array(vec![])),
+                        ("e", Value::String("x".to_string())),
+                    ]),
+                ),
+                ("f", Value::Null),
+            ])
+        );
+
+        let mut value = map(vec![("a", Value::Null)]);
+        assert_eq!(merge(&mut value, Value::Bool(false)), 1);
+        assert_eq!(value, Value::Bool(false));
+    }
+
+    #[test]
+    fn arrays_and_maps() {
+        let mut value = map(vec![(
+            "a",
+            Value::Array(vec![Value::Null, Value::Array(vec![Value::Bool(true)])]),
+        )]);
+        let expected = map(vec![(
+            "a",
+            map(vec![
+                ("0", Value::Null),
+                ("1", map(vec![("0", Value::Bool(true))])),
+            ]),
+        )]);
+
+        assert_eq!(arrays_to_maps(&mut value), 2);
+        assert_eq!(value, expected);
+        assert_eq!(maps_to_arrays(&mut value), 2);
+        assert_eq!(
+            value,
+            map(vec![(
+                "a",
+                Value::Array(vec![Value::Null, Value::Array(vec![Value::Bool(true)])]),
+            )])
+        );
+
+        let mut value = map(vec![("1", Value::Bool(false)), ("0", Value::Bool(true))]);
+        assert_eq!(maps_to_arrays(&mut value), 1);
+        assert_eq!(
+            value,
+            Value::Array(vec![Value::Bool(true), Value::Bool(false)])
+        );
+
+        for entries in [vec![("1", Value::Null)], vec![("00", Value::Null)], vec![]] {
+            let mut value = map(entries);
+            assert_eq!(maps_to_arrays(&mut value), 0);
+        }
+    }
+
+    #[test]
+    fn apply_options() {
+        let value = map(vec![
+            ("a", Value::Array(vec![Value::Null, Value::Null])),
+            ("b", Value::String(" x ".to_string())),
+        ]);
+        let messages = RefCell::new(Vec::new());
+        let audit = |message| messages.borrow_mut().push(message);
+        let options = Options::new()
+            .transform_order(vec![Transform::NullAs])
+            .null_as(Some("-".to_string()))
+            .dedupe_array(true)
+            .trim_strings(true)
+            .set(vec![("/c".to_string(), Value::Bool(true))]);
+
+        assert_eq!(
+            apply(value.clone(), &options, &audit).unwrap(),
+            map(vec![
+                ("a", Value::Array(vec![Value::String("-".to_string())])),
+                ("b", Value::String("x".to_string())),
+                ("c", Value::Bool(true)),
+            ])
+        );
+        assert_eq!(
+            messages.into_inner(),
+            [
+                "null-as: replaced 2 nulls",
+                "dedupe-array: removed 1 elements",
+                "trim-strings: trimmed 1 strings",
+                "set: set 1 values",
+            ]
+        );
+        assert_eq!(
+            apply(value.clone(), &Options::default(), &|_| ()).unwrap(),
+            value
+        );
+
+        let options = Options::new().transform_order(vec![Transform::Set, Transform::Set]);
+        assert!(apply(value, &options, &|_| ()).is_err());
+    }
+
+    #[test]
+    fn apply_output_options() {
+        let messages = RefCell::new(Vec::new());
+        let audit = |message| messages.borrow_mut().push(message);
+        let options = Options::new()
+            .merge_into(Some(map(vec![
+                ("b", Value::Bool(false)),
+                ("a", Value::Null),
+            ])))
+            .sort_keys(Some(SortScope::Text))
+            .js_safe_integers(true);
+        let value = map(vec![("b", Value::Integer(u64::MAX.into()))]);
+
+        assert_eq!(
+            apply_output(value.clone(), Format::Json, &options, &audit),
+            map(vec![
+                ("a", Value::Null),
+                ("b", Value::String(u64::MAX.to_string())),
+            ])
+        );
+        assert_eq!(
+            messages.into_inner(),
+            [
+                "merge-into: replaced 1 values",
+                "sort-keys: sorted 1 maps",
+                "js-safe-integers: converted 1 integers",
+            ]
+        );
+        assert_eq!(
+            apply_output(value.clone(), Format::Cbor, &options, &|_| ()),
+            map(vec![
+                ("b", Value::Integer(u64::MAX.into())),
+                ("a", Value::Null),
+            ])
+        );
+
+        let options = Options::new().wrap(Some("value".to_string()));
+        assert_eq!(
+            apply_output(Value::Bool(true), Format::Toml, &options, &|_| ()),
+            map(vec![("value", Value::Bool(true))])
+        );
+        assert_eq!(
+            apply_output(Value::Bool(true), Format::Json, &options, &|_| ()),
+            Value::Bool(true)
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::error::Error;
+use std::fmt;
+
+use indexmap::map::Entry;
+use indexmap::IndexMap;
+
+use crate::convert::ConvertError;
+use crate::lossy::Tracker;
+use crate::value::Value;
+
+/// The error type for parsing a URL-encoded form.
+#[derive(Debug)]
+pub struct ParseError {
+    /// What was wrong.
+    pub message: String,
+    /// The key of the pair where the error occurred.
+    pub key: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in {:?}", self.message, self.key)
+    }
+}
+
+impl Error for ParseError {}
+
+/// A part of a key in bracket notation.
+#[derive(Debug)]
+enum Segment {
+    /// `[name]`, which is a key of a map.
+    Key(String),
+    /// `[]`, which appends to an array.
+    Push,
+}
+
+/// Parse a URL-encoded form into a map.
+///
+/// Values are always strings. Keys in bracket notation build nested values:
+/// `a[b]=1` is a map and `a[]=1` appends to an array. Repeated keys without
+/// brackets also become arrays. A leading `?` and surrounding whitespace are
+/// ignored.
+pub fn from_str(input: &str) -> Result<Value, ParseError> {
+    let input = input.trim();
+    let input = input.strip_prefix('?').unwrap_or(input);
+
+    let mut map = IndexMap::new();
+    for pair in input.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let error = |message: &str| ParseError {
+            message: message.to_string(),
+            key: key.to_string(),
+        };
+
+        let (name, segments) = split_key(key).ok_or_else(|| error("Malformed brackets"))?;
+        let name = decode(name).ok_or_else(|| error("Invalid percent-encoding"))?;
+        let segments = segments
+            .into_iter()
+            .map(|s| match s {
+                "" => Some(Segment::Push),
+                s => decode(s).map(Segment::Key),
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| error("Invalid percent-encoding"))?;
+        let value = decode(value).ok_or_else(|| error("Invalid percent-encoding"))?;
+
+        insert(&mut map, name, &segments, value)
+            .ok_or_else(|| error("Conflicting types of the value"))?;
+    }
+
+    Ok(Value::Map(map))
+}
+
+/// Split `a[b][]` into `a` and `["b", ""]`.
+///
+/// Returns `None` if a bracket is not closed or is followed by other
+/// characters.
+fn split_key(key: &str) -> Option<(&str, Vec<&str>)> {
+    let (name, mut rest) = match key.find('[') {
+        Some(0) | None => return Some((key, Vec::new())),
+        Some(pos) => key.split_at(pos),
+    };
+
+    let mut segments = Vec::new();
+    while let Some(stripped) = rest.strip_prefix('[') {
+        let end = stripped.find(']')?;
+        segments.push(&stripped[..end]);
+        rest = &stripped[end + 1..];
+    }
+    if !rest.is_empty() {
+        return None;
+    }
+
+    Some((name, segments))
+}
+
+/// Insert `value` into `map` at `name` followed by `segments`.
+///
+/// Returns `None` if an existing value has the wrong type.
+fn insert(
+    map: &mut IndexMap<String, Value>,
+    name: String,
+    segments: &[Segment],
+    value: String,
+) -> Option<()> {
+    match (map.entry(name), segments) {
+        (Entry::Vacant(entry), []) => {
+            entry.insert(Value::String(value));
+        }
+        (Entry::Occupied(mut entry), []) => match entry.get_mut() {
+            Value::Array(arr) => arr.push(Value::String(value)),
+            existing @ Value::String(_) => {
+                let first = existing.clone();
+                *existing = Value::Array(vec![first, Value::String(value)]);
+            }
+            _ => return None,
+        },
+        (entry, [first, rest @ ..]) => {
+            let container = entry.or_insert_with(|| match first {
+                Segment::Key(_) => Value::Map(IndexMap::new()),
+                Segment::Push => Value::Array(Vec::new()),
+            });
+            match (container, first) {
+                (Value::Map(map), Segment::Key(key)) => insert(map, key.clone(), rest, value)?,
+                (Value::Array(arr), Segment::Push) => arr.push(element(rest, value)?),
+                _ => return None,
+            }
+        }
+    }
+
+    Some(())
+}
+
+/// Build a new element of an array from the remaining segments.
+fn element(segments: &[Segment], value: String) -> Option<Value> {
+    match segments {
+        [] => Some(Value::String(value)),
+        [Segment::Key(key), rest @ ..] => {
+            let mut map = IndexMap::new();
+            insert(&mut map, key.clone(), rest, value)?;
+
+            Some(Value::Map(map))
+        }
+        [Segment::Push, rest @ ..] => Some(Value::Array(vec![element(rest, value)?])),
+    }
+}
+
+/// Decode `+` and percent-encoded bytes.
+///
+/// Returns `None` if the encoding is malformed or the result is not UTF-8.
+fn decode(str: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(str.len());
+    let mut iter = str.bytes();
+    while let Some(b) = iter.next() {
+        match b {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [iter.next()?, iter.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            b => bytes.push(b),
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+/// Serialize a map into a URL-encoded form.
+///
+/// Nested maps are written in bracket notation. Arrays of scalars are written
+/// as `a[]=...`, and arrays which contain collections are written with
+/// indices as `a[0][b]=...`. Nulls are written as keys without values, and
+/// empty collections are omitted.
+pub fn to_string(value: &Value) -> Result<String, ConvertError> {
+    to_string_tracked(value, &mut Tracker::new())
+}
+
+/// Convert a map into a URL-encoded form like `to_string`, reporting the
+/// values which become strings or are dropped to `tracker`.
+pub(crate) fn to_string_tracked(
+    value: &Value,
+    tracker: &mut Tracker,
+) -> Result<String, ConvertError> {
+    let map = match value {
+        Value::Map(map) => map,
+        _ => return Err(ConvertError::NonMapRoot),
+    };
+
+    let mut pairs = Vec::new();
+    for (key, value) in map {
+        tracker.nest(key, |tracker| {
+            write_pairs(&mut pairs, encode(key), value, tracker)
+        });
+    }
+
+    Ok(pairs.join("&"))
+}
+
+fn write_pairs(pairs: &mut Vec<String>, key: String, value: &Value, tracker: &mut Tracker) {
+    let str = match value {
+        Value::Null => String::new(),
+        Value::Bool(bool) => bool.to_string(),
+        Value::Integer(int) => int.to_string(),
+        Value::Float(float) => float.to_string(),
+        Value::String(str) => str.clone(),
+        Value::Array(arr) if arr.is_empty() => {
+            tracker.report("the empty array is dropped");
+            return;
+        }
+        Value::Map(map) if map.is_empty() => {
+            tracker.report("the empty map is dropped");
+            return;
+        }
+        Value::Array(arr) => {
+            let has_collections = arr
+                .iter()
+                .any(|v| matches!(v, Value::Array(_) | Value::Map(_)));
+            if has_collections {
+                tracker.report("the array is converted to a map keyed by indices");
+            }
+            for (i, v) in arr.iter().enumerate() {
+                let key = if has_collections {
+                    format!("{}[{}]", key, i)
+                } else {
+                    format!("{}[]", key)
+                };
+                tracker.nest(&i.to_string(), |tracker| {
+                    write_pairs(pairs, key, v, tracker)
+                });
+            }
+            return;
+        }
+        Value::Map(map) => {
+            for (k, v) in map {
+                let key = format!("{}[{}]", key, encode(k));
+                tracker.nest(k, |tracker| write_pairs(pairs, key, v, tracker));
+            }
+            return;
+        }
+    };
+    tracker.compare(value, &Value::String(str.clone()));
+
+    match value {
+        Value::Null => pairs.push(key),
+        _ => pairs.push(format!("{}={}", key, encode(&str))),
+    }
+}
+
+/// Percent-encode the string, leaving unreserved characters and encoding
+/// spaces as `+`.
+fn encode(str: &str) -> String {
+    str.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(b).to_string()
+            }
+            b' ' => "+".to_string(),
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    }
+
+    fn string(str: &str) -> Value {
+        Value::String(str.to_string())
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            from_str("?a=1&b=x+y%21&c[]=x&c[]=y&d[e][f]=2&g=1&g=2&h\n").unwrap(),
+            map(vec![
+                ("a", string("1")),
+                ("b", string("x y!")),
+                ("c", Value::Array(vec![string("x"), string("y")])),
+                ("d", map(vec![("e", map(vec![("f", string("2"))]))])),
+                ("g", Value::Array(vec![string("1"), string("2")])),
+                ("h", string("")),
+            ])
+        );
+        assert_eq!(
+            from_str("a[][b]=1&a[][b]=2&%5Bc%5D=3").unwrap(),
+            map(vec![
+                (
+                    "a",
+                    Value::Array(vec![
+                        map(vec![("b", string("1"))]),
+                        map(vec![("b", string("2"))]),
+                    ]),
+                ),
+                ("[c]", string("3")),
+            ])
+        );
+        assert_eq!(from_str("").unwrap(), map(vec![]));
+    }
+
+    #[test]
+    fn parse_error() {
+        assert_eq!(
+            from_str("a[b=1").unwrap_err().to_string(),
+            "Malformed brackets in \"a[b\""
+        );
+        assert!(from_str("a[b]c=1").is_err());
+        assert!(from_str("a=%zz").is_err());
+        assert!(from_str("a=%ff").is_err());
+        assert_eq!(
+            from_str("a=1&a[b]=2").unwrap_err().to_string(),
+            "Conflicting types of the value in \"a[b]\""
+        );
+    }
+
+    #[test]
+    fn serialize() {
+        let value = map(vec![
+            ("a", Value::Integer(1_u64.into())),
+            ("b", string("x y&z")),
+            ("c", Value::Array(vec![string("x"), Value::Bool(true)])),
+            ("d", map(vec![("e", Value::Float(0.5))])),
+            ("f", Value::Array(vec![map(vec![("g", Value::Null)])])),
+            ("h", Value::Array(vec![])),
+        ]);
+
+        assert_eq!(
+            to_string(&value).unwrap(),
+            "a=1&b=x+y%26z&c[]=x&c[]=true&d[e]=0.5&f[0][g]"
+        );
+        assert!(matches!(
+            to_string(&Value::Null),
+            Err(ConvertError::NonMapRoot)
+        ));
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = map(vec![
+            ("a b", string("1+1=2")),
+            ("c", Value::Array(vec![string("x"), string("y")])),
+            ("d", map(vec![("e[f]", string("\u{3042}"))])),
+        ]);
+
+        assert_eq!(from_str(&to_string(&value).unwrap()).unwrap(), value);
+    }
+}
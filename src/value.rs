@@ -4,38 +4,385 @@
 // Copyright (C) 2021 Shun Sakai
 //
 
+use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::fmt::{self, Display};
+use std::hash::{Hash, Hasher};
+use std::num::ParseIntError;
+use std::str::FromStr;
 
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat, Utc};
 use clap::ArgEnum;
+use indexmap::map::Entry;
 use indexmap::IndexMap;
+use num_bigint::{BigInt, ParseBigIntError};
+use num_traits::{FromPrimitive, ToPrimitive};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
 use strum::{Display, EnumString, EnumVariantNames};
 
+use crate::lossy::Tracker;
+
 #[derive(Clone, Copy, Display, EnumString, EnumVariantNames)]
 #[strum(serialize_all = "UPPERCASE", ascii_case_insensitive)]
 pub enum Format {
     Cbor,
+    #[strum(serialize = "h", to_string = "C header")]
+    CHeader,
+    Csv,
+    #[strum(serialize = "env", serialize = "sh", to_string = "shell exports")]
+    Env,
     #[strum(to_string = "Hjson")]
     Hjson,
     Json,
     Json5,
+    #[strum(serialize = "jsonc", to_string = "JSONC")]
+    Jsonc,
+    #[strum(serialize = "json-seq", to_string = "JSON text sequence")]
+    JsonSeq,
+    #[strum(to_string = "Lines")]
+    Lines,
     #[strum(serialize = "msgpack", to_string = "MessagePack")]
     MessagePack,
+    #[strum(serialize = "jsonl", to_string = "NDJSON")]
+    Ndjson,
+    #[strum(serialize = "nginx", to_string = "nginx configuration")]
+    Nginx,
+    #[strum(to_string = "OpenStep")]
+    OpenStep,
+    #[strum(to_string = "Parquet")]
+    Parquet,
+    Reg,
     Ron,
     Toml,
+    Tsv,
+    #[strum(serialize = "urlencoded", to_string = "URL-encoded form")]
+    UrlEncoded,
     #[strum(serialize = "yml", to_string = "YAML")]
     Yaml,
 }
 
+/// How a format holds integers out of the range of 64-bit integers.
+#[derive(Clone, Copy, PartialEq)]
+enum WideIntegers {
+    Kept,
+    Strings,
+    Error,
+}
+
+/// What a format can represent.
+struct Capabilities {
+    comments: bool,
+    byte_strings: bool,
+    tags: bool,
+    extension_types: bool,
+    undefined: bool,
+    non_string_keys: bool,
+    null: bool,
+    non_finite_floats: bool,
+    mixed_arrays: bool,
+    unsigned_integers: bool,
+    wide_integers: WideIntegers,
+    non_map_root: bool,
+    key_order: bool,
+    non_string_values: bool,
+}
+
+impl Format {
+    const fn capabilities(self) -> Capabilities {
+        let all = Capabilities {
+            comments: false,
+            byte_strings: false,
+            tags: false,
+            extension_types: false,
+            undefined: false,
+            non_string_keys: false,
+            null: true,
+            non_finite_floats: true,
+            mixed_arrays: true,
+            unsigned_integers: true,
+            wide_integers: WideIntegers::Strings,
+            non_map_root: true,
+            key_order: true,
+            non_string_values: true,
+        };
+        match self {
+            Self::Cbor => Capabilities {
+                byte_strings: true,
+                tags: true,
+                undefined: true,
+                non_string_keys: true,
+                wide_integers: WideIntegers::Kept,
+                key_order: false,
+                ..all
+            },
+            Self::CHeader | Self::Reg => Capabilities {
+                wide_integers: WideIntegers::Error,
+                non_map_root: false,
+                ..all
+            },
+            Self::Env | Self::UrlEncoded => Capabilities {
+                wide_integers: WideIntegers::Kept,
+                non_map_root: false,
+                ..all
+            },
+            Self::Hjson | Self::Json5 => Capabilities {
+                comments: true,
+                ..all
+            },
+            Self::Jsonc => Capabilities {
+                comments: true,
+                non_finite_floats: false,
+                wide_integers: WideIntegers::Kept,
+                ..all
+            },
+            Self::Csv | Self::Tsv => all,
+            Self::Nginx | Self::OpenStep => Capabilities {
+                comments: true,
+                ..all
+            },
+            Self::Json | Self::JsonSeq | Self::Ndjson => Capabilities {
+                non_finite_floats: false,
+                wide_integers: WideIntegers::Kept,
+                ..all
+            },
+            Self::Lines => Capabilities {
+                non_string_values: false,
+                ..all
+            },
+            Self::MessagePack => Capabilities {
+                byte_strings: true,
+                extension_types: true,
+                non_string_keys: true,
+                ..all
+            },
+            Self::Parquet => Capabilities {
+                byte_strings: true,
+                mixed_arrays: false,
+                unsigned_integers: false,
+                wide_integers: WideIntegers::Error,
+                ..all
+            },
+            Self::Ron => Capabilities {
+                comments: true,
+                non_string_keys: true,
+                ..all
+            },
+            Self::Toml => Capabilities {
+                comments: true,
+                null: false,
+                mixed_arrays: false,
+                unsigned_integers: false,
+                wide_integers: WideIntegers::Error,
+                non_map_root: false,
+                ..all
+            },
+            Self::Yaml => Capabilities {
+                comments: true,
+                non_string_keys: true,
+                ..all
+            },
+        }
+    }
+
+    /// The collection which the format requires at the root when it is
+    /// written.
+    ///
+    /// Returns `None` if the root can be any value.
+    pub const fn required_root(self) -> Option<Root> {
+        match self {
+            Self::CHeader | Self::Env | Self::Reg | Self::Toml | Self::UrlEncoded => {
+                Some(Root::Map)
+            }
+            Self::Lines => Some(Root::Array),
+            _ => None,
+        }
+    }
+
+    /// The filename extension of the format, which is determined as this
+    /// format.
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Cbor => "cbor",
+            Self::CHeader => "h",
+            Self::Csv => "csv",
+            Self::Env => "sh",
+            Self::Hjson => "hjson",
+            Self::Json => "json",
+            Self::Json5 => "json5",
+            Self::Jsonc => "jsonc",
+            Self::JsonSeq => "json-seq",
+            Self::Lines => "lines",
+            Self::MessagePack => "msgpack",
+            Self::Ndjson => "jsonl",
+            Self::Nginx => "nginx",
+            Self::OpenStep => "openstep",
+            Self::Parquet => "parquet",
+            Self::Reg => "reg",
+            Self::Ron => "ron",
+            Self::Toml => "toml",
+            Self::Tsv => "tsv",
+            Self::UrlEncoded => "urlencoded",
+            Self::Yaml => "yaml",
+        }
+    }
+
+    /// Returns `true` if the format is a binary format.
+    pub const fn is_binary(self) -> bool {
+        matches!(self, Self::Cbor | Self::MessagePack | Self::Parquet)
+    }
+
+    /// Returns `true` if the format is a sequence of records, which
+    /// `ParseErrorPolicy` applies to.
+    pub const fn is_record_oriented(self) -> bool {
+        matches!(self, Self::Csv | Self::JsonSeq | Self::Ndjson | Self::Tsv)
+    }
+
+    /// The prefix of line comments in this format when it is written.
+    ///
+    /// Returns `None` if comments cannot be written at the beginning of the
+    /// output.
+    pub const fn comment_prefix(self) -> Option<&'static str> {
+        match self {
+            Self::CHeader | Self::Jsonc | Self::Ron => Some("//"),
+            Self::Env | Self::Toml | Self::Yaml => Some("#"),
+            _ => None,
+        }
+    }
+
+    /// Describe the known lossy transformations when converting to `to`.
+    pub fn lossy_conversions(self, to: Self) -> Vec<String> {
+        let (input, output) = (self.capabilities(), to.capabilities());
+        let mut notes = Vec::new();
+
+        if input.comments {
+            notes.push(format!("{} comments will be dropped", self));
+        }
+        if input.byte_strings {
+            notes.push(format!(
+                "{} byte strings will cause an error unless `--bytes` is specified",
+                self
+            ));
+        }
+        if input.tags {
+            notes.push(format!(
+                "{} bignums will be read as byte strings unless `--preserve-cbor-bignum` is \
+                 specified",
+                self
+            ));
+            notes.push(format!(
+                "{} tags other than bignums and self-describe will cause an error",
+                self
+            ));
+        }
+        if input.extension_types {
+            notes.push(format!("{} extension types will cause an error", self));
+        }
+        if input.undefined {
+            notes.push(format!(
+                "{} undefined will be converted to null unless `--cbor-strict-undefined` is \
+                 specified",
+                self
+            ));
+        }
+        if input.non_string_keys {
+            notes.push(format!("{} non-string map keys will cause an error", self));
+        }
+        if input.null && !output.null {
+            notes.push(format!("{} has no null: nulls will cause an error", to));
+        }
+        if input.non_finite_floats && !output.non_finite_floats {
+            notes.push(format!(
+                "{} cannot represent NaN or infinity: they will cause an error",
+                to
+            ));
+        }
+        if input.mixed_arrays && !output.mixed_arrays {
+            notes.push(format!(
+                "{} arrays must have elements of the same type: mixed arrays will cause an \
+                 error unless `--toml-stringify-mixed-arrays` is specified",
+                to
+            ));
+        }
+        if input.unsigned_integers && !output.unsigned_integers {
+            notes.push(format!(
+                "{} integers are signed 64-bit: larger integers will cause an error",
+                to
+            ));
+        }
+        // Formats which only have signed integers are described above.
+        if input.wide_integers == WideIntegers::Kept
+            && output.unsigned_integers
+            && output.non_string_values
+        {
+            match output.wide_integers {
+                WideIntegers::Kept => (),
+                WideIntegers::Strings => notes.push(format!(
+                    "{} integers are 64-bit: wider integers will be written as strings",
+                    to
+                )),
+                WideIntegers::Error => notes.push(format!(
+                    "{} integers are 64-bit: wider integers will cause an error",
+                    to
+                )),
+            }
+        }
+        if input.non_map_root && !output.non_map_root {
+            notes.push(format!("{} requires a map at the root", to));
+        }
+        if !output.non_string_values {
+            notes.push(format!(
+                "{} can only represent an array of strings: other values will cause an error",
+                to
+            ));
+        }
+        if !output.key_order {
+            notes.push(format!("{} map keys will be sorted", to));
+        }
+
+        notes
+    }
+}
+
+/// The collection at the root of a value.
+#[derive(Clone, Copy, Debug, Display, PartialEq)]
+pub enum Root {
+    #[strum(to_string = "a map")]
+    Map,
+    #[strum(to_string = "an array")]
+    Array,
+}
+
+impl Root {
+    /// Wrap `value` in this collection, using `key` as the key of a map.
+    pub fn wrap(self, key: &str, value: Value) -> Value {
+        match self {
+            Self::Map => Value::Map(std::iter::once((key.to_string(), value)).collect()),
+            Self::Array => Value::Array(vec![value]),
+        }
+    }
+}
+
 #[derive(ArgEnum, Clone, Copy)]
 #[clap(rename_all = "lower")]
 pub enum InputFormat {
     Cbor,
+    Csv,
     Hjson,
     Json,
     Json5,
+    Jsonc,
+    #[clap(name = "json-seq")]
+    JsonSeq,
+    Lines,
     MessagePack,
+    Ndjson,
+    Nginx,
+    OpenStep,
+    Parquet,
     Ron,
     Toml,
+    Tsv,
+    UrlEncoded,
     Yaml,
 }
 
@@ -43,12 +390,22 @@ impl From<InputFormat> for Format {
     fn from(value: InputFormat) -> Self {
         match value {
             InputFormat::Cbor => Self::Cbor,
+            InputFormat::Csv => Self::Csv,
             InputFormat::Hjson => Self::Hjson,
             InputFormat::Json => Self::Json,
             InputFormat::Json5 => Self::Json5,
+            InputFormat::Jsonc => Self::Jsonc,
+            InputFormat::JsonSeq => Self::JsonSeq,
+            InputFormat::Lines => Self::Lines,
             InputFormat::MessagePack => Self::MessagePack,
+            InputFormat::Ndjson => Self::Ndjson,
+            InputFormat::Nginx => Self::Nginx,
+            InputFormat::OpenStep => Self::OpenStep,
+            InputFormat::Parquet => Self::Parquet,
             InputFormat::Ron => Self::Ron,
             InputFormat::Toml => Self::Toml,
+            InputFormat::Tsv => Self::Tsv,
+            InputFormat::UrlEncoded => Self::UrlEncoded,
             InputFormat::Yaml => Self::Yaml,
         }
     }
@@ -58,9 +415,20 @@ impl From<InputFormat> for Format {
 #[clap(rename_all = "lower")]
 pub enum OutputFormat {
     Cbor,
+    CHeader,
+    Env,
     Json,
+    Jsonc,
+    #[clap(name = "json-seq")]
+    JsonSeq,
+    Lines,
     MessagePack,
+    Ndjson,
+    Parquet,
+    Reg,
+    Ron,
     Toml,
+    UrlEncoded,
     Yaml,
 }
 
@@ -68,21 +436,36 @@ impl From<OutputFormat> for Format {
     fn from(value: OutputFormat) -> Self {
         match value {
             OutputFormat::Cbor => Self::Cbor,
+            OutputFormat::CHeader => Self::CHeader,
+            OutputFormat::Env => Self::Env,
             OutputFormat::Json => Self::Json,
+            OutputFormat::Jsonc => Self::Jsonc,
+            OutputFormat::JsonSeq => Self::JsonSeq,
+            OutputFormat::Lines => Self::Lines,
             OutputFormat::MessagePack => Self::MessagePack,
+            OutputFormat::Ndjson => Self::Ndjson,
+            OutputFormat::Parquet => Self::Parquet,
+            OutputFormat::Reg => Self::Reg,
+            OutputFormat::Ron => Self::Ron,
             OutputFormat::Toml => Self::Toml,
+            OutputFormat::UrlEncoded => Self::UrlEncoded,
             OutputFormat::Yaml => Self::Yaml,
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 enum Int {
     Pos(u64),
     Neg(i64),
+    /// Out of the range of both `i64` and `u64`.
+    Big(BigInt),
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// An integer of arbitrary precision.
+///
+/// Integers are ordered by their values.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Integer {
     int: Int,
 }
@@ -90,25 +473,114 @@ pub struct Integer {
 impl Integer {
     pub fn as_i64(&self) -> Option<i64> {
         match self.int {
-            Int::Pos(uint) if i64::try_from(uint).is_ok() => Some(uint as i64),
+            Int::Pos(uint) => i64::try_from(uint).ok(),
             Int::Neg(sint) => Some(sint),
-            Int::Pos(_) => None,
+            Int::Big(_) => None,
         }
     }
 
     pub const fn as_u64(&self) -> Option<u64> {
         match self.int {
             Int::Pos(uint) => Some(uint),
-            Int::Neg(_) => None,
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the integer is out of the range of both `i64` and
+    /// `u64`.
+    ///
+    /// Formats which only have 64-bit integers write such an integer as a
+    /// string of its decimal representation.
+    pub const fn is_wide(&self) -> bool {
+        matches!(self.int, Int::Big(_))
+    }
+
+    /// Convert to a `BigInt`.
+    pub fn to_bigint(&self) -> BigInt {
+        match self.int {
+            Int::Pos(uint) => uint.into(),
+            Int::Neg(sint) => sint.into(),
+            Int::Big(ref int) => int.clone(),
+        }
+    }
+
+    /// Convert to the nearest float.
+    pub fn as_f64(&self) -> f64 {
+        match self.int {
+            Int::Pos(uint) => uint as f64,
+            Int::Neg(sint) => sint as f64,
+            Int::Big(ref int) => int.to_f64().unwrap_or(f64::NAN),
+        }
+    }
+}
+
+/// Compare the integer with the float exactly.
+///
+/// The integer comes first if they are the same value.
+fn cmp_integer_float(int: &Integer, float: f64) -> Ordering {
+    if float.is_nan() {
+        return if float.is_sign_negative() {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        };
+    }
+    if float.is_infinite() {
+        return if float.is_sign_negative() {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        };
+    }
+
+    let trunc = float.trunc();
+    // A finite float without the fractional part is exactly an integer.
+    let trunc_int = BigInt::from_f64(trunc).map_or_else(Integer::default, Integer::from);
+    int.cmp(&trunc_int).then(if float < trunc {
+        Ordering::Greater
+    } else {
+        Ordering::Less
+    })
+}
+
+/// Get the key of the float which orders floats as IEEE 754 `totalOrder`
+/// does.
+fn total_order_key(float: f64) -> i64 {
+    let bits = float.to_bits() as i64;
+
+    bits ^ (((bits >> 63) as u64) >> 1) as i64
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.int, &other.int) {
+            (Int::Pos(a), Int::Pos(b)) => a.cmp(b),
+            (Int::Neg(a), Int::Neg(b)) => a.cmp(b),
+            (Int::Neg(_), Int::Pos(_)) => Ordering::Less,
+            (Int::Pos(_), Int::Neg(_)) => Ordering::Greater,
+            _ => self.to_bigint().cmp(&other.to_bigint()),
         }
     }
 }
 
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Default for Integer {
+    fn default() -> Self {
+        Self { int: Int::Pos(0) }
+    }
+}
+
 impl fmt::Display for Integer {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.int {
             Int::Pos(uint) => Display::fmt(&uint, fmt),
             Int::Neg(sint) => Display::fmt(&sint, fmt),
+            Int::Big(ref int) => Display::fmt(int, fmt),
         }
     }
 }
@@ -135,7 +607,51 @@ impl From<u64> for Integer {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+impl From<i128> for Integer {
+    fn from(integer: i128) -> Self {
+        BigInt::from(integer).into()
+    }
+}
+
+impl From<u128> for Integer {
+    fn from(integer: u128) -> Self {
+        BigInt::from(integer).into()
+    }
+}
+
+impl From<BigInt> for Integer {
+    fn from(integer: BigInt) -> Self {
+        match (integer.to_u64(), integer.to_i64()) {
+            (Some(uint), _) => uint.into(),
+            (_, Some(sint)) => sint.into(),
+            _ => Self {
+                int: Int::Big(integer),
+            },
+        }
+    }
+}
+
+impl FromStr for Integer {
+    type Err = ParseBigIntError;
+
+    /// Parse a decimal integer.
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        str.parse::<BigInt>().map(Into::into)
+    }
+}
+
+/// The intermediate representation of values.
+///
+/// Values are totally ordered, so they can be sorted, deduplicated and used as
+/// keys of sets and maps. Values of different types are ordered as null,
+/// booleans, numbers, strings, arrays and maps. Integers and floats are
+/// compared by their values, and an integer comes before a float of the same
+/// value. Floats are ordered as IEEE 754 `totalOrder` does, so `-0.0` comes
+/// before `0.0` and NaN comes after infinity. Since equality follows this
+/// order, NaN is equal to itself and `-0.0` is not equal to `0.0`. Arrays are
+/// compared element by element, and maps are compared by their entries sorted
+/// by key, so the order of keys does not matter.
+#[derive(Clone, Debug)]
 pub enum Value {
     Null,
     Bool(bool),
@@ -146,6 +662,229 @@ pub enum Value {
     Map(IndexMap<String, Value>),
 }
 
+/// Get the entries of the map sorted by key.
+fn sorted_entries(map: &IndexMap<String, Value>) -> Vec<(&String, &Value)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by_key(|(k, _)| *k);
+
+    entries
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Null, Self::Null) => true,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Integer(a), Self::Integer(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a.to_bits() == b.to_bits(),
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Array(a), Self::Array(b)) => a == b,
+            // `IndexMap` ignores the order of keys.
+            (Self::Map(a), Self::Map(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Null, Self::Null) => Ordering::Equal,
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            (Self::Integer(a), Self::Integer(b)) => a.cmp(b),
+            (Self::Integer(a), Self::Float(b)) => cmp_integer_float(a, *b),
+            (Self::Float(a), Self::Integer(b)) => cmp_integer_float(b, *a).reverse(),
+            (Self::Float(a), Self::Float(b)) => total_order_key(*a).cmp(&total_order_key(*b)),
+            (Self::String(a), Self::String(b)) => a.cmp(b),
+            (Self::Array(a), Self::Array(b)) => a.cmp(b),
+            (Self::Map(a), Self::Map(b)) => sorted_entries(a).cmp(&sorted_entries(b)),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Self::Null => {}
+            Self::Bool(bool) => bool.hash(state),
+            // Integers and floats share the rank, and never equal each other.
+            Self::Integer(int) => {
+                false.hash(state);
+                int.hash(state);
+            }
+            Self::Float(float) => {
+                true.hash(state);
+                float.to_bits().hash(state);
+            }
+            Self::String(str) => str.hash(state),
+            Self::Array(arr) => arr.hash(state),
+            Self::Map(map) => sorted_entries(map).hash(state),
+        }
+    }
+}
+
+impl Value {
+    /// Return the name of the type of the value.
+    pub const fn type_name(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Bool(_) => "boolean",
+            Self::Integer(_) => "integer",
+            Self::Float(_) => "float",
+            Self::String(_) => "string",
+            Self::Array(_) => "array",
+            Self::Map(_) => "map",
+        }
+    }
+
+    /// Returns `true` if the value is neither an array nor a map.
+    pub const fn is_scalar(&self) -> bool {
+        !matches!(self, Self::Array(_) | Self::Map(_))
+    }
+
+    /// Return a value of the same shape, where each scalar is replaced with
+    /// the name of its type.
+    pub fn types(&self) -> Self {
+        match self {
+            Self::Array(arr) => Self::Array(arr.iter().map(Self::types).collect()),
+            Self::Map(map) => Self::Map(map.iter().map(|(k, v)| (k.clone(), v.types())).collect()),
+            value => Self::String(value.type_name().to_string()),
+        }
+    }
+
+    /// Get the rank of the type in the order of values.
+    const fn rank(&self) -> u8 {
+        match self {
+            Self::Null => 0,
+            Self::Bool(_) => 1,
+            Self::Integer(_) | Self::Float(_) => 2,
+            Self::String(_) => 3,
+            Self::Array(_) => 4,
+            Self::Map(_) => 5,
+        }
+    }
+
+    /// Look up a value by a JSON Pointer.
+    ///
+    /// Returns `None` if the pointer is malformed or the value does not exist.
+    pub fn pointer(&self, pointer: &str) -> Option<&Self> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+
+        pointer
+            .strip_prefix('/')?
+            .split('/')
+            .map(|t| t.replace("~1", "/").replace("~0", "~"))
+            .try_fold(self, |target, token| match target {
+                Self::Array(arr) => token.parse::<usize>().ok().and_then(|i| arr.get(i)),
+                Self::Map(map) => map.get(&token),
+                _ => None,
+            })
+    }
+
+    /// Look up a value by a JSON Pointer, and return a mutable reference to
+    /// it.
+    ///
+    /// Returns `None` if the pointer is malformed or the value does not exist.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Self> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+
+        pointer
+            .strip_prefix('/')?
+            .split('/')
+            .map(|t| t.replace("~1", "/").replace("~0", "~"))
+            .try_fold(self, |target, token| match target {
+                Self::Array(arr) => token
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(move |i| arr.get_mut(i)),
+                Self::Map(map) => map.get_mut(&token),
+                _ => None,
+            })
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Tracked {
+            value: self,
+            tracker: &RefCell::new(Tracker::new()),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// A value which is serialized like `Value`, reporting the integers which are
+/// serialized as strings to the tracker.
+pub(crate) struct Tracked<'a> {
+    pub(crate) value: &'a Value,
+    pub(crate) tracker: &'a RefCell<Tracker>,
+}
+
+impl Tracked<'_> {
+    fn child<'a>(&'a self, value: &'a Value) -> Tracked<'a> {
+        Tracked {
+            value,
+            tracker: self.tracker,
+        }
+    }
+}
+
+impl Serialize for Tracked<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.value {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(bool) => serializer.serialize_bool(*bool),
+            Value::Integer(int) => match (int.as_u64(), int.as_i64()) {
+                (Some(uint), _) => serializer.serialize_u64(uint),
+                (_, Some(sint)) => serializer.serialize_i64(sint),
+                // Many serializers do not support integers wider than 64 bits.
+                _ => {
+                    let str = int.to_string();
+                    self.tracker
+                        .borrow_mut()
+                        .compare(self.value, &Value::String(str.clone()));
+
+                    serializer.serialize_str(&str)
+                }
+            },
+            Value::Float(float) => serializer.serialize_f64(*float),
+            Value::String(str) => serializer.serialize_str(str),
+            Value::Array(arr) => {
+                let mut seq = serializer.serialize_seq(Some(arr.len()))?;
+                for (i, elem) in arr.iter().enumerate() {
+                    let len = self.tracker.borrow_mut().enter(&i.to_string());
+                    seq.serialize_element(&self.child(elem))?;
+                    self.tracker.borrow_mut().leave(len);
+                }
+                seq.end()
+            }
+            Value::Map(map) => {
+                let mut obj = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map {
+                    let len = self.tracker.borrow_mut().enter(key);
+                    obj.serialize_entry(key, &self.child(value))?;
+                    self.tracker.borrow_mut().leave(len);
+                }
+                obj.end()
+            }
+        }
+    }
+}
+
 #[derive(ArgEnum, Clone, Display, EnumString, EnumVariantNames)]
 #[strum(serialize_all = "lowercase", ascii_case_insensitive)]
 #[clap(rename_all = "lower")]
@@ -160,3 +899,872 @@ impl Default for Color {
         Self::Auto
     }
 }
+
+#[derive(ArgEnum, Clone, Copy, Debug, Display, EnumString, EnumVariantNames, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[clap(rename_all = "lower")]
+pub enum KeyConflict {
+    Error,
+    First,
+    Last,
+}
+
+impl KeyConflict {
+    /// Insert a key-value pair into the map according to the policy.
+    ///
+    /// If the key already exists and the policy is `Error`, the key is
+    /// returned as an error.
+    pub fn insert(
+        self,
+        map: &mut IndexMap<String, Value>,
+        key: String,
+        value: Value,
+    ) -> Result<(), String> {
+        match map.entry(key) {
+            Entry::Occupied(mut entry) => match self {
+                Self::Error => Err(entry.key().clone()),
+                Self::First => Ok(()),
+                Self::Last => {
+                    entry.insert(value);
+
+                    Ok(())
+                }
+            },
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Default for KeyConflict {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
+/// A transform of the pipeline, which is applied after parsing.
+#[derive(ArgEnum, Clone, Copy, Debug, Display, EnumString, EnumVariantNames, PartialEq)]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+#[clap(rename_all = "kebab-case")]
+pub enum Transform {
+    /// `--group-by`.
+    GroupBy,
+    /// `--ungroup`.
+    Ungroup,
+    /// `--fold-keys`.
+    FoldKeys,
+    /// `--strip-prefix`.
+    StripPrefix,
+    /// `--key-order`.
+    KeyOrder,
+    /// `--dedupe-array` and `--dedupe-array-at`.
+    DedupeArray,
+    /// `--sort-arrays` and `--sort-arrays-at`.
+    SortArrays,
+    /// `--trim-strings`.
+    TrimStrings,
+    /// `--replace`.
+    Replace,
+    /// `--replace-regex`.
+    ReplaceRegex,
+    /// `--max-string-length`.
+    MaxStringLength,
+    /// `--stringify-under`.
+    StringifyUnder,
+    /// `--coerce-bools`.
+    CoerceBools,
+    /// `--parse-radix`.
+    ParseRadix,
+    /// `--datetime-tz`.
+    DatetimeTz,
+    /// `--null-as`.
+    NullAs,
+    /// `--flatten-arrays`.
+    FlattenArrays,
+    /// `--arrays-as-maps`.
+    ArraysAsMaps,
+    /// `--maps-as-arrays`.
+    MapsAsArrays,
+    /// `--delete`.
+    Delete,
+    /// `--set`.
+    Set,
+}
+
+impl Transform {
+    /// The default order of the pipeline.
+    ///
+    /// Reshaping comes first, then keys, strings, scalars and arrays are
+    /// processed in this order. Editing by JSON Pointers comes last, so values
+    /// set by `--set` are not processed.
+    pub const DEFAULT_ORDER: [Self; 21] = [
+        Self::GroupBy,
+        Self::Ungroup,
+        Self::FoldKeys,
+        Self::StripPrefix,
+        Self::KeyOrder,
+        Self::DedupeArray,
+        Self::SortArrays,
+        Self::TrimStrings,
+        Self::Replace,
+        Self::ReplaceRegex,
+        Self::MaxStringLength,
+        Self::StringifyUnder,
+        Self::CoerceBools,
+        Self::ParseRadix,
+        Self::DatetimeTz,
+        Self::NullAs,
+        Self::FlattenArrays,
+        Self::ArraysAsMaps,
+        Self::MapsAsArrays,
+        Self::Delete,
+        Self::Set,
+    ];
+
+    /// Get the order of the pipeline which applies `first` in the given
+    /// order, followed by the others in the default order.
+    ///
+    /// Returns the transform as an error if it appears more than once in
+    /// `first`.
+    pub fn order(first: &[Self]) -> Result<Vec<Self>, Self> {
+        let mut order = Vec::with_capacity(Self::DEFAULT_ORDER.len());
+        for &transform in first {
+            if order.contains(&transform) {
+                return Err(transform);
+            }
+            order.push(transform);
+        }
+        for transform in Self::DEFAULT_ORDER {
+            if !order.contains(&transform) {
+                order.push(transform);
+            }
+        }
+
+        Ok(order)
+    }
+}
+
+/// How to handle malformed records of NDJSON, CSV and TSV.
+#[derive(ArgEnum, Clone, Copy, Debug, Display, EnumString, EnumVariantNames, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[clap(rename_all = "lower")]
+pub enum ParseErrorPolicy {
+    /// Fail the conversion.
+    Abort,
+    /// Drop the record.
+    Skip,
+    /// Replace the record with null.
+    Null,
+}
+
+impl Default for ParseErrorPolicy {
+    fn default() -> Self {
+        Self::Abort
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[clap(rename_all = "lower")]
+pub enum FloatWidth {
+    #[strum(serialize = "16")]
+    #[clap(name = "16")]
+    Half,
+    #[strum(serialize = "32")]
+    #[clap(name = "32")]
+    Single,
+    #[strum(serialize = "64")]
+    #[clap(name = "64")]
+    Double,
+    Shortest,
+}
+
+impl Default for FloatWidth {
+    fn default() -> Self {
+        Self::Shortest
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[clap(rename_all = "lower")]
+pub enum FloatFormat {
+    Shortest,
+    Go,
+    Python,
+    Javascript,
+}
+
+impl Default for FloatFormat {
+    fn default() -> Self {
+        Self::Shortest
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[clap(rename_all = "lower")]
+pub enum KeyCase {
+    Lower,
+    Upper,
+}
+
+impl KeyCase {
+    /// Convert the key to this case.
+    pub fn convert(self, key: &str) -> String {
+        match self {
+            Self::Lower => key.to_lowercase(),
+            Self::Upper => key.to_uppercase(),
+        }
+    }
+}
+
+impl Default for KeyCase {
+    fn default() -> Self {
+        Self::Lower
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[clap(rename_all = "lower")]
+pub enum SortScope {
+    All,
+    Binary,
+    Text,
+}
+
+impl SortScope {
+    /// Returns `true` if keys of maps are sorted when the output is `format`.
+    pub const fn includes(self, format: Format) -> bool {
+        match self {
+            Self::All => true,
+            Self::Binary => format.is_binary(),
+            Self::Text => !format.is_binary(),
+        }
+    }
+}
+
+impl Default for SortScope {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
+#[derive(ArgEnum, Clone, Copy, Debug, Display, EnumString, EnumVariantNames)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[clap(rename_all = "lower")]
+pub enum Encoding {
+    Base64,
+    Base32,
+    Hex,
+}
+
+impl Encoding {
+    /// Encode bytes into a string.
+    pub fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Self::Base64 => data_encoding::BASE64.encode(bytes),
+            Self::Base32 => data_encoding::BASE32.encode(bytes),
+            Self::Hex => data_encoding::HEXLOWER.encode(bytes),
+        }
+    }
+
+    /// Decode bytes from an encoded string.
+    ///
+    /// ASCII whitespace in the input is ignored. Hex digits are
+    /// case-insensitive.
+    pub fn decode(self, input: &[u8]) -> Result<Vec<u8>, data_encoding::DecodeError> {
+        let input: Vec<u8> = input
+            .iter()
+            .copied()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        match self {
+            Self::Base64 => data_encoding::BASE64.decode(&input),
+            Self::Base32 => data_encoding::BASE32.decode(&input),
+            Self::Hex => data_encoding::HEXLOWER_PERMISSIVE.decode(&input),
+        }
+    }
+}
+
+/// The number of bytes which `ByteStringMode::Hexdump` shows.
+const HEXDUMP_PREVIEW_LEN: usize = 32;
+
+#[derive(ArgEnum, Clone, Copy, Debug, Display, EnumString, EnumVariantNames, PartialEq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+#[clap(rename_all = "lower")]
+pub enum ByteStringMode {
+    Error,
+    Base64,
+    Hexdump,
+}
+
+impl ByteStringMode {
+    /// Convert the byte string into a string, or return `None` if byte
+    /// strings are an error.
+    ///
+    /// `Hexdump` writes each byte as two hex digits separated by spaces (e.g.
+    /// `a1 61 62`). Only the first 32 bytes of longer byte strings are
+    /// written, followed by the length of the whole.
+    pub fn encode(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Self::Error => None,
+            Self::Base64 => Some(Encoding::Base64.encode(bytes)),
+            Self::Hexdump => {
+                let preview = bytes
+                    .iter()
+                    .take(HEXDUMP_PREVIEW_LEN)
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if bytes.len() > HEXDUMP_PREVIEW_LEN {
+                    Some(format!("{} ... ({} bytes)", preview, bytes.len()))
+                } else {
+                    Some(preview)
+                }
+            }
+        }
+    }
+}
+
+impl Default for ByteStringMode {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
+/// The encoding of text output.
+///
+/// This is any encoding of the WHATWG Encoding Standard except `replacement`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextEncoding(&'static encoding_rs::Encoding);
+
+impl TextEncoding {
+    /// Return `true` if this is UTF-8.
+    pub fn is_utf8(self) -> bool {
+        self.0 == encoding_rs::UTF_8
+    }
+
+    /// Return `true` if this is UTF-8 or UTF-16, which have the byte order
+    /// mark.
+    pub fn has_bom(self) -> bool {
+        self.is_utf8() || self.0 == encoding_rs::UTF_16LE || self.0 == encoding_rs::UTF_16BE
+    }
+
+    /// Encode the string, prefixing the byte order mark if `bom` is `true`.
+    ///
+    /// Returns `None` if the string contains characters which this encoding
+    /// cannot represent.
+    pub fn encode(self, str: &str, bom: bool) -> Option<Vec<u8>> {
+        let str = if bom {
+            format!("\u{feff}{}", str)
+        } else {
+            str.to_string()
+        };
+        // `encoding_rs` encodes UTF-16 as UTF-8, as the standard specifies for
+        // form submission.
+        if self.0 == encoding_rs::UTF_16LE {
+            return Some(str.encode_utf16().flat_map(u16::to_le_bytes).collect());
+        }
+        if self.0 == encoding_rs::UTF_16BE {
+            return Some(str.encode_utf16().flat_map(u16::to_be_bytes).collect());
+        }
+        match self.0.encode(&str) {
+            (_, _, true) => None,
+            (bytes, _, false) => Some(bytes.into_owned()),
+        }
+    }
+}
+
+impl Default for TextEncoding {
+    fn default() -> Self {
+        Self(encoding_rs::UTF_8)
+    }
+}
+
+impl Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.name())
+    }
+}
+
+impl FromStr for TextEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match encoding_rs::Encoding::for_label(s.as_bytes()) {
+            Some(encoding) if encoding != encoding_rs::REPLACEMENT => Ok(Self(encoding)),
+            _ => Err(format!("Unknown encoding: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Depth {
+    Limited(usize),
+    Full,
+}
+
+impl Default for Depth {
+    fn default() -> Self {
+        Self::Limited(1)
+    }
+}
+
+impl FromStr for Depth {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("full") {
+            Ok(Self::Full)
+        } else {
+            s.parse().map(Self::Limited)
+        }
+    }
+}
+
+/// The timezone which datetimes are converted into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeZone {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl TimeZone {
+    /// Convert the datetime into this timezone, and format it as RFC 3339.
+    ///
+    /// UTC is written with `Z`, and fractional seconds are kept.
+    pub fn convert(self, datetime: DateTime<FixedOffset>) -> String {
+        match self {
+            Self::Utc => datetime
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            Self::Local => datetime
+                .with_timezone(&Local)
+                .to_rfc3339_opts(SecondsFormat::AutoSi, false),
+            Self::Fixed(offset) => datetime
+                .with_timezone(&offset)
+                .to_rfc3339_opts(SecondsFormat::AutoSi, false),
+        }
+    }
+}
+
+impl FromStr for TimeZone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid timezone, expected utc, local or +HH:MM: {}", s);
+
+        if s.eq_ignore_ascii_case("utc") {
+            return Ok(Self::Utc);
+        }
+        if s.eq_ignore_ascii_case("local") {
+            return Ok(Self::Local);
+        }
+
+        let (sign, offset) = match (s.strip_prefix('+'), s.strip_prefix('-')) {
+            (Some(offset), _) => (1, offset),
+            (_, Some(offset)) => (-1, offset),
+            _ => return Err(err()),
+        };
+        let (hours, minutes) = offset.split_once(':').ok_or_else(err)?;
+        let is_two_digits = |str: &str| str.len() == 2 && str.bytes().all(|b| b.is_ascii_digit());
+        if !is_two_digits(hours) || !is_two_digits(minutes) {
+            return Err(err());
+        }
+        let hours: i32 = hours.parse().map_err(|_| err())?;
+        let minutes: i32 = minutes.parse().map_err(|_| err())?;
+        if minutes >= 60 {
+            return Err(err());
+        }
+
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+            .map(Self::Fixed)
+            .ok_or_else(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+
+    use super::*;
+
+    #[test]
+    fn sort_scope() {
+        assert!(SortScope::All.includes(Format::Json));
+        assert!(SortScope::Binary.includes(Format::MessagePack));
+        assert!(!SortScope::Binary.includes(Format::Yaml));
+        assert!(SortScope::Text.includes(Format::Toml));
+        assert!(!SortScope::Text.includes(Format::Cbor));
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(Encoding::Base64.encode(b"\xa1ab"), "oWFi");
+        assert_eq!(Encoding::Base32.encode(b"\xa1ab"), "UFQWE===");
+        assert_eq!(Encoding::Hex.encode(b"\xa1ab"), "a16162");
+    }
+
+    #[test]
+    fn decode() {
+        assert_eq!(Encoding::Base64.decode(b"oW\nFi\n").unwrap(), b"\xa1ab");
+        assert_eq!(Encoding::Base32.decode(b"UFQW E===").unwrap(), b"\xa1ab");
+        assert_eq!(Encoding::Hex.decode(b"A1 61\r\n62").unwrap(), b"\xa1ab");
+        assert!(Encoding::Hex.decode(b"a16").is_err());
+    }
+
+    #[test]
+    fn byte_string_mode() {
+        assert_eq!(ByteStringMode::Error.encode(b"\xa1ab"), None);
+        assert_eq!(ByteStringMode::Base64.encode(b"\xa1ab").unwrap(), "oWFi");
+        assert_eq!(
+            ByteStringMode::Hexdump.encode(b"\xa1ab").unwrap(),
+            "a1 61 62"
+        );
+        assert_eq!(ByteStringMode::Hexdump.encode(b"").unwrap(), "");
+        assert_eq!(
+            ByteStringMode::Hexdump.encode(&[0; 33]).unwrap(),
+            format!("{} ... (33 bytes)", ["00"; 32].join(" "))
+        );
+    }
+
+    #[test]
+    fn types() {
+        let value = Value::Map(
+            vec![
+                ("a".to_string(), Value::Integer(1_u64.into())),
+                (
+                    "b".to_string(),
+                    Value::Array(vec![Value::Float(0.5), Value::Null, Value::Array(vec![])]),
+                ),
+                ("c".to_string(), Value::String("x".to_string())),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let types = Value::Map(
+            vec![
+                ("a".to_string(), Value::String("integer".to_string())),
+                (
+                    "b".to_string(),
+                    Value::Array(vec![
+                        Value::String("float".to_string()),
+                        Value::String("null".to_string()),
+                        Value::Array(vec![]),
+                    ]),
+                ),
+                ("c".to_string(), Value::String("string".to_string())),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        assert_eq!(value.types(), types);
+        assert_eq!(
+            Value::Bool(true).types(),
+            Value::String("boolean".to_string())
+        );
+    }
+
+    #[test]
+    fn extension() {
+        for format in InputFormat::value_variants()
+            .iter()
+            .map(|f| Format::from(*f))
+            .chain(
+                OutputFormat::value_variants()
+                    .iter()
+                    .map(|f| Format::from(*f)),
+            )
+        {
+            let parsed = format.extension().parse::<Format>().unwrap();
+            assert_eq!(parsed.to_string(), format.to_string());
+        }
+        // `.conf` is used by many formats.
+        assert!("conf".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn required_root() {
+        assert_eq!(Format::Toml.required_root(), Some(Root::Map));
+        assert_eq!(Format::Lines.required_root(), Some(Root::Array));
+        assert_eq!(Format::Json.required_root(), None);
+
+        let value = Value::Integer(1_u64.into());
+        assert_eq!(
+            Root::Map.wrap("a", value.clone()),
+            Value::Map(vec![("a".to_string(), value.clone())].into_iter().collect())
+        );
+        assert_eq!(
+            Root::Array.wrap("a", value.clone()),
+            Value::Array(vec![value])
+        );
+        assert_eq!(Root::Array.to_string(), "an array");
+    }
+
+    #[test]
+    fn time_zone() {
+        let datetime = DateTime::parse_from_rfc3339("2021-12-31T23:30:00.5-01:00").unwrap();
+
+        assert_eq!("UTC".parse::<TimeZone>().unwrap(), TimeZone::Utc);
+        assert_eq!(TimeZone::Utc.convert(datetime), "2022-01-01T00:30:00.500Z");
+        assert_eq!(
+            "+09:00".parse::<TimeZone>().unwrap().convert(datetime),
+            "2022-01-01T09:30:00.500+09:00"
+        );
+        assert_eq!(
+            "-00:30".parse::<TimeZone>().unwrap().convert(datetime),
+            "2022-01-01T00:00:00.500-00:30"
+        );
+        assert!("+9:00".parse::<TimeZone>().is_err());
+        assert!("+09:60".parse::<TimeZone>().is_err());
+        assert!("09:00".parse::<TimeZone>().is_err());
+        assert!("\u{3042}".parse::<TimeZone>().is_err());
+    }
+
+    #[test]
+    fn order() {
+        let hash = |value: &Value| {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        };
+        let values = [
+            Value::Null,
+            Value::Bool(false),
+            Value::Bool(true),
+            Value::Float(f64::NEG_INFINITY),
+            Value::Float(-1e40),
+            Value::Integer(i128::MIN.into()),
+            Value::Integer(i64::MIN.into()),
+            Value::Float(-1.5),
+            Value::Integer((-1_i64).into()),
+            Value::Float(-0.5),
+            Value::Integer(0_u64.into()),
+            Value::Float(-0.0),
+            Value::Float(0.0),
+            Value::Integer(9_007_199_254_740_992_u64.into()),
+            Value::Float(9_007_199_254_740_992.0),
+            Value::Integer(9_007_199_254_740_993_u64.into()),
+            Value::Integer(u64::MAX.into()),
+            Value::Float(1e20),
+            Value::Integer(u128::MAX.into()),
+            Value::Float(1e40),
+            Value::Float(f64::INFINITY),
+            Value::Float(f64::NAN),
+            Value::String(String::new()),
+            Value::String("a".to_string()),
+            Value::Array(vec![]),
+            Value::Array(vec![Value::Null]),
+            Value::Array(vec![Value::Null, Value::Null]),
+            Value::Array(vec![Value::Bool(false)]),
+            Value::Map(IndexMap::new()),
+            Value::Map(
+                vec![
+                    ("b".to_string(), Value::Null),
+                    ("a".to_string(), Value::Bool(true)),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            Value::Map(vec![("b".to_string(), Value::Null)].into_iter().collect()),
+        ];
+
+        for (i, a) in values.iter().enumerate() {
+            for (j, b) in values.iter().enumerate() {
+                assert_eq!(a.cmp(b), i.cmp(&j), "{:?} and {:?}", a, b);
+                assert_eq!(a == b, i == j);
+            }
+        }
+        let a = Value::Map(
+            vec![
+                ("a".to_string(), Value::Null),
+                ("b".to_string(), Value::Null),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let b = Value::Map(
+            vec![
+                ("b".to_string(), Value::Null),
+                ("a".to_string(), Value::Null),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_eq!(hash(&Value::Float(f64::NAN)), hash(&Value::Float(f64::NAN)));
+        assert_eq!(Value::Float(f64::NAN), Value::Float(f64::NAN));
+        assert_ne!(Value::Float(-0.0), Value::Float(0.0));
+        assert_ne!(Value::Integer(1_u64.into()), Value::Float(1.0));
+        assert!(Integer::from(-1_i64) < Integer::from(0_u64));
+    }
+
+    #[test]
+    fn integer() {
+        let int: Integer = "-170141183460469231731687303715884105729".parse().unwrap();
+        assert_eq!(int.to_string(), "-170141183460469231731687303715884105729");
+        assert_eq!(int.as_i64(), None);
+        assert!(int.is_wide());
+
+        let int: Integer = "18446744073709551615".parse().unwrap();
+        assert_eq!(int.as_u64(), Some(u64::MAX));
+        assert_eq!(int.as_i64(), None);
+        assert!(!int.is_wide());
+
+        assert_eq!(Integer::from(i128::from(i64::MIN)), Integer::from(i64::MIN));
+        assert!(!Integer::from(u128::from(u64::MAX)).is_wide());
+        assert!(Integer::from(u128::from(u64::MAX) + 1).is_wide());
+        assert!(Integer::from(i128::from(i64::MIN) - 1).is_wide());
+        assert!("1.0".parse::<Integer>().is_err());
+    }
+
+    #[test]
+    fn transform_order() {
+        assert_eq!(Transform::order(&[]).unwrap(), Transform::DEFAULT_ORDER);
+
+        let order = Transform::order(&[Transform::NullAs, Transform::GroupBy]).unwrap();
+        assert_eq!(order.len(), Transform::DEFAULT_ORDER.len());
+        assert_eq!(
+            order[..4],
+            [
+                Transform::NullAs,
+                Transform::GroupBy,
+                Transform::Ungroup,
+                Transform::FoldKeys
+            ]
+        );
+        assert_eq!(order.last(), Some(&Transform::Set));
+
+        assert_eq!(
+            Transform::order(&[Transform::Replace, Transform::Replace]),
+            Err(Transform::Replace)
+        );
+        assert_eq!(
+            "max-string-length".parse::<Transform>().unwrap(),
+            Transform::MaxStringLength
+        );
+        assert_eq!(Transform::ReplaceRegex.to_string(), "replace-regex");
+    }
+
+    #[test]
+    fn text_encoding() {
+        let encoding = |label: &str| label.parse::<TextEncoding>().unwrap();
+
+        assert_eq!(
+            encoding("utf-8").encode("a\u{3042}", false).unwrap(),
+            "a\u{3042}".as_bytes()
+        );
+        assert_eq!(
+            encoding("UTF8").encode("a", true).unwrap(),
+            b"\xef\xbb\xbfa"
+        );
+        assert_eq!(
+            encoding("utf-16le").encode("a\u{3042}", true).unwrap(),
+            b"\xff\xfea\x00\x42\x30"
+        );
+        assert_eq!(
+            encoding("utf-16be").encode("a\u{1f600}", false).unwrap(),
+            b"\x00a\xd8\x3d\xde\x00"
+        );
+        assert_eq!(
+            encoding("sjis").encode("a\u{3042}", false).unwrap(),
+            b"a\x82\xa0"
+        );
+        assert_eq!(encoding("latin1").encode("\u{e9}", false).unwrap(), b"\xe9");
+        assert!(encoding("shift_jis").encode("a", true).is_none());
+        assert!(encoding("windows-1252").encode("\u{3042}", false).is_none());
+        assert!(encoding("utf-8").has_bom());
+        assert!(encoding("utf-16le").has_bom());
+        assert!(encoding("utf-16be").has_bom());
+        assert!(!encoding("gb18030").has_bom());
+        assert!(!encoding("shift_jis").has_bom());
+        assert!("replacement".parse::<TextEncoding>().is_err());
+        assert!("utf-7".parse::<TextEncoding>().is_err());
+    }
+
+    #[test]
+    fn lossy_conversions() {
+        assert!(Format::Json.lossy_conversions(Format::Json).is_empty());
+        assert_eq!(
+            Format::Json.lossy_conversions(Format::Yaml),
+            ["YAML integers are 64-bit: wider integers will be written as strings"]
+        );
+        assert_eq!(
+            Format::Json.lossy_conversions(Format::Toml),
+            [
+                "TOML has no null: nulls will cause an error",
+                "TOML arrays must have elements of the same type: mixed arrays will cause an \
+                 error unless `--toml-stringify-mixed-arrays` is specified",
+                "TOML integers are signed 64-bit: larger integers will cause an error",
+                "TOML requires a map at the root",
+            ]
+        );
+        assert_eq!(
+            Format::Yaml.lossy_conversions(Format::Json),
+            [
+                "YAML comments will be dropped",
+                "YAML non-string map keys will cause an error",
+                "JSON cannot represent NaN or infinity: they will cause an error",
+            ]
+        );
+        assert_eq!(
+            Format::Toml.lossy_conversions(Format::Toml),
+            ["TOML comments will be dropped"]
+        );
+        assert_eq!(
+            Format::MessagePack.lossy_conversions(Format::Yaml),
+            [
+                "MessagePack byte strings will cause an error unless `--bytes` is specified",
+                "MessagePack extension types will cause an error",
+                "MessagePack non-string map keys will cause an error",
+            ]
+        );
+        assert_eq!(
+            Format::Cbor.lossy_conversions(Format::Yaml),
+            [
+                "CBOR byte strings will cause an error unless `--bytes` is specified",
+                "CBOR bignums will be read as byte strings unless `--preserve-cbor-bignum` is \
+                 specified",
+                "CBOR tags other than bignums and self-describe will cause an error",
+                "CBOR undefined will be converted to null unless `--cbor-strict-undefined` is \
+                 specified",
+                "CBOR non-string map keys will cause an error",
+                "YAML integers are 64-bit: wider integers will be written as strings",
+            ]
+        );
+    }
+
+    #[test]
+    fn serialize() {
+        let value = Value::Map(
+            vec![
+                ("a".to_string(), Value::Integer((-1_i64).into())),
+                (
+                    "b".to_string(),
+                    Value::Array(vec![Value::Null, Value::Float(0.5)]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"{"a":-1,"b":[null,0.5]}"#
+        );
+        assert_eq!(ron::to_string(&value).unwrap(), r#"{"a":-1,"b":[(),0.5]}"#);
+        assert_eq!(
+            serde_json::to_string(&Value::Integer(u128::MAX.into())).unwrap(),
+            format!(r#""{}""#, u128::MAX)
+        );
+    }
+}
@@ -0,0 +1,708 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+use std::fmt;
+
+use serde_yaml::Value as Yaml;
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::{Marker, ScanError, TScalarStyle};
+
+use crate::convert::push_pointer_token;
+use crate::float;
+use crate::lossy::Tracker;
+use crate::value::{FloatFormat, Value};
+use crate::Options;
+
+/// The key of the tag of a tagged node.
+const TAG_KEY: &str = "__tag";
+
+/// The key of the content of a tagged node.
+const VALUE_KEY: &str = "__value";
+
+/// Serialize the value to a YAML string.
+///
+/// Block collections are emitted in the same style as `serde_yaml`, so the
+/// output is the same as `serde_yaml` with the default options. Integral
+/// floats keep the fractional part (e.g. `1.0`), so they are not read back as
+/// integers.
+///
+/// This respects `yaml_indent`, `yaml_tags`, `yaml_literal_block`,
+/// `compact_scalar_arrays` and `float_format` of `options`. If `yaml_tags` is
+/// set, maps which consist of `__tag` and `__value` are written as tagged
+/// nodes. The value of `__tag` is the tag, with or without the leading `!`,
+/// and the value of `__value` is the content of the node. If
+/// `compact_scalar_arrays` is set, non-empty arrays which consist only of
+/// scalars are emitted as flow sequences (e.g. `[1, 2, 3]`).
+pub fn to_string(value: &Value, options: &Options) -> Result<String, serde_yaml::Error> {
+    to_string_tracked(value, options, &mut Tracker::new())
+}
+
+/// Serialize the value to a YAML string like `to_string`, reporting the
+/// integers which become strings to `tracker`.
+pub(crate) fn to_string_tracked(
+    value: &Value,
+    options: &Options,
+    tracker: &mut Tracker,
+) -> Result<String, serde_yaml::Error> {
+    let mut emitter = Emitter {
+        buf: String::from("---\n"),
+        indent: options.yaml_indent,
+        tags: options.yaml_tags,
+        literal_block: options.yaml_literal_block,
+        compact_scalar_arrays: options.compact_scalar_arrays,
+        float_format: options.float_format,
+        tracker,
+    };
+    emitter.emit_node(value, 0)?;
+    emitter.buf.push('\n');
+
+    Ok(emitter.buf)
+}
+
+/// Serialize the scalar to a YAML string without the document start marker.
+fn scalar_to_string(value: Yaml) -> Result<String, serde_yaml::Error> {
+    let str = serde_yaml::to_string(&value)?;
+    let str = str.strip_prefix("---\n").unwrap_or(&str);
+
+    Ok(str.strip_suffix('\n').unwrap_or(str).to_string())
+}
+
+/// Check if the string can be written as a literal block scalar.
+///
+/// The string must consist of multiple lines, and must not start with
+/// whitespace, since the indentation of the content is detected from the
+/// first line.
+fn is_literal_block(str: &str) -> bool {
+    let content = str.trim_end_matches('\n');
+
+    content.contains('\n')
+        && !content.starts_with(char::is_whitespace)
+        && !content.chars().any(|c| {
+            (c.is_control() && c != '\n' && c != '\t')
+                || matches!(c, '\u{2028}' | '\u{2029}' | '\u{feff}')
+        })
+}
+
+struct Emitter<'a> {
+    buf: String,
+    indent: usize,
+    tags: bool,
+    literal_block: bool,
+    compact_scalar_arrays: bool,
+    float_format: FloatFormat,
+    tracker: &'a mut Tracker,
+}
+
+impl Emitter<'_> {
+    /// Get the tag and the content if the value is a tagged node.
+    fn tagged<'a>(
+        &self,
+        value: &'a Value,
+    ) -> Result<Option<(String, &'a Value)>, serde_yaml::Error> {
+        let map = match value {
+            Value::Map(map) if self.tags && map.len() == 2 => map,
+            _ => return Ok(None),
+        };
+        let (tag, content) = match (map.get(TAG_KEY), map.get(VALUE_KEY)) {
+            (Some(Value::String(tag)), Some(content)) => (tag, content),
+            _ => return Ok(None),
+        };
+
+        let name = tag.strip_prefix('!').unwrap_or(tag);
+        if name.is_empty()
+            || name
+                .chars()
+                .any(|c| c.is_whitespace() || matches!(c, ',' | '[' | ']' | '{' | '}'))
+        {
+            return Err(serde::ser::Error::custom(format!("Invalid tag: {}", tag)));
+        }
+
+        Ok(Some((format!("!{}", name), content)))
+    }
+
+    /// Check if the array is emitted as a flow sequence.
+    fn is_compact(&self, arr: &[Value]) -> bool {
+        self.compact_scalar_arrays
+            && !arr.is_empty()
+            && arr
+                .iter()
+                .all(|v| !matches!(v, Value::Array(_) | Value::Map(_)))
+    }
+
+    /// Serialize the scalar, writing finite floats as `float_format` selects.
+    fn scalar(&mut self, value: &Value) -> Result<String, serde_yaml::Error> {
+        match value {
+            Value::Float(f) if f.is_finite() => float::to_string(*f, self.float_format),
+            _ => None,
+        }
+        .map_or_else(|| scalar_to_string(value.to_yaml(self.tracker)), Ok)
+    }
+
+    fn write_indent(&mut self, depth: usize) {
+        self.buf.push('\n');
+        self.buf.extend((0..depth * self.indent).map(|_| ' '));
+    }
+
+    fn emit_node(&mut self, value: &Value, depth: usize) -> Result<(), serde_yaml::Error> {
+        if let Some((tag, content)) = self.tagged(value)? {
+            self.buf.push_str(&tag);
+            match content {
+                Value::Array(arr) if !arr.is_empty() && !self.is_compact(arr) => {
+                    self.write_indent(depth);
+                }
+                Value::Map(map) if !map.is_empty() => self.write_indent(depth),
+                _ => self.buf.push(' '),
+            }
+
+            let len = self.tracker.enter(VALUE_KEY);
+            self.emit_node(content, depth)?;
+            self.tracker.leave(len);
+
+            return Ok(());
+        }
+
+        match value {
+            Value::Array(arr) if arr.is_empty() => self.buf.push_str("[]"),
+            Value::Array(arr) if self.is_compact(arr) => {
+                let elems = arr
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        let len = self.tracker.enter(&i.to_string());
+                        let str = self.scalar(v);
+                        self.tracker.leave(len);
+
+                        str
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.buf.push('[');
+                self.buf.push_str(&elems.join(", "));
+                self.buf.push(']');
+            }
+            Value::Array(arr) => {
+                for (i, v) in arr.iter().enumerate() {
+                    if i > 0 {
+                        self.write_indent(depth);
+                    }
+                    self.buf.push('-');
+                    let len = self.tracker.enter(&i.to_string());
+                    self.emit_val(v, true, depth)?;
+                    self.tracker.leave(len);
+                }
+            }
+            Value::Map(map) if map.is_empty() => self.buf.push_str("{}"),
+            Value::Map(map) => {
+                for (i, (k, v)) in map.iter().enumerate() {
+                    if i > 0 {
+                        self.write_indent(depth);
+                    }
+                    self.buf
+                        .push_str(&scalar_to_string(Yaml::String(k.clone()))?);
+                    self.buf.push(':');
+                    let len = self.tracker.enter(k);
+                    self.emit_val(v, false, depth)?;
+                    self.tracker.leave(len);
+                }
+            }
+            Value::String(str) if self.literal_block && is_literal_block(str) => {
+                self.write_literal_block(str, depth.max(1));
+            }
+            _ => {
+                let str = self.scalar(value)?;
+                self.buf.push_str(&str);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the string as a literal block scalar whose content is indented
+    /// by `depth` levels.
+    fn write_literal_block(&mut self, str: &str, depth: usize) {
+        let content = str.trim_end_matches('\n');
+        let chomping = match str.len() - content.len() {
+            0 => "-",
+            1 => "",
+            _ => "+",
+        };
+        self.buf.push('|');
+        self.buf.push_str(chomping);
+        for line in content.split('\n') {
+            if line.is_empty() {
+                self.buf.push('\n');
+            } else {
+                self.write_indent(depth);
+                self.buf.push_str(line);
+            }
+        }
+        // The last line break is written by the following node or the end of
+        // the document.
+        self.buf
+            .extend((1..str.len() - content.len()).map(|_| '\n'));
+    }
+
+    /// Emit the value of an entry of a collection at `depth`, which follows
+    /// `-` if `inline` is `true`, otherwise `:`.
+    fn emit_val(
+        &mut self,
+        value: &Value,
+        inline: bool,
+        depth: usize,
+    ) -> Result<(), serde_yaml::Error> {
+        match value {
+            _ if self.tagged(value)?.is_some() => self.buf.push(' '),
+            Value::Array(arr) if arr.is_empty() || self.is_compact(arr) => self.buf.push(' '),
+            Value::Map(map) if map.is_empty() => self.buf.push(' '),
+            // Put the collection on the same line as `-` only if the content
+            // can be aligned with the indentation.
+            Value::Array(_) | Value::Map(_) if inline && self.indent >= 2 => {
+                self.buf.extend((1..self.indent).map(|_| ' '));
+            }
+            Value::Array(_) | Value::Map(_) => self.write_indent(depth + 1),
+            _ => self.buf.push(' '),
+        }
+
+        self.emit_node(value, depth + 1)
+    }
+}
+
+/// The plain scalars which YAML 1.1 reads as booleans, but which are read as
+/// strings.
+const YAML11_BOOLS: [&str; 20] = [
+    "y", "Y", "yes", "Yes", "YES", "n", "N", "no", "No", "NO", "True", "TRUE", "False", "FALSE",
+    "on", "On", "ON", "off", "Off", "OFF",
+];
+
+/// A plain scalar which is a boolean in YAML 1.1, but is read as a string.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AmbiguousBool {
+    /// The JSON Pointer to the node, or to the entry if the scalar is a key.
+    pub pointer: String,
+    /// The line number of the scalar, starting from 1.
+    pub line: usize,
+    /// The scalar as written.
+    pub scalar: String,
+    /// `true` if the scalar is a key of a map.
+    pub is_key: bool,
+}
+
+impl fmt::Display for AmbiguousBool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "(root)"
+        } else {
+            &self.pointer
+        };
+        let kind = if self.is_key { "key" } else { "scalar" };
+
+        write!(
+            f,
+            "{}: the bare {} `{}` at line {} is a boolean in YAML 1.1, but is read as a string",
+            pointer, kind, self.scalar, self.line
+        )
+    }
+}
+
+/// Find the plain scalars which YAML 1.1 reads as booleans, such as `no` and
+/// `on`.
+///
+/// They are strings in the YAML read by this crate, but other parsers may
+/// read them as booleans. Quoted and tagged scalars are not reported. The
+/// pointers are relative to the root of each document.
+pub fn find_ambiguous_bools(input: &str) -> Result<Vec<AmbiguousBool>, ScanError> {
+    let mut finder = BoolFinder::default();
+    Parser::new(input.chars()).load(&mut finder, true)?;
+
+    Ok(finder.found)
+}
+
+/// What the next node in a collection is.
+enum Next {
+    /// The element at the index of a sequence.
+    Index(usize),
+    /// A key of a mapping.
+    Key,
+    /// The value of the key of a mapping.
+    Value(String),
+}
+
+/// A sequence or a mapping which is being parsed.
+struct Frame {
+    /// The length of the pointer to the collection.
+    len: usize,
+    next: Next,
+}
+
+#[derive(Default)]
+struct BoolFinder {
+    pointer: String,
+    frames: Vec<Frame>,
+    found: Vec<AmbiguousBool>,
+}
+
+impl BoolFinder {
+    /// Append the token of the next node to the pointer, and advance the
+    /// current collection.
+    fn enter(&mut self) {
+        let frame = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => return,
+        };
+        match frame.next {
+            Next::Index(ref mut i) => {
+                push_pointer_token(&mut self.pointer, &i.to_string());
+                *i += 1;
+            }
+            // A complex key, which has no pointer.
+            Next::Key => frame.next = Next::Value(String::new()),
+            Next::Value(ref key) => {
+                push_pointer_token(&mut self.pointer, key);
+                frame.next = Next::Key;
+            }
+        }
+    }
+}
+
+impl MarkedEventReceiver for BoolFinder {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::Scalar(scalar, style, _, tag) => {
+                let len = self.pointer.len();
+                let is_key = self
+                    .frames
+                    .last()
+                    .map_or(false, |f| matches!(f.next, Next::Key));
+                if is_key {
+                    push_pointer_token(&mut self.pointer, &scalar);
+                } else {
+                    self.enter();
+                }
+                if style == TScalarStyle::Plain
+                    && tag.is_none()
+                    && YAML11_BOOLS.contains(&scalar.as_str())
+                {
+                    self.found.push(AmbiguousBool {
+                        pointer: self.pointer.clone(),
+                        line: mark.line(),
+                        scalar: scalar.clone(),
+                        is_key,
+                    });
+                }
+                self.pointer.truncate(len);
+
+                if let (true, Some(frame)) = (is_key, self.frames.last_mut()) {
+                    frame.next = Next::Value(scalar);
+                }
+            }
+            Event::Alias(_) => {
+                let len = self.pointer.len();
+                self.enter();
+                self.pointer.truncate(len);
+            }
+            Event::SequenceStart(_) | Event::MappingStart(_) => {
+                let len = self.pointer.len();
+                self.enter();
+                let next = match event {
+                    Event::SequenceStart(_) => Next::Index(0),
+                    _ => Next::Key,
+                };
+                self.frames.push(Frame { len, next });
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                if let Some(frame) = self.frames.pop() {
+                    self.pointer.truncate(frame.len);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Value {
+        Value::Map(
+            vec![
+                ("a".to_string(), Value::String("b: c".to_string())),
+                (
+                    "d".to_string(),
+                    Value::Array(vec![
+                        Value::Integer(1_u64.into()),
+                        Value::Map(
+                            vec![
+                                ("e".to_string(), Value::Null),
+                                ("f".to_string(), Value::Array(vec![])),
+                            ]
+                            .into_iter()
+                            .collect(),
+                        ),
+                        Value::Array(vec![Value::Bool(true), Value::Float(1.5)]),
+                    ]),
+                ),
+                (
+                    "g".to_string(),
+                    Value::Map(
+                        vec![("h".to_string(), Value::Map(Default::default()))]
+                            .into_iter()
+                            .collect(),
+                    ),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        )
+    }
+
+    #[test]
+    fn same_as_serde_yaml() {
+        assert_eq!(
+            to_string(&sample(), &Options::default()).unwrap(),
+            serde_yaml::to_string(&Yaml::from(sample())).unwrap()
+        );
+    }
+
+    #[test]
+    fn indent() {
+        assert_eq!(
+            to_string(&sample(), &Options::new().yaml_indent(4)).unwrap(),
+            concat!(
+                "---\n",
+                "a: \"b: c\"\n",
+                "d:\n",
+                "    - 1\n",
+                "    -   e: ~\n",
+                "        f: []\n",
+                "    -   - true\n",
+                "        - 1.5\n",
+                "g:\n",
+                "    h: {}\n"
+            )
+        );
+
+        for indent in 1..=8 {
+            assert_eq!(
+                serde_yaml::from_str::<Yaml>(
+                    &to_string(&sample(), &Options::new().yaml_indent(indent)).unwrap()
+                )
+                .unwrap(),
+                Yaml::from(sample())
+            );
+        }
+    }
+
+    #[test]
+    fn compact_scalar_arrays() {
+        assert_eq!(
+            to_string(&sample(), &Options::new().compact_scalar_arrays(true)).unwrap(),
+            concat!(
+                "---\n",
+                "a: \"b: c\"\n",
+                "d:\n",
+                "  - 1\n",
+                "  - e: ~\n",
+                "    f: []\n",
+                "  - [true, 1.5]\n",
+                "g:\n",
+                "  h: {}\n"
+            )
+        );
+        let value = Value::Array(vec![
+            Value::String("a, b".to_string()),
+            Value::String("[c]".to_string()),
+            Value::Null,
+            Value::Float(f64::NAN),
+        ]);
+        let yaml = to_string(&value, &Options::new().compact_scalar_arrays(true)).unwrap();
+        assert_eq!(yaml, "---\n[\"a, b\", \"[c]\", ~, .nan]\n");
+        assert_eq!(
+            serde_yaml::from_str::<Yaml>(&yaml).unwrap()[0],
+            Yaml::String("a, b".to_string())
+        );
+    }
+
+    #[test]
+    fn literal_block() {
+        let options = Options::new().yaml_literal_block(true);
+        let value = Value::Map(
+            vec![
+                ("a".to_string(), Value::String("b\n\n  c\n".to_string())),
+                (
+                    "d".to_string(),
+                    Value::Array(vec![
+                        Value::String("e\nf".to_string()),
+                        Value::String("g\nh\n\n".to_string()),
+                        Value::String("i\n".to_string()),
+                        Value::String(" j\nk".to_string()),
+                    ]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let yaml = to_string(&value, &options).unwrap();
+
+        assert_eq!(
+            yaml,
+            concat!(
+                "---\n",
+                "a: |\n",
+                "  b\n",
+                "\n",
+                "    c\n",
+                "d:\n",
+                "  - |-\n",
+                "    e\n",
+                "    f\n",
+                "  - |+\n",
+                "    g\n",
+                "    h\n",
+                "\n",
+                "  - \"i\\n\"\n",
+                "  - \" j\\nk\"\n"
+            )
+        );
+        assert_eq!(
+            serde_yaml::from_str::<Yaml>(&yaml).unwrap(),
+            Yaml::from(value)
+        );
+        assert_eq!(
+            to_string(&Value::String("a\nb".to_string()), &options).unwrap(),
+            "---\n|-\n  a\n  b\n"
+        );
+    }
+
+    #[test]
+    fn tags() {
+        let tagged = |tag: &str, content: Value| {
+            Value::Map(
+                vec![
+                    ("__tag".to_string(), Value::String(tag.to_string())),
+                    ("__value".to_string(), content),
+                ]
+                .into_iter()
+                .collect(),
+            )
+        };
+        let value = Value::Map(
+            vec![
+                (
+                    "a".to_string(),
+                    tagged("Ref", Value::String("b".to_string())),
+                ),
+                (
+                    "c".to_string(),
+                    Value::Array(vec![tagged(
+                        "!Point",
+                        Value::Map(
+                            vec![
+                                ("x".to_string(), Value::Integer(1_u64.into())),
+                                ("y".to_string(), Value::Integer(2_u64.into())),
+                            ]
+                            .into_iter()
+                            .collect(),
+                        ),
+                    )]),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        assert_eq!(
+            to_string(&value, &Options::new().yaml_tags(true)).unwrap(),
+            concat!(
+                "---\n",
+                "a: !Ref b\n",
+                "c:\n",
+                "  - !Point\n",
+                "    x: 1\n",
+                "    y: 2\n"
+            )
+        );
+        assert_eq!(
+            to_string(&value, &Options::default()).unwrap(),
+            serde_yaml::to_string(&Yaml::from(value)).unwrap()
+        );
+        assert!(to_string(&tagged("a b", Value::Null), &Options::new().yaml_tags(true)).is_err());
+    }
+
+    #[test]
+    fn float_format() {
+        let value = Value::Array(vec![Value::Float(1e16), Value::Float(f64::INFINITY)]);
+
+        assert_eq!(
+            to_string(&value, &Options::new().float_format(FloatFormat::Python)).unwrap(),
+            "---\n- 1e+16\n- .inf\n"
+        );
+        assert_eq!(
+            to_string(
+                &value,
+                &Options::new()
+                    .float_format(FloatFormat::Go)
+                    .compact_scalar_arrays(true)
+            )
+            .unwrap(),
+            "---\n[1e+16, .inf]\n"
+        );
+    }
+
+    #[test]
+    fn integral_float() {
+        let value = Value::Array(vec![
+            Value::Float(1.0),
+            Value::Float(-2.0),
+            Value::Integer(1_u64.into()),
+        ]);
+        let expected = "---\n- 1.0\n- -2.0\n- 1\n";
+
+        assert_eq!(
+            serde_yaml::to_string(&Yaml::from(value.clone())).unwrap(),
+            expected
+        );
+        assert_eq!(to_string(&value, &Options::default()).unwrap(), expected);
+        assert_eq!(
+            Value::try_from(serde_yaml::from_str::<Yaml>(expected).unwrap()).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn ambiguous_bools() {
+        let input = "on:\n  push: [main]\nlist:\n  - no\n  - 'no'\n  - !!str off\n  - {a/b: Yes}\n\
+                     c: true\nd: TRUE\n---\nN\n";
+        let found = find_ambiguous_bools(input).unwrap();
+
+        assert_eq!(
+            found
+                .iter()
+                .map(|f| (f.pointer.as_str(), f.line, f.scalar.as_str(), f.is_key))
+                .collect::<Vec<_>>(),
+            vec![
+                ("/on", 1, "on", true),
+                ("/list/0", 4, "no", false),
+                ("/list/3/a~1b", 7, "Yes", false),
+                ("/d", 9, "TRUE", false),
+                ("", 11, "N", false),
+            ]
+        );
+        assert_eq!(
+            found[1].to_string(),
+            "/list/0: the bare scalar `no` at line 4 is a boolean in YAML 1.1, but is read as a \
+             string"
+        );
+        assert!(find_ambiguous_bools("a: [").is_err());
+
+        // Only the lowercase forms are booleans in `serde_yaml`.
+        for scalar in YAML11_BOOLS {
+            assert_eq!(
+                serde_yaml::from_str::<Yaml>(scalar).unwrap(),
+                Yaml::String(scalar.to_string())
+            );
+        }
+    }
+}
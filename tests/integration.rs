@@ -348,3 +348,2279 @@ fn yaml2yaml() {
         .assert()
         .stdout(predicate::eq(include_str!("resource/sample.yaml")));
 }
+
+#[test]
+fn cbor_undefined() {
+    command()
+        .arg("-f")
+        .arg("cbor")
+        .arg("-t")
+        .arg("json")
+        .write_stdin(b"\x82\xf6\xf7" as &[u8])
+        .assert()
+        .success()
+        .stdout(predicate::eq("[null,null]\n"));
+    command()
+        .arg("-f")
+        .arg("cbor")
+        .arg("-t")
+        .arg("json")
+        .arg("--cbor-strict-undefined")
+        .write_stdin(b"\x82\xf6\xf7" as &[u8])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "CBOR undefined at byte offset 2 cannot be converted",
+        ));
+    command()
+        .arg("-f")
+        .arg("cbor")
+        .arg("-t")
+        .arg("json")
+        .write_stdin(b"\x81\xf0" as &[u8])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn detect_only() {
+    command()
+        .arg("--detect-only")
+        .arg("resource/sample.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::eq("YAML\n"));
+    command()
+        .arg("--detect-only")
+        .arg("resource/sample.json")
+        .arg("resource/sample.toml")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "resource/sample.json: JSON\nresource/sample.toml: TOML\n",
+        ));
+    command()
+        .arg("--detect-only")
+        .write_stdin(include_bytes!("resource/sample.msgpack") as &[u8])
+        .assert()
+        .success()
+        .stdout(predicate::eq("MessagePack\n"));
+    command()
+        .arg("--detect-only")
+        .write_stdin("{}")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("Unable to determine input format"));
+}
+
+#[test]
+#[cfg(unix)]
+fn detect_binary_input() {
+    command()
+        .arg("-t")
+        .arg("json")
+        .write_stdin(include_bytes!("resource/sample.cbor") as &[u8])
+        .assert()
+        .stdout(predicate::eq(include_str!("resource/sample.json")));
+    command()
+        .arg("-t")
+        .arg("json")
+        .write_stdin(include_bytes!("resource/sample.msgpack") as &[u8])
+        .assert()
+        .stdout(predicate::eq(include_str!("resource/sample.json")));
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn parquet() {
+    let output = command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("parquet")
+        .write_stdin(r#"[{"a": 1, "b": {"c": [true]}}, {"a": 2.5, "b": null}]"#)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    command()
+        .arg("-t")
+        .arg("json")
+        .write_stdin(output.stdout)
+        .assert()
+        .stdout(predicate::eq(
+            "[{\"a\":1.0,\"b\":{\"c\":[true]}},{\"a\":2.5,\"b\":null}]\n",
+        ));
+
+    command()
+        .arg("-t")
+        .arg("json")
+        .write_stdin("PAR1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read a Parquet file"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("parquet")
+        .write_stdin("[1]")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The element at index 0 is not a map",
+        ));
+}
+
+#[cfg(not(feature = "parquet"))]
+#[test]
+fn parquet_unavailable() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("parquet")
+        .write_stdin("[]")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Parquet requires dsconv built with the `parquet` feature",
+        ));
+    command()
+        .arg("-t")
+        .arg("json")
+        .write_stdin("PAR1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Parquet requires dsconv built with the `parquet` feature",
+        ));
+}
+
+#[test]
+fn sort_keys() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--sort-keys")
+        .write_stdin(r#"{"b":{"d":0,"c":0},"a":0}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":0,\"b\":{\"c\":0,\"d\":0}}\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--sort-keys=binary")
+        .write_stdin(r#"{"b":0,"a":0}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"b\":0,\"a\":0}\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("messagepack")
+        .arg("--sort-keys=binary")
+        .write_stdin(r#"{"b":0,"a":0}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"\x97\xcc\x82\xcc\xa1a\x00\xcc\xa1b\x00" as &[u8],
+        ));
+}
+
+#[test]
+fn deterministic() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--deterministic")
+        .write_stdin(r#"{"b":[2,1],"a":{"d":0.5,"c":null}}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "---\na:\n  c: ~\n  d: 0.5\nb:\n  - 2\n  - 1\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--deterministic")
+        .arg("--sort-keys=binary")
+        .write_stdin(r#"{"b":0,"a":0}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"b\":0,\"a\":0}\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--deterministic")
+        .write_stdin(r#"{"b":1.0e0,"a":[0.5,1E21]}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(r#"{"a":[0.5,1e+21],"b":1}"#));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--deterministic")
+        .arg("--pretty=false")
+        .arg("--float-format")
+        .arg("python")
+        .write_stdin(r#"{"b":1.0,"a":0}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":0,\"b\":1.0}\n"));
+}
+
+#[test]
+fn toml_literal_strings() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("toml")
+        .arg("--toml-literal-strings")
+        .write_stdin(r#"{"path":"C:\\Windows"}"#)
+        .assert()
+        .stdout(predicate::str::contains(r"path = 'C:\Windows'"));
+}
+
+#[test]
+fn assert_equal_to() {
+    command()
+        .arg("-f")
+        .arg("toml")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--assert-equal-to")
+        .arg("resource/sample.yaml")
+        .arg("resource/sample.toml")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+    command()
+        .arg("-f")
+        .arg("toml")
+        .arg("-t")
+        .arg("json")
+        .arg("--assert-equal-to")
+        .arg("resource/sample.yaml")
+        .arg("resource/sample.toml")
+        .assert()
+        .failure();
+}
+
+#[test]
+#[cfg(unix)]
+fn jsonc2json() {
+    command()
+        .arg("-f")
+        .arg("jsonc")
+        .arg("-t")
+        .arg("json")
+        .write_stdin("{\n  // comment\n  \"key\": /* comment */ \"value\",\n}\n")
+        .assert()
+        .stdout(predicate::eq(include_str!("resource/sample.json")));
+}
+
+#[test]
+fn json2jsonc() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("jsonc")
+        .arg("--header-comment")
+        .arg("Generated by dsconv")
+        .write_stdin(r#"{"key":"value"}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "// Generated by dsconv\n{\"key\":\"value\"}\n",
+        ));
+}
+
+#[test]
+fn explain() {
+    command()
+        .arg("-f")
+        .arg("yaml")
+        .arg("-t")
+        .arg("toml")
+        .arg("--explain")
+        .arg("resource/sample.yaml")
+        .assert()
+        .success()
+        .stdout(predicate::eq(include_str!("resource/sample.toml")))
+        .stderr(predicate::str::contains("TOML has no null"));
+}
+
+#[test]
+fn collect() {
+    command()
+        .arg("-f")
+        .arg("yaml")
+        .arg("-t")
+        .arg("json")
+        .arg("--collect")
+        .write_stdin("---\na: 1\n---\nb: 2\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("[{\"a\":1},{\"b\":2}]\n"));
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--collect")
+        .arg("resource/sample.yaml")
+        .arg("resource/sample.toml")
+        .assert()
+        .success()
+        .stdout(predicate::eq("[{\"key\":\"value\"},{\"key\":\"value\"}]\n"));
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("resource/sample.yaml")
+        .arg("resource/sample.toml")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn concat() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let (a, b) = (dir.join("concat_a.json"), dir.join("concat_b.yaml"));
+    std::fs::write(&a, "[1,[2]]").unwrap();
+    std::fs::write(&b, "- 3\n").unwrap();
+
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--concat")
+        .arg(&a)
+        .arg(&b)
+        .assert()
+        .success()
+        .stdout(predicate::eq("[1,[2],3]\n"));
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--concat")
+        .arg(&a)
+        .arg("resource/sample.json")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the root of resource/sample.json is map",
+        ));
+}
+
+#[test]
+fn unknown_format() {
+    command()
+        .arg("-f")
+        .arg("xml")
+        .arg("-t")
+        .arg("json")
+        .arg("resource/sample.json")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("Unknown input format: xml"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("resource/sample.json")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "Unable to determine output format",
+        ));
+}
+
+#[test]
+fn encode_output() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("cbor")
+        .arg("--encode-output")
+        .arg("hex")
+        .arg("resource/sample.json")
+        .assert()
+        .success()
+        .stdout(predicate::eq("a1636b65796576616c7565\n"));
+}
+
+#[test]
+fn yaml_tags() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--yaml-tags")
+        .write_stdin(r#"{"a":{"__tag":"Ref","__value":"b"}}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("---\na: !Ref b\n"));
+}
+
+#[test]
+fn header_comment() {
+    command()
+        .arg("-t")
+        .arg("toml")
+        .arg("--header-comment")
+        .arg("Generated by dsconv.\n\nDo not edit.")
+        .arg("resource/sample.json")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "# Generated by dsconv.\n#\n# Do not edit.\nkey = \"value\"\n",
+        ));
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--header-comment")
+        .arg("Generated by dsconv.")
+        .arg("resource/sample.json")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("JSON does not support comments"));
+}
+
+#[test]
+#[cfg(unix)]
+fn pipe_through() {
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--pipe-through")
+        .arg("tr a-z A-Z")
+        .arg("resource/sample.json")
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"KEY\":\"VALUE\"}\n"));
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--pipe-through")
+        .arg("exit 1")
+        .arg("resource/sample.json")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("`exit 1` exited with"));
+}
+
+#[test]
+#[cfg(unix)]
+fn decode_input() {
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--decode-input")
+        .arg("base64")
+        .write_stdin("oWNrZXlldm\nFsdWU=\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq(include_str!("resource/sample.json")));
+}
+
+#[test]
+fn lines() {
+    command()
+        .arg("-f")
+        .arg("lines")
+        .arg("-t")
+        .arg("json")
+        .write_stdin("a\nb\n\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("[\"a\",\"b\"]\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("lines")
+        .write_stdin("[\"a\",\"b\"]")
+        .assert()
+        .success()
+        .stdout(predicate::eq("a\nb\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("lines")
+        .write_stdin("[\"a\",1]")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn dedupe_array() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--audit")
+        .arg("--dedupe-array-at")
+        .arg("/a")
+        .write_stdin(r#"{"a":[1,2,1],"b":[1,1]}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":[1,2],\"b\":[1,1]}\n"))
+        .stderr(predicate::eq(
+            "dedupe-array-at: removed 1 elements from /a\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--dedupe-array")
+        .write_stdin(r#"{"a":[1,2,1],"b":[{"c":0},{"c":0}]}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":[1,2],\"b\":[{\"c\":0}]}\n"));
+    // NaN is equal to itself, but `-0.0` and `0.0`, and `1` and `1.0` differ.
+    command()
+        .arg("-f")
+        .arg("yaml")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--dedupe-array")
+        .write_stdin("[.nan, .nan, -0.0, 0.0, 1, 1.0]")
+        .assert()
+        .success()
+        .stdout(predicate::eq("---\n- .nan\n- -0.0\n- 0.0\n- 1\n- 1.0\n"));
+}
+
+#[test]
+fn lowercase_keys() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--lowercase-keys")
+        .arg("--on-key-conflict")
+        .arg("last")
+        .write_stdin(r#"{"Key":1,"KEY":2,"Map":{"A":3}}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"key\":2,\"map\":{\"a\":3}}\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--uppercase-keys")
+        .write_stdin(r#"{"key":1,"Map":{"a":3}}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"KEY\":1,\"MAP\":{\"A\":3}}\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--lowercase-keys")
+        .arg("--uppercase-keys")
+        .write_stdin("{}")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn duplicate_keys() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .write_stdin(r#"{"a":1,"a":2}"#)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("The key is duplicated: a"));
+    command()
+        .arg("-f")
+        .arg("yaml")
+        .arg("-t")
+        .arg("json")
+        .arg("--on-key-conflict")
+        .arg("first")
+        .write_stdin("a: 1\na: 2\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":1}\n"));
+    command()
+        .arg("-f")
+        .arg("csv")
+        .arg("-t")
+        .arg("json")
+        .arg("--on-key-conflict")
+        .arg("last")
+        .write_stdin("a,a\n1,2\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("[{\"a\":\"2\"}]\n"));
+}
+
+#[test]
+fn sort_arrays() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--audit")
+        .arg("--sort-arrays-at")
+        .arg("/a")
+        .write_stdin(r#"{"a":["b",{"c":0},2,null,1.5],"b":[2,1]}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "{\"a\":[null,1.5,2,\"b\",{\"c\":0}],\"b\":[2,1]}\n",
+        ))
+        .stderr(predicate::eq("sort-arrays-at: reordered 1 arrays at /a\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--sort-arrays")
+        .write_stdin(r#"[[2,1],[1,3]]"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("[[1,2],[1,3]]\n"));
+}
+
+#[test]
+fn replace() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--audit")
+        .arg("--replace")
+        .arg("a")
+        .arg("o")
+        .arg("--replace-regex")
+        .arg("([a-z]+)-([0-9]+)")
+        .arg("$2-$1")
+        .arg("--replace-keys")
+        .write_stdin(r#"{"name":"banana-1","tags":["ab-2"]}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "{\"nome\":\"1-bonono\",\"togs\":[\"2-ob\"]}\n",
+        ))
+        .stderr(predicate::eq(
+            "replace: replaced 6 occurrences of \"a\"\n\
+             replace-regex: replaced 2 matches of \"([a-z]+)-([0-9]+)\"\n",
+        ));
+}
+
+#[test]
+fn audit() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--audit")
+        .arg("--max-string-length")
+        .arg("1")
+        .arg("resource/sample.json")
+        .assert()
+        .success()
+        .stderr(predicate::eq("max-string-length: truncated 1 strings\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--audit")
+        .arg("--quiet")
+        .arg("--max-string-length")
+        .arg("1")
+        .arg("resource/sample.json")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn yaml_integral_float() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .write_stdin("[1.0, 1]")
+        .assert()
+        .success()
+        .stdout(predicate::eq("---\n- 1.0\n- 1\n"));
+    command()
+        .arg("-f")
+        .arg("yaml")
+        .arg("-t")
+        .arg("json")
+        .write_stdin("---\n- 1.0\n- 1\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("[1.0,1]\n"));
+}
+
+#[test]
+fn inspect() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("cbor")
+        .arg("--inspect")
+        .arg("--color")
+        .arg("never")
+        .arg("resource/sample.json")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            include_bytes!("resource/sample.cbor") as &[u8]
+        ))
+        .stderr(predicate::eq("{\n  \"key\": \"value\"\n}\n"));
+}
+
+#[test]
+fn max_file_size() {
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--max-file-size")
+        .arg("16")
+        .arg("resource/sample.json")
+        .assert()
+        .success();
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--max-file-size")
+        .arg("15")
+        .arg("resource/sample.json")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "exceeds the maximum size of 15 bytes",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--max-file-size")
+        .arg("4")
+        .write_stdin("[1, 2]")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn infer_schema() {
+    command()
+        .arg("--infer-schema")
+        .arg("resource/sample.json")
+        .assert()
+        .success()
+        .stdout(predicate::eq(concat!(
+            r#"{"$schema":"http://json-schema.org/draft-07/schema#","#,
+            r#""type":"object","properties":{"key":{"type":"string"}},"required":["key"]}"#,
+            "\n"
+        )));
+}
+
+#[test]
+fn stats() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("--stats")
+        .write_stdin(r#"{"a":[1,2],"b":"xyz"}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(concat!(
+            r#"{"nodes":5,"depth":2,"types":{"null":0,"boolean":0,"integer":2,"float":0,"#,
+            r#""string":1,"array":1,"map":1},"array_length":{"min":2,"max":2,"avg":2.0},"#,
+            r#""unique_keys":2,"string_bytes":3}"#,
+            "\n"
+        )));
+}
+
+#[test]
+fn size_report() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("--size-report")
+        .write_stdin(r#"{"a":1}"#)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "FORMAT           BYTES\nCBOR                 4\nMessagePack ",
+        ));
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--size-report")
+        .arg("resource/sample.json")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn minify_keys() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("--minify-keys")
+        .write_stdin(r#"[{"message":1},{"message":2},{"message":3}]"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(concat!(
+            "KEYS                  3\n",
+            "DISTINCT              1\n",
+            "KEY BYTES            24\n",
+            "SHARED BYTES         17\n",
+            "SAVED BYTES           7\n"
+        )));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("--minify-keys")
+        .arg("--size-report")
+        .write_stdin("{}")
+        .assert()
+        .failure();
+}
+
+#[cfg(not(feature = "interactive"))]
+#[test]
+fn interactive_unavailable() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("--interactive")
+        .write_stdin("{}")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "requires dsconv built with the `interactive` feature",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--interactive")
+        .write_stdin("{}")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn json2ron() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("ron")
+        .arg("resource/sample.json")
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"key\":\"value\"}\n"));
+}
+
+#[test]
+fn null_as() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("toml")
+        .arg("--null-as")
+        .arg("")
+        .write_stdin(r#"{"a":null}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("a = \"\"\n"));
+}
+
+#[test]
+fn toml_source_order() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("toml")
+        .write_stdin(r#"{"z":{"a":1},"y":[{"b":1}],"x":1,"w":{"c":1}}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "x = 1\n\n[z]\na = 1\n\n[[y]]\nb = 1\n\n[w]\nc = 1\n",
+        ));
+}
+
+#[test]
+fn toml_array_of_tables_round_trip() {
+    let json = command()
+        .arg("-t")
+        .arg("json")
+        .arg("resource/array_of_tables.toml")
+        .output()
+        .unwrap()
+        .stdout;
+
+    for pretty in ["false", "true"] {
+        let toml = command()
+            .arg("-f")
+            .arg("json")
+            .arg("-t")
+            .arg("toml")
+            .arg(format!("--pretty={}", pretty))
+            .write_stdin(json.clone())
+            .output()
+            .unwrap()
+            .stdout;
+        let toml = String::from_utf8(toml).unwrap();
+        assert_eq!(toml.matches("[[servers]]\n").count(), 2, "{}", toml);
+        assert_eq!(toml.matches("[[servers.disks]]\n").count(), 2, "{}", toml);
+        assert!(toml.contains("[servers.meta]\n"), "{}", toml);
+
+        command()
+            .arg("-f")
+            .arg("toml")
+            .arg("-t")
+            .arg("json")
+            .write_stdin(toml)
+            .assert()
+            .success()
+            .stdout(predicate::eq(json.as_slice()));
+    }
+}
+
+#[test]
+fn empty_collections() {
+    for empty in ["{}", "[]"] {
+        for format in [
+            "cbor",
+            "json",
+            "lines",
+            "messagepack",
+            "ron",
+            "toml",
+            "yaml",
+        ] {
+            let output = command()
+                .arg("-f")
+                .arg("json")
+                .arg("-t")
+                .arg(format)
+                .write_stdin(empty)
+                .output()
+                .unwrap();
+            if !output.status.success() {
+                assert!(
+                    matches!((empty, format), ("{}", "lines") | ("[]", "toml")),
+                    "{} to {} failed",
+                    empty,
+                    format
+                );
+                assert!(!output.stderr.is_empty());
+                continue;
+            }
+
+            command()
+                .arg("-f")
+                .arg(format)
+                .arg("-t")
+                .arg("json")
+                .write_stdin(output.stdout)
+                .assert()
+                .success()
+                .stdout(predicate::eq(format!("{}\n", empty).as_str()));
+        }
+    }
+}
+
+#[test]
+fn openstep2json() {
+    command()
+        .arg("-f")
+        .arg("openstep")
+        .arg("-t")
+        .arg("json")
+        .write_stdin("{ key = value; /* comment */ data = <a1>; }")
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"key\":\"value\",\"data\":\"oQ==\"}\n"));
+}
+
+#[test]
+fn from_env() {
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--from-env")
+        .arg("DSCONV_TEST")
+        .env("DSCONV_TEST__DB__PORT", "5432")
+        .env("DSCONV_TEST__DB__HOST", "localhost")
+        .env("DSCONV_TEST__NAME", "dsconv")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "{\"DB\":{\"HOST\":\"localhost\",\"PORT\":\"5432\"},\"NAME\":\"dsconv\"}\n",
+        ));
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--from-env")
+        .arg("DSCONV_TEST")
+        .arg("--env-separator")
+        .arg("_")
+        .env("DSCONV_TEST_A_B", "1")
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"A\":{\"B\":\"1\"}}\n"));
+}
+
+#[test]
+fn json2env() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("--to-env")
+        .arg("app")
+        .write_stdin(r#"{"db":{"host":"localhost","port":5432},"list":["a","it's"]}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "export APP__DB__HOST='localhost'\n\
+             export APP__DB__PORT='5432'\n\
+             export APP__LIST__0='a'\n\
+             export APP__LIST__1='it'\\''s'\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("env")
+        .arg("--env-separator")
+        .arg("_")
+        .write_stdin(r#"{"a":{"b":null}}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("export A_B=''\n"));
+}
+
+#[test]
+fn nginx2json() {
+    command()
+        .arg("-f")
+        .arg("nginx")
+        .arg("-t")
+        .arg("json")
+        .write_stdin("server { listen 80; listen 443 ssl; location / { root /srv; } } # comment")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "{\"server\":{\"listen\":[\"80\",[\"443\",\"ssl\"]],\"location\":{\"/\":{\"root\":\"/srv\"}}}}\n",
+        ));
+}
+
+#[test]
+fn json2reg() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("reg")
+        .write_stdin(r#"{"HKEY_CURRENT_USER\\Software\\dsconv":{"Name":"a","Count":1}}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "Windows Registry Editor Version 5.00\r\n\
+             \r\n\
+             [HKEY_CURRENT_USER\\Software\\dsconv]\r\n\
+             \"Name\"=\"a\"\r\n\
+             \"Count\"=dword:00000001\r\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("reg")
+        .write_stdin(r#"{"HKEY_CURRENT_USER":{"a":0.5}}"#)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The value at HKEY_CURRENT_USER\\a cannot be represented",
+        ));
+}
+
+#[test]
+fn csv2ndjson() {
+    for stream in [false, true] {
+        let convert = || {
+            let mut command = command();
+            if stream {
+                command.arg("--stream");
+            }
+            command
+        };
+        convert()
+            .arg("-f")
+            .arg("csv")
+            .arg("-t")
+            .arg("ndjson")
+            .write_stdin("a,b\n1,x\n2,y\n")
+            .assert()
+            .success()
+            .stdout(predicate::eq(
+                "{\"a\":\"1\",\"b\":\"x\"}\n{\"a\":\"2\",\"b\":\"y\"}\n",
+            ));
+
+        convert()
+            .arg("-f")
+            .arg("csv")
+            .arg("-t")
+            .arg("ndjson")
+            .arg("--decode-input")
+            .arg("base64")
+            .write_stdin("YSxiCjEseAo=\n")
+            .assert()
+            .success()
+            .stdout(predicate::eq("{\"a\":\"1\",\"b\":\"x\"}\n"));
+
+        convert()
+            .arg("-f")
+            .arg("csv")
+            .arg("-t")
+            .arg("ndjson")
+            .arg("--max-file-size")
+            .arg("4")
+            .write_stdin("a,b\n1,x\n")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("maximum size of 4 bytes"));
+    }
+    command()
+        .arg("--stream")
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("ndjson")
+        .write_stdin("[]")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn required_keys() {
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--required-keys")
+        .arg("key")
+        .arg("resource/sample.json")
+        .assert()
+        .success();
+    command()
+        .arg("-t")
+        .arg("json")
+        .arg("--required-keys")
+        .arg("key,a,b")
+        .arg("resource/sample.json")
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("Missing required keys: a, b"));
+}
+
+#[test]
+fn fail_on_lossy() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("cbor")
+        .arg("--cbor-float-width")
+        .arg("16")
+        .arg("--fail-on-lossy")
+        .write_stdin(r#"{"a":[0.5,0.1],"b":0.2}"#)
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains(
+            "/a/1: the float 0.1 is rounded to 0.0999755859375\n  \
+             /b: the float 0.2 is rounded to 0.199951171875",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("cbor")
+        .arg("--fail-on-lossy")
+        .write_stdin(r#"{"a":[0.5,0.1],"b":0.2}"#)
+        .assert()
+        .success();
+}
+
+#[test]
+fn limit() {
+    command()
+        .arg("-f")
+        .arg("ndjson")
+        .arg("-t")
+        .arg("ndjson")
+        .arg("--limit")
+        .arg("2")
+        .write_stdin("1\n2\n3\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("1\n2\n"));
+    // The records after the limit are not read.
+    command()
+        .arg("-f")
+        .arg("ndjson")
+        .arg("-t")
+        .arg("ndjson")
+        .arg("--skip")
+        .arg("1")
+        .arg("--limit")
+        .arg("1")
+        .write_stdin("1\n2\n{\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("2\n"));
+    command()
+        .arg("-f")
+        .arg("ndjson")
+        .arg("-t")
+        .arg("ndjson")
+        .arg("--limit")
+        .arg("1")
+        .arg("--max-file-size")
+        .arg("4")
+        .write_stdin("1234\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("maximum size of 4 bytes"));
+    command()
+        .arg("--stream")
+        .arg("-f")
+        .arg("csv")
+        .arg("-t")
+        .arg("ndjson")
+        .arg("--limit")
+        .arg("1")
+        .write_stdin("a\n1\n2\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":\"1\"}\n"));
+}
+
+#[test]
+fn skip() {
+    command()
+        .arg("-f")
+        .arg("ndjson")
+        .arg("-t")
+        .arg("ndjson")
+        .arg("--skip")
+        .arg("1")
+        .arg("--limit")
+        .arg("1")
+        .write_stdin("1\n2\n3\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("2\n"));
+    command()
+        .arg("--stream")
+        .arg("-f")
+        .arg("csv")
+        .arg("-t")
+        .arg("ndjson")
+        .arg("--skip")
+        .arg("1")
+        .write_stdin("a\n1\n2\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":\"2\"}\n"));
+}
+
+#[test]
+fn cbor_self_describe() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("cbor")
+        .arg("--cbor-self-describe")
+        .write_stdin("null")
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"\xd9\xd9\xf7\xf6" as &[u8]));
+}
+
+#[test]
+fn preserve_cbor_bignum() {
+    let input: &[u8] = &[
+        0xa2, 0x61, 0x6e, 0xc2, 0x49, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x61,
+        0x6d, 0xc3, 0x41, 0x01,
+    ];
+    command()
+        .arg("-f")
+        .arg("cbor")
+        .arg("-t")
+        .arg("json")
+        .arg("--preserve-cbor-bignum")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"m\":-2,\"n\":18446744073709551616}\n"));
+    command()
+        .arg("-f")
+        .arg("cbor")
+        .arg("-t")
+        .arg("json")
+        .write_stdin(input)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn wide_integers() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .write_stdin("[-1701411834604692317316873037158841057280,3402823669209384634633746074317682114550]")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "[-1701411834604692317316873037158841057280,3402823669209384634633746074317682114550]\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .write_stdin("[18446744073709551616]")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("- \"18446744073709551616\""));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("cbor")
+        .write_stdin("18446744073709551616")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            b"\xc2\x49\x01\x00\x00\x00\x00\x00\x00\x00\x00" as &[u8],
+        ));
+}
+
+#[test]
+fn merge_into() {
+    let file = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("merge_into.json");
+    std::fs::write(&file, r#"{"a":1,"b":{"c":[1,2],"d":true}}"#).unwrap();
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-o")
+        .arg(&file)
+        .arg("--merge-into")
+        .write_stdin(r#"{"b":{"c":[3]},"e":null}"#)
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "{\"a\":1,\"b\":{\"c\":[3],\"d\":true},\"e\":null}\n"
+    );
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--merge-into")
+        .write_stdin("{}")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(
+            "--merge-into requires --output <FILE>",
+        ));
+}
+
+#[test]
+fn types_out() {
+    let file = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("types_out.json");
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--types-out")
+        .arg(&file)
+        .write_stdin(r#"{"a":1,"b":[0.5,true],"c":{"d":"x"}}"#)
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "{\n  \"a\": \"integer\",\n  \"b\": [\n    \"float\",\n    \"boolean\"\n  ],\n  \"c\": {\n    \"d\": \"string\"\n  }\n}\n"
+    );
+}
+
+#[test]
+fn compact_scalar_arrays() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("-p")
+        .arg("--compact-scalar-arrays")
+        .write_stdin(r#"{"a":[1,2,3],"b":[{"c":["d"]}]}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "{\n  \"a\": [1, 2, 3],\n  \"b\": [\n    {\n      \"c\": [\"d\"]\n    }\n  ]\n}\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--compact-scalar-arrays")
+        .write_stdin(r#"{"a":[1,2,3],"b":[{"c":["d"]}]}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("---\na: [1, 2, 3]\nb:\n  - c: [d]\n"));
+}
+
+#[test]
+fn yaml_literal_block() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--yaml-literal-block")
+        .write_stdin(r#"{"script":"echo a\necho b\n","name":"c"}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "---\nscript: |\n  echo a\n  echo b\nname: c\n",
+        ));
+}
+
+#[test]
+fn tolerant_yaml() {
+    command()
+        .arg("-f")
+        .arg("yaml")
+        .arg("-t")
+        .arg("json")
+        .arg("--tolerant-yaml")
+        .write_stdin("country: NO\nquoted: 'no'\nenabled: true\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "{\"country\":\"NO\",\"quoted\":\"no\",\"enabled\":true}\n",
+        ))
+        .stderr(predicate::eq(
+            "Warning: /country: the bare scalar `NO` at line 1 is a boolean in YAML 1.1, \
+             but is read as a string\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("yaml")
+        .arg("-t")
+        .arg("json")
+        .arg("--tolerant-yaml")
+        .arg("-q")
+        .write_stdin("country: NO\n")
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn jcs() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--jcs")
+        .write_stdin(r#"{"b": [1.50, 1E3, -0.0], "a": {"d": "é", "c": null}}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "{\"a\":{\"c\":null,\"d\":\"\u{e9}\"},\"b\":[1.5,1000,0]}",
+        ));
+}
+
+#[test]
+fn transform_order() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--null-as")
+        .arg("x")
+        .arg("--replace")
+        .arg("x")
+        .arg("y")
+        .write_stdin(r#"{"a":null}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":\"x\"}\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--null-as")
+        .arg("x")
+        .arg("--replace")
+        .arg("x")
+        .arg("y")
+        .arg("--transform-order")
+        .arg("null-as")
+        .write_stdin(r#"{"a":null}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":\"y\"}\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("--transform-order")
+        .arg("replace,replace")
+        .write_stdin("{}")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn wrap() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("toml")
+        .write_stdin("42")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "TOML requires a map at the root, but the root is a scalar (integer)",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("toml")
+        .arg("--wrap")
+        .arg("value")
+        .write_stdin(r#""hello""#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("value = \"hello\"\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--wrap")
+        .arg("value")
+        .write_stdin("42")
+        .assert()
+        .success()
+        .stdout(predicate::eq("42\n"));
+}
+
+#[test]
+fn json_seq() {
+    command()
+        .arg("-f")
+        .arg("json-seq")
+        .arg("-t")
+        .arg("json")
+        .write_stdin(&b"\x1e{\"a\":1}\n\x1e[true]\n"[..])
+        .assert()
+        .success()
+        .stdout(predicate::eq("[{\"a\":1},[true]]\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json-seq")
+        .write_stdin(r#"[{"a":1},"b"]"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(&b"\x1e{\"a\":1}\n\x1e\"b\"\n"[..]));
+}
+
+#[test]
+fn lossy_utf8() {
+    command()
+        .arg("-f")
+        .arg("cbor")
+        .arg("-t")
+        .arg("json")
+        .write_stdin(&[0xa1, 0x61, 0xff, 0x62, 0xc3, 0x28][..])
+        .assert()
+        .failure();
+    command()
+        .arg("-f")
+        .arg("cbor")
+        .arg("-t")
+        .arg("json")
+        .arg("--lossy-utf8")
+        .write_stdin(&[0xa1, 0x61, 0xff, 0x62, 0xc3, 0x28][..])
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"\u{fffd}\":\"\u{fffd}(\"}\n"));
+    command()
+        .arg("-f")
+        .arg("yaml")
+        .arg("-t")
+        .arg("json")
+        .arg("--lossy-utf8")
+        .write_stdin(&b"a: \xff\n"[..])
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":\"\u{fffd}\"}\n"));
+}
+
+#[test]
+fn datetime_tz() {
+    command()
+        .arg("-f")
+        .arg("toml")
+        .arg("-t")
+        .arg("toml")
+        .arg("--datetime-tz")
+        .arg("utc")
+        .write_stdin("a = 1979-05-27T07:32:00-07:00\nb = 1979-05-27T07:32:00\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "a = 1979-05-27T14:32:00Z\nb = 1979-05-27T07:32:00\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--datetime-tz")
+        .arg("+09:00")
+        .write_stdin(r#"["1979-05-27T14:32:00Z"]"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("[\"1979-05-27T23:32:00+09:00\"]\n"));
+    command()
+        .arg("--datetime-tz")
+        .arg("9")
+        .arg("resource/sample.json")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn explode() {
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("explode");
+
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("toml")
+        .arg("--explode")
+        .arg("--output-dir")
+        .arg(&dir)
+        .write_stdin(r#"{"web":{"port":80},"db":{"port":5432}}"#)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("web.toml")).unwrap(),
+        "port = 80\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("db.toml")).unwrap(),
+        "port = 5432\n"
+    );
+
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--explode")
+        .arg("--output-dir")
+        .arg(&dir)
+        .write_stdin("[]")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "`--explode` requires a map at the root, but the root is array",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--explode")
+        .arg("--output-dir")
+        .arg(&dir)
+        .write_stdin(r#"{"../a":1}"#)
+        .assert()
+        .failure();
+    let dir = dir.join("invalid");
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--explode")
+        .arg("--output-dir")
+        .arg(&dir)
+        .write_stdin(r#"{"a":1,"b/c":2}"#)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "\"b/c\" cannot be used as a filename",
+        ));
+    assert!(!dir.exists());
+}
+
+#[test]
+fn set() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--set")
+        .arg(r#"/metadata/version="1.2.3""#)
+        .arg("--set")
+        .arg("/a/0={\"b\":null}")
+        .write_stdin(r#"{"a":[1]}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "{\"a\":[{\"b\":null}],\"metadata\":{\"version\":\"1.2.3\"}}\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--set")
+        .arg("/a/1=2")
+        .write_stdin(r#"{"a":[1]}"#)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("out of range"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--set")
+        .arg("/a=tru")
+        .write_stdin("{}")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Failed to parse the value for /a as JSON",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--set")
+        .arg("/a=b/c=d=1")
+        .write_stdin("{}")
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a=b\":{\"c=d\":1}}\n"));
+}
+
+#[test]
+fn delete() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--delete")
+        .arg("/secret")
+        .arg("--delete")
+        .arg("/items/0")
+        .write_stdin(r#"{"secret":"x","items":[1,2]}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"items\":[2]}\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--delete")
+        .arg("/secret")
+        .write_stdin("{}")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The value does not exist: /secret",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--delete")
+        .arg("/secret")
+        .arg("--delete-missing-ok")
+        .write_stdin("{}")
+        .assert()
+        .success()
+        .stdout(predicate::eq("{}\n"));
+}
+
+#[test]
+fn float_format() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--float-format")
+        .arg("python")
+        .write_stdin("[1e16, 1.0, 0.00001, 1]")
+        .assert()
+        .success()
+        .stdout(predicate::eq("[1e+16,1.0,1e-05,1]\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--float-format")
+        .arg("go")
+        .write_stdin("{\"a\": 1234567.0}")
+        .assert()
+        .success()
+        .stdout(predicate::eq("---\na: 1.234567e+06\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--float-format")
+        .arg("javascript")
+        .arg("--jcs")
+        .write_stdin("[1.5]")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn bytes() {
+    command()
+        .arg("-f")
+        .arg("cbor")
+        .arg("-t")
+        .arg("json")
+        .write_stdin(&[0xa1, 0x61, 0x61, 0x43, 0xa1, 0x61, 0x62][..])
+        .assert()
+        .failure();
+    command()
+        .arg("-f")
+        .arg("cbor")
+        .arg("-t")
+        .arg("json")
+        .arg("--bytes")
+        .arg("hexdump")
+        .write_stdin(&[0xa1, 0x61, 0x61, 0x43, 0xa1, 0x61, 0x62][..])
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":\"a1 61 62\"}\n"));
+    command()
+        .arg("-f")
+        .arg("cbor")
+        .arg("-t")
+        .arg("json")
+        .arg("--bytes")
+        .arg("base64")
+        .write_stdin(&[0xa1, 0x61, 0x61, 0x43, 0xa1, 0x61, 0x62][..])
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":\"oWFi\"}\n"));
+}
+
+#[test]
+fn escape_control() {
+    // Output to pipes is not escaped.
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("lines")
+        .arg("--escape-control")
+        .write_stdin(r#"["a\u001b[2Jb"]"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("a\x1b[2Jb\n"));
+}
+
+#[test]
+fn js_safe_integers() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--js-safe-integers")
+        .write_stdin("[9007199254740991,9007199254740992,-9007199254740992]")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "[9007199254740991,\"9007199254740992\",\"-9007199254740992\"]\n",
+        ));
+}
+
+#[test]
+fn warn_number_loss() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("cbor")
+        .arg("--cbor-float-width")
+        .arg("32")
+        .arg("--warn-number-loss")
+        .write_stdin(r#"{"a":0.5,"b":0.1}"#)
+        .assert()
+        .success()
+        .stderr(predicate::eq(
+            "Warning: /b: the float 0.1 is rounded to 0.10000000149011612\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--warn-number-loss")
+        .write_stdin(r#"{"a":true,"b":[123456789012345678901234567890]}"#)
+        .assert()
+        .success()
+        .stderr(predicate::eq(
+            "Warning: /b/0: the integer is converted to a string\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--warn-number-loss")
+        .arg("-q")
+        .write_stdin(r#"{"a":true,"b":[123456789012345678901234567890]}"#)
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+}
+
+#[test]
+fn json2cheader() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("cheader")
+        .arg("--header-comment")
+        .arg("Generated by dsconv")
+        .write_stdin(r#"{"name":"dsconv","debug":false,"net":{"port":80}}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "// Generated by dsconv\n\
+             #define NAME \"dsconv\"\n\
+             #define DEBUG 0\n\
+             #define NET_PORT 80\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("cheader")
+        .write_stdin(r#"{"a":[1]}"#)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The value at A cannot be represented",
+        ));
+}
+
+#[test]
+fn arrays_as_maps() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--arrays-as-maps")
+        .write_stdin(r#"{"a":[1,[2]]}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":{\"0\":1,\"1\":{\"0\":2}}}\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--maps-as-arrays")
+        .write_stdin(r#"{"a":{"1":{"0":2},"0":1},"b":{"1":null}}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":[1,[2]],\"b\":{\"1\":null}}\n"));
+}
+
+#[test]
+fn urlencoded() {
+    command()
+        .arg("-f")
+        .arg("urlencoded")
+        .arg("-t")
+        .arg("json")
+        .write_stdin("a=1&b=x+y&c[]=x&c[]=y&d[e]=%E3%81%82")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "{\"a\":\"1\",\"b\":\"x y\",\"c\":[\"x\",\"y\"],\"d\":{\"e\":\"\u{3042}\"}}\n",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("urlencoded")
+        .write_stdin(r#"{"a":1,"b":"x y","c":["x","y"],"d":{"e":"&"}}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("a=1&b=x+y&c[]=x&c[]=y&d[e]=%26\n"));
+}
+
+#[test]
+fn on_parse_error() {
+    command()
+        .arg("-f")
+        .arg("ndjson")
+        .arg("-t")
+        .arg("ndjson")
+        .write_stdin("1\n{\n2\n")
+        .assert()
+        .failure();
+    command()
+        .arg("-f")
+        .arg("ndjson")
+        .arg("-t")
+        .arg("ndjson")
+        .arg("--on-parse-error")
+        .arg("skip")
+        .write_stdin("1\n{\n2\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("1\n2\n"))
+        .stderr(predicate::eq("Warning: Skipped 1 malformed records\n"));
+    command()
+        .arg("-f")
+        .arg("ndjson")
+        .arg("-t")
+        .arg("ndjson")
+        .arg("--on-parse-error")
+        .arg("skip")
+        .arg("-q")
+        .write_stdin("1\n{\n2\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("1\n2\n"))
+        .stderr(predicate::str::is_empty());
+    command()
+        .arg("--stream")
+        .arg("-f")
+        .arg("csv")
+        .arg("-t")
+        .arg("ndjson")
+        .arg("--on-parse-error")
+        .arg("null")
+        .write_stdin("a\n1\n2,3\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"a\":\"1\"}\nnull\n"))
+        .stderr(predicate::eq(
+            "Warning: Replaced 1 malformed records with null\n",
+        ));
+}
+
+#[test]
+fn output_encoding() {
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--output-encoding")
+        .arg("utf-16le")
+        .arg("--bom")
+        .write_stdin("1")
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"\xff\xfe1\x00\n\x00" as &[u8]));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("cbor")
+        .arg("--output-encoding")
+        .arg("utf-16be")
+        .write_stdin("1")
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"\x01" as &[u8]));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--output-encoding")
+        .arg("Shift_JIS")
+        .write_stdin(r#"{"a":"\u3042"}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq(b"---\na: \x82\xa0\n" as &[u8]));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--output-encoding")
+        .arg("latin1")
+        .write_stdin(r#""\u3042""#)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The output contains characters which windows-1252 cannot represent",
+        ));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--output-encoding")
+        .arg("gb18030")
+        .arg("--bom")
+        .write_stdin("1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--bom requires UTF-8 or UTF-16 as --output-encoding, but got gb18030",
+        ));
+}
+
+#[test]
+fn trim_strings() {
+    command()
+        .arg("-f")
+        .arg("csv")
+        .arg("-t")
+        .arg("json")
+        .arg("--trim-strings")
+        .arg("--trim-keys")
+        .write_stdin(" a ,b\n 1 ,x  y\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("[{\"a\":\"1\",\"b\":\"x  y\"}]\n"));
+    command()
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("json")
+        .arg("--trim-strings")
+        .arg("--collapse-whitespace")
+        .write_stdin(r#"{" a ":" x \n y "}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\" a \":\"x y\"}\n"));
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+#[test]
+fn profile() {
+    let config_home = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("profile");
+    std::fs::create_dir_all(config_home.join("dsconv")).unwrap();
+    std::fs::write(
+        config_home.join("dsconv/config.toml"),
+        "[profile.ordered]\nto = \"json\"\nkey_order = [\"b\"]\n",
+    )
+    .unwrap();
+
+    command()
+        .env("XDG_CONFIG_HOME", &config_home)
+        .arg("-f")
+        .arg("json")
+        .arg("--profile")
+        .arg("ordered")
+        .write_stdin(r#"{"a":1,"b":2}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("{\"b\":2,\"a\":1}\n"));
+    command()
+        .env("XDG_CONFIG_HOME", &config_home)
+        .arg("-f")
+        .arg("json")
+        .arg("-t")
+        .arg("yaml")
+        .arg("--profile")
+        .arg("ordered")
+        .write_stdin(r#"{"a":1}"#)
+        .assert()
+        .success()
+        .stdout(predicate::eq("---\na: 1\n"));
+    command()
+        .env("XDG_CONFIG_HOME", &config_home)
+        .arg("-f")
+        .arg("json")
+        .arg("--profile")
+        .arg("undefined")
+        .write_stdin("{}")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The profile is not defined: undefined",
+        ));
+}
@@ -0,0 +1,354 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright (C) 2021 Shun Sakai
+//
+
+//! Property-based round-trip tests over arbitrary values.
+
+use proptest::prelude::*;
+use proptest::sample::select;
+use proptest::test_runner::TestCaseError;
+
+use dsconv::value::{Format, Value};
+use dsconv::Options;
+
+/// The number of generated values for each format.
+const CASES: u32 = 256;
+
+/// The maximum nesting of generated arrays and maps.
+const MAX_DEPTH: u32 = 4;
+
+const INTEGERS: [i128; 9] = [
+    0,
+    1,
+    -1,
+    23,
+    -24,
+    i64::MIN as i128,
+    i64::MAX as i128,
+    i64::MAX as i128 + 1,
+    u64::MAX as i128,
+];
+
+const FLOATS: [f64; 9] = [
+    0.5,
+    -1.25,
+    2.0,
+    -0.0,
+    0.1,
+    1e300,
+    -1e-300,
+    f64::MAX,
+    f64::MIN_POSITIVE,
+];
+
+const STRINGS: [&str; 16] = [
+    "",
+    "a",
+    "no",
+    "null",
+    "true",
+    "1",
+    "0x1f",
+    "1e3",
+    "~",
+    " leading",
+    "trailing ",
+    "a: b",
+    "- item",
+    "# comment",
+    "multi\nline\n",
+    "\u{65e5}\u{672c}\u{8a9e} \u{1f980}",
+];
+
+/// Generate integers in the range of 64-bit integers, preferring the edges.
+fn arb_integer() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        select(&INTEGERS[..]).prop_map(|int| match int {
+            int if int < 0 => Value::Integer((int as i64).into()),
+            int => Value::Integer((int as u64).into()),
+        }),
+        any::<i64>().prop_map(|int| Value::Integer(int.into())),
+        any::<u64>().prop_map(|int| Value::Integer(int.into())),
+    ]
+}
+
+/// Generate finite floats.
+fn arb_float() -> impl Strategy<Value = Value> {
+    use proptest::num::f64::{NEGATIVE, NORMAL, POSITIVE, SUBNORMAL, ZERO};
+
+    prop_oneof![
+        select(&FLOATS[..]),
+        (POSITIVE | NEGATIVE | NORMAL | SUBNORMAL | ZERO),
+    ]
+    .prop_map(Value::Float)
+}
+
+/// Generate strings which look like other scalars, and arbitrary strings.
+fn arb_string() -> impl Strategy<Value = String> {
+    prop_oneof![select(&STRINGS[..]).prop_map(str::to_string), "\\PC{0,8}",]
+}
+
+/// Generate arbitrary values of which floats are finite.
+fn arb_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        arb_integer(),
+        arb_float(),
+        arb_string().prop_map(Value::String),
+    ];
+
+    leaf.prop_recursive(MAX_DEPTH, 64, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+            prop::collection::vec((arb_string(), inner), 0..4)
+                .prop_map(|entries| Value::Map(entries.into_iter().collect())),
+        ]
+    })
+}
+
+/// Generate integers which are out of the range of 64-bit integers, and others.
+fn arb_big_integer() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        arb_integer(),
+        any::<i128>().prop_map(|int| Value::Integer(int.into())),
+        any::<u128>().prop_map(|int| Value::Integer(int.into())),
+        "-?[1-9][0-9]{39,60}".prop_map(|int| Value::Integer(int.parse().unwrap())),
+    ]
+}
+
+/// Generate values which JSON and CBOR represent in the same way.
+fn arb_json_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        arb_big_integer(),
+        arb_float(),
+        arb_string().prop_map(Value::String),
+    ];
+
+    leaf.prop_recursive(MAX_DEPTH, 64, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+            prop::collection::vec((arb_string(), inner), 0..4)
+                .prop_map(|entries| Value::Map(entries.into_iter().collect())),
+        ]
+    })
+}
+
+/// Generate maps which YAML and TOML represent in the same way.
+///
+/// TOML has neither null nor integers out of the range of `i64`, and its
+/// arrays consist of values of the same type.
+fn arb_toml_value() -> impl Strategy<Value = Value> {
+    let array =
+        |element: BoxedStrategy<Value>| prop::collection::vec(element, 0..4).prop_map(Value::Array);
+    let table = |value: BoxedStrategy<Value>| {
+        prop::collection::vec(("[a-z][a-z0-9_-]{0,8}", value), 0..4)
+            .prop_map(|entries| Value::Map(entries.into_iter().collect()))
+    };
+    let boolean = || any::<bool>().prop_map(Value::Bool).boxed();
+    let integer = || {
+        any::<i64>()
+            .prop_map(|int| Value::Integer(int.into()))
+            .boxed()
+    };
+    let string = || arb_string().prop_map(Value::String).boxed();
+    let leaf = prop_oneof![
+        boolean(),
+        integer(),
+        arb_float(),
+        string(),
+        array(boolean()),
+        array(integer()),
+        array(arb_float().boxed()),
+        array(string()),
+    ];
+
+    let value = leaf.prop_recursive(MAX_DEPTH, 64, 4, move |inner| {
+        let inner = inner.boxed();
+        prop_oneof![table(inner.clone()), array(table(inner).boxed()),]
+    });
+
+    table(value.boxed())
+}
+
+fn write(format: Format, value: &Value) -> anyhow::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    dsconv::io::write_value(format, value, &mut buf, &Options::new())?;
+
+    Ok(buf)
+}
+
+fn roundtrip(format: Format, value: &Value) -> anyhow::Result<Value> {
+    let buf = write(format, value)?;
+
+    dsconv::io::read_value(format, buf.as_slice(), &Options::default())
+}
+
+/// Convert the value from `format` to `via`, and then back to `format`.
+fn roundtrip_via(format: Format, via: Format, value: &Value) -> anyhow::Result<Value> {
+    let options = Options::new().preserve_cbor_bignum(true);
+    let value = dsconv::io::read_value(format, write(format, value)?.as_slice(), &options)?;
+    let value = dsconv::io::read_value(via, write(via, &value)?.as_slice(), &options)?;
+
+    dsconv::io::read_value(format, write(format, &value)?.as_slice(), &options)
+}
+
+fn assert_lossless_via(format: Format, via: Format, value: Value) -> Result<(), TestCaseError> {
+    let actual =
+        roundtrip_via(format, via, &value).map_err(|e| TestCaseError::fail(format!("{:?}", e)))?;
+    prop_assert_eq!(actual, value, "{} via {}", format, via);
+
+    Ok(())
+}
+
+fn assert_lossless(format: Format, value: Value) -> Result<(), TestCaseError> {
+    let actual = roundtrip(format, &value).map_err(|e| TestCaseError::fail(format!("{:?}", e)))?;
+    prop_assert_eq!(actual, value, "{}", format);
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    fn cbor(value in arb_value()) {
+        assert_lossless(Format::Cbor, value)?;
+    }
+
+    #[test]
+    fn json(value in arb_value()) {
+        assert_lossless(Format::Json, value)?;
+    }
+
+    #[test]
+    fn messagepack(value in arb_value()) {
+        assert_lossless(Format::MessagePack, value)?;
+    }
+
+    #[test]
+    fn yaml(value in arb_value()) {
+        assert_lossless(Format::Yaml, value)?;
+    }
+
+    #[test]
+    fn json_via_cbor(value in arb_json_value()) {
+        assert_lossless_via(Format::Json, Format::Cbor, value)?;
+    }
+
+    #[test]
+    fn yaml_via_toml(value in arb_toml_value()) {
+        assert_lossless_via(Format::Yaml, Format::Toml, value)?;
+    }
+
+    #[test]
+    fn json_rejects_non_finite_floats(
+        value in arb_value(),
+        float in select(&[f64::NAN, f64::INFINITY, f64::NEG_INFINITY][..]),
+    ) {
+        let value = Value::Array(vec![value, Value::Float(float)]);
+        prop_assert!(write(Format::Json, &value).is_err());
+    }
+
+    #[test]
+    fn toml_rejects_null(value in arb_value()) {
+        let value = Value::Map(
+            vec![
+                ("a".to_string(), value),
+                ("b".to_string(), Value::Null),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        prop_assert!(write(Format::Toml, &value).is_err());
+    }
+}
+
+#[test]
+fn deep_nesting() {
+    let value = (0..32).fold(Value::Null, |value, i| {
+        if i % 2 == 0 {
+            Value::Array(vec![value])
+        } else {
+            Value::Map(std::iter::once((i.to_string(), value)).collect())
+        }
+    });
+
+    for format in [
+        Format::Cbor,
+        Format::Json,
+        Format::MessagePack,
+        Format::Yaml,
+    ] {
+        assert_eq!(roundtrip(format, &value).unwrap(), value, "{}", format);
+    }
+}
+
+#[test]
+fn non_finite_floats() {
+    for float in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let value = Value::Array(vec![Value::Float(float)]);
+
+        for format in [Format::Cbor, Format::MessagePack, Format::Yaml] {
+            assert_eq!(roundtrip(format, &value).unwrap(), value, "{}", format);
+        }
+        assert!(write(Format::Json, &value).is_err());
+    }
+}
+
+#[test]
+fn ron() {
+    let value = Value::Array(
+        [-0.0, 0.0, 2.0, 0.5, 1e300]
+            .into_iter()
+            .map(Value::Float)
+            .collect(),
+    );
+    assert_eq!(roundtrip(Format::Ron, &value).unwrap(), value);
+
+    let mut buf = Vec::new();
+    let options = Options::new().pretty(true);
+    dsconv::io::write_value(Format::Ron, &value, &mut buf, &options).unwrap();
+    assert_eq!(
+        dsconv::io::read_value(Format::Ron, buf.as_slice(), &Options::default()).unwrap(),
+        value
+    );
+}
+
+#[test]
+fn toml() {
+    let map = |entries: Vec<(&str, Value)>| {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        )
+    };
+
+    let value = map(vec![
+        ("int", Value::Integer(i64::MIN.into())),
+        ("float", Value::Float(0.5)),
+        ("negative_zero", Value::Float(-0.0)),
+        ("str", Value::String("\u{1f980}".to_string())),
+        ("arr", Value::Array(vec![Value::Bool(true)])),
+        ("map", map(vec![("a", Value::String(String::new()))])),
+    ]);
+    assert_eq!(roundtrip(Format::Toml, &value).unwrap(), value);
+
+    for lossy in [
+        map(vec![("a", Value::Null)]),
+        map(vec![("a", Value::Integer(u64::MAX.into()))]),
+        map(vec![(
+            "a",
+            Value::Array(vec![Value::Bool(true), Value::Float(1.5)]),
+        )]),
+        Value::Array(vec![]),
+    ] {
+        assert!(write(Format::Toml, &lossy).is_err(), "{:?}", lossy);
+    }
+}